@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mason_rs::Value;
+
+// Parsing arbitrary bytes must never panic or hang, and anything that parses
+// must serialize to something that parses to the same value.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(value) = Value::from_slice(data) {
+        let string = value.to_string();
+        let parsed = Value::from_slice(string.as_bytes()).expect("serialized value must parse");
+        assert_eq!(parsed.to_string(), string);
+    }
+});
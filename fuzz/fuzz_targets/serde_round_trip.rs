@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mason_rs::Value;
+
+fuzz_target!(|value: Value| {
+    let string = mason_rs::to_string(&value).expect("any value can be serialized");
+    let parsed: Value = mason_rs::from_str(&string).expect("serialized value must deserialize");
+    assert_eq!(parsed, value);
+});
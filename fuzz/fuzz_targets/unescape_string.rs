@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mason_rs::text::{escape_string, unescape_string};
+
+// Unescaping must never panic on invalid escapes, and must undo escaping.
+fuzz_target!(|data: &[u8]| {
+    let _ = unescape_string(data);
+
+    if let Ok(string) = std::str::from_utf8(data) {
+        let escaped = escape_string(string);
+        let unescaped = unescape_string(escaped.as_bytes()).expect("escaped strings unescape");
+        assert_eq!(unescaped, string.as_bytes());
+    }
+});
@@ -0,0 +1,143 @@
+//! Derive macros for the `FromMason` and `ToMason` traits of
+//! [mason-rs](https://docs.rs/mason-rs). Enable the `derive` feature of
+//! mason-rs instead of depending on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Error, Fields, GenericParam, Generics, Ident, LitStr, Result, ext::IdentExt,
+    parse_macro_input, parse_quote,
+};
+
+/// Derive `FromMason` for a struct with named fields, which is converted from
+/// an object. Missing fields are converted from `null`, so `Option` fields can
+/// be left out. Use `#[mason(rename = "key")]` to read a field from a different key.
+#[proc_macro_derive(FromMason, attributes(mason))]
+pub fn derive_from_mason(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_mason(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derive `ToMason` for a struct with named fields, which is converted to an
+/// object. Use `#[mason(rename = "key")]` to write a field to a different key.
+#[proc_macro_derive(ToMason, attributes(mason))]
+pub fn derive_to_mason(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    to_mason(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn from_mason(input: DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = named_fields(&input)?;
+    let generics = add_bound(input.generics.clone(), quote!(::mason_rs::FromMason));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let fields = fields.iter().map(|(ident, key)| {
+        quote! {
+            #ident: match object.remove(#key) {
+                ::std::option::Option::Some(value) => ::mason_rs::FromMason::from_mason(value)
+                    .map_err(|err| err.in_field(#key))?,
+                ::std::option::Option::None => {
+                    ::mason_rs::FromMason::from_mason(::mason_rs::Value::Null)
+                        .map_err(|_| ::mason_rs::Error::missing_field(#key))?
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::mason_rs::FromMason for #name #ty_generics #where_clause {
+            fn from_mason(value: ::mason_rs::Value) -> ::mason_rs::Result<Self> {
+                let mut object = match value {
+                    ::mason_rs::Value::Object(object) => object,
+                    value => return ::std::result::Result::Err(
+                        ::mason_rs::Error::invalid_type("an object", &value),
+                    ),
+                };
+                ::std::result::Result::Ok(Self { #(#fields,)* })
+            }
+        }
+    })
+}
+
+fn to_mason(input: DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = named_fields(&input)?;
+    let generics = add_bound(input.generics.clone(), quote!(::mason_rs::ToMason));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let inserts = fields.iter().map(|(ident, key)| {
+        quote! {
+            object.insert(#key, ::mason_rs::ToMason::to_mason(&self.#ident));
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::mason_rs::ToMason for #name #ty_generics #where_clause {
+            fn to_mason(&self) -> ::mason_rs::Value {
+                let mut object = ::mason_rs::Map::new();
+                #(#inserts)*
+                ::mason_rs::Value::Object(object)
+            }
+        }
+    })
+}
+
+/// Get the fields of a struct with named fields, and the keys they have in MASON.
+fn named_fields(input: &DeriveInput) -> Result<Vec<(Ident, LitStr)>> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "only structs with named fields are supported",
+                ));
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "only structs with named fields are supported",
+            ));
+        }
+    };
+
+    fields
+        .iter()
+        .map(|field| {
+            let ident = field.ident.clone().expect("named fields have identifiers");
+            let mut key = LitStr::new(&ident.unraw().to_string(), ident.span());
+            for attr in field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("mason"))
+            {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("rename") {
+                        key = meta.value()?.parse()?;
+                        Ok(())
+                    } else {
+                        Err(meta.error("unknown mason attribute"))
+                    }
+                })?;
+            }
+            Ok((ident, key))
+        })
+        .collect()
+}
+
+/// Require every type parameter to implement `bound`.
+fn add_bound(mut generics: Generics, bound: TokenStream2) -> Generics {
+    for param in &mut generics.params {
+        if let GenericParam::Type(param) = param {
+            param.bounds.push(parse_quote!(#bound));
+        }
+    }
+    generics
+}
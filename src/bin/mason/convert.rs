@@ -0,0 +1,183 @@
+//! `mason convert`: convert documents between MASON and other formats.
+//!
+//! MASON and JSON are always supported. YAML, TOML and CBOR are supported when
+//! the binary is built with the `yaml`, `toml` and `cbor` features. Conversions
+//! go through a [`Value`], so byte strings are kept by formats that have them,
+//! like CBOR, and written as arrays of numbers by the rest, except that
+//! `--bytes-as` can write them as strings in JSON.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, Read, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use mason_rs::{ParseOptions, Value};
+
+use crate::json::{BytesAs, write_json};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+const USAGE: &str =
+    "usage: mason convert [--from <format>] --to <format> [--bytes-as <format>] [file]";
+
+/// A document format that `mason convert` can read and write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Mason,
+    Json,
+    Yaml,
+    Toml,
+    Cbor,
+}
+
+impl FromStr for Format {
+    type Err = Box<dyn Error>;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format.to_ascii_lowercase().as_str() {
+            "mason" => Ok(Self::Mason),
+            "json" => Ok(Self::Json),
+            "yaml" | "yml" => Ok(Self::Yaml),
+            "toml" => Ok(Self::Toml),
+            "cbor" => Ok(Self::Cbor),
+            _ => Err(format!(
+                "unknown format {format:?}, expected mason, json, yaml, toml or cbor"
+            )
+            .into()),
+        }
+    }
+}
+
+/// `mason convert [--from <format>] --to <format> [file]`: convert `file`, or
+/// stdin if no file is given, and print the result. If `--from` is not given,
+/// the format is guessed from the file extension, and is MASON for stdin.
+/// `--bytes-as` is how byte strings are written in JSON, like in `mason to-json`.
+pub fn run(args: &[String]) -> Result<()> {
+    let mut from = None;
+    let mut to = None;
+    let mut bytes_as = BytesAs::default();
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => from = Some(args.next().ok_or(USAGE)?.parse()?),
+            "--to" => to = Some(args.next().ok_or(USAGE)?.parse()?),
+            "--bytes-as" => bytes_as = args.next().ok_or(USAGE)?.parse()?,
+            _ => positional.push(arg),
+        }
+    }
+    let Some(to) = to else {
+        return Err(USAGE.into());
+    };
+
+    let mut input = Vec::new();
+    let from = match positional[..] {
+        [] => {
+            io::stdin().lock().read_to_end(&mut input)?;
+            from.unwrap_or(Format::Mason)
+        }
+        [path] => {
+            File::open(path)?.read_to_end(&mut input)?;
+            match from {
+                Some(from) => from,
+                None => Path::new(path)
+                    .extension()
+                    .and_then(|extension| extension.to_str()?.parse().ok())
+                    .ok_or_else(|| format!("can not guess the format of {path:?}, use --from"))?,
+            }
+        }
+        _ => return Err(USAGE.into()),
+    };
+
+    let value = read(from, &input)?;
+    let mut output = write(to, &value, bytes_as)?;
+    if to != Format::Cbor && !output.ends_with(b"\n") {
+        output.push(b'\n');
+    }
+    io::stdout().lock().write_all(&output)?;
+    Ok(())
+}
+
+/// Parse `input` as `format`.
+fn read(format: Format, input: &[u8]) -> Result<Value> {
+    match format {
+        Format::Mason => Ok(Value::from_slice(input)?),
+        Format::Json => Ok(ParseOptions::new()
+            .json_only(true)
+            .parse_slice(input)?
+            .value),
+        #[cfg(feature = "yaml")]
+        Format::Yaml => Ok(serde_yaml::from_slice(input)?),
+        #[cfg(feature = "toml")]
+        Format::Toml => Ok(toml::from_str(std::str::from_utf8(input)?)?),
+        #[cfg(feature = "cbor")]
+        Format::Cbor => Ok(ciborium::from_reader(input)?),
+        #[allow(unreachable_patterns)]
+        format => Err(unsupported(format)),
+    }
+}
+
+/// Write `value` as `format`, with byte strings written as `bytes_as` in JSON.
+fn write(format: Format, value: &Value, bytes_as: BytesAs) -> Result<Vec<u8>> {
+    match format {
+        Format::Mason => Ok(format!("{value:#}").into_bytes()),
+        Format::Json => {
+            let mut string = String::new();
+            write_json(value, &mut string, 0, bytes_as)?;
+            Ok(string.into_bytes())
+        }
+        #[cfg(feature = "yaml")]
+        Format::Yaml => Ok(serde_yaml::to_string(value)?.into_bytes()),
+        #[cfg(feature = "toml")]
+        Format::Toml => Ok(toml::to_string_pretty(value)?.into_bytes()),
+        #[cfg(feature = "cbor")]
+        Format::Cbor => {
+            let mut bytes = Vec::new();
+            ciborium::into_writer(value, &mut bytes)?;
+            Ok(bytes)
+        }
+        #[allow(unreachable_patterns)]
+        format => Err(unsupported(format)),
+    }
+}
+
+fn unsupported(format: Format) -> Box<dyn Error> {
+    let feature = format!("{format:?}").to_ascii_lowercase();
+    format!("{feature} is not supported, build mason with the `{feature}` feature").into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(format: Format, value: &Value) -> Value {
+        read(format, &write(format, value, BytesAs::default()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_convert() {
+        assert_eq!(Format::from_str("YML").unwrap(), Format::Yaml);
+        assert!(Format::from_str("xml").is_err());
+
+        let value = Value::from_str("name: \"app\", ports: [80, 443], debug: false").unwrap();
+        for format in [Format::Mason, Format::Json] {
+            assert_eq!(round_trip(format, &value), value);
+        }
+        assert!(read(Format::Json, b"{name: \"app\"}").is_err());
+
+        #[cfg(feature = "yaml")]
+        assert_eq!(round_trip(Format::Yaml, &value), value);
+        #[cfg(feature = "toml")]
+        assert_eq!(round_trip(Format::Toml, &value), value);
+        #[cfg(feature = "cbor")]
+        {
+            let value = Value::from_str("key: b\"\\x00\\xff\", list: [null, 1.5]").unwrap();
+            assert_eq!(round_trip(Format::Cbor, &value), value);
+        }
+        #[cfg(not(feature = "cbor"))]
+        assert!(write(Format::Cbor, &value, BytesAs::default()).is_err());
+    }
+}
@@ -0,0 +1,38 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+};
+
+use mason_rs::{Value, diff::diff};
+
+/// `mason diff [--exit-code] <old> <new>`: print the differences between `old`
+/// and `new`, one per line. With `--exit-code`, fail if there are any, so the
+/// command can be used to check that two files are equivalent.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut exit_code = false;
+    let mut positional = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--exit-code" => exit_code = true,
+            _ => positional.push(arg),
+        }
+    }
+    let [old_path, new_path] = positional[..] else {
+        return Err("usage: mason diff [--exit-code] <old> <new>".into());
+    };
+    let old = Value::from_file(old_path)?;
+    let new = Value::from_file(new_path)?;
+    let changes = diff(&old, &new);
+
+    let mut stdout = io::stdout().lock();
+    for change in &changes {
+        writeln!(stdout, "{change}")?;
+    }
+
+    match changes.len() {
+        _ if !exit_code => Ok(()),
+        0 => Ok(()),
+        1 => Err("found 1 difference".into()),
+        count => Err(format!("found {count} differences").into()),
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+};
+
+use mason_rs::{
+    SerializeOptions, Value,
+    merge::{MergeOptions, MergedDocument, Origin},
+};
+
+use crate::merge::parse_option;
+
+const USAGE: &str =
+    "usage: mason explain [--arrays replace|append|by-index] [--null-deletes] <file>... <path>";
+
+/// `mason explain [--arrays <mode>] [--null-deletes] <file>... <path>`: merge the
+/// files in order, like `mason merge`, and print the final value at `path`, where
+/// it was set, and the values it overrode.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut options = MergeOptions::new();
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !parse_option(arg, &mut args, &mut options, USAGE)? {
+            positional.push(arg.as_str());
+        }
+    }
+    let Some((path, files)) = positional.split_last() else {
+        return Err(USAGE.into());
+    };
+    if files.is_empty() || path.is_empty() {
+        return Err(USAGE.into());
+    }
+
+    // every change to the value at `path`, with the file that made it
+    let mut changes: Vec<Change> = Vec::new();
+    let mut document = MergedDocument::with_options(options);
+    for file in files {
+        document.merge_file(file)?;
+        let value = document
+            .value()
+            .get_opt(path)
+            .map(|value| value.cloned().unwrap_or(Value::Null));
+        let previous = changes.last().and_then(|change| change.value.as_ref());
+        if value.as_ref() != previous {
+            changes.push(Change {
+                file,
+                value,
+                origin: document.origin(path).cloned(),
+            });
+        }
+    }
+
+    let mut stdout = io::stdout().lock();
+    let Some((last, overridden)) = changes.split_last() else {
+        writeln!(stdout, "{path} is not set by any file")?;
+        return Ok(());
+    };
+    match &last.value {
+        Some(value) => {
+            writeln!(stdout, "{path}: {}", compact(value))?;
+            writeln!(stdout, "    set by {}", last.location())?;
+        }
+        None => {
+            writeln!(stdout, "{path} is not set")?;
+            writeln!(stdout, "    removed by {}", last.location())?;
+        }
+    }
+    for change in overridden.iter().rev() {
+        match &change.value {
+            Some(value) => writeln!(
+                stdout,
+                "    overrides {} from {}",
+                compact(value),
+                change.location()
+            )?,
+            None => writeln!(stdout, "    overrides its removal by {}", change.location())?,
+        }
+    }
+    Ok(())
+}
+
+struct Change<'a> {
+    file: &'a str,
+    /// The value after the change, or None if it was removed.
+    value: Option<Value>,
+    /// Where the value came from, if it came from a single place.
+    origin: Option<Origin>,
+}
+
+impl Change<'_> {
+    /// The file and position the value came from, or only the file if it was
+    /// merged from several places.
+    fn location(&self) -> String {
+        match &self.origin {
+            Some(origin) => format!(
+                "{}:{}:{}",
+                origin.source, origin.position.line, origin.position.column
+            ),
+            None => self.file.to_owned(),
+        }
+    }
+}
+
+/// Write `value` on a single line, with braces around objects.
+fn compact(value: &Value) -> String {
+    let options = SerializeOptions::new().compact(true).braced_root(true);
+    let mut string = String::new();
+    value
+        .to_writer_with_options(&mut string, &options)
+        .expect("writing to a String does not fail");
+    string
+}
@@ -0,0 +1,79 @@
+use std::{
+    error::Error,
+    fs,
+    io::{self, Read, Write},
+};
+
+use mason_rs::{LineEnding, ParseOptions, SerializeOptions};
+
+const USAGE: &str = "usage: mason fmt [-i [--backup <suffix>]] [file]";
+
+/// `mason fmt [-i [--backup <suffix>]] [file]`: format `file`, or stdin if no file
+/// is given or the file is `-`, and print it. With `-i`, the file is rewritten in
+/// place instead, and with `--backup`, it is first copied to its name with `suffix`
+/// appended.
+///
+/// Formatting can not keep comments yet, so documents with comments are rejected
+/// instead of losing them.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut in_place = false;
+    let mut backup = None;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-i" | "--in-place" => in_place = true,
+            "--backup" => backup = Some(args.next().ok_or(USAGE)?),
+            _ => paths.push(arg.as_str()),
+        }
+    }
+    let path = match paths[..] {
+        [] | ["-"] => None,
+        [path] => Some(path),
+        _ => return Err(USAGE.into()),
+    };
+    if backup.is_some() && !in_place {
+        return Err("--backup can only be used with -i".into());
+    }
+
+    let source = match path {
+        Some(path) => fs::read_to_string(path)?,
+        None if in_place => return Err("-i needs a file to rewrite".into()),
+        None => {
+            let mut source = String::new();
+            io::stdin().lock().read_to_string(&mut source)?;
+            source
+        }
+    };
+    let name = path.unwrap_or("<stdin>");
+
+    let parsed = ParseOptions::new()
+        .capture_comments(true)
+        .capture_string_styles(true)
+        .parse_str(&source)
+        .map_err(|err| format!("{name}: {err}"))?;
+    if !parsed.comments.is_empty() {
+        return Err(format!("{name} has comments, which formatting would remove").into());
+    }
+
+    let line_ending = LineEnding::detect(&source);
+    let mut formatted = String::new();
+    parsed.to_writer_with_options(
+        &mut formatted,
+        &SerializeOptions::new().line_ending(line_ending),
+    )?;
+
+    match path {
+        Some(path) if in_place => {
+            // leave formatted files alone, so their modification time does not change
+            if formatted != source {
+                if let Some(suffix) = backup {
+                    fs::copy(path, format!("{path}{suffix}"))?;
+                }
+                parsed.write_to_file(path)?;
+            }
+        }
+        _ => write!(io::stdout().lock(), "{formatted}{}", line_ending.as_str())?,
+    }
+    Ok(())
+}
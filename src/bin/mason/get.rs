@@ -0,0 +1,22 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+};
+
+use mason_rs::{Query, Value};
+
+/// `mason get <file> <query>`: print every value in `file` that matches
+/// `query` as compact MASON, one per line.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let [path, query] = args else {
+        return Err("usage: mason get <file> <query>".into());
+    };
+    let query: Query = query.parse()?;
+    let value = Value::from_file(path)?;
+
+    let mut stdout = io::stdout().lock();
+    for selected in query.select(&value) {
+        writeln!(stdout, "{selected}")?;
+    }
+    Ok(())
+}
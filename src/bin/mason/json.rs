@@ -0,0 +1,193 @@
+//! Writing values as JSON.
+
+use std::{
+    error::Error,
+    fmt::{self, Write},
+    str::FromStr,
+};
+
+use mason_rs::{Value, hex};
+
+const INDENTATION: &str = "    ";
+
+/// How byte strings are written in JSON, which has no byte strings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BytesAs {
+    /// An array of numbers, like serde writes them.
+    #[default]
+    Array,
+    /// A string of hex digits.
+    Hex,
+    /// A string of standard base64.
+    #[cfg(feature = "base64")]
+    Base64,
+    /// A string of the bytes as UTF-8, where invalid UTF-8 is replaced with the
+    /// replacement character.
+    LossyString,
+}
+
+impl FromStr for BytesAs {
+    type Err = Box<dyn Error>;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "array" => Ok(Self::Array),
+            "hex" => Ok(Self::Hex),
+            "lossy-string" => Ok(Self::LossyString),
+            #[cfg(feature = "base64")]
+            "base64" => Ok(Self::Base64),
+            #[cfg(not(feature = "base64"))]
+            "base64" => {
+                Err("base64 is not supported, build mason with the `base64` feature".into())
+            }
+            _ => Err(format!(
+                "unknown byte string format {string:?}, expected array, hex, base64 or lossy-string"
+            )
+            .into()),
+        }
+    }
+}
+
+/// Write `value` as indented JSON. Byte strings are written as `bytes_as` says,
+/// and tagged values as objects with the tag, prefixed by `@`, as the only key,
+/// like when serializing them with serde. Numbers that JSON cannot represent are
+/// written as `null`.
+pub fn write_json<W: Write>(
+    value: &Value,
+    w: &mut W,
+    indentation_level: usize,
+    bytes_as: BytesAs,
+) -> fmt::Result {
+    match value {
+        Value::Object(map) if map.is_empty() => write!(w, "{{}}"),
+        Value::Object(map) => {
+            writeln!(w, "{{")?;
+            for (i, (key, value)) in map.iter().enumerate() {
+                if i != 0 {
+                    writeln!(w, ",")?;
+                }
+                write!(w, "{}", INDENTATION.repeat(indentation_level + 1))?;
+                write_json_string(w, key)?;
+                write!(w, ": ")?;
+                write_json(value, w, indentation_level + 1, bytes_as)?;
+            }
+            write!(w, "\n{}}}", INDENTATION.repeat(indentation_level))
+        }
+        Value::Array(vec) if vec.is_empty() => write!(w, "[]"),
+        Value::Array(vec) => {
+            writeln!(w, "[")?;
+            for (i, value) in vec.iter().enumerate() {
+                if i != 0 {
+                    writeln!(w, ",")?;
+                }
+                write!(w, "{}", INDENTATION.repeat(indentation_level + 1))?;
+                write_json(value, w, indentation_level + 1, bytes_as)?;
+            }
+            write!(w, "\n{}]", INDENTATION.repeat(indentation_level))
+        }
+        Value::ByteString(bytes) if bytes_as == BytesAs::Hex => {
+            write!(w, "\"{}\"", hex::encode(bytes))
+        }
+        #[cfg(feature = "base64")]
+        Value::ByteString(_) if bytes_as == BytesAs::Base64 => {
+            write!(w, "\"{}\"", value.as_base64().unwrap_or_default())
+        }
+        Value::ByteString(bytes) if bytes_as == BytesAs::LossyString => {
+            write_json_string(w, &String::from_utf8_lossy(bytes))
+        }
+        Value::ByteString(bytes) => {
+            write!(w, "[")?;
+            for (i, byte) in bytes.iter().enumerate() {
+                if i != 0 {
+                    write!(w, ", ")?;
+                }
+                write!(w, "{byte}")?;
+            }
+            write!(w, "]")
+        }
+        Value::String(string) => write_json_string(w, string),
+        Value::Number(_) => match value.as_f64() {
+            Some(num) if num.is_finite() => write!(w, "{num}"),
+            _ => write!(w, "null"),
+        },
+        Value::Null => write!(w, "null"),
+        Value::Bool(b) => write!(w, "{b}"),
+        Value::Tagged(tag, value) => {
+            write!(w, "{{\n{}", INDENTATION.repeat(indentation_level + 1))?;
+            write_json_string(w, &format!("@{tag}"))?;
+            write!(w, ": ")?;
+            write_json(value, w, indentation_level + 1, bytes_as)?;
+            write!(w, "\n{}}}", INDENTATION.repeat(indentation_level))
+        }
+    }
+}
+
+fn write_json_string<W: Write>(w: &mut W, string: &str) -> fmt::Result {
+    write!(w, "\"")?;
+    for c in string.chars() {
+        match c {
+            '"' => write!(w, "\\\"")?,
+            '\\' => write!(w, "\\\\")?,
+            '\n' => write!(w, "\\n")?,
+            '\r' => write!(w, "\\r")?,
+            '\t' => write!(w, "\\t")?,
+            '\u{8}' => write!(w, "\\b")?,
+            '\u{c}' => write!(w, "\\f")?,
+            c if c < ' ' => write!(w, "\\u{:04x}", c as u32)?,
+            c => write!(w, "{c}")?,
+        }
+    }
+    write!(w, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn to_json(value: &str) -> String {
+        to_json_with_bytes(value, BytesAs::Array)
+    }
+
+    fn to_json_with_bytes(value: &str, bytes_as: BytesAs) -> String {
+        let mut string = String::new();
+        write_json(&Value::from_str(value).unwrap(), &mut string, 0, bytes_as).unwrap();
+        string
+    }
+
+    #[test]
+    fn test_write_json() {
+        assert_eq!(to_json("{}"), "{}");
+        assert_eq!(to_json("[]"), "[]");
+        assert_eq!(
+            to_json(r#"[1.5, "a\"\\\x01", b"\x00\xff", true, null]"#),
+            "[\n    1.5,\n    \"a\\\"\\\\\\u0001\",\n    [0, 255],\n    true,\n    null\n]"
+        );
+        assert_eq!(
+            to_json("a: {b: [1]}"),
+            "{\n    \"a\": {\n        \"b\": [\n            1\n        ]\n    }\n}"
+        );
+        assert_eq!(to_json("[0x10]"), "[\n    16\n]");
+    }
+
+    #[test]
+    fn test_bytes_as() {
+        let value = "b\"\\x00ab\\xff\"";
+        assert_eq!(
+            to_json_with_bytes(value, BytesAs::Array),
+            "[0, 97, 98, 255]"
+        );
+        assert_eq!(to_json_with_bytes(value, BytesAs::Hex), "\"006162ff\"");
+        #[cfg(feature = "base64")]
+        assert_eq!(to_json_with_bytes(value, BytesAs::Base64), "\"AGFi/w==\"");
+
+        assert_eq!(
+            to_json_with_bytes(value, BytesAs::LossyString),
+            "\"\\u0000ab\u{FFFD}\""
+        );
+
+        assert_eq!("hex".parse::<BytesAs>().unwrap(), BytesAs::Hex);
+        assert!("bytes".parse::<BytesAs>().is_err());
+    }
+}
@@ -0,0 +1,32 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, Write},
+};
+
+use mason_rs::lint::lint;
+
+/// `mason lint <file>`: print the warnings for `file`, one per line, and fail
+/// if there are any.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let [path] = args else {
+        return Err("usage: mason lint <file>".into());
+    };
+    let warnings = lint(File::open(path)?)?;
+
+    let mut stdout = io::stdout().lock();
+    for warning in &warnings {
+        let position = warning.position;
+        writeln!(
+            stdout,
+            "{path}:{}:{}: {}",
+            position.line, position.column, warning.message
+        )?;
+    }
+
+    match warnings.len() {
+        0 => Ok(()),
+        1 => Err("found 1 warning".into()),
+        count => Err(format!("found {count} warnings").into()),
+    }
+}
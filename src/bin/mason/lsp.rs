@@ -0,0 +1,445 @@
+use std::{collections::HashMap, error::Error, ops::Range};
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentFormattingParams, DocumentRangeFormattingParams,
+    DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, Hover, HoverContents,
+    HoverParams, HoverProviderCapability, MarkupContent, MarkupKind, OneOf, Position,
+    PublishDiagnosticsParams, ServerCapabilities, SymbolKind, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextEdit, Uri,
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as LspNotification, PublishDiagnostics,
+    },
+    request::{
+        DocumentSymbolRequest, Formatting, HoverRequest, RangeFormatting, Request as LspRequest,
+    },
+};
+use mason_rs::{ParseOptions, SerializeOptions, Value, format_range, lint::lint};
+
+/// Values are shortened to this many characters in hovers and symbol details.
+const PREVIEW_LENGTH: usize = 40;
+
+/// `mason lsp`: run a language server on stdin and stdout. It reports parse errors
+/// and lint warnings as diagnostics, formats documents and selections, lists the
+/// keys of documents as symbols, and describes the value under the cursor.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    if !args.is_empty() {
+        return Err("usage: mason lsp".into());
+    }
+
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        document_range_formatting_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        ..ServerCapabilities::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut server = Server {
+        connection,
+        documents: HashMap::new(),
+    };
+    server.serve()?;
+    drop(server);
+    io_threads.join()?;
+    Ok(())
+}
+
+struct Server {
+    connection: Connection,
+    /// The text of every open document.
+    documents: HashMap<Uri, String>,
+}
+
+impl Server {
+    fn serve(&mut self) -> Result<(), Box<dyn Error>> {
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.respond(request)?;
+                    self.connection.sender.send(response.into())?;
+                }
+                Message::Notification(notification) => self.notify(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn respond(&self, request: Request) -> Result<Response, Box<dyn Error>> {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            Formatting::METHOD => {
+                let (_, params) =
+                    request.extract::<DocumentFormattingParams>(Formatting::METHOD)?;
+                let edits = self
+                    .documents
+                    .get(&params.text_document.uri)
+                    .and_then(|text| format_edits(text, 0..text.len()));
+                serde_json::to_value(edits)?
+            }
+            RangeFormatting::METHOD => {
+                let (_, params) =
+                    request.extract::<DocumentRangeFormattingParams>(RangeFormatting::METHOD)?;
+                let edits = self
+                    .documents
+                    .get(&params.text_document.uri)
+                    .and_then(|text| {
+                        let range =
+                            offset(text, params.range.start)..offset(text, params.range.end);
+                        format_edits(text, range)
+                    });
+                serde_json::to_value(edits)?
+            }
+            DocumentSymbolRequest::METHOD => {
+                let (_, params) =
+                    request.extract::<DocumentSymbolParams>(DocumentSymbolRequest::METHOD)?;
+                let symbols = self
+                    .documents
+                    .get(&params.text_document.uri)
+                    .and_then(|text| document_symbols(text));
+                serde_json::to_value(symbols.map(DocumentSymbolResponse::Nested))?
+            }
+            HoverRequest::METHOD => {
+                let (_, params) = request.extract::<HoverParams>(HoverRequest::METHOD)?;
+                let params = params.text_document_position_params;
+                let hover = self
+                    .documents
+                    .get(&params.text_document.uri)
+                    .and_then(|text| hover(text, offset(text, params.position)));
+                serde_json::to_value(hover)?
+            }
+            method => {
+                return Ok(Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("unsupported request {method}"),
+                ));
+            }
+        };
+        Ok(Response::new_ok(id, result))
+    }
+
+    fn notify(&mut self, notification: Notification) -> Result<(), Box<dyn Error>> {
+        let uri = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.clone(), params.text_document.text);
+                uri
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                // with full syncing, the last change contains the whole document
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(uri.clone(), change.text);
+                }
+                uri
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                params.text_document.uri
+            }
+            _ => return Ok(()),
+        };
+
+        let diagnostics = self
+            .documents
+            .get(&uri)
+            .map(|text| diagnostics(text))
+            .unwrap_or_default();
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        };
+        self.connection
+            .sender
+            .send(Notification::new(PublishDiagnostics::METHOD.to_owned(), params).into())?;
+        Ok(())
+    }
+}
+
+/// Find the parse error in `text`, or if it has none, its lint warnings.
+fn diagnostics(text: &str) -> Vec<Diagnostic> {
+    let diagnostic = |offset, severity, message| {
+        let position = position(text, offset);
+        Diagnostic {
+            range: lsp_types::Range::new(position, position),
+            severity: Some(severity),
+            source: Some("mason".to_owned()),
+            message,
+            ..Diagnostic::default()
+        }
+    };
+
+    match ParseOptions::new().parse_str(text) {
+        Ok(_) => lint(text.as_bytes())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|warning| {
+                diagnostic(
+                    warning.position.offset,
+                    DiagnosticSeverity::WARNING,
+                    warning.message,
+                )
+            })
+            .collect(),
+        Err(err) => {
+            let offset = err
+                .position()
+                .map_or(text.len(), |position| position.offset);
+            vec![diagnostic(
+                offset,
+                DiagnosticSeverity::ERROR,
+                err.message().to_owned(),
+            )]
+        }
+    }
+}
+
+/// Format `range` of `text`, or None if it is not a valid document.
+fn format_edits(text: &str, range: Range<usize>) -> Option<Vec<TextEdit>> {
+    let edit = format_range(text, range).ok()?;
+    if edit.is_empty() {
+        return Some(Vec::new());
+    }
+    let range = lsp_types::Range::new(
+        position(text, edit.range.start),
+        position(text, edit.range.end),
+    );
+    Some(vec![TextEdit::new(range, edit.new_text)])
+}
+
+/// The keys and array elements of `text` as a tree of symbols, or None if it is not
+/// a valid document.
+fn document_symbols(text: &str) -> Option<Vec<DocumentSymbol>> {
+    let parsed = ParseOptions::new()
+        .capture_spans(true)
+        .parse_str(text)
+        .ok()?;
+    Some(symbols(text, &parsed.spans, &parsed.value, ""))
+}
+
+/// The symbols of the elements of `value`, which is at `path`, in the order they
+/// appear in `text`.
+fn symbols(text: &str, spans: &Spans, value: &Value, path: &str) -> Vec<DocumentSymbol> {
+    let mut symbols: Vec<_> = children(value, path)
+        .into_iter()
+        .filter_map(|(name, path, child)| {
+            let range = range(text, spans.get(&path)?);
+            #[allow(deprecated)]
+            let symbol = DocumentSymbol {
+                name,
+                detail: Some(preview(child)),
+                kind: symbol_kind(child),
+                tags: None,
+                deprecated: None,
+                range,
+                selection_range: range,
+                children: Some(symbols(text, spans, child, &path)),
+            };
+            Some(symbol)
+        })
+        .collect();
+    symbols.sort_by_key(|symbol| symbol.range.start);
+    symbols
+}
+
+/// Describe the innermost value at `offset` in `text`, or None if `text` is not a
+/// valid document.
+fn hover(text: &str, offset: usize) -> Option<Hover> {
+    let parsed = ParseOptions::new()
+        .capture_spans(true)
+        .parse_str(text)
+        .ok()?;
+    let contains = |path: &str| {
+        parsed
+            .spans
+            .get(path)
+            .is_some_and(|span| span.start.offset <= offset && offset <= span.end.offset)
+    };
+
+    let mut path = String::new();
+    let mut value = &parsed.value;
+    while let Some((_, child_path, child)) = children(value, &path)
+        .into_iter()
+        .find(|(_, path, _)| contains(path))
+    {
+        path = child_path;
+        value = child;
+    }
+
+    let name = if path.is_empty() { "document" } else { &path };
+    let contents = format!(
+        "`{name}`: {}\n```mason\n{}\n```",
+        value.kind(),
+        preview(value)
+    );
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: contents,
+        }),
+        range: parsed.spans.get(&path).map(|span| range(text, span)),
+    })
+}
+
+/// The spans of the values in a document, by path, see [`mason_rs::Parsed::spans`].
+type Spans = HashMap<String, Range<mason_rs::Position>>;
+
+/// The names, paths and values of the elements of `value`, which is at `path`.
+/// The elements of a tagged value are the elements of the value it tags.
+fn children<'a>(value: &'a Value, path: &str) -> Vec<(String, String, &'a Value)> {
+    let child_path = |key: &str| {
+        if path.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+    match value {
+        Value::Object(object) => object
+            .iter()
+            .map(|(key, child)| (key.to_string(), child_path(key), child))
+            .collect(),
+        Value::Array(array) => array
+            .iter()
+            .enumerate()
+            .map(|(i, child)| (i.to_string(), child_path(&i.to_string()), child))
+            .collect(),
+        Value::Tagged(_, value) => children(value, path),
+        _ => Vec::new(),
+    }
+}
+
+fn symbol_kind(value: &Value) -> SymbolKind {
+    match value {
+        Value::Object(_) => SymbolKind::OBJECT,
+        Value::Array(_) => SymbolKind::ARRAY,
+        Value::String(_) | Value::ByteString(_) => SymbolKind::STRING,
+        Value::Number(_) => SymbolKind::NUMBER,
+        Value::Bool(_) => SymbolKind::BOOLEAN,
+        Value::Null => SymbolKind::NULL,
+        Value::Tagged(_, value) => symbol_kind(value),
+    }
+}
+
+/// `value` written as compact MASON, shortened to [`PREVIEW_LENGTH`] characters.
+fn preview(value: &Value) -> String {
+    let options = SerializeOptions::new().compact(true).braced_root(true);
+    let mut text = String::new();
+    if value.to_writer_with_options(&mut text, &options).is_err() {
+        return value.kind().to_string();
+    }
+    match text.char_indices().nth(PREVIEW_LENGTH) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    }
+}
+
+/// Convert a span of `text` to a range.
+fn range(text: &str, span: &Range<mason_rs::Position>) -> lsp_types::Range {
+    lsp_types::Range::new(
+        position(text, span.start.offset),
+        position(text, span.end.offset),
+    )
+}
+
+/// Convert a byte offset in `text` to a position, where the character is counted in
+/// UTF-16 code units, like the protocol uses by default.
+fn position(text: &str, offset: usize) -> Position {
+    let offset = offset.min(text.len());
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position::new(
+        before.matches('\n').count() as u32,
+        before[line_start..].encode_utf16().count() as u32,
+    )
+}
+
+/// Convert a position to a byte offset in `text`, clamped to the end of its line.
+fn offset(text: &str, position: Position) -> usize {
+    let line_start = text
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum::<usize>();
+    let line = text[line_start..].split('\n').next().unwrap_or_default();
+
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= position.character as usize {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    line_start + line.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_symbols() {
+        let text = "name: \"mason\"\nserver: {port: 80, hosts: [\"a\", \"é\"]}\n";
+        let symbols = document_symbols(text).unwrap();
+        let names: Vec<_> = symbols.iter().map(|symbol| symbol.name.as_str()).collect();
+        assert_eq!(names, ["name", "server"]);
+        assert_eq!(symbols[1].kind, SymbolKind::OBJECT);
+        assert_eq!(symbols[1].range.start, Position::new(1, 8));
+
+        let server = symbols[1].children.as_ref().unwrap();
+        let names: Vec<_> = server.iter().map(|symbol| symbol.name.as_str()).collect();
+        assert_eq!(names, ["port", "hosts"]);
+        assert_eq!(server[0].detail.as_deref(), Some("80"));
+        let hosts = server[1].children.as_ref().unwrap();
+        assert_eq!(hosts[1].name, "1");
+        assert_eq!(
+            hosts[1].range,
+            lsp_types::Range::new(Position::new(1, 32), Position::new(1, 35))
+        );
+
+        assert!(document_symbols("a: [1").is_none());
+    }
+
+    #[test]
+    fn test_hover() {
+        let contents = |text: &str, offset| match hover(text, offset).unwrap().contents {
+            HoverContents::Markup(markup) => markup.value,
+            contents => panic!("unexpected contents {contents:?}"),
+        };
+
+        let text = "a: {b: [1, true]}\nc: \"x\"";
+        assert_eq!(contents(text, 8), "`a.b.0`: number\n```mason\n1\n```");
+        assert_eq!(contents(text, 12), "`a.b.1`: boolean\n```mason\ntrue\n```");
+        assert_eq!(contents(text, 22), "`c`: string\n```mason\n\"x\"\n```");
+        assert!(contents(text, 0).starts_with("`document`: object"));
+        assert_eq!(
+            hover(text, 12).unwrap().range,
+            Some(lsp_types::Range::new(
+                Position::new(0, 11),
+                Position::new(0, 15)
+            ))
+        );
+
+        let long = format!("a: \"{}\"", "x".repeat(100));
+        assert!(contents(&long, 4).ends_with(&format!("\"{}...\n```", "x".repeat(39))));
+        assert!(hover("a: [1", 3).is_none());
+    }
+}
@@ -0,0 +1,91 @@
+//! The `mason` command line tool.
+
+mod convert;
+mod diff;
+mod explain;
+mod fmt;
+mod get;
+mod json;
+mod lint;
+#[cfg(feature = "lsp")]
+mod lsp;
+mod merge;
+mod normalize;
+mod sort;
+mod to_json;
+mod transform;
+mod typegen;
+
+use std::{
+    env,
+    io::{self, Write},
+    process::ExitCode,
+};
+
+const USAGE: &str = "\
+Usage: mason <command> [args]
+
+Commands:
+    convert [--from <f>] --to <f> [file]   Convert [file], or stdin, between mason, json, yaml, toml
+                                           and cbor (yaml, toml and cbor need features). Byte strings
+                                           are written in json as --bytes-as, like to-json
+    diff [--exit-code] <old> <new>         Print the differences between <old> and <new>, and with
+                                           --exit-code, fail if there are any
+    explain [options] <file>... <path>     Merge the files like merge, and print the value at <path>,
+                                           the file and line that set it, and the values it overrode
+    fmt [-i [--backup <s>]] [file]         Format [file], or stdin if [file] is missing or -, and print
+                                           it, or with -i, rewrite [file], after copying it to [file]<s>
+                                           with --backup. Files with comments are rejected
+    get <file> <query>                     Print the values in <file> that match <query>, one per line
+    lint <file>                            Print warnings for suspicious but valid parts of <file>
+    lsp                                    Run a language server with diagnostics, formatting, symbols
+                                           and hover on stdin and stdout (needs the lsp feature)
+    merge [options] <base> <overlay>...    Merge each <overlay> into <base>, and print the result or
+                                           write it to -o <out>. Arrays are replaced, unless
+                                           --arrays is append or by-index, and with --null-deletes,
+                                           null values in an overlay remove their key
+    normalize [file]                       Print [file], or stdin, in canonical form, with sorted keys
+                                           and normalized numbers and strings, for stable diffs
+    sort [file]                            Print [file], or stdin, with the keys of every object sorted
+    to-json [--bytes-as <f>] [file]        Print [file], or stdin, as json, with byte strings written as
+                                           array, hex, base64 (needs a feature) or lossy-string
+    transform [--json] <expr> [file]       Transform [file], or stdin, with a pipeline like
+                                           'servers[*] | select(port > 1000) | {host}', and with
+                                           --json, print json with --bytes-as like convert
+    typegen [--name <name>] [file]         Print Rust structs with serde derives for [file], or stdin";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("convert") => convert::run(&args[1..]),
+        Some("diff") => diff::run(&args[1..]),
+        Some("explain") => explain::run(&args[1..]),
+        Some("fmt") => fmt::run(&args[1..]),
+        Some("get") => get::run(&args[1..]),
+        Some("lint") => lint::run(&args[1..]),
+        #[cfg(feature = "lsp")]
+        Some("lsp") => lsp::run(&args[1..]),
+        #[cfg(not(feature = "lsp"))]
+        Some("lsp") => Err("lsp is not supported, build mason with the `lsp` feature".into()),
+        Some("merge") => merge::run(&args[1..]),
+        Some("normalize") => normalize::run(&args[1..]),
+        Some("sort") => sort::run(&args[1..]),
+        Some("to-json") => to_json::run(&args[1..]),
+        Some("transform") => transform::run(&args[1..]),
+        Some("typegen") => typegen::run(&args[1..]),
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Some(command) => Err(format!("unknown command {command:?}\n\n{USAGE}").into()),
+        None => Err(USAGE.into()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            let _ = writeln!(io::stderr(), "mason: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+    mem, slice,
+};
+
+use mason_rs::{
+    Value,
+    merge::{ArrayMerge, MergeOptions},
+};
+
+const USAGE: &str = "usage: mason merge [--arrays replace|append|by-index] [--null-deletes] [-o <out>] <base> <overlay>...";
+
+/// `mason merge [--arrays <mode>] [--null-deletes] [-o <out>] <base> <overlay>...`:
+/// merge each overlay into `base` in order, and write the result to `out`, or
+/// print it if no output file is given.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut options = MergeOptions::new();
+    let mut output = None;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(args.next().ok_or(USAGE)?),
+            _ if parse_option(arg, &mut args, &mut options, USAGE)? => {}
+            _ => paths.push(arg),
+        }
+    }
+    let Some((base, overlays)) = paths.split_first() else {
+        return Err(USAGE.into());
+    };
+    if overlays.is_empty() {
+        return Err(USAGE.into());
+    }
+
+    let mut value = Value::from_file(base)?;
+    for overlay in overlays {
+        options.merge(&mut value, Value::from_file(overlay)?);
+    }
+
+    match output {
+        Some(path) => value.write_to_file(path)?,
+        None => writeln!(io::stdout().lock(), "{value:#}")?,
+    }
+    Ok(())
+}
+
+/// Apply `arg` to `options` if it is `--arrays <mode>` or `--null-deletes`, taking
+/// the mode from `args`. Returns whether `arg` was one of them.
+pub fn parse_option(
+    arg: &str,
+    args: &mut slice::Iter<String>,
+    options: &mut MergeOptions,
+    usage: &str,
+) -> Result<bool, Box<dyn Error>> {
+    match arg {
+        "--arrays" => {
+            let arrays = match args.next().map(String::as_str) {
+                Some("replace") => ArrayMerge::Replace,
+                Some("append") => ArrayMerge::Append,
+                Some("by-index") => ArrayMerge::ByIndex,
+                Some(mode) => return Err(format!("unknown array mode {mode:?}").into()),
+                None => return Err(usage.into()),
+            };
+            *options = mem::take(options).arrays(arrays);
+        }
+        "--null-deletes" => *options = mem::take(options).null_deletes_key(true),
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
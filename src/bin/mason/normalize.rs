@@ -0,0 +1,22 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+};
+
+use mason_rs::{SerializeOptions, Value};
+
+/// `mason normalize [file]`: print `file`, or stdin if no file is given, in
+/// canonical form, so documents with the same values are printed the same way.
+/// See [`SerializeOptions::canonical`].
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let value = match args {
+        [] => Value::from_reader(io::stdin().lock())?,
+        [path] => Value::from_file(path)?,
+        _ => return Err("usage: mason normalize [file]".into()),
+    };
+
+    let mut string = String::new();
+    value.to_writer_with_options(&mut string, &SerializeOptions::canonical())?;
+    writeln!(io::stdout().lock(), "{string}")?;
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+};
+
+use mason_rs::{SerializeOptions, Value};
+
+/// `mason sort [file]`: print `file`, or stdin if no file is given, with the keys
+/// of every object sorted, so it can be diffed.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let value = match args {
+        [] => Value::from_reader(io::stdin().lock())?,
+        [path] => Value::from_file(path)?,
+        _ => return Err("usage: mason sort [file]".into()),
+    };
+
+    let mut string = String::new();
+    value.to_writer_with_options(&mut string, &SerializeOptions::new().sort_keys(true))?;
+    writeln!(io::stdout().lock(), "{string}")?;
+    Ok(())
+}
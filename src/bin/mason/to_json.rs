@@ -0,0 +1,35 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+};
+
+use mason_rs::Value;
+
+use crate::json::{BytesAs, write_json};
+
+const USAGE: &str = "usage: mason to-json [--bytes-as <format>] [file]";
+
+/// `mason to-json [--bytes-as <format>] [file]`: print `file`, or stdin if no file
+/// is given, as JSON, with byte strings written as `--bytes-as` says: array,
+/// hex, base64 or lossy-string.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut bytes_as = BytesAs::default();
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bytes-as" => bytes_as = args.next().ok_or(USAGE)?.parse()?,
+            _ => positional.push(arg),
+        }
+    }
+    let value = match positional[..] {
+        [] => Value::from_reader(io::stdin().lock())?,
+        [path] => Value::from_file(path)?,
+        _ => return Err(USAGE.into()),
+    };
+
+    let mut string = String::new();
+    write_json(&value, &mut string, 0, bytes_as)?;
+    writeln!(io::stdout().lock(), "{string}")?;
+    Ok(())
+}
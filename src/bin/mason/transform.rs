@@ -0,0 +1,348 @@
+//! `mason transform`: apply a pipeline of transformations to a document.
+//!
+//! A pipeline is a list of stages separated by `|`. Each stage takes every value
+//! produced by the previous stage and produces zero or more new values:
+//!
+//! | Stage                 | Output                                                       |
+//! |-----------------------|--------------------------------------------------------------|
+//! | `.`                   | The value itself.                                            |
+//! | `<query>`             | Every value matching the query, like `servers[*]`.           |
+//! | `{a, b: <query>}`     | An object with `a` and the first match of the query as `b`.  |
+//! |                       | Keys whose query matches nothing are left out.               |
+//! | `rename(old, new)`    | The value with the key `old` renamed to `new`.               |
+//! | `select(<condition>)` | The value, if it matches a query filter like `port > 1000`.  |
+//! | `merge(<object>)`     | The value deeply merged with a MASON object, like `{a: 1}`.  |
+//!
+//! See the `query` module of the library for the query syntax.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufReader, Write},
+    str::FromStr,
+};
+
+use mason_rs::{Map, MasonString, Query, Value, merge::merge};
+
+use crate::json::{BytesAs, write_json};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+const USAGE: &str = "usage: mason transform [--json [--bytes-as <format>]] <expr> [file]";
+
+/// `mason transform [--json [--bytes-as <format>]] <expr> [file]`: transform
+/// `file`, or stdin if no file is given, and print the results as MASON or JSON.
+/// `--bytes-as` is how byte strings are written in JSON, like in `mason to-json`.
+pub fn run(args: &[String]) -> Result<()> {
+    let mut json = false;
+    let mut bytes_as = BytesAs::default();
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--bytes-as" => bytes_as = args.next().ok_or(USAGE)?.parse()?,
+            _ => positional.push(arg),
+        }
+    }
+    let (expr, path) = match positional[..] {
+        [expr] => (expr, None),
+        [expr, path] => (expr, Some(path)),
+        _ => return Err(USAGE.into()),
+    };
+
+    let pipeline: Pipeline = expr.parse()?;
+    let value = match path {
+        Some(path) => Value::from_reader(BufReader::new(File::open(path)?))?,
+        None => Value::from_reader(io::stdin().lock())?,
+    };
+
+    let mut stdout = io::stdout().lock();
+    for value in pipeline.apply(value) {
+        if json {
+            let mut string = String::new();
+            write_json(&value, &mut string, 0, bytes_as)?;
+            writeln!(stdout, "{string}")?;
+        } else {
+            writeln!(stdout, "{value:#}")?;
+        }
+    }
+    Ok(())
+}
+
+/// A parsed transformation expression.
+#[derive(Debug)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+}
+
+#[derive(Debug)]
+enum Stage {
+    Query(Query),
+    Project(Vec<(MasonString, Query)>),
+    Rename(MasonString, MasonString),
+    Select(Query),
+    Merge(Value),
+}
+
+impl Pipeline {
+    /// Run `value` through every stage, and return the values that come out.
+    pub fn apply(&self, value: Value) -> Vec<Value> {
+        self.stages.iter().fold(vec![value], |values, stage| {
+            values
+                .into_iter()
+                .flat_map(|value| stage.apply(value))
+                .collect()
+        })
+    }
+}
+
+impl Stage {
+    fn apply(&self, value: Value) -> Vec<Value> {
+        match self {
+            Self::Query(query) => query.select(&value).into_iter().cloned().collect(),
+            Self::Project(fields) => {
+                let object: Map = fields
+                    .iter()
+                    .filter_map(|(key, query)| {
+                        let selected = query.select(&value).into_iter().next()?;
+                        Some((key.clone(), selected.clone()))
+                    })
+                    .collect();
+                vec![Value::Object(object)]
+            }
+            Self::Rename(old, new) => {
+                let mut value = value;
+                if let Some(object) = value.as_object_mut() {
+                    if let Some(renamed) = object.remove(old.as_str()) {
+                        object.insert(new.clone(), renamed);
+                    }
+                }
+                vec![value]
+            }
+            Self::Select(filter) => {
+                // The filter is written as `[?condition]`, so it applies to the
+                // elements of an array containing just the value
+                let wrapped = Value::Array(vec![value]);
+                if filter.select(&wrapped).is_empty() {
+                    return Vec::new();
+                }
+                let Value::Array(mut vec) = wrapped else {
+                    unreachable!()
+                };
+                vec.pop().into_iter().collect()
+            }
+            Self::Merge(patch) => {
+                let mut value = value;
+                merge(&mut value, patch.clone());
+                vec![value]
+            }
+        }
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = Box<dyn Error>;
+
+    fn from_str(expr: &str) -> Result<Self> {
+        let stages = split_top_level(expr, b'|')?
+            .into_iter()
+            .map(|stage| parse_stage(stage.trim()))
+            .collect::<Result<_>>()?;
+        Ok(Self { stages })
+    }
+}
+
+fn parse_stage(stage: &str) -> Result<Stage> {
+    if stage == "." {
+        return Ok(Stage::Query(Query::from_str("")?));
+    }
+    if let Some(fields) = stage.strip_prefix('{') {
+        let fields = fields
+            .strip_suffix('}')
+            .ok_or_else(|| format!("unclosed object in {stage:?}"))?;
+        return split_top_level(fields, b',')?
+            .into_iter()
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(parse_field)
+            .collect::<Result<_>>()
+            .map(Stage::Project);
+    }
+
+    let Some((function, args)) = stage.split_once('(') else {
+        return Ok(Stage::Query(Query::from_str(stage)?));
+    };
+    let args = args
+        .strip_suffix(')')
+        .ok_or_else(|| format!("unclosed parenthesis in {stage:?}"))?;
+    match function.trim() {
+        "rename" => {
+            let [old, new] = split_top_level(args, b',')?[..] else {
+                return Err(format!("rename takes two keys, got {args:?}").into());
+            };
+            Ok(Stage::Rename(
+                parse_key(old.trim())?,
+                parse_key(new.trim())?,
+            ))
+        }
+        "select" => Ok(Stage::Select(Query::from_str(&format!("[?{args}]"))?)),
+        "merge" => {
+            let patch = Value::from_str(args)?;
+            if !patch.is_object() {
+                return Err(format!("merge takes an object, got {args:?}").into());
+            }
+            Ok(Stage::Merge(patch))
+        }
+        function => Err(format!("unknown function {function:?}").into()),
+    }
+}
+
+/// Parse a projected field, which is either `key: query` or just `key`.
+fn parse_field(field: &str) -> Result<(MasonString, Query)> {
+    match split_top_level(field, b':')?[..] {
+        [key] => {
+            let query = if key.starts_with('"') {
+                Query::from_str(&format!("[{key}]"))?
+            } else {
+                Query::from_str(key)?
+            };
+            Ok((parse_key(key)?, query))
+        }
+        [key, query] => Ok((parse_key(key.trim())?, Query::from_str(query.trim())?)),
+        _ => Err(format!("invalid field {field:?}").into()),
+    }
+}
+
+/// Parse an identifier or a quoted string.
+fn parse_key(key: &str) -> Result<MasonString> {
+    if key.starts_with('"') {
+        match Value::from_str(key)? {
+            Value::String(key) => Ok(key),
+            _ => Err(format!("invalid key {key:?}").into()),
+        }
+    } else if !key.is_empty()
+        && key
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-'))
+    {
+        Ok(key.into())
+    } else {
+        Err(format!("invalid key {key:?}").into())
+    }
+}
+
+/// Split `string` at every `separator` that is not inside brackets, braces,
+/// parentheses or quotes.
+fn split_top_level(string: &str, separator: u8) -> Result<Vec<&str>> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, byte) in string.bytes().enumerate() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' | b'(' => depth += 1,
+            b']' | b'}' | b')' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| format!("unbalanced {:?} in {string:?}", byte as char))?;
+            }
+            _ if byte == separator && depth == 0 => {
+                parts.push(&string[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if in_string || depth != 0 {
+        return Err(format!("unclosed string or bracket in {string:?}").into());
+    }
+    parts.push(&string[start..]);
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform(expr: &str, value: &str) -> Vec<Value> {
+        let pipeline = Pipeline::from_str(expr).unwrap();
+        pipeline.apply(Value::from_str(value).unwrap())
+    }
+
+    fn values(values: &[&str]) -> Vec<Value> {
+        values
+            .iter()
+            .map(|value| Value::from_str(value).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_transform() {
+        let config = r#"
+        name: "app"
+        servers: [
+            {host: "a", port: 80, "tls cert": null},
+            {host: "b", port: 8080, tags: ["x", "y"]},
+        ]
+        "#;
+
+        assert_eq!(transform(".", config), values(&[config]));
+        assert_eq!(
+            transform("servers[*] | select(port > 1000) | .host", config),
+            values(&["\"b\""])
+        );
+        assert_eq!(
+            transform("servers[*] | {host, first_tag: tags[0]}", config),
+            values(&["host: \"a\"", "host: \"b\", first_tag: \"x\""])
+        );
+        assert_eq!(
+            transform(r#"servers[0] | {"tls cert", "p|x": port}"#, config),
+            values(&[r#""tls cert": null, "p|x": 80"#])
+        );
+        assert_eq!(
+            transform("servers[*] | rename(host, address) | address", config),
+            values(&["\"a\"", "\"b\""])
+        );
+        assert_eq!(
+            transform(r#"rename(name, "app name") | {"app name"}"#, config),
+            values(&[r#""app name": "app""#])
+        );
+        assert_eq!(
+            transform(
+                "merge({a: {b: 1, c: 2}, d: [3]}) | merge({a: {b: 4}, d: 5})",
+                "a: {b: 0}, d: []"
+            ),
+            values(&["a: {b: 4, c: 2}, d: 5"])
+        );
+        assert_eq!(
+            transform("servers[*] | select(missing)", config),
+            Vec::<Value>::new()
+        );
+
+        for expr in [
+            "servers[",
+            "{host",
+            "rename(host)",
+            "rename(host, [x])",
+            "merge([1])",
+            "frobnicate(1)",
+            "select(port >)",
+            "a)",
+        ] {
+            assert!(Pipeline::from_str(expr).is_err(), "{expr:?}");
+        }
+    }
+}
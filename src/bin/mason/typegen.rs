@@ -0,0 +1,33 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufReader, Write},
+};
+
+use mason_rs::{Value, typegen::generate};
+
+/// `mason typegen [--name <name>] [file]`: print Rust structs with serde derives
+/// that can hold `file`, or stdin if no file is given. The top level struct is
+/// called `name`, which is `Config` by default.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut name = "Config";
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--name" => match args.next() {
+                Some(arg) => name = arg,
+                None => return Err("--name takes a struct name".into()),
+            },
+            _ => positional.push(arg),
+        }
+    }
+    let value = match positional[..] {
+        [] => Value::from_reader(io::stdin().lock())?,
+        [path] => Value::from_reader(BufReader::new(File::open(path)?))?,
+        _ => return Err("usage: mason typegen [--name <name>] [file]".into()),
+    };
+
+    write!(io::stdout().lock(), "{}", generate(&value, name))?;
+    Ok(())
+}
@@ -0,0 +1,217 @@
+//! A C API, for embedding the parser in programs that are not written in Rust.
+//!
+//! The library and the `mason.h` header are built with
+//! [cargo-c](https://github.com/lu-zero/cargo-c), by running `cargo cbuild` or
+//! `cargo cinstall`, which enable the `capi` feature. A parsed document is an
+//! opaque `MasonValue`, and strings returned by the library must be freed with
+//! `mason_string_free`:
+//!
+//! ```c
+//! char *error = NULL;
+//! MasonValue *config = mason_parse("servers: [{host: \"localhost\"}]", &error);
+//! if (config == NULL) {
+//!     fprintf(stderr, "invalid config: %s\n", error);
+//!     mason_string_free(error);
+//!     return 1;
+//! }
+//!
+//! const MasonValue *host = mason_get(config, "servers[0].host");
+//! char *string = mason_serialize(host);
+//! printf("%s\n", string); // "localhost"
+//! mason_string_free(string);
+//! mason_free(config);
+//! ```
+
+use std::{
+    ffi::{CStr, CString, c_char},
+    ptr,
+    str::FromStr,
+};
+
+use crate::{Query, Value};
+
+/// Parse the nul-terminated MASON string `text`. The returned value must be
+/// freed with [`mason_free`].
+///
+/// If `text` is not valid MASON or not valid UTF-8, null is returned, and if
+/// `error` is not null, it is set to an error message, which must be freed with
+/// [`mason_string_free`].
+///
+/// # Safety
+///
+/// `text` must be a valid nul-terminated string, and `error` must be null or
+/// valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mason_parse(text: *const c_char, error: *mut *mut c_char) -> *mut Value {
+    if text.is_null() {
+        unsafe { set_error(error, "text is null") };
+        return ptr::null_mut();
+    }
+
+    // Safety: the caller guarantees that `text` is a valid nul-terminated string
+    let text = match unsafe { CStr::from_ptr(text) }.to_str() {
+        Ok(text) => text,
+        Err(err) => {
+            unsafe { set_error(error, &format!("text is not valid UTF-8: {err}")) };
+            return ptr::null_mut();
+        }
+    };
+
+    match Value::from_str(text) {
+        Ok(value) => Box::into_raw(Box::new(value)),
+        Err(err) => {
+            unsafe { set_error(error, &err.to_string()) };
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a value returned by [`mason_parse`]. Freeing null does nothing.
+///
+/// # Safety
+///
+/// `value` must be null or a value returned by [`mason_parse`] that has not
+/// already been freed. Values returned by [`mason_get`] must not be freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mason_free(value: *mut Value) {
+    if !value.is_null() {
+        // Safety: the caller guarantees that `value` was created by `Box::into_raw`
+        drop(unsafe { Box::from_raw(value) });
+    }
+}
+
+/// Get the first value inside `value` that is selected by the nul-terminated
+/// `query`, which uses the syntax of [`Query`]. Null is returned if nothing is
+/// selected, or if `value` or `query` are invalid.
+///
+/// The returned value belongs to `value`, so it must not be freed, and it can
+/// only be used until `value` is freed.
+///
+/// # Safety
+///
+/// `value` must be null or a valid value, and `query` must be null or a valid
+/// nul-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mason_get(value: *const Value, query: *const c_char) -> *const Value {
+    if value.is_null() || query.is_null() {
+        return ptr::null();
+    }
+
+    // Safety: the caller guarantees that `value` and `query` are valid
+    let (value, query) = unsafe { (&*value, CStr::from_ptr(query)) };
+    let Some(query) = query
+        .to_str()
+        .ok()
+        .and_then(|query| query.parse::<Query>().ok())
+    else {
+        return ptr::null();
+    };
+    query
+        .select(value)
+        .first()
+        .map_or(ptr::null(), |selected| ptr::from_ref(*selected))
+}
+
+/// Serialize `value` to a nul-terminated MASON string, which must be freed with
+/// [`mason_string_free`]. Null is returned if `value` is null, or if the MASON
+/// string would contain a nul byte.
+///
+/// # Safety
+///
+/// `value` must be null or a valid value.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mason_serialize(value: *const Value) -> *mut c_char {
+    if value.is_null() {
+        return ptr::null_mut();
+    }
+
+    // Safety: the caller guarantees that `value` is valid
+    let value = unsafe { &*value };
+    CString::new(value.to_string()).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Free a string returned by the library. Freeing null does nothing.
+///
+/// # Safety
+///
+/// `string` must be null or a string returned by the library that has not
+/// already been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mason_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        // Safety: the caller guarantees that `string` was created by `CString::into_raw`
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+/// Set `error` to a copy of `message`, if it is not null.
+///
+/// # Safety
+///
+/// `error` must be null or valid for writes.
+unsafe fn set_error(error: *mut *mut c_char, message: &str) {
+    if error.is_null() {
+        return;
+    }
+    let message = CString::new(message.replace('\0', "\\0")).unwrap_or_default();
+    // Safety: the caller guarantees that `error` is valid for writes
+    unsafe { *error = message.into_raw() };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serialize `value` and copy the string, freeing the C string.
+    unsafe fn serialize(value: *const Value) -> Option<String> {
+        let string = unsafe { mason_serialize(value) };
+        if string.is_null() {
+            return None;
+        }
+        let copy = unsafe { CStr::from_ptr(string) }
+            .to_str()
+            .unwrap()
+            .to_owned();
+        unsafe { mason_string_free(string) };
+        Some(copy)
+    }
+
+    #[test]
+    fn test_capi() {
+        unsafe {
+            let mut error = ptr::null_mut();
+            let value = mason_parse(
+                c"servers: [{host: \"a\"}, {host: \"b\"}]".as_ptr(),
+                &mut error,
+            );
+            assert!(!value.is_null());
+            assert!(error.is_null());
+
+            let host = mason_get(value, c"servers[-1].host".as_ptr());
+            assert_eq!(serialize(host).as_deref(), Some("\"b\""));
+            let hosts = mason_get(value, c"servers[*].host".as_ptr());
+            assert_eq!(serialize(hosts).as_deref(), Some("\"a\""));
+            assert!(mason_get(value, c"missing".as_ptr()).is_null());
+            assert!(mason_get(value, c"servers[".as_ptr()).is_null());
+            assert!(mason_get(ptr::null(), c"servers".as_ptr()).is_null());
+            assert!(mason_get(value, ptr::null()).is_null());
+
+            let servers = Value::from_str("[{host: \"a\"}, {host: \"b\"}]").unwrap();
+            let serialized = serialize(mason_get(value, c"servers".as_ptr())).unwrap();
+            assert_eq!(Value::from_str(&serialized).unwrap(), servers);
+            assert_eq!(serialize(ptr::null()), None);
+            mason_free(value);
+
+            let value = mason_parse(c"[1, 2".as_ptr(), &mut error);
+            assert!(value.is_null());
+            assert!(!error.is_null());
+            assert!(CStr::from_ptr(error).to_str().unwrap().contains("EOF"));
+            mason_string_free(error);
+
+            assert!(mason_parse(c"[1, 2".as_ptr(), ptr::null_mut()).is_null());
+            assert!(mason_parse(ptr::null(), ptr::null_mut()).is_null());
+            mason_free(ptr::null_mut());
+            mason_string_free(ptr::null_mut());
+        }
+    }
+}
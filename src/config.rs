@@ -0,0 +1,521 @@
+//! Load typed configuration from layers of defaults, files, environment variables
+//! and command line overrides, and report where a bad value came from.
+//!
+//! ```
+//! # use mason_rs::{Value, config::Loader};
+//! # use serde::Deserialize;
+//! # use std::str::FromStr;
+//! #
+//! #[derive(Debug, PartialEq, Deserialize)]
+//! struct Config {
+//!     server: Server,
+//! }
+//!
+//! #[derive(Debug, PartialEq, Deserialize)]
+//! struct Server {
+//!     host: String,
+//!     port: u16,
+//! }
+//!
+//! let loader = Loader::new()
+//!     .defaults(Value::from_str("server: {host: \"localhost\", port: 80}").unwrap())
+//!     .env_vars("APP", [("APP__SERVER__PORT".to_owned(), "8080".to_owned())])
+//!     .arg("server.host=example.com")
+//!     .unwrap();
+//!
+//! let config: Config = loader.extract().unwrap();
+//! assert_eq!(config.server.host, "example.com");
+//! assert_eq!(config.server.port, 8080);
+//!
+//! let loader = loader.arg("server.port=high").unwrap();
+//! let err = loader.extract::<Config>().unwrap_err();
+//! assert_eq!(err.path(), Some("server.port"));
+//! assert!(err.message().ends_with("(from override `server.port=high`)"));
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    Deserializer, Error, ErrorKind, Map, Result, SerializeOptions, Value,
+    merge::merge,
+    serialize::{Child, WriteHooks, write_indented_value_with},
+    utils::{push_key, with_key},
+};
+
+/// Where a value in a [`Loader`] came from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Source {
+    /// The defaults given to [`Loader::defaults`].
+    Defaults,
+    /// The file at this path, given to [`Loader::file`].
+    File(PathBuf),
+    /// The environment variable with this name, see [`Loader::env`].
+    Env(String),
+    /// This command line override, given to [`Loader::arg`] or [`Loader::set`].
+    Override(String),
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Defaults => f.write_str("defaults"),
+            Self::File(path) => write!(f, "file `{}`", path.display()),
+            Self::Env(name) => write!(f, "environment variable `{name}`"),
+            Self::Override(arg) => write!(f, "override `{arg}`"),
+        }
+    }
+}
+
+/// Builds a configuration by merging layers on top of each other with
+/// [`merge`], and deserializes it into a typed value. Each layer overrides the
+/// values of the layers before it.
+///
+/// The loader remembers which layer set each value, so errors from
+/// [`Loader::extract`] can say where a bad value came from.
+#[derive(Debug, Clone)]
+pub struct Loader {
+    value: Value,
+    /// The source of every value that is not a non-empty object, by path.
+    sources: HashMap<String, Source>,
+}
+
+impl Default for Loader {
+    fn default() -> Self {
+        Self {
+            value: Value::Object(Map::new()),
+            sources: HashMap::new(),
+        }
+    }
+}
+
+impl Loader {
+    /// Create a loader with no layers, whose configuration is an empty object.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `value` as a layer of defaults.
+    pub fn defaults(self, value: Value) -> Self {
+        self.layer(value, &|_| Source::Defaults)
+    }
+
+    /// Add the MASON file at `path` as a layer.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the file could not be read, or if it is not valid MASON.
+    pub fn file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let value = Value::from_file(path)?;
+        Ok(self.layer(value, &|_| Source::File(path.to_owned())))
+    }
+
+    /// Add the environment variables whose names start with `prefix` followed by
+    /// `__` as a layer, see [`Loader::env_vars`].
+    pub fn env(self, prefix: &str) -> Self {
+        self.env_vars(prefix, std::env::vars())
+    }
+
+    /// Add the variables in `vars` whose names start with `prefix` followed by
+    /// `__` as a layer, like [`Loader::env`] does with the environment variables.
+    ///
+    /// The rest of the name is split into keys at `__`, and the keys are
+    /// lowercased, so `APP__SERVER__PORT` sets `server.port` for the prefix
+    /// `APP`. Values are parsed like [`Loader::arg`] does.
+    pub fn env_vars(
+        mut self,
+        prefix: &str,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        let prefix = format!("{prefix}__");
+        let mut vars: Vec<_> = vars
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(&prefix))
+            .collect();
+        // sort the variables, so `APP__A` and `APP__A__B` are applied in the same order every time
+        vars.sort_unstable();
+        for (name, value) in vars {
+            let keys: Vec<String> = name[prefix.len()..]
+                .split("__")
+                .map(str::to_lowercase)
+                .collect();
+            if keys.iter().any(String::is_empty) {
+                continue;
+            }
+            self = self.set_keys(&keys, parse_override(&value), Source::Env(name));
+        }
+        self
+    }
+
+    /// Add a command line override like `server.port=8080` as a layer, which
+    /// sets the value at the path before the `=`.
+    ///
+    /// The value is parsed as MASON, so `8080` is a number and `[1, 2]` is an
+    /// array. Values that are not valid MASON, or that are key-value pairs
+    /// without braces, are used as strings, so `localhost` is a string.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`ErrorKind::Syntax`] if `arg` has no `=`.
+    pub fn arg(self, arg: &str) -> Result<Self> {
+        let Some((path, value)) = arg.split_once('=') else {
+            return Err(Error::new(
+                ErrorKind::Syntax,
+                format!("expected an override like `key=value`, got {arg:?}"),
+            ));
+        };
+        let keys: Vec<String> = path.split('.').map(str::to_owned).collect();
+        Ok(self.set_keys(
+            &keys,
+            parse_override(value),
+            Source::Override(arg.to_owned()),
+        ))
+    }
+
+    /// Add a layer that sets the value at `path`, a list of keys separated by
+    /// `.`, to `value`.
+    pub fn set(self, path: &str, value: Value) -> Self {
+        let source = Source::Override(format!("{path}={value}"));
+        let keys: Vec<String> = path.split('.').map(str::to_owned).collect();
+        self.set_keys(&keys, value, source)
+    }
+
+    /// The configuration with all the layers merged.
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Find the layer that set the value at `path`. For an object, this is the
+    /// layer that created it, if it was not created by merging several layers.
+    ///
+    /// ```
+    /// # use mason_rs::{Value, config::{Loader, Source}};
+    /// # use std::str::FromStr;
+    /// #
+    /// let loader = Loader::new()
+    ///     .defaults(Value::from_str("server: {port: 80}").unwrap())
+    ///     .arg("server.host=localhost")
+    ///     .unwrap();
+    /// assert_eq!(loader.source("server.port"), Some(&Source::Defaults));
+    /// assert_eq!(
+    ///     loader.source("server.host"),
+    ///     Some(&Source::Override("server.host=localhost".to_owned())),
+    /// );
+    /// assert_eq!(loader.source("server"), None);
+    /// ```
+    pub fn source(&self, path: &str) -> Option<&Source> {
+        let mut path = path;
+        loop {
+            if let Some(source) = self.sources.get(path) {
+                return Some(source);
+            }
+            path = path.rsplit_once('.')?.0;
+        }
+    }
+
+    /// Deserialize the configuration into a `T`.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`ErrorKind::InvalidType`] if the configuration
+    /// does not match `T`. The error has the path of the value that did not
+    /// match, and its message says which layer the value came from.
+    pub fn extract<T: DeserializeOwned>(&self) -> Result<T> {
+        let (text, paths) = write_with_paths(&self.value);
+        let mut deserializer = Deserializer::from_str(&text);
+        T::deserialize(&mut deserializer).map_err(|err| {
+            let line = deserializer.position().line;
+            let path = paths.get(line - 1).map_or("", String::as_str);
+            let message = match self.source(path) {
+                Some(source) => format!("{err} (from {source})"),
+                None => err.to_string(),
+            };
+            let err = Error::new(ErrorKind::InvalidType, message);
+            if path.is_empty() {
+                err
+            } else {
+                err.in_field(path)
+            }
+        })
+    }
+
+    /// Add a layer that sets the value at `keys` to `value`.
+    fn set_keys(self, keys: &[String], value: Value, source: Source) -> Self {
+        let overlay = keys.iter().rev().fold(value, |value, key| {
+            let mut map = Map::new();
+            map.insert(key.as_str(), value);
+            Value::Object(map)
+        });
+        self.layer(overlay, &|_| source.clone())
+    }
+
+    /// Merge `overlay` into the configuration, and record the source of each
+    /// value in it, given its path.
+    fn layer(mut self, overlay: Value, source: &dyn Fn(&str) -> Source) -> Self {
+        record_sources(&overlay, &mut String::new(), source, &mut self.sources);
+        merge(&mut self.value, overlay);
+        self
+    }
+}
+
+/// Record the source of the values in `overlay` at `path`, and forget the sources
+/// of the values they replace.
+fn record_sources(
+    overlay: &Value,
+    path: &mut String,
+    source: &dyn Fn(&str) -> Source,
+    sources: &mut HashMap<String, Source>,
+) {
+    match overlay {
+        // non-empty objects are merged, so the values in them keep their sources
+        Value::Object(map) if !map.is_empty() => {
+            sources.remove(path.as_str());
+            for (key, value) in map {
+                with_key(path, key, |path| {
+                    record_sources(value, path, source, sources)
+                });
+            }
+        }
+        _ => {
+            let prefix = format!("{path}.");
+            sources.retain(|other, _| !other.starts_with(&prefix));
+            sources.insert(path.clone(), source(path));
+        }
+    }
+}
+
+/// Parse the value of an override or environment variable, see [`Loader::arg`].
+fn parse_override(text: &str) -> Value {
+    match Value::from_str(text) {
+        Ok(Value::Object(_)) if !text.trim_start().starts_with('{') => Value::String(text.into()),
+        Ok(value) => value,
+        Err(_) => Value::String(text.into()),
+    }
+}
+
+/// Write `value` with braces and a single key-value pair or array element per
+/// line, and find the path of the value on each line. A line that ends a value
+/// gets the path of the value it ends.
+fn write_with_paths(value: &Value) -> (String, Vec<String>) {
+    let options = SerializeOptions::new()
+        .inline_arrays(false)
+        .sort_keys(true)
+        .braced_root(true);
+    let mut text = String::new();
+    let mut tracker = PathTracker::default();
+    write_indented_value_with(value, &mut text, &options, 0, false, &mut tracker)
+        .expect("writing to a String does not fail");
+    let mut paths = tracker.paths;
+    paths.mark(&text, "");
+    (text, paths.paths)
+}
+
+/// The path of the value on each line of a text that is being written.
+#[derive(Default)]
+struct LinePaths {
+    paths: Vec<String>,
+    /// The length of the text when the last line was marked.
+    counted: usize,
+}
+
+impl LinePaths {
+    /// Give the last line of `text` the path `path`.
+    fn mark(&mut self, text: &str, path: &str) {
+        let lines = text[self.counted..].matches('\n').count();
+        self.counted = text.len();
+        self.paths
+            .resize(self.paths.len().max(1) + lines, String::new());
+        if let Some(last) = self.paths.last_mut() {
+            path.clone_into(last);
+        }
+    }
+}
+
+/// Hooks that mark the lines where the values inside the written value start and
+/// end with their paths.
+#[derive(Default)]
+struct PathTracker {
+    paths: LinePaths,
+    /// The path of the value that is being written.
+    path: String,
+    /// The lengths of `path` before the values that are being written were entered.
+    lengths: Vec<usize>,
+}
+
+impl WriteHooks<String> for PathTracker {
+    fn enter(&mut self, child: &Child, w: &mut String) -> std::result::Result<bool, fmt::Error> {
+        self.lengths.push(self.path.len());
+        let key = match child.parent {
+            Value::Object(_) => child.key.unwrap_or_default(),
+            Value::Array(_) => &child.index.to_string(),
+            // the value inside a tagged value has the same path
+            _ => return Ok(false),
+        };
+        push_key(&mut self.path, key);
+        self.paths.mark(w, &self.path);
+        Ok(false)
+    }
+
+    fn exit(&mut self, parent: &Value, w: &mut String) -> fmt::Result {
+        if !matches!(parent, Value::Tagged(..)) {
+            self.paths.mark(w, &self.path);
+        }
+        if let Some(length) = self.lengths.pop() {
+            self.path.truncate(length);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        name: String,
+        server: Server,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    fn value(string: &str) -> Value {
+        Value::from_str(string).unwrap()
+    }
+
+    fn defaults() -> Loader {
+        Loader::new().defaults(value(
+            "name: \"demo\", server: {host: \"localhost\", port: 80}",
+        ))
+    }
+
+    #[test]
+    fn test_layers() {
+        let path = std::env::temp_dir().join(format!("mason-config-{}.mason", std::process::id()));
+        fs::write(&path, "server: {port: 443}, tags: [\"a\"]").unwrap();
+        let loader = defaults()
+            .file(&path)
+            .unwrap()
+            .env_vars(
+                "APP",
+                [
+                    ("APP__SERVER__HOST".to_owned(), "example.com".to_owned()),
+                    ("APP__".to_owned(), "ignored".to_owned()),
+                    ("OTHER__NAME".to_owned(), "ignored".to_owned()),
+                ],
+            )
+            .arg("tags=[\"b\", \"c\"]")
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let config: Config = loader.extract().unwrap();
+        assert_eq!(
+            config,
+            Config {
+                name: "demo".to_owned(),
+                server: Server {
+                    host: "example.com".to_owned(),
+                    port: 443,
+                },
+                tags: vec!["b".to_owned(), "c".to_owned()],
+            }
+        );
+
+        assert_eq!(loader.source("name"), Some(&Source::Defaults));
+        assert_eq!(loader.source("server.port"), Some(&Source::File(path)));
+        assert_eq!(
+            loader.source("server.host"),
+            Some(&Source::Env("APP__SERVER__HOST".to_owned()))
+        );
+        assert_eq!(
+            loader.source("tags.1"),
+            Some(&Source::Override("tags=[\"b\", \"c\"]".to_owned()))
+        );
+        assert_eq!(loader.source("missing"), None);
+    }
+
+    #[test]
+    fn test_replaced_sources() {
+        let loader = defaults()
+            .set("server", Value::from(1.0))
+            .set("server.port", Value::from(2.0));
+        assert_eq!(loader.value(), &value("name: \"demo\", server: {port: 2}"));
+        assert_eq!(loader.source("server.host"), None);
+        assert_eq!(
+            loader.source("server.port"),
+            Some(&Source::Override("server.port=2".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_parse_override() {
+        assert_eq!(parse_override("8080"), Value::from(8080.0));
+        assert_eq!(parse_override("true"), Value::Bool(true));
+        assert_eq!(parse_override("[1, 2]"), value("[1, 2]"));
+        assert_eq!(parse_override("{a: 1}"), value("a: 1"));
+        assert_eq!(parse_override("a: 1"), Value::String("a: 1".into()));
+        assert_eq!(
+            parse_override("local host"),
+            Value::String("local host".into())
+        );
+        assert_eq!(parse_override(""), Value::String("".into()));
+        assert!(defaults().arg("server.port").is_err());
+    }
+
+    #[test]
+    fn test_extract_errors() {
+        let extract = |loader: Loader| {
+            let err = loader.extract::<Config>().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidType);
+            (err.path().map(str::to_owned), err.message().to_owned())
+        };
+
+        assert_eq!(
+            extract(defaults().env_vars(
+                "APP",
+                [("APP__SERVER__PORT".to_owned(), "99999".to_owned())]
+            )),
+            (
+                Some("server.port".to_owned()),
+                "invalid type: integer `99999`, expected u16 \
+                 (from environment variable `APP__SERVER__PORT`)"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            extract(defaults().arg("tags=[\"a\", 1]").unwrap()),
+            (
+                Some("tags.1".to_owned()),
+                "invalid type: character `1`, expected string \
+                 (from override `tags=[\"a\", 1]`)"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            extract(Loader::new().defaults(value("name: \"a\", server: {host: \"a\"}"))),
+            (Some("server".to_owned()), "missing field `port`".to_owned())
+        );
+        assert_eq!(
+            extract(Loader::new().defaults(value("server: {host: \"a\", port: 1}"))),
+            (None, "missing field `name`".to_owned())
+        );
+    }
+}
@@ -0,0 +1,431 @@
+//! Conversions between [`Value`]s and Rust types without serde.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{Error, Map, MasonString, Number, Result, Value};
+
+/// A type that can be created from a [`Value`].
+///
+/// This is a lightweight alternative to serde's `Deserialize`, for users who do
+/// not want to depend on serde. It is implemented for [`Value`], booleans,
+/// numbers, strings, and for [`Option`], [`Vec`], [`Box`], [`HashMap`] and
+/// [`BTreeMap`] of types that implement it. `null` converts to `None`, and
+/// integers must be whole numbers that fit in the integer type.
+///
+/// With the `derive` feature, `FromMason` can be derived for structs with named
+/// fields, which are converted from objects. Missing fields are treated as
+/// `null`, so `Option` fields can be left out, and a field can be given a
+/// different key with `#[mason(rename = "key")]`:
+///
+/// ```ignore
+/// use mason_rs::{FromMason, ToMason, Value};
+/// use std::str::FromStr;
+///
+/// #[derive(FromMason, ToMason)]
+/// struct Server {
+///     host: String,
+///     #[mason(rename = "port-number")]
+///     port: u16,
+///     tls: Option<bool>,
+/// }
+///
+/// let value = Value::from_str("host: \"a\", port-number: 80").unwrap();
+/// let server = Server::from_mason(value).unwrap();
+/// assert_eq!((server.port, server.tls), (80, None));
+/// ```
+///
+/// Implementing it by hand is straightforward too:
+///
+/// ```
+/// # use mason_rs::{Error, FromMason, Result, Value};
+/// # use std::str::FromStr;
+/// #
+/// #[derive(Debug)]
+/// struct Port(u16);
+///
+/// impl FromMason for Port {
+///     fn from_mason(value: Value) -> Result<Self> {
+///         match u16::from_mason(value)? {
+///             0 => Err(Error::invalid_type("a port number", &Value::from(0.0))),
+///             port => Ok(Port(port)),
+///         }
+///     }
+/// }
+///
+/// let ports = Vec::<Port>::from_mason(Value::from_str("[80, 443]").unwrap()).unwrap();
+/// assert_eq!(ports[1].0, 443);
+///
+/// let err = Vec::<Port>::from_mason(Value::from_str("[80, \"443\"]").unwrap()).unwrap_err();
+/// assert_eq!(err.to_string(), "expected u16, got string in `1`");
+/// ```
+pub trait FromMason: Sized {
+    /// Convert `value` into `Self`.
+    ///
+    /// # Errors
+    ///
+    /// This function fails with [`ErrorKind::InvalidType`](crate::ErrorKind::InvalidType)
+    /// if `value` does not have the structure `Self` expects.
+    fn from_mason(value: Value) -> Result<Self>;
+}
+
+/// A type that can be converted into a [`Value`].
+///
+/// This is the counterpart to [`FromMason`], and is implemented for the same
+/// types. With the `derive` feature, it can be derived for structs with named
+/// fields, which are converted to objects.
+///
+/// ```
+/// # use mason_rs::{ToMason, Value};
+/// # use std::{collections::HashMap, str::FromStr};
+/// #
+/// let ports = HashMap::from([("http", vec![80, 8080]), ("https", vec![443])]);
+/// assert_eq!(ports.to_mason(), Value::from_str("http: [80, 8080], https: [443]").unwrap());
+/// ```
+pub trait ToMason {
+    /// Convert `self` into a [`Value`].
+    fn to_mason(&self) -> Value;
+}
+
+impl FromMason for Value {
+    fn from_mason(value: Value) -> Result<Self> {
+        Ok(value)
+    }
+}
+
+impl ToMason for Value {
+    fn to_mason(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl FromMason for bool {
+    fn from_mason(value: Value) -> Result<Self> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            value => Err(Error::invalid_type("bool", &value)),
+        }
+    }
+}
+
+impl ToMason for bool {
+    fn to_mason(&self) -> Value {
+        Value::Bool(*self)
+    }
+}
+
+impl FromMason for String {
+    #[allow(clippy::useless_conversion)]
+    fn from_mason(value: Value) -> Result<Self> {
+        match value {
+            Value::String(string) => Ok(string.into()),
+            value => Err(Error::invalid_type("a string", &value)),
+        }
+    }
+}
+
+impl FromMason for MasonString {
+    fn from_mason(value: Value) -> Result<Self> {
+        match value {
+            Value::String(string) => Ok(string),
+            value => Err(Error::invalid_type("a string", &value)),
+        }
+    }
+}
+
+impl ToMason for str {
+    fn to_mason(&self) -> Value {
+        Value::String(self.into())
+    }
+}
+
+impl ToMason for String {
+    fn to_mason(&self) -> Value {
+        self.as_str().to_mason()
+    }
+}
+
+impl ToMason for MasonString {
+    fn to_mason(&self) -> Value {
+        Value::String(self.clone())
+    }
+}
+
+impl FromMason for f64 {
+    fn from_mason(value: Value) -> Result<Self> {
+        match value {
+            Value::Number(number) => Ok(number.as_f64()),
+            value => Err(Error::invalid_type("a number", &value)),
+        }
+    }
+}
+
+impl ToMason for f64 {
+    fn to_mason(&self) -> Value {
+        Value::from(*self)
+    }
+}
+
+impl FromMason for f32 {
+    fn from_mason(value: Value) -> Result<Self> {
+        f64::from_mason(value).map(|number| number as Self)
+    }
+}
+
+impl ToMason for f32 {
+    fn to_mason(&self) -> Value {
+        Value::from(f64::from(*self))
+    }
+}
+
+macro_rules! impl_integer {
+    ($($int:ident),*) => {
+        $(
+            impl FromMason for $int {
+                fn from_mason(value: Value) -> Result<Self> {
+                    let int = match &value {
+                        Value::Number(number) => number.as_integer(),
+                        _ => None,
+                    };
+                    int.ok_or_else(|| Error::invalid_type(stringify!($int), &value))
+                }
+            }
+
+            impl ToMason for $int {
+                fn to_mason(&self) -> Value {
+                    Value::Number(Number::from(*self))
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
+);
+
+impl<T: FromMason> FromMason for Option<T> {
+    fn from_mason(value: Value) -> Result<Self> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_mason(value).map(Some),
+        }
+    }
+}
+
+impl<T: ToMason> ToMason for Option<T> {
+    fn to_mason(&self) -> Value {
+        match self {
+            Some(value) => value.to_mason(),
+            None => Value::Null,
+        }
+    }
+}
+
+impl<T: FromMason> FromMason for Box<T> {
+    fn from_mason(value: Value) -> Result<Self> {
+        T::from_mason(value).map(Self::new)
+    }
+}
+
+impl<T: ToMason + ?Sized> ToMason for Box<T> {
+    fn to_mason(&self) -> Value {
+        (**self).to_mason()
+    }
+}
+
+impl<T: ToMason + ?Sized> ToMason for &T {
+    fn to_mason(&self) -> Value {
+        (**self).to_mason()
+    }
+}
+
+impl<T: FromMason> FromMason for Vec<T> {
+    fn from_mason(value: Value) -> Result<Self> {
+        match value {
+            Value::Array(vec) => vec
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| T::from_mason(value).map_err(|err| err.in_field(i)))
+                .collect(),
+            value => Err(Error::invalid_type("an array", &value)),
+        }
+    }
+}
+
+impl<T: ToMason> ToMason for [T] {
+    fn to_mason(&self) -> Value {
+        Value::Array(self.iter().map(T::to_mason).collect())
+    }
+}
+
+impl<T: ToMason> ToMason for Vec<T> {
+    fn to_mason(&self) -> Value {
+        self.as_slice().to_mason()
+    }
+}
+
+/// Convert the entries of an object with [`FromMason`].
+fn from_object<K, V, C>(value: Value) -> Result<C>
+where
+    K: From<MasonString>,
+    V: FromMason,
+    C: FromIterator<(K, V)>,
+{
+    match value {
+        Value::Object(map) => map
+            .into_iter()
+            .map(|(key, value)| match V::from_mason(value) {
+                Ok(value) => Ok((K::from(key), value)),
+                Err(err) => Err(err.in_field(key)),
+            })
+            .collect(),
+        value => Err(Error::invalid_type("an object", &value)),
+    }
+}
+
+/// Convert the entries of a map with [`ToMason`].
+fn to_object<'a, K, V>(entries: impl Iterator<Item = (&'a K, &'a V)>) -> Value
+where
+    K: AsRef<str> + 'a,
+    V: ToMason + 'a,
+{
+    Value::Object(
+        entries
+            .map(|(key, value)| (MasonString::from(key.as_ref()), value.to_mason()))
+            .collect::<Map>(),
+    )
+}
+
+impl<K, V, S> FromMason for HashMap<K, V, S>
+where
+    K: From<MasonString> + Eq + Hash,
+    V: FromMason,
+    S: BuildHasher + Default,
+{
+    fn from_mason(value: Value) -> Result<Self> {
+        from_object(value)
+    }
+}
+
+impl<K: AsRef<str>, V: ToMason, S> ToMason for HashMap<K, V, S> {
+    fn to_mason(&self) -> Value {
+        to_object(self.iter())
+    }
+}
+
+impl<K: From<MasonString> + Ord, V: FromMason> FromMason for BTreeMap<K, V> {
+    fn from_mason(value: Value) -> Result<Self> {
+        from_object(value)
+    }
+}
+
+impl<K: AsRef<str>, V: ToMason> ToMason for BTreeMap<K, V> {
+    fn to_mason(&self) -> Value {
+        to_object(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::ErrorKind;
+
+    fn from_str<T: FromMason>(string: &str) -> Result<T> {
+        T::from_mason(Value::from_str(string).unwrap())
+    }
+
+    #[test]
+    fn test_from_mason() {
+        assert_eq!(from_str::<Vec<u8>>("[0, 255]").unwrap(), [0, 255]);
+        assert_eq!(from_str::<i64>("-0x10").unwrap(), -16);
+        assert_eq!(from_str::<f32>("1.5").unwrap(), 1.5);
+        assert_eq!(
+            from_str::<Vec<Option<bool>>>("[true, null]").unwrap(),
+            [Some(true), None]
+        );
+        assert_eq!(
+            from_str::<BTreeMap<String, String>>("a: \"b\"").unwrap(),
+            BTreeMap::from([("a".to_owned(), "b".to_owned())])
+        );
+
+        for (string, message) in [
+            ("256", "expected u8, got number"),
+            ("-1", "expected u8, got number"),
+            ("1.5", "expected u8, got number"),
+            ("\"1\"", "expected u8, got string"),
+        ] {
+            let err = from_str::<u8>(string).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidType);
+            assert_eq!(err.to_string(), message);
+        }
+
+        let err = from_str::<HashMap<String, Vec<bool>>>("a: [true], b: [false, 1]").unwrap_err();
+        assert_eq!(err.path(), Some("b.1"));
+        assert_eq!(err.message(), "expected bool, got number");
+    }
+
+    #[test]
+    fn test_to_mason() {
+        let value = BTreeMap::from([("a", vec![Some(1u8), None])]).to_mason();
+        assert_eq!(value, Value::from_str("a: [1, null]").unwrap());
+        assert_eq!("s".to_mason(), Value::String("s".into()));
+        let boxed = Box::new(true);
+        assert_eq!(boxed.to_mason(), Value::Bool(true));
+        assert_eq!(1.5f32.to_mason(), Value::from(1.5));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive() {
+        use crate::{FromMason, ToMason};
+
+        #[derive(Debug, PartialEq, FromMason, ToMason)]
+        struct Server {
+            host: String,
+            #[mason(rename = "port-number")]
+            port: u16,
+            tls: Option<bool>,
+        }
+
+        #[derive(Debug, PartialEq, FromMason, ToMason)]
+        struct Config<T> {
+            servers: Vec<Server>,
+            extra: T,
+        }
+
+        let value = Value::from_str(
+            "servers: [{host: \"a\", port-number: 80}, {host: \"b\", port-number: 443, tls: true}], extra: 1",
+        )
+        .unwrap();
+        let config = Config::<u8>::from_mason(value).unwrap();
+        assert_eq!(
+            config.servers[1],
+            Server {
+                host: "b".to_owned(),
+                port: 443,
+                tls: Some(true),
+            }
+        );
+        assert_eq!(config.extra, 1);
+
+        let value = config.to_mason();
+        assert_eq!(value["servers"][0]["port-number"], Value::from(80.0));
+        assert_eq!(value["servers"][0]["tls"], Value::Null);
+        assert_eq!(Config::from_mason(value).unwrap(), config);
+
+        let err = Server::from_mason(Value::from_str("host: \"a\"").unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "missing field \"port-number\"");
+        let value = Value::from_str("servers: [{host: 1, port-number: 1}], extra: 1").unwrap();
+        let err = Config::<u8>::from_mason(value).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected a string, got number in `servers.0.host`"
+        );
+        assert!(Server::from_mason(Value::Null).is_err());
+    }
+}
@@ -0,0 +1,298 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::BufRead,
+    mem,
+    ops::Range,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use crate::{
+    Error, ErrorKind, Position, Result,
+    deserialize::{
+        Comments, ParseOptions, ParseWarning, ParseWarningKind, StringStyle, WarningSink,
+        parse_json_sep, parse_raw_string_with_hashes, parse_sep_collecting, parse_string_literal,
+        skip_json_whitespace, skip_whitespace_collecting,
+    },
+    peek_reader::PeekReader,
+};
+
+/// State that is shared between the functions that parse a [`Value`](crate::Value).
+#[derive(Debug)]
+pub struct ParseContext {
+    /// Only set if comments should be captured.
+    comments: Option<CommentCollector>,
+    warning_sink: Option<WarningSink>,
+    /// The keys leading to the value that is currently being parsed. Only kept
+    /// track of if comments, spans or string styles are captured, or warnings are
+    /// reported.
+    path: Vec<String>,
+    /// Where each value is, by path. Only set if spans are captured.
+    spans: Option<HashMap<String, Range<Position>>>,
+    /// How strings were written, by path. Only set if string styles are captured.
+    string_styles: Option<HashMap<String, StringStyle>>,
+    /// The strings that have been interned, see [`ParseContext::intern`].
+    interned: HashSet<Arc<str>>,
+    max_depth: usize,
+    tags: bool,
+    hex_blocks: bool,
+    braced_unicode_escapes: bool,
+    json_only: bool,
+    reject_control_characters: bool,
+    reject_duplicate_keys: bool,
+    max_raw_string_hashes: usize,
+    cancel_token: Option<Arc<AtomicBool>>,
+}
+
+impl Default for ParseContext {
+    fn default() -> Self {
+        Self::new(&ParseOptions::default())
+    }
+}
+
+#[derive(Debug, Default)]
+struct CommentCollector {
+    /// Comments that have not yet been attached to a value.
+    pending: Vec<String>,
+    comments: Comments,
+}
+
+impl ParseContext {
+    pub fn new(options: &ParseOptions) -> Self {
+        Self {
+            comments: options.capture_comments.then(CommentCollector::default),
+            warning_sink: options.warning_sink.clone(),
+            path: Vec::new(),
+            spans: options.capture_spans.then(HashMap::new),
+            string_styles: options.capture_string_styles.then(HashMap::new),
+            interned: HashSet::new(),
+            max_depth: options.max_depth,
+            tags: options.tags && !options.json_only,
+            hex_blocks: options.hex_blocks && !options.json_only,
+            braced_unicode_escapes: options.braced_unicode_escapes && !options.json_only,
+            json_only: options.json_only,
+            reject_control_characters: options.reject_control_characters || options.json_only,
+            reject_duplicate_keys: options.reject_duplicate_keys,
+            max_raw_string_hashes: options.max_raw_string_hashes,
+            cancel_token: options.cancel_token.clone(),
+        }
+    }
+
+    /// Fail if parsing has been cancelled, see [`ParseOptions::cancel_token`].
+    pub fn check_cancelled(&self) -> Result<()> {
+        match &self.cancel_token {
+            Some(token) if token.load(Ordering::Relaxed) => {
+                Err(Error::new(ErrorKind::Cancelled, "parsing was cancelled"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Report a warning about the value that is currently being parsed, if
+    /// warnings are reported.
+    pub fn warn(
+        &self,
+        kind: ParseWarningKind,
+        span: Range<Position>,
+        message: impl FnOnce() -> String,
+    ) {
+        if let Some(sink) = &self.warning_sink {
+            (sink.0)(ParseWarning {
+                kind,
+                message: message(),
+                path: self.path.join("."),
+                span,
+            });
+        }
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Whether tagged values like `@date "2024-01-01"` are allowed.
+    pub fn tags(&self) -> bool {
+        self.tags
+    }
+
+    /// Whether byte strings can be written as hex blocks like `b|00ff`.
+    pub fn hex_blocks(&self) -> bool {
+        self.hex_blocks
+    }
+
+    /// Whether only JSON is allowed, see [`ParseOptions::json_only`].
+    pub fn json_only(&self) -> bool {
+        self.json_only
+    }
+
+    /// Whether duplicate keys are an error, see [`ParseOptions::reject_duplicate_keys`].
+    pub fn reject_duplicate_keys(&self) -> bool {
+        self.reject_duplicate_keys
+    }
+
+    /// Get a shared copy of `string`, which is only allocated the first time an
+    /// equal string is interned.
+    pub fn intern(&mut self, string: &str) -> Arc<str> {
+        if let Some(interned) = self.interned.get(string) {
+            return Arc::clone(interned);
+        }
+        let interned: Arc<str> = Arc::from(string);
+        self.interned.insert(Arc::clone(&interned));
+        interned
+    }
+
+    /// Skip whitespace and comments, collecting the comments if they are captured.
+    /// In JSON, comments are an error.
+    pub fn skip_whitespace<R: BufRead>(&mut self, reader: &mut PeekReader<R>) -> Result<()> {
+        if self.json_only {
+            skip_json_whitespace(reader)
+        } else {
+            skip_whitespace_collecting(reader, self.pending_comments())
+        }
+    }
+
+    /// Parse a quoted string. Unescaped control characters are an error if
+    /// [`ParseOptions::reject_control_characters`] or [`ParseOptions::json_only`] is
+    /// set, and escapes like `\u{1F600}` are an error unless
+    /// [`ParseOptions::braced_unicode_escapes`] is set.
+    pub fn parse_string<R: BufRead>(&self, reader: &mut PeekReader<R>) -> Result<String> {
+        self.parse_string_literal(reader).map(|(string, _)| string)
+    }
+
+    /// Parse a string like [`ParseContext::parse_string`], and also return the
+    /// text between the quotes as it was written.
+    pub fn parse_string_literal<R: BufRead>(
+        &self,
+        reader: &mut PeekReader<R>,
+    ) -> Result<(String, Vec<u8>)> {
+        parse_string_literal(
+            reader,
+            self.reject_control_characters,
+            self.braced_unicode_escapes,
+        )
+    }
+
+    /// Parse a raw string that starts with at most
+    /// [`ParseOptions::max_raw_string_hashes`] hashes, and return it and the
+    /// number of hashes.
+    pub fn parse_raw_string<R: BufRead>(
+        &self,
+        reader: &mut PeekReader<R>,
+    ) -> Result<(String, usize)> {
+        parse_raw_string_with_hashes(reader, self.max_raw_string_hashes)
+    }
+
+    /// Parse the separator after a value, collecting comments if they are
+    /// captured. Returns `false` if there is no valid separator. In JSON, the
+    /// separator must be a comma.
+    pub fn parse_sep<R: BufRead>(&mut self, reader: &mut PeekReader<R>) -> Result<bool> {
+        if self.json_only {
+            parse_json_sep(reader)
+        } else {
+            parse_sep_collecting(reader, self.pending_comments())
+        }
+    }
+
+    /// Where skipped comments should be put, if comments are captured.
+    pub fn pending_comments(&mut self) -> Option<&mut Vec<String>> {
+        self.comments
+            .as_mut()
+            .map(|collector| &mut collector.pending)
+    }
+
+    /// Start parsing the value with the given key or array index.
+    pub fn enter(&mut self, key: impl ToString) {
+        if self.comments.is_some()
+            || self.warning_sink.is_some()
+            || self.spans.is_some()
+            || self.string_styles.is_some()
+        {
+            self.path.push(key.to_string());
+        }
+    }
+
+    /// Record that the current value starts at `position`, if spans are captured.
+    pub fn record_start(&mut self, position: Position) {
+        if let Some(spans) = &mut self.spans {
+            spans.insert(self.path.join("."), position..position);
+        }
+    }
+
+    /// Record that the current value ends at `position`, if spans are captured.
+    pub fn record_end(&mut self, position: Position) {
+        if let Some(span) = self
+            .spans
+            .as_mut()
+            .and_then(|spans| spans.get_mut(&self.path.join(".")))
+        {
+            span.end = position;
+        }
+    }
+
+    /// Take the spans recorded by [`ParseContext::record_start`] and
+    /// [`ParseContext::record_end`].
+    pub fn take_spans(&mut self) -> HashMap<String, Range<Position>> {
+        self.spans.take().unwrap_or_default()
+    }
+
+    /// Record that the current value is a string written in the style `style`
+    /// returns, if string styles are captured.
+    pub fn record_string_style(&mut self, style: impl FnOnce() -> StringStyle) {
+        if let Some(string_styles) = &mut self.string_styles {
+            string_styles.insert(self.path.join("."), style());
+        }
+    }
+
+    /// Take the styles recorded by [`ParseContext::record_string_style`].
+    pub fn take_string_styles(&mut self) -> HashMap<String, StringStyle> {
+        self.string_styles.take().unwrap_or_default()
+    }
+
+    /// Stop parsing the value that was last entered.
+    pub fn exit(&mut self) {
+        self.path.pop();
+    }
+
+    /// Attach the pending comments to the current value as leading comments.
+    pub fn attach_leading(&mut self) {
+        if let Some(collector) = &mut self.comments {
+            if !collector.pending.is_empty() {
+                let pending = mem::take(&mut collector.pending);
+                collector
+                    .comments
+                    .entry(self.path.join("."))
+                    .leading
+                    .extend(pending);
+            }
+        }
+    }
+
+    /// Attach the pending comments to the current value as trailing comments.
+    pub fn attach_trailing(&mut self) {
+        if let Some(collector) = &mut self.comments {
+            if !collector.pending.is_empty() {
+                let pending = mem::take(&mut collector.pending);
+                collector
+                    .comments
+                    .entry(self.path.join("."))
+                    .trailing
+                    .extend(pending);
+            }
+        }
+    }
+
+    /// Forget the pending comments, as there is no value to attach them to.
+    pub fn discard_pending(&mut self) {
+        if let Some(pending) = self.pending_comments() {
+            pending.clear();
+        }
+    }
+
+    pub fn into_comments(self) -> Comments {
+        self.comments
+            .map(|collector| collector.comments)
+            .unwrap_or_default()
+    }
+}
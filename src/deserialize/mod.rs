@@ -1,27 +1,70 @@
+mod context;
+mod options;
+mod push;
+mod skip;
 mod value;
 mod whitespace;
 
-use std::io::{self, Read};
+use std::io::BufRead;
 
-use crate::{deserialize::value::parse_value, peek_reader::PeekReader, utils, value::Value};
+use crate::{
+    Error, Result,
+    deserialize::value::{ParseTarget, parse_value},
+    peek_reader::PeekReader,
+    utils,
+};
+pub(crate) use context::ParseContext;
+pub use options::{
+    Comments, ParseOptions, ParseWarning, ParseWarningKind, Parsed, StringStyle, ValueComments,
+};
+pub(crate) use options::{DEFAULT_MAX_RAW_STRING_HASHES, WarningSink};
+pub use push::PushParser;
+pub(crate) use skip::skip_value;
 pub(crate) use value::{
-    parse_byte_string, parse_identifier, parse_multi_line_string, parse_number, parse_raw_string,
-    parse_string,
+    ParsedNumber, parse_byte_string, parse_identifier, parse_multi_line_string, parse_number,
+    parse_raw_string, parse_raw_string_with_hashes, parse_string, parse_string_literal,
+};
+pub(crate) use whitespace::{
+    Separator, parse_json_sep, parse_sep, parse_sep_collecting, parse_separator,
+    skip_byte_order_mark, skip_json_whitespace, skip_whitespace, skip_whitespace_collecting,
 };
-pub(crate) use whitespace::{parse_sep, skip_whitespace};
 
-pub fn parse_document<R: Read>(reader: &mut PeekReader<R>) -> io::Result<Value> {
-    skip_whitespace(reader)?;
-    let value = parse_value(reader, 100, true)?;
-    skip_whitespace(reader)?;
+/// Create a [`PeekReader`] that reads a MASON document from `reader`. With the
+/// `encoding` feature, UTF-16 input is transcoded to UTF-8.
+#[cfg(feature = "encoding")]
+pub(crate) fn document_reader<R: BufRead>(reader: R) -> Result<PeekReader<impl BufRead>> {
+    Ok(PeekReader::from_buf_read(crate::encoding::decode(reader)?))
+}
+
+/// Create a [`PeekReader`] that reads a MASON document from `reader`. With the
+/// `encoding` feature, UTF-16 input is transcoded to UTF-8.
+#[cfg(not(feature = "encoding"))]
+pub(crate) fn document_reader<R: BufRead>(reader: R) -> Result<PeekReader<impl BufRead>> {
+    Ok(PeekReader::from_buf_read(reader))
+}
+
+pub fn parse_document<R: BufRead, V: ParseTarget>(
+    reader: &mut PeekReader<R>,
+    context: &mut ParseContext,
+) -> Result<V> {
+    parse_document_without_position(reader, context).map_err(|err| err.at(reader.position()))
+}
+
+fn parse_document_without_position<R: BufRead, V: ParseTarget>(
+    reader: &mut PeekReader<R>,
+    context: &mut ParseContext,
+) -> Result<V> {
+    skip_byte_order_mark(reader)?;
+    context.skip_whitespace(reader)?;
+    // JSON has no objects without braces
+    let top_level = !context.json_only();
+    let value = parse_value(reader, context, context.max_depth(), top_level)?;
+    context.skip_whitespace(reader)?;
     if let Some(garbage) = reader.peek()? {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!(
-                "Trailing garbage after document: {:?}",
-                utils::to_char(garbage)
-            ),
-        ));
+        return Err(Error::syntax(format!(
+            "Trailing garbage after document: {:?}",
+            utils::to_char(garbage)
+        )));
     }
     Ok(value)
 }
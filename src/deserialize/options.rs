@@ -0,0 +1,811 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    io::{BufRead, BufReader, Read},
+    ops::Range,
+    sync::{Arc, atomic::AtomicBool},
+};
+
+use crate::{
+    ArcValue, Position, Result, SerializeOptions,
+    deserialize::{self, ParseContext, parse_document},
+    peek_reader::PeekReader,
+    serialize,
+    value::Value,
+};
+
+/// Options that change how MASON is parsed into a [`Value`].
+///
+/// ```
+/// # use mason_rs::ParseOptions;
+/// #
+/// let parsed = ParseOptions::new()
+///     .capture_comments(true)
+///     .parse_str("// the port to listen on\nport: 8080")
+///     .unwrap();
+/// assert_eq!(parsed.value["port"].as_f64(), Some(8080.0));
+/// assert_eq!(parsed.comments.get("port").unwrap().leading, ["the port to listen on"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    pub(crate) capture_comments: bool,
+    pub(crate) capture_string_styles: bool,
+    pub(crate) capture_spans: bool,
+    pub(crate) max_depth: usize,
+    pub(crate) tags: bool,
+    pub(crate) hex_blocks: bool,
+    pub(crate) braced_unicode_escapes: bool,
+    pub(crate) json_only: bool,
+    pub(crate) reject_control_characters: bool,
+    pub(crate) reject_duplicate_keys: bool,
+    pub(crate) max_raw_string_hashes: usize,
+    pub(crate) cancel_token: Option<Arc<AtomicBool>>,
+    pub(crate) warning_sink: Option<WarningSink>,
+}
+
+/// The callback set with [`ParseOptions::warning_sink`].
+#[derive(Clone)]
+pub(crate) struct WarningSink(pub(crate) Arc<dyn Fn(ParseWarning) + Send + Sync>);
+
+impl fmt::Debug for WarningSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WarningSink").finish_non_exhaustive()
+    }
+}
+
+/// The default for [`ParseOptions::max_raw_string_hashes`], which is also used
+/// where no options can be given, like when deserializing with serde.
+pub(crate) const DEFAULT_MAX_RAW_STRING_HASHES: usize = 255;
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            capture_comments: false,
+            capture_string_styles: false,
+            capture_spans: false,
+            max_depth: 100,
+            tags: false,
+            hex_blocks: false,
+            braced_unicode_escapes: true,
+            json_only: false,
+            reject_control_characters: false,
+            reject_duplicate_keys: false,
+            max_raw_string_hashes: DEFAULT_MAX_RAW_STRING_HASHES,
+            cancel_token: None,
+            warning_sink: None,
+        }
+    }
+}
+
+/// The result of parsing MASON with [`ParseOptions`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Parsed {
+    /// The parsed value.
+    pub value: Value,
+    /// The comments in the document, if [`ParseOptions::capture_comments`] was set.
+    pub comments: Comments,
+    /// How the strings in the document that were not written as plain quoted
+    /// strings were written, by path, if [`ParseOptions::capture_string_styles`]
+    /// was set. Paths are like the paths of [`Comments`].
+    pub string_styles: HashMap<String, StringStyle>,
+    /// Where each value is in the document, by path, if
+    /// [`ParseOptions::capture_spans`] was set. Paths are like the paths of
+    /// [`Comments`], and the document itself has the path `""`.
+    pub spans: HashMap<String, Range<Position>>,
+}
+
+impl Parsed {
+    /// Write the parsed value as MASON into `writer`, formatted as described by
+    /// `options`, like [`Value::to_writer_with_options`]. Strings are written in
+    /// the style recorded in [`Parsed::string_styles`], as long as the string is
+    /// unchanged and the style fits where it is written. Multi-line strings can
+    /// only be kept where the string is on a line of its own.
+    ///
+    /// ```
+    /// # use mason_rs::{ParseOptions, SerializeOptions};
+    /// #
+    /// let source = "path: r\"C:\\dir\"\nemoji: \"\\u{1F643}\"\ntext: |first\n      |second";
+    /// let parsed = ParseOptions::new().capture_string_styles(true).parse_str(source).unwrap();
+    ///
+    /// let mut string = String::new();
+    /// let options = SerializeOptions::new().sort_keys(true);
+    /// parsed.to_writer_with_options(&mut string, &options).unwrap();
+    /// assert_eq!(string, "emoji: \"\\u{1F643}\"\npath: r\"C:\\dir\"\ntext: |first\n    |second");
+    /// ```
+    pub fn to_writer_with_options<W: fmt::Write>(
+        &self,
+        writer: &mut W,
+        options: &SerializeOptions,
+    ) -> fmt::Result {
+        serialize::write_styled_value(&self.value, writer, options, &self.string_styles)
+    }
+}
+
+impl ParseOptions {
+    /// Create the default options, which parse MASON the same way as
+    /// [`Value::from_reader`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Capture the comments in the document, and attach them to the values
+    /// they belong to. See [`Comments`] for details. The default is `false`.
+    pub fn capture_comments(mut self, capture_comments: bool) -> Self {
+        self.capture_comments = capture_comments;
+        self
+    }
+
+    /// Record how strings were written, so that [`Parsed::to_writer_with_options`]
+    /// can write them the same way. See [`StringStyle`] for details. The default
+    /// is `false`.
+    pub fn capture_string_styles(mut self, capture_string_styles: bool) -> Self {
+        self.capture_string_styles = capture_string_styles;
+        self
+    }
+
+    /// Record where each value starts and ends, so that a position in the document
+    /// can be mapped to the value it is in, and back. See [`Parsed::spans`] for
+    /// details. The default is `false`.
+    ///
+    /// ```
+    /// # use mason_rs::ParseOptions;
+    /// #
+    /// let parsed = ParseOptions::new()
+    ///     .capture_spans(true)
+    ///     .parse_str("name: \"mason\"\nports: [80, 443]")
+    ///     .unwrap();
+    /// let span = &parsed.spans["ports.1"];
+    /// assert_eq!((span.start.line, span.start.column), (2, 13));
+    /// assert_eq!((span.end.line, span.end.column), (2, 16));
+    /// ```
+    pub fn capture_spans(mut self, capture_spans: bool) -> Self {
+        self.capture_spans = capture_spans;
+        self
+    }
+
+    /// The maximum number of arrays and objects a value can be nested inside.
+    /// Documents that are nested deeper are rejected with
+    /// [`ErrorKind::DepthExceeded`](crate::ErrorKind::DepthExceeded). The default is 100.
+    ///
+    /// Parsing, writing and comparing a [`Value`] do not recurse, so the limit can
+    /// be raised, but note that dropping, cloning and hashing a `Value` do, and can
+    /// overflow the stack for values that are nested tens of thousands of levels deep.
+    ///
+    /// ```
+    /// # use mason_rs::{ErrorKind, ParseOptions};
+    /// #
+    /// let deep = format!("{}{}", "[".repeat(1000), "]".repeat(1000));
+    /// let err = ParseOptions::new().parse_str(&deep).unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::DepthExceeded);
+    /// assert!(ParseOptions::new().max_depth(1000).parse_str(&deep).is_ok());
+    /// ```
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Allow values to be tagged, like `@date "2024-01-01"`, which gives
+    /// applications a way to mark values as having a special type. Tagged values
+    /// are parsed as [`Value::Tagged`]. A tag is an identifier, and applies to
+    /// the value after it, which can itself be tagged. Tags are not part of
+    /// MASON, so the default is `false`.
+    ///
+    /// ```
+    /// # use mason_rs::{ParseOptions, Value};
+    /// #
+    /// let parsed = ParseOptions::new()
+    ///     .tags(true)
+    ///     .parse_str("created: @date \"2024-01-01\"")
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     parsed.value["created"],
+    ///     Value::Tagged("date".to_owned(), Box::new(Value::String("2024-01-01".into())))
+    /// );
+    /// assert_eq!(parsed.value.to_string(), "created: @date \"2024-01-01\"");
+    ///
+    /// assert!(ParseOptions::new().parse_str("created: @date \"2024-01-01\"").is_err());
+    /// ```
+    pub fn tags(mut self, tags: bool) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Allow byte strings to be written as hex blocks, where each line starts with
+    /// `b|` followed by pairs of hex digits, which can be separated by spaces. The
+    /// lines of a block are joined into one [`Value::ByteString`], so large binary
+    /// values can be split over many lines. Hex blocks are not part of MASON, so
+    /// the default is `false`. See [`SerializeOptions::hex_blocks`](crate::SerializeOptions::hex_blocks)
+    /// for writing them.
+    ///
+    /// ```
+    /// # use mason_rs::{ParseOptions, Value};
+    /// #
+    /// let source = "key: b|00010203 04050607\n     b|0809\nname: \"key\"";
+    /// let parsed = ParseOptions::new().hex_blocks(true).parse_str(source).unwrap();
+    /// assert_eq!(parsed.value["key"], Value::ByteString((0..10).collect()));
+    ///
+    /// assert!(ParseOptions::new().parse_str(source).is_err());
+    /// ```
+    pub fn hex_blocks(mut self, hex_blocks: bool) -> Self {
+        self.hex_blocks = hex_blocks;
+        self
+    }
+
+    /// Allow characters in quoted strings and keys to be escaped like in Rust, as
+    /// `\u{X}` with one to six hex digits, besides `\uXXXX` and `\UXXXXXX`. See
+    /// [`SerializeOptions::braced_unicode_escapes`](crate::SerializeOptions::braced_unicode_escapes)
+    /// for writing them. The default is `true`.
+    ///
+    /// ```
+    /// # use mason_rs::{ParseOptions, Value};
+    /// #
+    /// let source = "\"bl\\u{E5}b\\u{E6}r \\u{1FAD0}\"";
+    /// let parsed = ParseOptions::new().parse_str(source).unwrap();
+    /// assert_eq!(parsed.value, Value::String("blåbær 🫐".into()));
+    ///
+    /// assert!(ParseOptions::new().braced_unicode_escapes(false).parse_str(source).is_err());
+    /// ```
+    pub fn braced_unicode_escapes(mut self, braced_unicode_escapes: bool) -> Self {
+        self.braced_unicode_escapes = braced_unicode_escapes;
+        self
+    }
+
+    /// Only accept documents that are valid JSON, which makes the parser a strict
+    /// JSON validator. Unquoted keys, comments, newline separators, trailing
+    /// commas, raw strings, multi-line strings, byte strings, numbers that are
+    /// not written like JSON numbers (such as `0xff` or `+1`) and objects
+    /// without braces are rejected, as are unescaped control characters in strings.
+    /// Tags, hex blocks and escapes like `\u{1F600}` are not JSON either, so
+    /// [`ParseOptions::tags`], [`ParseOptions::hex_blocks`] and
+    /// [`ParseOptions::braced_unicode_escapes`] are ignored. The default is `false`.
+    ///
+    /// ```
+    /// # use mason_rs::ParseOptions;
+    /// #
+    /// let json = ParseOptions::new().json_only(true);
+    /// assert!(json.parse_str("{\"ports\": [80, 443], \"debug\": false}").is_ok());
+    ///
+    /// assert!(json.parse_str("{ports: [80, 443]}").is_err());
+    /// assert!(json.parse_str("[80\n443]").is_err());
+    /// assert!(json.parse_str("[0x50] // http").is_err());
+    /// assert!(json.parse_str("\"ports\": [80, 443]").is_err());
+    /// ```
+    pub fn json_only(mut self, json_only: bool) -> Self {
+        self.json_only = json_only;
+        self
+    }
+
+    /// Reject unescaped control characters, like a raw carriage return or escape
+    /// character, in quoted strings and keys. Newlines, tabs and null are always
+    /// rejected, but other control characters are allowed by default. Raw strings
+    /// and multi-line strings are not affected. The default is `false`.
+    ///
+    /// ```
+    /// # use mason_rs::ParseOptions;
+    /// #
+    /// let strict = ParseOptions::new().reject_control_characters(true);
+    /// assert!(strict.parse_str("\"ring \\u0007\"").is_ok());
+    ///
+    /// let err = strict.parse_str("a: 1\nb: \"ring \u{7}\"").unwrap_err();
+    /// let position = err.position().unwrap();
+    /// assert_eq!((position.line, position.column), (2, 10));
+    /// assert!(ParseOptions::new().parse_str("a: 1\nb: \"ring \u{7}\"").is_ok());
+    /// ```
+    pub fn reject_control_characters(mut self, reject_control_characters: bool) -> Self {
+        self.reject_control_characters = reject_control_characters;
+        self
+    }
+
+    /// Reject objects that contain the same key more than once with
+    /// [`ErrorKind::DuplicateKey`](crate::ErrorKind::DuplicateKey). Otherwise, the
+    /// last value of a key overwrites the earlier ones. The default is `false`.
+    ///
+    /// ```
+    /// # use mason_rs::{ErrorKind, ParseOptions, Value};
+    /// #
+    /// let parsed = ParseOptions::new().parse_str("a: 1, a: 2").unwrap();
+    /// assert_eq!(parsed.value["a"], Value::from(2.0));
+    ///
+    /// let err = ParseOptions::new()
+    ///     .reject_duplicate_keys(true)
+    ///     .parse_str("a: 1, a: 2")
+    ///     .unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::DuplicateKey);
+    /// ```
+    pub fn reject_duplicate_keys(mut self, reject_duplicate_keys: bool) -> Self {
+        self.reject_duplicate_keys = reject_duplicate_keys;
+        self
+    }
+
+    /// The maximum number of hashes a raw string, like `r#"a "quoted" string"#`,
+    /// can start with. Raw strings that start with more hashes are rejected
+    /// before the rest of the string is read. The default is 255.
+    ///
+    /// The end of a raw string is found in a single pass over the string, so
+    /// the limit only bounds how much input the start of a raw string can take up.
+    ///
+    /// ```
+    /// # use mason_rs::ParseOptions;
+    /// #
+    /// let string = r####"r###"a "## string"###"####;
+    /// assert!(ParseOptions::new().parse_str(string).is_ok());
+    /// assert!(ParseOptions::new().max_raw_string_hashes(2).parse_str(string).is_err());
+    /// ```
+    pub fn max_raw_string_hashes(mut self, max_raw_string_hashes: usize) -> Self {
+        self.max_raw_string_hashes = max_raw_string_hashes;
+        self
+    }
+
+    /// Stop parsing with [`ErrorKind::Cancelled`](crate::ErrorKind::Cancelled) once
+    /// `cancel_token` is set to `true`, so that another thread can abort the
+    /// parsing of a huge or endless input. The token is checked before every
+    /// value, so parsing stops soon after it is set, but a read from the input that
+    /// blocks is not interrupted. The default is no token.
+    ///
+    /// ```
+    /// # use mason_rs::{ErrorKind, ParseOptions};
+    /// # use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+    /// #
+    /// let cancelled = Arc::new(AtomicBool::new(false));
+    /// let options = ParseOptions::new().cancel_token(Arc::clone(&cancelled));
+    /// assert!(options.parse_str("[1, 2, 3]").is_ok());
+    ///
+    /// cancelled.store(true, Ordering::Relaxed);
+    /// let err = options.parse_str("[1, 2, 3]").unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::Cancelled);
+    /// ```
+    pub fn cancel_token(mut self, cancel_token: Arc<AtomicBool>) -> Self {
+        self.cancel_token = Some(cancel_token);
+        self
+    }
+
+    /// Call `warning_sink` with a [`ParseWarning`] for every valid, but suspicious,
+    /// part of the document, like an integer that can not be stored exactly or a
+    /// duplicate key. See [`ParseWarningKind`] for what is reported. Warnings are
+    /// reported as they are found, so a document that fails to parse can still
+    /// report warnings before the error. The default is no callback.
+    ///
+    /// ```
+    /// # use mason_rs::{ParseOptions, ParseWarningKind};
+    /// # use std::sync::{Arc, Mutex};
+    /// #
+    /// let warnings = Arc::new(Mutex::new(Vec::new()));
+    /// let sink = Arc::clone(&warnings);
+    /// let options = ParseOptions::new()
+    ///     .warning_sink(move |warning| sink.lock().unwrap().push(warning));
+    ///
+    /// options.parse_str("id: 12345678901234567891, ok: 1").unwrap();
+    /// # #[cfg(not(feature = "arbitrary_precision"))] {
+    /// let warnings = warnings.lock().unwrap();
+    /// assert_eq!(warnings.len(), 1);
+    /// assert_eq!(warnings[0].kind, ParseWarningKind::PrecisionLoss);
+    /// assert_eq!(warnings[0].path, "id");
+    /// assert_eq!(
+    ///     warnings[0].to_string(),
+    ///     "12345678901234567891 can not be stored exactly, and is rounded to 12345678901234567000 at line 1, column 5",
+    /// );
+    /// # }
+    /// ```
+    pub fn warning_sink(
+        mut self,
+        warning_sink: impl Fn(ParseWarning) + Send + Sync + 'static,
+    ) -> Self {
+        self.warning_sink = Some(WarningSink(Arc::new(warning_sink)));
+        self
+    }
+
+    /// Parse a [`Value`] from an I/O stream of MASON using these options.
+    ///
+    /// # Errors
+    ///
+    /// This function can fail if the I/O stream is not valid MASON, or if any errors were
+    /// encountered while reading from the stream.
+    pub fn parse_reader(&self, reader: impl Read) -> Result<Parsed> {
+        self.parse_peek_reader(&mut deserialize::document_reader(BufReader::new(reader))?)
+    }
+
+    /// Parse a [`Value`] from a slice of MASON bytes using these options.
+    ///
+    /// # Errors
+    ///
+    /// This function can fail if the byte slice is not valid MASON.
+    pub fn parse_slice(&self, bytes: &[u8]) -> Result<Parsed> {
+        self.parse_peek_reader(&mut deserialize::document_reader(bytes)?)
+    }
+
+    /// Parse a [`Value`] from a MASON string using these options.
+    ///
+    /// # Errors
+    ///
+    /// This function can fail if the string is not valid MASON.
+    pub fn parse_str(&self, string: &str) -> Result<Parsed> {
+        self.parse_slice(string.as_bytes())
+            .map_err(|err| err.with_source(string.as_bytes()))
+    }
+
+    /// Parse an [`ArcValue`] from an I/O stream of MASON using these options,
+    /// where equal strings, keys and tags share one allocation. Strings are interned
+    /// as they are parsed, so no [`Value`] is built first. This takes a hash lookup
+    /// per string, but uses much less memory for documents that repeat the same
+    /// strings many times.
+    ///
+    /// ```
+    /// # use mason_rs::ParseOptions;
+    /// #
+    /// let data = "[{level: \"info\"}, {level: \"info\"}, {level: \"warn\"}]";
+    /// let value = ParseOptions::new().parse_reader_interned(data.as_bytes()).unwrap();
+    /// assert!(value[0]["level"].ptr_eq(&value[1]["level"]));
+    /// assert!(!value[0]["level"].ptr_eq(&value[2]["level"]));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function can fail if the I/O stream is not valid MASON, or if any errors were
+    /// encountered while reading from the stream.
+    pub fn parse_reader_interned(&self, reader: impl Read) -> Result<ArcValue> {
+        let mut context = ParseContext::new(self);
+        parse_document(
+            &mut deserialize::document_reader(BufReader::new(reader))?,
+            &mut context,
+        )
+    }
+
+    fn parse_peek_reader<R: BufRead>(&self, reader: &mut PeekReader<R>) -> Result<Parsed> {
+        let mut context = ParseContext::new(self);
+        let value = parse_document(reader, &mut context)?;
+        Ok(Parsed {
+            value,
+            spans: context.take_spans(),
+            string_styles: context.take_string_styles(),
+            comments: context.into_comments(),
+        })
+    }
+}
+
+/// The comments in a MASON document, keyed by the path of the value they belong
+/// to. A path is a list of keys separated by `.`, where array elements are
+/// identified by their index, like in [`Value::insert_path`]. The path of the
+/// top level value is the empty string.
+///
+/// Comments before a value (or before its key) are leading comments, and comments
+/// on the same line after a value are trailing comments. Comments that do not
+/// belong to any value, such as comments at the end of an object, are discarded.
+///
+/// ```
+/// # use mason_rs::ParseOptions;
+/// #
+/// let parsed = ParseOptions::new().capture_comments(true).parse_str("
+/// // The server settings
+/// server: {
+///     /* where to listen */
+///     ports: [80, 443 // https
+///     ]
+/// }
+/// ").unwrap();
+///
+/// let comments = parsed.comments;
+/// assert_eq!(comments.get("server").unwrap().leading, ["The server settings"]);
+/// assert_eq!(comments.get("server.ports").unwrap().leading, ["where to listen"]);
+/// assert_eq!(comments.get("server.ports.1").unwrap().trailing, ["https"]);
+/// assert_eq!(comments.len(), 3);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comments {
+    comments: HashMap<String, ValueComments>,
+}
+
+/// The comments that belong to a single value. See [`Comments`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValueComments {
+    /// The comments before the value, with the comment markers and surrounding
+    /// whitespace removed.
+    pub leading: Vec<String>,
+    /// The comments on the same line after the value, with the comment markers
+    /// and surrounding whitespace removed.
+    pub trailing: Vec<String>,
+}
+
+impl Comments {
+    /// Get the comments of the value at `path`.
+    pub fn get(&self, path: &str) -> Option<&ValueComments> {
+        self.comments.get(path)
+    }
+
+    /// Iterate over the paths and comments of all values that have comments, in
+    /// arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ValueComments)> {
+        self.comments
+            .iter()
+            .map(|(path, comments)| (path.as_str(), comments))
+    }
+
+    /// The number of values that have comments.
+    pub fn len(&self) -> usize {
+        self.comments.len()
+    }
+
+    /// Returns true if no values have comments.
+    pub fn is_empty(&self) -> bool {
+        self.comments.is_empty()
+    }
+
+    pub(crate) fn entry(&mut self, path: String) -> &mut ValueComments {
+        self.comments.entry(path).or_default()
+    }
+}
+
+/// How a string was written in a MASON document, recorded with
+/// [`ParseOptions::capture_string_styles`]. Plain quoted strings without escape
+/// sequences are not recorded, since that is how strings are written by default.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum StringStyle {
+    /// A quoted string with escape sequences. This is the text between the quotes,
+    /// as it was written.
+    Escaped(String),
+    /// A raw string like `r#"..."#`, with this many hashes.
+    Raw(usize),
+    /// A multi-line string, where every line starts with `|`.
+    MultiLine,
+}
+
+/// Something valid, but suspicious, that was found while parsing a document.
+/// See [`ParseOptions::warning_sink`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ParseWarning {
+    /// What kind of problem was found.
+    pub kind: ParseWarningKind,
+    /// A description of the problem.
+    pub message: String,
+    /// The path of the value the problem was found in, like the paths of
+    /// [`Comments`].
+    pub path: String,
+    /// Where the problem is in the document.
+    pub span: Range<Position>,
+}
+
+/// The category of a [`ParseWarning`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ParseWarningKind {
+    /// An integer is too big to be stored exactly in a 64-bit float, so it was
+    /// rounded. Numbers are not rounded with the `arbitrary_precision` feature, so
+    /// this is never reported then.
+    PrecisionLoss,
+    /// A value is nested close to [`ParseOptions::max_depth`], so a slightly
+    /// deeper document would be rejected. This is reported once per document,
+    /// for the first value that is nested more than 90% of the maximum depth.
+    DeepNesting,
+    /// An object contains the same key more than once, so the earlier value is
+    /// overwritten. This is an error instead if
+    /// [`ParseOptions::reject_duplicate_keys`] is set.
+    DuplicateKey,
+}
+
+impl Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.span.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::atomic::Ordering};
+
+    use super::*;
+    use crate::ErrorKind;
+
+    fn parse_comments(string: &str) -> Comments {
+        ParseOptions::new()
+            .capture_comments(true)
+            .parse_str(string)
+            .unwrap()
+            .comments
+    }
+
+    #[test]
+    fn test_capture_comments() {
+        let comments = parse_comments("// the root\n/* really */ [1, /* two */ 2 /* still two */]");
+        assert_eq!(comments.get("").unwrap().leading, ["the root", "really"]);
+        assert_eq!(comments.get("1").unwrap().leading, ["two"]);
+        assert_eq!(comments.get("1").unwrap().trailing, ["still two"]);
+        assert_eq!(comments.len(), 2);
+
+        let comments = parse_comments(
+            "a: 1 // one\n// about b\nb /* also about b */: {c: [{d: null}] // deep\n// dangling\n}",
+        );
+        assert_eq!(comments.get("a").unwrap().trailing, ["one"]);
+        assert_eq!(
+            comments.get("b").unwrap().leading,
+            ["about b", "also about b"]
+        );
+        assert_eq!(comments.get("b.c").unwrap().trailing, ["deep"]);
+        assert_eq!(comments.len(), 3);
+
+        let comments = parse_comments("// just a string\n\"string\"");
+        assert_eq!(comments.get("").unwrap().leading, ["just a string"]);
+
+        let parsed = ParseOptions::new().parse_str("// ignored\na: 1").unwrap();
+        assert!(parsed.comments.is_empty());
+    }
+
+    #[test]
+    fn test_capture_spans() {
+        let data = "a: {b: [1, \"two\"], c: {}}\nd: @t null // comment\ne: |line\n   |line\n";
+        let parsed = ParseOptions::new()
+            .capture_spans(true)
+            .tags(true)
+            .parse_str(data)
+            .unwrap();
+        let span = |path: &str| {
+            let span = &parsed.spans[path];
+            &data[span.start.offset..span.end.offset]
+        };
+        assert_eq!(span(""), data);
+        assert_eq!(span("a"), "{b: [1, \"two\"], c: {}}");
+        assert_eq!(span("a.b"), "[1, \"two\"]");
+        assert_eq!(span("a.b.1"), "\"two\"");
+        assert_eq!(span("a.c"), "{}");
+        assert_eq!(span("d"), "@t null");
+        assert_eq!(span("e"), "|line\n   |line\n");
+        assert_eq!(parsed.spans.len(), 8);
+
+        let parsed = ParseOptions::new().parse_str("a: 1").unwrap();
+        assert!(parsed.spans.is_empty());
+    }
+
+    #[test]
+    fn test_warning_sink() {
+        fn warnings(options: ParseOptions, string: &str) -> Vec<ParseWarning> {
+            let warnings = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let sink = Arc::clone(&warnings);
+            options
+                .warning_sink(move |warning| sink.lock().unwrap().push(warning))
+                .parse_str(string)
+                .unwrap();
+            Arc::into_inner(warnings).unwrap().into_inner().unwrap()
+        }
+
+        let found = warnings(
+            ParseOptions::new(),
+            "a: {b: [1, -18446744073709551617]}\nc: [9007199254740993, 1152921504606846976, 1.5]",
+        );
+        #[cfg(not(feature = "arbitrary_precision"))]
+        {
+            let found: Vec<_> = found
+                .iter()
+                .map(|warning| {
+                    let span = &warning.span;
+                    (
+                        warning.kind,
+                        warning.path.as_str(),
+                        (span.start.line, span.start.column),
+                        (span.end.line, span.end.column),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                found,
+                [
+                    (ParseWarningKind::PrecisionLoss, "a.b.1", (1, 12), (1, 33)),
+                    (ParseWarningKind::PrecisionLoss, "c.0", (2, 5), (2, 21)),
+                ]
+            );
+        }
+        #[cfg(feature = "arbitrary_precision")]
+        assert!(found.is_empty());
+
+        // paths are the same when comments are captured
+        let found = warnings(
+            ParseOptions::new().capture_comments(true).max_depth(20),
+            "a: // deep\n[[[[[[[[[[[[[[[[[[1, 2]]]]]]]]]]]]]]]]]]",
+        );
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, ParseWarningKind::DeepNesting);
+        assert_eq!(found[0].path, format!("a{}", ".0".repeat(18)));
+        assert_eq!(
+            found[0].to_string(),
+            "value is nested 19 levels deep, close to the maximum depth of 20 at line 2, column 19"
+        );
+
+        let nested = format!("{}1{}", "[".repeat(18), "]".repeat(18));
+        assert!(warnings(ParseOptions::new().max_depth(20), &nested).is_empty());
+
+        let found = warnings(ParseOptions::new(), "a: {b: 1, c: 2,\n  b: 3}");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, ParseWarningKind::DuplicateKey);
+        assert_eq!(found[0].path, "a.b");
+        assert_eq!(
+            (found[0].span.start.line, found[0].span.start.column),
+            (2, 3)
+        );
+        assert_eq!(
+            found[0].to_string(),
+            "duplicate key \"b\" overwrites the earlier value at line 2, column 3"
+        );
+    }
+
+    #[test]
+    fn test_cancel_token() {
+        /// An endless array, which cancels parsing after some reads.
+        struct Endless {
+            reads: usize,
+            cancelled: Arc<AtomicBool>,
+        }
+
+        impl Read for Endless {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                self.reads += 1;
+                if self.reads == 100 {
+                    self.cancelled.store(true, Ordering::Relaxed);
+                }
+                let data: &[u8] = if self.reads == 1 { b"[" } else { b"1," };
+                let len = data.len().min(buf.len());
+                buf[..len].copy_from_slice(&data[..len]);
+                Ok(len)
+            }
+        }
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut endless = Endless {
+            reads: 0,
+            cancelled: Arc::clone(&cancelled),
+        };
+        let err = ParseOptions::new()
+            .cancel_token(cancelled)
+            .parse_reader(&mut endless)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Cancelled);
+        assert!(endless.reads >= 100);
+    }
+
+    #[test]
+    fn test_json_only() {
+        let json = ParseOptions::new().json_only(true).tags(true);
+        for data in [
+            "null",
+            " \"string\" ",
+            "-1.5e3",
+            "[]",
+            "{}",
+            "[1, \"a\\nb\", {\"key\": [true, null]}]",
+            "{\r\n\t\"a\": {\"b\": 0},\n\"c\": [\n1,\n2\n]\n}",
+        ] {
+            let value = json.parse_str(data).unwrap().value;
+            assert_eq!(value, Value::from_str(data).unwrap(), "{data:?}");
+        }
+
+        for data in [
+            "{a: 1}",
+            "a: 1",
+            "\"a\": 1",
+            "[1\n2]",
+            "{\"a\": 1\n\"b\": 2}",
+            "[1, 2,]",
+            "{\"a\": 1,}",
+            "// comment\nnull",
+            "[1 /* one */]",
+            "null // comment",
+            "r\"raw\"",
+            "[|multi-line\n]",
+            "b\"bytes\"",
+            "0xff",
+            "[+1]",
+            "{\"a\": .5}",
+            "01",
+            "1'000",
+            "\"escape \u{1b}\"",
+            "\"\\u{1F600}\"",
+            "{\"\\u{1F600}\": 1}",
+            "@tag 1",
+        ] {
+            assert!(json.parse_str(data).is_err(), "{data:?}");
+            // everything is valid MASON, except for the tag
+            if !data.starts_with('@') {
+                Value::from_str(data).unwrap();
+            }
+        }
+    }
+}
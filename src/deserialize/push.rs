@@ -0,0 +1,202 @@
+//! Parse a stream of MASON values from chunks of bytes as they arrive.
+
+use crate::{
+    ParseOptions, Result, Value,
+    deserialize::{skip_byte_order_mark, skip_value, skip_whitespace},
+    peek_reader::PeekReader,
+};
+
+/// A parser for a stream of MASON values that is fed bytes as they arrive, like
+/// from a non-blocking socket, instead of reading them itself.
+///
+/// The stream is a list of values separated by whitespace or comments, like
+/// `{a: 1} {a: 2}` or one value per line. Each value is parsed with the
+/// [`ParseOptions`] of the parser, except that objects must have braces, and
+/// tagged values are not supported. The stream must be UTF-8.
+///
+/// ```
+/// # use mason_rs::{PushParser, Value};
+/// # use std::str::FromStr;
+/// #
+/// let mut parser = PushParser::new();
+/// assert_eq!(parser.feed(b"{id: 1}\n{id").unwrap(), [Value::from_str("{id: 1}").unwrap()]);
+/// assert!(parser.feed(b": 2} [1, ").unwrap().len() == 1);
+/// assert!(parser.feed(b"2]").unwrap().is_empty());
+/// assert_eq!(parser.finish().unwrap(), Some(Value::from_str("[1, 2]").unwrap()));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PushParser {
+    options: ParseOptions,
+    /// The bytes that have been fed, but are not part of a returned value.
+    buffer: Vec<u8>,
+    /// Whether the start of the stream has been seen, so that a byte order mark
+    /// is only skipped there.
+    started: bool,
+}
+
+impl PushParser {
+    /// Create a parser with the default [`ParseOptions`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a parser that parses each value with `options`.
+    pub fn with_options(options: ParseOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Add `bytes` to the stream, and return the values that were completed by
+    /// them. A value at the end of the stream is only returned once something
+    /// follows it, since more digits could be added to a number, so the last value
+    /// is returned by [`PushParser::finish`].
+    ///
+    /// A value that arrives in many chunks is scanned again for every chunk, so
+    /// large values should be fed in large chunks.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the stream is not valid MASON. The positions in the
+    /// error are relative to the start of the value that is invalid. After an
+    /// error, the parser should not be used again.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<Vec<Value>> {
+        self.buffer.extend_from_slice(bytes);
+        if !self.started {
+            // the byte order mark could be split between chunks
+            if self.buffer.len() < 3 && b"\xEF\xBB\xBF".starts_with(&self.buffer) {
+                return Ok(Vec::new());
+            }
+            let mut reader = PeekReader::from_buf_read(self.buffer.as_slice());
+            skip_byte_order_mark(&mut reader)?;
+            let offset = reader.position().offset;
+            self.buffer.drain(..offset);
+            self.started = true;
+        }
+
+        let mut values = Vec::new();
+        let mut start = 0;
+        while let Some((value_start, value_end)) = self.next_value(start)? {
+            let value = self
+                .options
+                .parse_slice(&self.buffer[value_start..value_end])?
+                .value;
+            values.push(value);
+            start = value_end;
+        }
+        self.buffer.drain(..start);
+        Ok(values)
+    }
+
+    /// End the stream, and return the value at the end of it, if there is one.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the end of the stream is not a complete value.
+    pub fn finish(self) -> Result<Option<Value>> {
+        let mut reader = PeekReader::from_buf_read(self.buffer.as_slice());
+        skip_whitespace(&mut reader)?;
+        if reader.peek()?.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(self.options.parse_slice(&self.buffer)?.value))
+    }
+
+    /// Find the next complete value in the buffer after `start`, and return its
+    /// start and end, or None if the buffer ends before it is known to be complete.
+    fn next_value(&self, start: usize) -> Result<Option<(usize, usize)>> {
+        let mut reader = PeekReader::from_buf_read(&self.buffer[start..]);
+        match skip_whitespace(&mut reader) {
+            Ok(()) => {}
+            // an unclosed comment at the end of the buffer could be closed later
+            Err(_) if reader.peek()?.is_none() => return Ok(None),
+            Err(err) => return Err(err.at(reader.position())),
+        }
+        let value_start = start + reader.position().offset;
+        let rest = &self.buffer[value_start..];
+        // a lone `/` could be the start of a comment
+        if rest.is_empty() || rest == b"/" {
+            return Ok(None);
+        }
+
+        let mut reader = PeekReader::from_buf_read(rest);
+        let result = skip_value(&mut reader, self.options.max_depth, false);
+        // the value is only complete if something follows it, since a number at
+        // the end of the buffer could get more digits
+        let remaining = rest.len() - reader.position().offset;
+        match result {
+            Ok(()) if remaining > 0 => {
+                Ok(Some((value_start, value_start + reader.position().offset)))
+            }
+            Ok(()) => Ok(None),
+            // the parser looks up to two bytes ahead, so an error close to the end
+            // of the buffer could go away once more bytes arrive
+            Err(err) if remaining <= 2 || err.is_eof() => Ok(None),
+            Err(err) => Err(err.at(reader.position())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn value(string: &str) -> Value {
+        Value::from_str(string).unwrap()
+    }
+
+    #[test]
+    fn test_feed() {
+        let stream = "\u{FEFF}{a: 1, b: [true, \"x\"]} // one\n[1, 2.5e3]\n\"s\" /* two */ null r#\"raw\"# 17";
+        let expected = [
+            value("{a: 1, b: [true, \"x\"]}"),
+            value("[1, 2.5e3]"),
+            value("\"s\""),
+            value("null"),
+            value("\"raw\""),
+        ];
+
+        // the result does not depend on how the stream is split into chunks
+        for chunk_size in [1, 2, 3, 7, stream.len()] {
+            let mut parser = PushParser::new();
+            let mut values = Vec::new();
+            for chunk in stream.as_bytes().chunks(chunk_size) {
+                values.extend(parser.feed(chunk).unwrap());
+            }
+            assert_eq!(values, expected, "chunk size {chunk_size}");
+            assert_eq!(parser.finish().unwrap(), Some(value("17")));
+        }
+
+        let mut parser = PushParser::new();
+        assert!(parser.feed(b"1 // comment").unwrap().len() == 1);
+        assert_eq!(parser.finish().unwrap(), None);
+        assert_eq!(PushParser::new().finish().unwrap(), None);
+    }
+
+    #[test]
+    fn test_feed_errors() {
+        let mut parser = PushParser::new();
+        assert!(parser.feed(b"[1, 2]").unwrap().is_empty());
+        let err = parser.feed(b"] 3\n").unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::Syntax, "{err}");
+
+        let mut parser = PushParser::new();
+        assert!(parser.feed(b"nul").unwrap().is_empty());
+        assert!(parser.feed(b"x").unwrap().is_empty());
+        assert!(parser.feed(b" [1]").is_err());
+
+        // objects need braces
+        assert!(PushParser::new().feed(b"a: 1\n").is_err());
+
+        let mut parser = PushParser::new();
+        assert!(parser.feed(b"[1, ").unwrap().is_empty());
+        assert!(parser.finish().unwrap_err().is_eof());
+
+        let mut parser = PushParser::with_options(ParseOptions::new().json_only(true));
+        assert!(parser.feed(b"[1] ").unwrap().len() == 1);
+        assert!(parser.feed(b"[1,] ").is_err());
+    }
+}
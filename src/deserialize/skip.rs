@@ -0,0 +1,316 @@
+//! Validate and discard MASON values without building them.
+
+use std::io::BufRead;
+
+use crate::{
+    Error, ErrorKind, Result,
+    deserialize::{DEFAULT_MAX_RAW_STRING_HASHES, parse_number, parse_sep, skip_whitespace},
+    peek_reader::PeekReader,
+    utils,
+};
+
+/// Skip a single value, checking that it is valid MASON. Unlike
+/// [`parse_value`](super::parse_value), no strings, arrays or objects are
+/// allocated, and escape sequences and duplicate keys are not checked.
+pub fn skip_value<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    depth: usize,
+    top_level: bool,
+) -> Result<()> {
+    if depth == 0 {
+        return Err(Error::new(
+            ErrorKind::DepthExceeded,
+            "Reached maximum depth",
+        ));
+    }
+
+    let Some(first_byte) = reader.peek()? else {
+        return Err(Error::eof("Got EOF when parsing value"));
+    };
+
+    match first_byte {
+        b'{' => {
+            reader.consume(1);
+            return skip_object(reader, depth, false);
+        }
+        b'[' => return skip_array(reader, depth),
+        b'"' => {
+            reader.consume(1);
+            skip_quoted(reader, |byte| matches!(byte, b'\n' | b'\t' | b'\0'))?;
+            return skip_key_value_pairs_if_key(reader, depth, top_level);
+        }
+        b'|' => return skip_multi_line_string(reader),
+        b'r' | b'b' => match *reader.peek_n(2)? {
+            [b'r', b'"' | b'#'] => return skip_raw_string(reader),
+            [b'b', b'"'] => {
+                reader.consume(2);
+                return skip_quoted(reader, |byte| {
+                    !byte.is_ascii() || matches!(byte, b'\n' | b'\t')
+                });
+            }
+            _ => {}
+        },
+        _ => {}
+    }
+
+    if first_byte.is_ascii_digit() || matches!(first_byte, b'+' | b'-' | b'.') {
+        parse_number(reader)?;
+        return Ok(());
+    }
+
+    if !(first_byte.is_ascii_alphabetic() || first_byte == b'_') {
+        return Err(Error::syntax(format!(
+            "key identifier starts with invalid char: {:?}",
+            utils::to_char(first_byte)
+        )));
+    }
+    let identifier = reader.peek_until(|byte| !utils::is_identifier_char(byte))?;
+    let is_keyword = matches!(identifier, b"true" | b"false" | b"null");
+    let len = identifier.len();
+    reader.consume(len);
+
+    if top_level {
+        skip_whitespace(reader)?;
+        if reader.peek()? == Some(b':') {
+            return skip_object(reader, depth, true);
+        }
+    }
+    if is_keyword {
+        Ok(())
+    } else {
+        Err(Error::syntax("Malformed value"))
+    }
+}
+
+/// If we are at the top level and the string we just skipped is followed by a
+/// colon, it was the first key of an object without braces.
+fn skip_key_value_pairs_if_key<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    depth: usize,
+    top_level: bool,
+) -> Result<()> {
+    if top_level {
+        skip_whitespace(reader)?;
+        if reader.peek()? == Some(b':') {
+            return skip_object(reader, depth, true);
+        }
+    }
+    Ok(())
+}
+
+/// Skip the rest of an object. If `after_first_key` is set, the first key has
+/// already been skipped, and the object has no braces.
+fn skip_object<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    depth: usize,
+    after_first_key: bool,
+) -> Result<()> {
+    let eof_err = || Error::eof("got EOF while parsing object");
+
+    if !after_first_key {
+        skip_whitespace(reader)?;
+        match reader.peek()? {
+            Some(b'}') => {
+                reader.consume(1);
+                return Ok(());
+            }
+            Some(_) => {
+                skip_key(reader)?;
+                skip_whitespace(reader)?;
+            }
+            None => return Err(eof_err()),
+        }
+    }
+
+    loop {
+        match reader.read_byte()? {
+            Some(b':') => {}
+            Some(byte) => {
+                return Err(Error::syntax(format!(
+                    "got unexpected byte {:?} after key",
+                    utils::to_char(byte)
+                )));
+            }
+            None => return Err(eof_err()),
+        }
+        skip_whitespace(reader)?;
+
+        let is_multi_line_string = reader.peek()? == Some(b'|');
+        skip_value(reader, depth - 1, false)?;
+        let valid_sep = is_multi_line_string || parse_sep(reader)?;
+        skip_whitespace(reader)?;
+
+        let Some(next_byte) = reader.peek()? else {
+            return if after_first_key {
+                Ok(())
+            } else {
+                Err(eof_err())
+            };
+        };
+        if next_byte == b'}' {
+            reader.consume(1);
+            return Ok(());
+        } else if !valid_sep {
+            return Err(Error::syntax(format!(
+                "invalid separator {}",
+                utils::to_char(next_byte)
+            )));
+        }
+
+        skip_key(reader)?;
+        skip_whitespace(reader)?;
+    }
+}
+
+fn skip_key<R: BufRead>(reader: &mut PeekReader<R>) -> Result<()> {
+    match reader.peek()? {
+        Some(b'"') => {
+            reader.consume(1);
+            skip_quoted(reader, |byte| matches!(byte, b'\n' | b'\t' | b'\0'))
+        }
+        Some(byte) if byte.is_ascii_alphabetic() || byte == b'_' => {
+            let len = reader
+                .peek_until(|byte| !utils::is_identifier_char(byte))?
+                .len();
+            reader.consume(len);
+            Ok(())
+        }
+        Some(byte) => Err(Error::syntax(format!(
+            "key identifier starts with invalid char: {:?}",
+            utils::to_char(byte)
+        ))),
+        None => Err(Error::eof("Got EOF when parsing key")),
+    }
+}
+
+fn skip_array<R: BufRead>(reader: &mut PeekReader<R>, depth: usize) -> Result<()> {
+    let eof_err = || Error::eof("got EOF while parsing array");
+
+    reader.consume(1);
+    skip_whitespace(reader)?;
+    loop {
+        let Some(next_byte) = reader.peek()? else {
+            return Err(eof_err());
+        };
+        if next_byte == b']' {
+            reader.consume(1);
+            return Ok(());
+        }
+
+        let is_multi_line_string = next_byte == b'|';
+        skip_value(reader, depth - 1, false)?;
+        let valid_sep = is_multi_line_string || parse_sep(reader)?;
+        skip_whitespace(reader)?;
+
+        let Some(next_byte) = reader.peek()? else {
+            return Err(eof_err());
+        };
+        if !valid_sep && next_byte != b']' {
+            return Err(Error::syntax(format!(
+                "invalid separator {}",
+                utils::to_char(next_byte)
+            )));
+        }
+    }
+}
+
+/// Skip the rest of a string whose opening quote has already been consumed.
+fn skip_quoted<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    is_byte_invalid: impl Fn(u8) -> bool,
+) -> Result<()> {
+    let mut escaped = false;
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Err(Error::eof("found no unquote"));
+        }
+
+        let mut end = None;
+        for (i, byte) in buf.iter().enumerate() {
+            if is_byte_invalid(*byte) {
+                return Err(Error::syntax(format!(
+                    "got invalid value in string: {:?}",
+                    utils::to_char(*byte)
+                )));
+            } else if escaped {
+                escaped = false;
+            } else if *byte == b'\\' {
+                escaped = true;
+            } else if *byte == b'"' {
+                end = Some(i + 1);
+                break;
+            }
+        }
+
+        let consumed = end.unwrap_or(buf.len());
+        reader.consume(consumed);
+        if end.is_some() {
+            return Ok(());
+        }
+    }
+}
+
+fn skip_raw_string<R: BufRead>(reader: &mut PeekReader<R>) -> Result<()> {
+    reader.consume(1);
+    let hashes = utils::read_raw_string_start(reader, DEFAULT_MAX_RAW_STRING_HASHES)?;
+    utils::read_until_raw_string_end(reader, hashes, |_| {})
+}
+
+fn skip_multi_line_string<R: BufRead>(reader: &mut PeekReader<R>) -> Result<()> {
+    while reader.peek()? == Some(b'|') {
+        reader.skip_until(b'\n')?;
+        skip_whitespace(reader)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Value,
+        deserialize::{ParseContext, parse_document},
+    };
+
+    #[test]
+    fn test_skip_value() {
+        for data in [
+            "null",
+            "[1, 0xff, true, \"\\\"]\", r#\"]\"#, b\"\\x00\"]",
+            "{a: {b: [{}], \"c\\\"\": |multi\n |line\n}, d: -1.5e3}",
+            "a: 1\n\"b\": [2, 3]",
+            "a: |line\nb: false",
+        ] {
+            let mut reader = PeekReader::new(data.as_bytes());
+            skip_value(&mut reader, 100, true).unwrap();
+            skip_whitespace(&mut reader).unwrap();
+            assert_eq!(reader.peek().unwrap(), None, "{data:?}");
+
+            // the skipped values must be valid
+            let mut reader = PeekReader::new(data.as_bytes());
+            parse_document::<_, Value>(&mut reader, &mut ParseContext::default()).unwrap();
+        }
+
+        for data in [
+            "nul",
+            "[1 2]",
+            "{a: 1",
+            "{a 1}",
+            "\"unclosed",
+            "r##\"unclosed\"#",
+            "\"tab\tin string\"",
+            "0x",
+            "1.",
+            &"[".repeat(101),
+        ] {
+            let mut reader = PeekReader::new(data.as_bytes());
+            assert!(skip_value(&mut reader, 100, true).is_err(), "{data:?}");
+        }
+
+        let data = "[1, 2] rest";
+        let mut reader = PeekReader::new(data.as_bytes());
+        skip_value(&mut reader, 100, false).unwrap();
+        assert_eq!(reader.peek_n(5).unwrap(), b" rest");
+    }
+}
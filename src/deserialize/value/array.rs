@@ -1,73 +1,92 @@
-use std::io::{self, BufRead, Read};
+use std::io::BufRead;
 
-use super::{Value, parse_value};
-use crate::{
-    deserialize::whitespace::{parse_sep, skip_whitespace},
-    peek_reader::PeekReader,
-    utils,
-};
-
-pub fn parse_array<R: Read>(reader: &mut PeekReader<R>, depth: u8) -> io::Result<Vec<Value>> {
-    let eof_err = io::Error::new(io::ErrorKind::UnexpectedEof, "got EOF while parsing array");
+use super::{Frame, ParseTarget, Start};
+use crate::{Error, Result, deserialize::ParseContext, peek_reader::PeekReader, utils};
 
+/// Start parsing an array. If it is not empty, the first element is entered
+/// in `context`.
+pub(super) fn start_array<R: BufRead, V: ParseTarget>(
+    reader: &mut PeekReader<R>,
+    context: &mut ParseContext,
+) -> Result<Start<V>> {
     // skip opening brackets and whitespace
     if reader.read_byte()? != Some(b'[') {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "array did not start with '['",
-        ));
+        return Err(Error::syntax("array did not start with '['"));
     }
-    skip_whitespace(reader)?;
-
-    let mut array = Vec::new();
-    loop {
-        let Some(next_byte) = reader.peek()? else {
-            return Err(eof_err);
-        };
+    context.skip_whitespace(reader)?;
 
-        if next_byte == b']' {
+    match reader.peek()? {
+        Some(b']') => {
             reader.consume(1);
-            return Ok(array);
+            context.discard_pending();
+            Ok(Start::Value(V::array(Vec::new())))
         }
+        Some(_) => {
+            context.enter(0);
+            Ok(Start::Frame(Frame::Array(Vec::new())))
+        }
+        None => Err(Error::eof("got EOF while parsing array")),
+    }
+}
 
-        let parsed_multi_line_string = reader.peek()? == Some(b'|');
-        array.push(parse_value(reader, depth - 1, false)?);
-
-        let valid_sep = parsed_multi_line_string || parse_sep(reader)?;
-        skip_whitespace(reader)?;
+/// Parse what comes after an element of `array`. Returns `true` if this was
+/// the last element, otherwise the next element is entered in `context`.
+pub(super) fn continue_array<R: BufRead, V>(
+    reader: &mut PeekReader<R>,
+    context: &mut ParseContext,
+    array: &[V],
+    parsed_multi_line_string: bool,
+) -> Result<bool> {
+    let valid_sep = parsed_multi_line_string || context.parse_sep(reader)?;
+    context.attach_trailing();
+    context.exit();
+    context.skip_whitespace(reader)?;
 
-        let Some(next_byte) = reader.peek()? else {
-            return Err(eof_err);
-        };
-        if !valid_sep && next_byte != b']' {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("invalid separator {}", utils::to_char(next_byte)),
-            ));
+    let Some(next_byte) = reader.peek()? else {
+        return Err(Error::eof("got EOF while parsing array"));
+    };
+    if next_byte == b']' {
+        if valid_sep && context.json_only() {
+            return Err(Error::syntax("trailing commas are not allowed in JSON"));
         }
+        reader.consume(1);
+        context.discard_pending();
+        return Ok(true);
+    } else if !valid_sep {
+        return Err(Error::syntax(format!(
+            "invalid separator {}",
+            utils::to_char(next_byte)
+        )));
     }
+
+    context.enter(array.len());
+    Ok(false)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{Value, deserialize::value::parse_value};
 
     #[test]
     fn test_parse_array() {
         let data = "[]";
         let mut reader = PeekReader::new(data.as_bytes());
-        assert_eq!(parse_array(&mut reader, 100).unwrap(), vec![]);
+        assert_eq!(
+            parse_value::<_, Value>(&mut reader, &mut ParseContext::default(), 100, false).unwrap(),
+            Value::Array(vec![])
+        );
 
         let data = "[1, 6, false, null]";
         let mut reader = PeekReader::new(data.as_bytes());
         assert_eq!(
-            parse_array(&mut reader, 100).unwrap(),
-            vec![
-                Value::Number(1.0),
-                Value::Number(6.0),
+            parse_value::<_, Value>(&mut reader, &mut ParseContext::default(), 100, false).unwrap(),
+            Value::Array(vec![
+                Value::from(1.0),
+                Value::from(6.0),
                 Value::Bool(false),
                 Value::Null
-            ]
+            ])
         );
 
         let data = "\
@@ -78,13 +97,13 @@ mod tests {
         ]";
         let mut reader = PeekReader::new(data.as_bytes());
         assert_eq!(
-            parse_array(&mut reader, 100).unwrap(),
-            vec![
-                Value::Number(1.0),
-                Value::Number(6.0),
+            parse_value::<_, Value>(&mut reader, &mut ParseContext::default(), 100, false).unwrap(),
+            Value::Array(vec![
+                Value::from(1.0),
+                Value::from(6.0),
                 Value::Bool(false),
                 Value::Null
-            ]
+            ])
         );
     }
 }
@@ -1,65 +1,217 @@
-use std::io::{self, Read};
+use std::{io::BufRead, mem};
 
-use crate::{deserialize::whitespace::skip_whitespace, peek_reader::PeekReader, value::Value};
+use crate::{
+    Error, ErrorKind, ParseWarningKind, Result,
+    deserialize::{ParseContext, StringStyle, skip_whitespace},
+    peek_reader::PeekReader,
+};
 
 mod array;
 mod number;
 mod object;
 mod string;
+mod target;
 
-pub use array::parse_array;
-pub use number::parse_number;
-pub use object::{parse_identifier, parse_key_value_pairs_after_key, parse_object};
-pub use string::{parse_byte_string, parse_multi_line_string, parse_raw_string, parse_string};
+pub use number::{ParsedNumber, parse_mason_number, parse_number};
+pub use object::parse_identifier;
+pub use string::{
+    parse_byte_string, parse_hex_block, parse_multi_line_string, parse_raw_string,
+    parse_raw_string_with_hashes, parse_string, parse_string_literal,
+};
+pub use target::ParseTarget;
 
-pub fn parse_value<R: Read>(
+/// An array or object whose elements are being parsed, or a tag whose value is
+/// being parsed.
+enum Frame<V: ParseTarget> {
+    Tag(String),
+    Array(Vec<V>),
+    Object {
+        object: V::Object,
+        /// The key of the value that is being parsed.
+        key: String,
+        top_level: bool,
+    },
+}
+
+/// The result of parsing the start of a value.
+enum Start<V: ParseTarget> {
+    /// The value was parsed completely.
+    Value(V),
+    /// The value is a non-empty container, whose first element should be parsed
+    /// next, or a tagged value, whose inner value should be parsed next.
+    Frame(Frame<V>),
+}
+
+/// Parse a value that is nested at most `depth` levels deep.
+///
+/// Containers are kept on an explicit stack instead of being parsed recursively,
+/// so deeply nested documents can not overflow the call stack.
+pub fn parse_value<R: BufRead, V: ParseTarget>(
     reader: &mut PeekReader<R>,
-    depth: u8,
+    context: &mut ParseContext,
+    depth: usize,
     top_level: bool,
-) -> io::Result<Value> {
-    if depth == 0 {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "Reached maximum depth",
-        ));
+) -> Result<V> {
+    let mut stack: Vec<Frame<V>> = Vec::new();
+    let mut warned_depth = false;
+    loop {
+        context.check_cancelled()?;
+        if stack.len() >= depth {
+            return Err(Error::new(
+                ErrorKind::DepthExceeded,
+                "Reached maximum depth",
+            ));
+        }
+        // warn about values nested more than 90% of the maximum depth
+        if !warned_depth && stack.len() > depth - depth / 10 {
+            warned_depth = true;
+            let position = reader.position();
+            context.warn(ParseWarningKind::DeepNesting, position..position, || {
+                format!(
+                    "value is nested {} levels deep, close to the maximum depth of {depth}",
+                    stack.len()
+                )
+            });
+        }
+
+        let mut parsed_multi_line_string = reader.peek()? == Some(b'|');
+        // a tagged value starts at its tag
+        if !matches!(stack.last(), Some(Frame::Tag(_))) {
+            context.record_start(reader.position());
+        }
+        let mut value = match start_value(reader, context, top_level && stack.is_empty())? {
+            Start::Value(value) => value,
+            Start::Frame(frame) => {
+                stack.push(frame);
+                continue;
+            }
+        };
+
+        // Add the value to its container. If that was the last element of the
+        // container, the container itself is done, and must be added to its parent.
+        loop {
+            context.record_end(reader.position());
+            let Some(frame) = stack.last_mut() else {
+                return Ok(value);
+            };
+            let finished = match frame {
+                Frame::Tag(tag) => {
+                    value = V::tagged(mem::take(tag), value, context);
+                    stack.pop();
+                    continue;
+                }
+                Frame::Array(array) => {
+                    array.push(value);
+                    array::continue_array(reader, context, array, parsed_multi_line_string)?
+                }
+                Frame::Object {
+                    object,
+                    key,
+                    top_level,
+                } => {
+                    let next_key = object::continue_object(
+                        reader,
+                        context,
+                        object,
+                        mem::take(key),
+                        value,
+                        *top_level,
+                        parsed_multi_line_string,
+                    )?;
+                    match next_key {
+                        Some(next_key) => {
+                            *key = next_key;
+                            false
+                        }
+                        None => true,
+                    }
+                }
+            };
+            if !finished {
+                break;
+            }
+
+            value = match stack.pop() {
+                Some(Frame::Array(array)) => V::array(array),
+                Some(Frame::Object { object, .. }) => V::object(object),
+                Some(Frame::Tag(_)) | None => unreachable!("the stack ends with a container"),
+            };
+            parsed_multi_line_string = false;
+        }
     }
+}
 
+fn start_value<R: BufRead, V: ParseTarget>(
+    reader: &mut PeekReader<R>,
+    context: &mut ParseContext,
+    top_level: bool,
+) -> Result<Start<V>> {
     let Some(first_byte) = reader.peek()? else {
-        return Err(io::Error::new(
-            io::ErrorKind::UnexpectedEof,
-            "Got EOF when parsing value",
-        ));
+        return Err(Error::eof("Got EOF when parsing value"));
     };
 
+    // At the top level, a string or an identifier can be the first key of an
+    // object without braces, in which case the leading comments belong to its value
+    let may_be_key = first_byte == b'"' || first_byte == b'_' || first_byte.is_ascii_alphabetic();
+    if !(top_level && may_be_key) {
+        context.attach_leading();
+    }
+
     match first_byte {
-        b'{' => return Ok(Value::Object(parse_object(reader, depth)?)),
-        b'[' => return Ok(Value::Array(parse_array(reader, depth)?)),
+        b'@' if context.tags() => return Ok(Start::Frame(Frame::Tag(parse_tag(reader, context)?))),
+        b'{' => return object::start_object(reader, context),
+        b'[' => return array::start_array(reader, context),
         b'"' => {
-            let string = parse_string(reader)?;
+            let (string, literal) = context.parse_string_literal(reader)?;
             if top_level {
                 skip_whitespace(reader)?;
                 if reader.peek()? == Some(b':') {
-                    return Ok(Value::Object(parse_key_value_pairs_after_key(
-                        reader, string, depth, true,
+                    return Ok(Start::Frame(object::start_key_value_pairs_after_key(
+                        reader, context, string, true,
                     )?));
                 }
             }
-            return Ok(Value::String(string));
+            context.attach_leading();
+            if literal.contains(&b'\\') {
+                context.record_string_style(|| {
+                    StringStyle::Escaped(String::from_utf8_lossy(&literal).into_owned())
+                });
+            }
+            return Ok(Start::Value(V::string(string, context)));
         }
         b'r' => {
             if let Some([_, second_byte]) = reader.peek2()? {
                 if matches!(second_byte, b'"' | b'#') {
-                    return Ok(Value::String(parse_raw_string(reader)?));
+                    if context.json_only() {
+                        return Err(Error::syntax("raw strings are not allowed in JSON"));
+                    }
+                    context.attach_leading();
+                    let (string, hashes) = context.parse_raw_string(reader)?;
+                    context.record_string_style(|| StringStyle::Raw(hashes));
+                    return Ok(Start::Value(V::string(string, context)));
                 }
             }
         }
         b'|' => {
-            return Ok(Value::String(parse_multi_line_string(reader)?));
+            if context.json_only() {
+                return Err(Error::syntax("multi-line strings are not allowed in JSON"));
+            }
+            let string = parse_multi_line_string(reader)?;
+            context.record_string_style(|| StringStyle::MultiLine);
+            return Ok(Start::Value(V::string(string, context)));
         }
         b'b' => {
             if let Some([_, second_byte]) = reader.peek2()? {
                 if matches!(second_byte, b'"') {
-                    return Ok(Value::ByteString(parse_byte_string(reader)?));
+                    if context.json_only() {
+                        return Err(Error::syntax("byte strings are not allowed in JSON"));
+                    }
+                    context.attach_leading();
+                    return Ok(Start::Value(V::byte_string(parse_byte_string(reader)?)));
+                }
+                if second_byte == b'|' && context.hex_blocks() {
+                    context.attach_leading();
+                    return Ok(Start::Value(V::byte_string(parse_hex_block(reader)?)));
                 }
             }
         }
@@ -67,56 +219,194 @@ pub fn parse_value<R: Read>(
     }
 
     if first_byte.is_ascii_digit() || matches!(first_byte, b'+' | b'-' | b'.') {
-        Ok(Value::Number(parse_number(reader)?))
+        if context.json_only() {
+            number::check_json_number(reader)?;
+        }
+        Ok(Start::Value(V::number(parse_mason_number(
+            reader, context,
+        )?)))
     } else {
         let identifier = parse_identifier(reader)?;
         if top_level {
             skip_whitespace(reader)?;
             if reader.peek()? == Some(b':') {
-                return Ok(Value::Object(parse_key_value_pairs_after_key(
-                    reader, identifier, depth, true,
+                return Ok(Start::Frame(object::start_key_value_pairs_after_key(
+                    reader, context, identifier, true,
                 )?));
             }
         }
-        match identifier.as_str() {
-            "true" => Ok(Value::Bool(true)),
-            "false" => Ok(Value::Bool(false)),
-            "null" => Ok(Value::Null),
-            _ => Err(io::Error::new(
-                io::ErrorKind::UnexpectedEof,
-                format!("Malformed value: {identifier}"),
-            )),
-        }
+        context.attach_leading();
+        let value = match identifier.as_str() {
+            "true" => V::bool(true),
+            "false" => V::bool(false),
+            "null" => V::null(),
+            _ => return Err(Error::syntax(format!("Malformed value: {identifier}"))),
+        };
+        Ok(Start::Value(value))
+    }
+}
+
+/// Parse a tag like `@date`, and the whitespace after it.
+fn parse_tag<R: BufRead>(reader: &mut PeekReader<R>, context: &mut ParseContext) -> Result<String> {
+    if reader.read_byte()? != Some(b'@') {
+        return Err(Error::syntax("tag did not start with '@'"));
     }
+    if reader.peek()? == Some(b'"') {
+        return Err(Error::syntax("tag must be an identifier"));
+    }
+    let tag = parse_identifier(reader)?;
+    context.skip_whitespace(reader)?;
+    Ok(tag)
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-
     use super::*;
+    use crate::{Map, ParseOptions, SerializeOptions, Value};
 
     #[test]
     fn test_parse_value() {
         let data = "1";
         let mut reader = PeekReader::new(data.as_bytes());
         assert_eq!(
-            parse_value(&mut reader, 100, true).unwrap(),
-            Value::Number(1.0)
+            parse_value::<_, Value>(&mut reader, &mut ParseContext::default(), 100, true).unwrap(),
+            Value::from(1.0)
         );
 
         let data = "false";
         let mut reader = PeekReader::new(data.as_bytes());
         assert_eq!(
-            parse_value(&mut reader, 100, true).unwrap(),
+            parse_value::<_, Value>(&mut reader, &mut ParseContext::default(), 100, true).unwrap(),
             Value::Bool(false)
         );
 
         let data = "false: false";
         let mut reader = PeekReader::new(data.as_bytes());
         assert_eq!(
-            parse_value(&mut reader, 100, true).unwrap(),
-            Value::Object(HashMap::from([("false".to_owned(), Value::Bool(false))]))
+            parse_value::<_, Value>(&mut reader, &mut ParseContext::default(), 100, true).unwrap(),
+            Value::Object(Map::from([("false".to_owned(), Value::Bool(false))]))
+        );
+    }
+
+    #[test]
+    fn test_parse_tagged_value() {
+        let tagged = |tag: &str, value| Value::Tagged(tag.to_owned(), Box::new(value));
+        let parse = |data: &str| {
+            let options = ParseOptions::new().tags(true);
+            let mut reader = PeekReader::new(data.as_bytes());
+            parse_value::<_, Value>(&mut reader, &mut ParseContext::new(&options), 100, true)
+        };
+
+        assert_eq!(
+            parse("@date \"2024-01-01\"").unwrap(),
+            tagged("date", Value::String("2024-01-01".into()))
+        );
+        assert_eq!(
+            parse("@a /* comment */ @b [1]").unwrap(),
+            tagged("a", tagged("b", Value::Array(vec![Value::from(1.0)])))
+        );
+        assert_eq!(
+            parse("a: @set [@t {}]\nb: @text |line\nc: @t null").unwrap(),
+            Value::Object(Map::from([
+                (
+                    "a".to_owned(),
+                    tagged(
+                        "set",
+                        Value::Array(vec![tagged("t", Value::Object(Map::new()))])
+                    )
+                ),
+                ("b".to_owned(), tagged("text", Value::String("line".into()))),
+                ("c".to_owned(), tagged("t", Value::Null)),
+            ]))
+        );
+
+        assert!(parse("@\"tag\" 1").is_err());
+        assert!(parse("@tag").is_err());
+        assert!(parse("[@tag]").is_err());
+
+        let mut reader = PeekReader::new("@date 1".as_bytes());
+        assert!(
+            parse_value::<_, Value>(&mut reader, &mut ParseContext::default(), 100, true).is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_hex_block() {
+        let bytes = |bytes: &[u8]| Value::ByteString(bytes.to_vec());
+        let parse = |data: &str| {
+            let options = ParseOptions::new().hex_blocks(true);
+            let mut reader = PeekReader::new(data.as_bytes());
+            parse_value::<_, Value>(&mut reader, &mut ParseContext::new(&options), 100, true)
+        };
+
+        assert_eq!(parse("b|00ff 10\t2A").unwrap(), bytes(b"\x00\xff\x10\x2a"));
+        assert_eq!(parse("b|").unwrap(), bytes(b""));
+        assert_eq!(
+            parse("a: b|0102\r\n    b|03 // three\nb: [b|04\n  b|05, b|06]").unwrap(),
+            Value::Object(Map::from([
+                ("a".to_owned(), bytes(b"\x01\x02\x03")),
+                (
+                    "b".to_owned(),
+                    Value::Array(vec![bytes(b"\x04\x05"), bytes(b"\x06")])
+                ),
+            ]))
         );
+
+        // the lines of a block must be on separate lines
+        assert!(parse("[b|01 b|02]").is_err());
+        assert!(parse("b|012").is_err());
+        assert!(parse("b|0 1").is_err());
+        assert!(parse("b|0g").is_err());
+
+        let mut reader = PeekReader::new("b|00".as_bytes());
+        assert!(
+            parse_value::<_, Value>(&mut reader, &mut ParseContext::default(), 100, true).is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_deep_value() {
+        let depth = 10_000;
+        let data = format!("{}[]{}", "[{a: ".repeat(depth), "}]".repeat(depth));
+
+        let mut reader = PeekReader::new(data.as_bytes());
+        let err = parse_value::<_, Value>(&mut reader, &mut ParseContext::default(), 100, true)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DepthExceeded);
+
+        let mut reader = PeekReader::new(data.as_bytes());
+        let mut value = parse_value::<_, Value>(
+            &mut reader,
+            &mut ParseContext::default(),
+            2 * depth + 1,
+            true,
+        )
+        .unwrap();
+        assert_eq!(value.depth(), 2 * depth + 1);
+        assert_eq!(value.total_nodes(), 2 * depth + 1);
+
+        // writing and comparing do not recurse either. The indentation would grow
+        // quadratically with the depth, so it is left out.
+        let indented = SerializeOptions::new().indentation("");
+        for options in [indented, SerializeOptions::new().compact(true)] {
+            let mut string = String::new();
+            value.to_writer_with_options(&mut string, &options).unwrap();
+            let mut reader = PeekReader::new(string.as_bytes());
+            let mut context = ParseContext::default();
+            let mut parsed =
+                parse_value::<_, Value>(&mut reader, &mut context, 2 * depth + 1, true).unwrap();
+            assert!(parsed == value);
+            take_apart(&mut parsed, depth);
+        }
+
+        // dropping a value recurses, so it is taken apart first
+        take_apart(&mut value, depth);
+        assert_eq!(value, Value::Array(vec![]));
+    }
+
+    fn take_apart(value: &mut Value, depth: usize) {
+        for _ in 0..depth {
+            *value = value[0]["a"].take();
+        }
     }
 }
@@ -1,22 +1,60 @@
-use std::io::{self, BufRead, Read};
+use std::io::BufRead;
 
-use crate::{peek_reader::PeekReader, utils};
+use crate::{
+    Error, ErrorKind, Number, Result, deserialize::ParseContext, peek_reader::PeekReader, utils,
+};
 
-pub fn parse_number<R: Read>(reader: &mut PeekReader<R>) -> io::Result<f64> {
-    let eof_err = io::Error::new(io::ErrorKind::UnexpectedEof, "got EOF while parsing number");
+/// A parsed MASON number, which remembers if it was written as an integer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParsedNumber {
+    /// A non-negative integer.
+    PosInt(u128),
+    /// A negative integer.
+    NegInt(i128),
+    /// A number that is not an integer, or that does not fit in an i128 or u128.
+    Float(f64),
+}
+
+impl ParsedNumber {
+    /// Create a number from the absolute value of an integer and its sign.
+    fn from_integer(abs: u128, negative: bool) -> Self {
+        if !negative {
+            Self::PosInt(abs)
+        } else if abs == 0 {
+            // -0 is not an integer, but it is a valid float
+            Self::Float(-0.0)
+        } else if let Some(int) = 0i128.checked_sub_unsigned(abs) {
+            Self::NegInt(int)
+        } else {
+            Self::Float(-(abs as f64))
+        }
+    }
+
+    pub fn as_f64(self) -> f64 {
+        match self {
+            Self::PosInt(int) => int as f64,
+            Self::NegInt(int) => int as f64,
+            Self::Float(float) => float,
+        }
+    }
+}
+
+pub fn parse_number<R: BufRead>(reader: &mut PeekReader<R>) -> Result<ParsedNumber> {
+    let eof_err = Error::eof("got EOF while parsing number");
 
-    let mut sign = 1.0;
+    let mut negative = false;
     match reader.peek()? {
         Some(b'+') => {
             reader.consume(1);
         }
         Some(b'-') => {
             reader.consume(1);
-            sign = -1.0;
+            negative = true;
         }
         None => return Err(eof_err),
         _ => {}
     }
+    let sign = if negative { -1.0 } else { 1.0 };
 
     let Some(first_byte) = reader.peek()? else {
         return Err(eof_err);
@@ -25,7 +63,8 @@ pub fn parse_number<R: Read>(reader: &mut PeekReader<R>) -> io::Result<f64> {
     let mut base_data: Option<(f64, Box<dyn Fn(_) -> _>)> = None;
     if first_byte == b'0' {
         let Some([_, second_byte]) = reader.peek2()? else {
-            return Ok(0.0);
+            reader.consume(1);
+            return Ok(ParsedNumber::from_integer(0, negative));
         };
 
         base_data = match second_byte {
@@ -71,113 +110,201 @@ pub fn parse_number<R: Read>(reader: &mut PeekReader<R>) -> io::Result<f64> {
             _ => None,
         };
     } else if matches!(first_byte, b'+' | b'-') {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
+        return Err(Error::new(
+            ErrorKind::InvalidNumber,
             "invalid start to number: two +/- signs",
         ));
     }
 
     if let Some((base, to_number)) = base_data {
-        let mut number_digits = Vec::new();
-        {
-            let Some(first_byte) = reader.read_byte()? else {
-                return Err(eof_err);
-            };
-            let Some(first_number) = to_number(first_byte) else {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    format!("invalid start to number: {:?}", utils::to_char(first_byte)),
-                ));
-            };
-            number_digits.push(first_number);
+        let token = reader.peek_until(|byte| byte != b'\'' && to_number(byte).is_none())?;
+        let Some(first_byte) = token.first() else {
+            return Err(match reader.peek()? {
+                Some(byte) => Error::new(
+                    ErrorKind::InvalidNumber,
+                    format!("invalid start to number: {:?}", utils::to_char(byte)),
+                ),
+                None => eof_err,
+            });
+        };
+        if *first_byte == b'\'' {
+            return Err(Error::new(
+                ErrorKind::InvalidNumber,
+                "number can't start with \"'\"",
+            ));
         }
 
-        loop {
-            match reader.peek()? {
-                Some(b'\'') => {
-                    reader.consume(1);
-                    continue;
-                }
-                Some(other) => {
-                    if let Some(number) = to_number(other) {
-                        reader.consume(1);
-                        number_digits.push(number);
-                        continue;
-                    } else {
-                        break;
-                    }
-                }
-                None => break,
-            }
+        let number_digits: Vec<f64> = token.iter().filter_map(|byte| to_number(*byte)).collect();
+        let token_len = token.len();
+        reader.consume(token_len);
+
+        // compute the number as an integer, falling back to a float if it overflows
+        let integer = number_digits.iter().try_fold(0u128, |number, digit| {
+            number
+                .checked_mul(base as u128)?
+                .checked_add(*digit as u128)
+        });
+        if let Some(integer) = integer {
+            return Ok(ParsedNumber::from_integer(integer, negative));
         }
 
         let mut number = 0.0;
         for (i, value) in number_digits.iter().rev().enumerate() {
             number += value * base.powi(i as i32);
         }
-        Ok(sign * number)
+        Ok(ParsedNumber::Float(sign * number))
     } else {
-        let mut number_bytes = Vec::new();
-        let mut current_byte = first_byte;
-        loop {
-            if !current_byte.is_ascii_digit()
-                && !matches!(current_byte, b'+' | b'-' | b'.' | b'\'' | b'e' | b'E')
-            {
-                break;
-            }
+        let token = reader.peek_until(|byte| {
+            !byte.is_ascii_digit() && !matches!(byte, b'+' | b'-' | b'.' | b'\'' | b'e' | b'E')
+        })?;
 
-            reader.consume(1);
-            if current_byte == b'\'' {
+        let mut number_bytes = Vec::with_capacity(token.len());
+        for byte in token {
+            if *byte == b'\'' {
                 if number_bytes.last().is_none_or(|byte| *byte == b'.') {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
+                    return Err(Error::new(
+                        ErrorKind::InvalidNumber,
                         "number can't start with \"'\"",
                     ));
                 }
             } else {
-                number_bytes.push(current_byte);
-            }
-
-            match reader.peek()? {
-                Some(byte) => current_byte = byte,
-                None => break,
+                number_bytes.push(*byte);
             }
         }
+        let token_len = token.len();
+        reader.consume(token_len);
 
         // Safety: we know number_bytes contains valid utf8
         let number_str = unsafe { std::str::from_utf8_unchecked(&number_bytes) };
 
         // a number ending with '.' will be parsed correctly, but is invalid mason >:(
         if number_str.ends_with(".") || number_str.contains(".e") || number_str.contains(".E") {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
+            return Err(Error::new(
+                ErrorKind::InvalidNumber,
                 format!("failed to convert {number_str} to number: numbers can't end with '.'"),
             ));
         }
 
+        if let Ok(integer) = number_str.parse::<u128>() {
+            return Ok(ParsedNumber::from_integer(integer, negative));
+        }
+
         let number: f64 = number_str.parse().map_err(|err| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
+            Error::new(
+                ErrorKind::InvalidNumber,
                 format!("Failed to parse number {number_str:?}: {err}"),
             )
         })?;
-        Ok(sign * number)
+        Ok(ParsedNumber::Float(sign * number))
+    }
+}
+
+/// Parse a number into a [`Number`].
+/// Integers that can not be stored exactly are reported as warnings.
+#[cfg(not(feature = "arbitrary_precision"))]
+pub fn parse_mason_number<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    context: &ParseContext,
+) -> Result<Number> {
+    let start = reader.position();
+    let number = parse_number(reader)?;
+    let float = number.as_f64();
+    let rounded = match number {
+        // u128::MAX rounds up to 2^128, which saturates back to u128::MAX
+        ParsedNumber::PosInt(int) if float as u128 != int || int == u128::MAX => {
+            Some(int.to_string())
+        }
+        ParsedNumber::NegInt(int) if float as i128 != int => Some(int.to_string()),
+        _ => None,
+    };
+    if let Some(int) = rounded {
+        context.warn(
+            crate::ParseWarningKind::PrecisionLoss,
+            start..reader.position(),
+            || format!("{int} can not be stored exactly, and is rounded to {float}"),
+        );
+    }
+    Ok(float.into())
+}
+
+/// Parse a number into a [`Number`], which remembers the literal it was parsed from.
+#[cfg(feature = "arbitrary_precision")]
+pub fn parse_mason_number<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    _context: &ParseContext,
+) -> Result<Number> {
+    reader.start_recording();
+    let number = parse_number(reader);
+    let literal = reader.stop_recording();
+    let number = number?;
+
+    // a number that was parsed without errors only contains ASCII characters
+    let literal = String::from_utf8(literal).map_err(|_| Error::syntax("number is not UTF-8"))?;
+    Ok(Number::from_literal(literal, number.as_f64()))
+}
+
+/// Check that the number at the start of `reader` is written like a JSON number,
+/// without consuming it. JSON numbers have no leading `+`, no leading zeros, no
+/// base prefix, no digit separators, and need digits on both sides of a `.`.
+pub fn check_json_number<R: BufRead>(reader: &mut PeekReader<R>) -> Result<()> {
+    let token = reader.peek_until(|byte| {
+        !(utils::is_identifier_char(byte) || matches!(byte, b'+' | b'-' | b'.' | b'\''))
+    })?;
+
+    let digits = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count()
+    };
+
+    let mut rest = token.strip_prefix(b"-").unwrap_or(token);
+    let integer_len = digits(rest);
+    let mut valid = integer_len == 1 || (integer_len > 1 && rest[0] != b'0');
+    rest = &rest[integer_len..];
+    if let Some(fraction) = rest.strip_prefix(b".") {
+        let fraction_len = digits(fraction);
+        valid &= fraction_len > 0;
+        rest = &fraction[fraction_len..];
+    }
+    if let Some(exponent) = rest.strip_prefix(b"e").or_else(|| rest.strip_prefix(b"E")) {
+        let exponent = exponent
+            .strip_prefix(b"+")
+            .or_else(|| exponent.strip_prefix(b"-"))
+            .unwrap_or(exponent);
+        let exponent_len = digits(exponent);
+        valid &= exponent_len > 0;
+        rest = &exponent[exponent_len..];
+    }
+
+    if valid && rest.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidNumber,
+            format!(
+                "{:?} is not a valid JSON number",
+                String::from_utf8_lossy(token)
+            ),
+        ))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::io::Read;
+
     use super::*;
 
     #[test]
     fn test_parse_number() {
         let data = "1";
         let mut reader = PeekReader::new(data.as_bytes());
-        assert_eq!(parse_number(&mut reader).unwrap(), 1.0);
+        assert_eq!(parse_number(&mut reader).unwrap().as_f64(), 1.0);
 
         let data = "0";
         let mut reader = PeekReader::new(data.as_bytes());
-        assert_eq!(parse_number(&mut reader).unwrap(), 0.0);
+        assert_eq!(parse_number(&mut reader).unwrap().as_f64(), 0.0);
 
         let data = "++0";
         let mut reader = PeekReader::new(data.as_bytes());
@@ -185,7 +312,7 @@ mod tests {
 
         let data = "-0'6.1'2'45";
         let mut reader = PeekReader::new(data.as_bytes());
-        assert_eq!(parse_number(&mut reader).unwrap(), -6.1245);
+        assert_eq!(parse_number(&mut reader).unwrap().as_f64(), -6.1245);
 
         let data = "06.'1245";
         let mut reader = PeekReader::new(data.as_bytes());
@@ -193,25 +320,84 @@ mod tests {
 
         let data = "+1.0'12e-2";
         let mut reader = PeekReader::new(data.as_bytes());
-        assert_eq!(parse_number(&mut reader).unwrap(), 0.01012);
+        assert_eq!(parse_number(&mut reader).unwrap().as_f64(), 0.01012);
 
         let data = "-.2E2";
         let mut reader = PeekReader::new(data.as_bytes());
-        assert_eq!(parse_number(&mut reader).unwrap(), -20.0);
+        assert_eq!(parse_number(&mut reader).unwrap().as_f64(), -20.0);
 
         let data = "1.23And then";
         let mut reader = PeekReader::new(data.as_bytes());
-        assert_eq!(parse_number(&mut reader).unwrap(), 1.23);
+        assert_eq!(parse_number(&mut reader).unwrap().as_f64(), 1.23);
         let mut buf = [0; 8];
         reader.read_exact(&mut buf).unwrap();
         assert_eq!(&buf, b"And then");
     }
 
+    #[test]
+    fn test_parse_integer() {
+        let data = "18446744073709551615";
+        let mut reader = PeekReader::new(data.as_bytes());
+        assert_eq!(
+            parse_number(&mut reader).unwrap(),
+            ParsedNumber::PosInt(u64::MAX.into())
+        );
+
+        let data = "-9223372036854775808";
+        let mut reader = PeekReader::new(data.as_bytes());
+        assert_eq!(
+            parse_number(&mut reader).unwrap(),
+            ParsedNumber::NegInt(i64::MIN.into())
+        );
+
+        let data = "-0x7fff'ffff'ffff'ffff";
+        let mut reader = PeekReader::new(data.as_bytes());
+        assert_eq!(
+            parse_number(&mut reader).unwrap(),
+            ParsedNumber::NegInt((-i64::MAX).into())
+        );
+
+        let data = "18446744073709551616";
+        let mut reader = PeekReader::new(data.as_bytes());
+        assert_eq!(
+            parse_number(&mut reader).unwrap(),
+            ParsedNumber::PosInt(18446744073709551616)
+        );
+
+        let data = "-0x8000'0000'0000'0000'0000'0000'0000'0000";
+        let mut reader = PeekReader::new(data.as_bytes());
+        assert_eq!(
+            parse_number(&mut reader).unwrap(),
+            ParsedNumber::NegInt(i128::MIN)
+        );
+
+        let data = "340282366920938463463374607431768211456";
+        let mut reader = PeekReader::new(data.as_bytes());
+        assert_eq!(
+            parse_number(&mut reader).unwrap(),
+            ParsedNumber::Float(340282366920938463463374607431768211456.0)
+        );
+
+        let data = "-0";
+        let mut reader = PeekReader::new(data.as_bytes());
+        assert_eq!(
+            parse_number(&mut reader).unwrap(),
+            ParsedNumber::Float(-0.0)
+        );
+
+        let data = "1e3";
+        let mut reader = PeekReader::new(data.as_bytes());
+        assert_eq!(
+            parse_number(&mut reader).unwrap(),
+            ParsedNumber::Float(1000.0)
+        );
+    }
+
     #[test]
     fn test_parse_base() {
         let data = "-0xa'bc''76";
         let mut reader = PeekReader::new(data.as_bytes());
-        assert_eq!(parse_number(&mut reader).unwrap(), -703606.0);
+        assert_eq!(parse_number(&mut reader).unwrap().as_f64(), -703606.0);
 
         let data = "0o'110";
         let mut reader = PeekReader::new(data.as_bytes());
@@ -219,17 +405,36 @@ mod tests {
 
         let data = "+0o712";
         let mut reader = PeekReader::new(data.as_bytes());
-        assert_eq!(parse_number(&mut reader).unwrap(), 458.0);
+        assert_eq!(parse_number(&mut reader).unwrap().as_f64(), 458.0);
 
         let data = "0b11'00'11'00";
         let mut reader = PeekReader::new(data.as_bytes());
-        assert_eq!(parse_number(&mut reader).unwrap(), 204.0);
+        assert_eq!(parse_number(&mut reader).unwrap().as_f64(), 204.0);
 
         let data = "0xff, ...";
         let mut reader = PeekReader::new(data.as_bytes());
-        assert_eq!(parse_number(&mut reader).unwrap(), 255.0);
+        assert_eq!(parse_number(&mut reader).unwrap().as_f64(), 255.0);
         let mut buf = [0; 5];
         reader.read_exact(&mut buf).unwrap();
         assert_eq!(&buf, b", ...");
     }
+
+    #[test]
+    fn test_check_json_number() {
+        for data in ["0", "-0", "12", "-1.5", "0.25e10", "1E-3", "2e+8", "7,"] {
+            let mut reader = PeekReader::new(data.as_bytes());
+            check_json_number(&mut reader).unwrap();
+            assert_eq!(
+                reader.read_byte().unwrap(),
+                data.as_bytes().first().copied()
+            );
+        }
+        for data in [
+            "+1", "01", "-", ".5", "1.", "1e", "0x10", "0b1", "0o7", "1'000", "1.5.5", "--1",
+        ] {
+            let mut reader = PeekReader::new(data.as_bytes());
+            let err = check_json_number(&mut reader).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidNumber, "{data:?}");
+        }
+    }
 }
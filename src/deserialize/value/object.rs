@@ -1,179 +1,218 @@
-use std::{
-    collections::HashMap,
-    io::{self, BufRead, Read},
-};
+use std::io::BufRead;
 
-use super::{Value, parse_value};
+use super::{Frame, ParseTarget, Start};
 use crate::{
-    deserialize::parse_string,
-    deserialize::whitespace::{parse_sep, skip_whitespace},
+    Error, ErrorKind, ParseWarningKind, Result,
+    deserialize::{ParseContext, parse_string},
     peek_reader::PeekReader,
     utils,
 };
 
-pub fn parse_object<R: Read>(
+/// Start parsing an object. If it is not empty, the value of the first key is
+/// entered in `context`.
+pub(super) fn start_object<R: BufRead, V: ParseTarget>(
     reader: &mut PeekReader<R>,
-    depth: u8,
-) -> io::Result<HashMap<String, Value>> {
+    context: &mut ParseContext,
+) -> Result<Start<V>> {
     // skip opening brackets and whitespace
     if reader.read_byte()? != Some(b'{') {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "object does not start with '{'",
-        ));
+        return Err(Error::syntax("object does not start with '{'"));
     }
-    skip_whitespace(reader)?;
+    context.skip_whitespace(reader)?;
 
     if reader.peek()? == Some(b'}') {
         reader.consume(1);
-        return Ok(HashMap::new());
+        context.discard_pending();
+        return Ok(Start::Value(V::object(Default::default())));
     }
 
-    let first_key = parse_identifier(reader)?;
-    parse_key_value_pairs_after_key(reader, first_key, depth, false)
+    let first_key = parse_object_key(reader, context)?;
+    context.skip_whitespace(reader)?;
+    Ok(Start::Frame(start_key_value_pairs_after_key(
+        reader, context, first_key, false,
+    )?))
 }
 
-pub fn parse_key_value_pairs_after_key<R: Read>(
+/// Start parsing the key-value pairs of an object whose first key has already
+/// been parsed. The value of the first key is entered in `context`.
+pub(super) fn start_key_value_pairs_after_key<R: BufRead, V: ParseTarget>(
     reader: &mut PeekReader<R>,
+    context: &mut ParseContext,
     first_key: String,
-    depth: u8,
     top_level: bool,
-) -> io::Result<HashMap<String, Value>> {
-    let eof_err = io::Error::new(io::ErrorKind::UnexpectedEof, "got EOF while parsing object");
-
+) -> Result<Frame<V>> {
     // skip colon and whitespace after key
     if reader.read_byte()? != Some(b':') {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
+        return Err(Error::syntax(
             "key value pairs after key does not start with ':'",
         ));
     }
-    skip_whitespace(reader)?;
-
-    let mut parsed_multi_line_string = reader.peek()? == Some(b'|');
-    let first_value = parse_value(reader, depth - 1, false)?;
-
-    let mut object = HashMap::new();
-    object.insert(first_key, first_value);
-
-    loop {
-        let valid_sep = parsed_multi_line_string || parse_sep(reader)?;
-
-        skip_whitespace(reader)?;
-
-        let Some(next_byte) = reader.peek()? else {
-            if top_level {
-                return Ok(object);
-            } else {
-                return Err(eof_err);
-            }
-        };
-        if next_byte == b'}' {
-            reader.consume(1);
-            return Ok(object);
-        } else if !valid_sep {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("invalid separator {}", utils::to_char(next_byte)),
-            ));
-        }
-
-        let (key, value, multi_line_string) = parse_key_value_pair(reader, depth)?;
-        parsed_multi_line_string = multi_line_string;
+    context.skip_whitespace(reader)?;
+
+    context.enter(&first_key);
+    Ok(Frame::Object {
+        object: Default::default(),
+        key: first_key,
+        top_level,
+    })
+}
 
-        object.insert(key, value);
+/// Insert the value of `key` into `object`, and parse what comes after it.
+/// Returns the next key, which is entered in `context`, or `None` if this was
+/// the last key-value pair.
+pub(super) fn continue_object<R: BufRead, V: ParseTarget>(
+    reader: &mut PeekReader<R>,
+    context: &mut ParseContext,
+    object: &mut V::Object,
+    key: String,
+    value: V,
+    top_level: bool,
+    parsed_multi_line_string: bool,
+) -> Result<Option<String>> {
+    V::insert(object, key, value, context);
+
+    let valid_sep = parsed_multi_line_string || context.parse_sep(reader)?;
+    context.attach_trailing();
+    context.exit();
+
+    context.skip_whitespace(reader)?;
+
+    let Some(next_byte) = reader.peek()? else {
+        if top_level {
+            context.discard_pending();
+            return Ok(None);
+        } else {
+            return Err(Error::eof("got EOF while parsing object"));
+        }
+    };
+    if next_byte == b'}' {
+        if valid_sep && context.json_only() {
+            return Err(Error::syntax("trailing commas are not allowed in JSON"));
+        }
+        reader.consume(1);
+        context.discard_pending();
+        return Ok(None);
+    } else if !valid_sep {
+        return Err(Error::syntax(format!(
+            "invalid separator {}",
+            utils::to_char(next_byte)
+        )));
     }
+
+    parse_key::<R, V>(reader, context, object).map(Some)
 }
 
-pub fn parse_identifier<R: Read>(reader: &mut PeekReader<R>) -> io::Result<String> {
+pub fn parse_identifier<R: BufRead>(reader: &mut PeekReader<R>) -> Result<String> {
     let Some(first_byte) = reader.peek()? else {
-        return Err(io::Error::new(
-            io::ErrorKind::UnexpectedEof,
-            "Got EOF when parsing key",
-        ));
+        return Err(Error::eof("Got EOF when parsing key"));
     };
 
     if first_byte == b'"' {
         parse_string(reader)
     } else {
-        reader.consume(1);
         let c = utils::to_char(first_byte);
         if !(c.is_ascii_alphabetic() || c == '_') {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("key identifier starts with invalid char: {c:?}",),
-            ));
+            return Err(Error::syntax(format!(
+                "key identifier starts with invalid char: {c:?}",
+            )));
         }
 
-        let mut key = vec![c];
-        loop {
-            let byte = reader.peek()?;
-            if let Some(byte) = byte {
-                if utils::to_char(byte).is_ascii_alphanumeric() || matches!(byte, b'_' | b'-') {
-                    reader.consume(1);
-                    key.push(utils::to_char(byte));
-                    continue;
-                }
-            }
-            return Ok(key.into_iter().collect());
-        }
+        let identifier = reader.peek_until(|byte| !utils::is_identifier_char(byte))?;
+        let key: String = identifier
+            .iter()
+            .map(|byte| utils::to_char(*byte))
+            .collect();
+        reader.consume(key.len());
+        Ok(key)
+    }
+}
+
+/// Parse an identifier that is used as an object key. In JSON, keys must be quoted.
+fn parse_object_key<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    context: &ParseContext,
+) -> Result<String> {
+    if reader.peek()? == Some(b'"') {
+        context.parse_string(reader)
+    } else if context.json_only() {
+        Err(Error::syntax("keys must be quoted in JSON"))
+    } else {
+        parse_identifier(reader)
     }
 }
 
-fn parse_key_value_pair<R: Read>(
+/// Parse the key of a key-value pair, and the colon after it. The key is
+/// entered in `context`. A key that is already in `object` is an error if
+/// duplicate keys are rejected, and a warning otherwise.
+fn parse_key<R: BufRead, V: ParseTarget>(
     reader: &mut PeekReader<R>,
-    depth: u8,
-) -> io::Result<(String, Value, bool)> {
-    let key = parse_identifier(reader)?;
+    context: &mut ParseContext,
+    object: &V::Object,
+) -> Result<String> {
+    let start = reader.position();
+    let key = parse_object_key(reader, context)?;
+    let end = reader.position();
+
+    let duplicate = V::contains_key(object, &key);
+    if duplicate && context.reject_duplicate_keys() {
+        return Err(Error::new(
+            ErrorKind::DuplicateKey,
+            format!("got duplicate key {key:?}"),
+        ));
+    }
 
     // skip whitespace before colon
-    skip_whitespace(reader)?;
+    context.skip_whitespace(reader)?;
 
     let Some(next_byte) = reader.read_byte()? else {
-        return Err(io::Error::new(
-            io::ErrorKind::UnexpectedEof,
-            "Got EOF when parsing key-value pair",
-        ));
+        return Err(Error::eof("Got EOF when parsing key-value pair"));
     };
     if next_byte != b':' {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!(
-                "got unexpected byte {:?} after key",
-                utils::to_char(next_byte)
-            ),
-        ));
+        return Err(Error::syntax(format!(
+            "got unexpected byte {:?} after key",
+            utils::to_char(next_byte)
+        )));
     }
 
     // skip whitespace after colon
-    skip_whitespace(reader)?;
+    context.skip_whitespace(reader)?;
 
-    let parsed_multiline_string = reader.peek()? == Some(b'|');
-    let value = parse_value(reader, depth - 1, false)?;
-
-    Ok((key, value, parsed_multiline_string))
+    context.enter(&key);
+    if duplicate {
+        context.warn(ParseWarningKind::DuplicateKey, start..end, || {
+            format!("duplicate key {key:?} overwrites the earlier value")
+        });
+    }
+    Ok(key)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{Map, Value, deserialize::value::parse_value};
 
     #[test]
     fn test_parse_object() {
         let data = "{}";
         let mut reader = PeekReader::new(data.as_bytes());
-        assert_eq!(parse_object(&mut reader, 100).unwrap(), HashMap::new());
-
-        let map: HashMap<String, Value> = HashMap::from([
-            ("key1".to_owned(), Value::Number(1.0)),
-            (" a fancy! key \r".to_owned(), Value::Number(6.0)),
+        assert_eq!(
+            parse_value::<_, Value>(&mut reader, &mut ParseContext::default(), 100, false).unwrap(),
+            Value::Object(Map::new())
+        );
+
+        let map = Map::from([
+            ("key1".to_owned(), Value::from(1.0)),
+            (" a fancy! key \r".to_owned(), Value::from(6.0)),
             ("🏳️‍⚧️".to_owned(), Value::Bool(true)),
             ("key4".to_owned(), Value::Null),
         ]);
 
         let data = "{key1: 1, \" a fancy! key \r\": 6, \"🏳️‍⚧️\": true, key4: null}";
         let mut reader = PeekReader::new(data.as_bytes());
-        assert_eq!(parse_object(&mut reader, 100).unwrap(), map);
+        assert_eq!(
+            parse_value::<_, Value>(&mut reader, &mut ParseContext::default(), 100, false).unwrap(),
+            Value::Object(map.clone())
+        );
 
         let data = "\
         {/* hey :)*/ key1:   \t 1 // so true
@@ -184,6 +223,9 @@ mod tests {
         \t\r\n
         }";
         let mut reader = PeekReader::new(data.as_bytes());
-        assert_eq!(parse_object(&mut reader, 100).unwrap(), map);
+        assert_eq!(
+            parse_value::<_, Value>(&mut reader, &mut ParseContext::default(), 100, false).unwrap(),
+            Value::Object(map.clone())
+        );
     }
 }
@@ -1,101 +1,106 @@
-use std::io::{self, BufRead, Read};
+use std::io::BufRead;
 
 use crate::{
-    deserialize::skip_whitespace, peek_reader::PeekReader, unescape_string::unescape_string, utils,
+    Error, Result,
+    deserialize::skip_whitespace,
+    hex,
+    peek_reader::PeekReader,
+    text::{unescape, unescape_string},
+    utils,
 };
 
-pub fn parse_string<R: Read>(reader: &mut PeekReader<R>) -> io::Result<String> {
+pub fn parse_string<R: BufRead>(reader: &mut PeekReader<R>) -> Result<String> {
+    parse_string_literal(reader, false, true).map(|(string, _)| string)
+}
+
+/// Parse a string like [`parse_string`], and also return the text between the
+/// quotes as it was written. If `reject_control_characters` is set, all unescaped
+/// control characters are rejected, not just newlines, tabs and null. Escapes like
+/// `\u{1F600}` are only accepted if `braced_unicode_escapes` is set.
+pub fn parse_string_literal<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    reject_control_characters: bool,
+    braced_unicode_escapes: bool,
+) -> Result<(String, Vec<u8>)> {
+    let is_byte_invalid = |byte: u8| {
+        if reject_control_characters {
+            byte.is_ascii_control()
+        } else {
+            matches!(byte, b'\n' | b'\t' | b'\0')
+        }
+    };
+
     if reader.read_byte()? != Some(b'"') {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "string did not start with '\"'",
-        ));
+        return Err(Error::syntax("string did not start with '\"'"));
     }
 
+    let start = reader.position();
     let value_bytes = utils::read_until_unquote(reader)?;
 
-    let is_byte_invalid = |byte: &&u8| matches!(byte, b'\n' | b'\t' | b'\0');
-    if let Some(invalid_byte) = value_bytes.iter().find(is_byte_invalid) {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!(
-                "got invalid value in string: {:?} (string: {:?})",
-                utils::to_char(*invalid_byte),
-                String::from_utf8_lossy(&value_bytes),
-            ),
-        ));
+    if let Some(index) = value_bytes.iter().position(|byte| is_byte_invalid(*byte)) {
+        // point at the invalid byte, not the end of the string
+        let mut position = start;
+        position.advance(&value_bytes[..index]);
+        return Err(Error::syntax(format!(
+            "got invalid value in string: {:?} (string: {:?})",
+            utils::to_char(value_bytes[index]),
+            String::from_utf8_lossy(&value_bytes),
+        ))
+        .at(position));
     }
 
-    let unescaped_bytes = unescape_string(&value_bytes)
-        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
-        .to_vec();
+    let unescaped_bytes = unescape(&value_bytes, braced_unicode_escapes)?.to_vec();
 
-    String::from_utf8(unescaped_bytes).map_err(|err| {
-        io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!(
-                "got non-utf8 string: {} (bytes: {:?})",
-                String::from_utf8_lossy(err.as_bytes()),
-                err.as_bytes(),
-            ),
-        )
-    })
+    let string = String::from_utf8(unescaped_bytes).map_err(|err| {
+        Error::syntax(format!(
+            "got non-utf8 string: {} (bytes: {:?})",
+            String::from_utf8_lossy(err.as_bytes()),
+            err.as_bytes(),
+        ))
+    })?;
+    Ok((string, value_bytes))
+}
+
+/// Parse a raw string, like `r#"a "quoted" string"#`, that starts with at most
+/// `max_hashes` hashes.
+pub fn parse_raw_string<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    max_hashes: usize,
+) -> Result<String> {
+    parse_raw_string_with_hashes(reader, max_hashes).map(|(string, _)| string)
 }
 
-pub fn parse_raw_string<R: Read>(reader: &mut PeekReader<R>) -> io::Result<String> {
+/// Parse a raw string like [`parse_raw_string`], and also return the number of
+/// hashes it started with.
+pub fn parse_raw_string_with_hashes<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    max_hashes: usize,
+) -> Result<(String, usize)> {
     if reader.read_byte()? != Some(b'r') {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "string did not start with 'r'",
-        ));
+        return Err(Error::syntax("string did not start with 'r'"));
     }
 
-    let mut pattern = Vec::new();
-    loop {
-        let Some(byte) = reader.read_byte()? else {
-            return Err(io::Error::new(
-                io::ErrorKind::UnexpectedEof,
-                "got EOF while parsing raw string",
-            ));
-        };
-        match byte {
-            b'#' => pattern.push(byte),
-            b'"' => {
-                pattern.push(byte);
-                break;
-            }
-            _ => {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    format!(
-                        "got invalid char {:?} in beginning of raw string",
-                        utils::to_char(byte)
-                    ),
-                ));
-            }
-        }
-    }
-    pattern.reverse();
-    let value_bytes = utils::read_until_pattern(reader, &pattern)?;
+    let hashes = utils::read_raw_string_start(reader, max_hashes)?;
+    let mut value_bytes = Vec::new();
+    utils::read_until_raw_string_end(reader, hashes, |chunk| {
+        value_bytes.extend_from_slice(chunk);
+    })?;
+    // remove the closing quote and hashes
+    value_bytes.truncate(value_bytes.len() - hashes - 1);
 
-    String::from_utf8(value_bytes).map_err(|err| {
-        io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!(
-                "got non-utf8 string: {} (bytes: {:?})",
-                String::from_utf8_lossy(err.as_bytes()),
-                err.as_bytes(),
-            ),
-        )
-    })
+    let string = String::from_utf8(value_bytes).map_err(|err| {
+        Error::syntax(format!(
+            "got non-utf8 string: {} (bytes: {:?})",
+            String::from_utf8_lossy(err.as_bytes()),
+            err.as_bytes(),
+        ))
+    })?;
+    Ok((string, hashes))
 }
 
-pub fn parse_multi_line_string<R: Read>(reader: &mut PeekReader<R>) -> io::Result<String> {
+pub fn parse_multi_line_string<R: BufRead>(reader: &mut PeekReader<R>) -> Result<String> {
     if reader.read_byte()? != Some(b'|') {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "multi line string did not start with '|'",
-        ));
+        return Err(Error::syntax("multi line string did not start with '|'"));
     }
 
     let mut out = String::new();
@@ -109,14 +114,11 @@ pub fn parse_multi_line_string<R: Read>(reader: &mut PeekReader<R>) -> io::Resul
         }
 
         let string = String::from_utf8(bytes).map_err(|err| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!(
-                    "got non-utf8 string: {} (bytes: {:?})",
-                    String::from_utf8_lossy(err.as_bytes()),
-                    err.as_bytes(),
-                ),
-            )
+            Error::syntax(format!(
+                "got non-utf8 string: {} (bytes: {:?})",
+                String::from_utf8_lossy(err.as_bytes()),
+                err.as_bytes(),
+            ))
         })?;
         out += &string;
 
@@ -132,30 +134,62 @@ pub fn parse_multi_line_string<R: Read>(reader: &mut PeekReader<R>) -> io::Resul
     Ok(out)
 }
 
-pub fn parse_byte_string<R: Read>(reader: &mut PeekReader<R>) -> io::Result<Vec<u8>> {
+pub fn parse_byte_string<R: BufRead>(reader: &mut PeekReader<R>) -> Result<Vec<u8>> {
     if (reader.read_byte()?, reader.read_byte()?) != (Some(b'b'), Some(b'"')) {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "string did not start with 'b\"'",
-        ));
+        return Err(Error::syntax("string did not start with 'b\"'"));
     }
 
     let value_bytes = utils::read_until_unquote(reader)?;
 
     let is_byte_invalid = |byte: &&u8| !byte.is_ascii() || matches!(byte, b'\n' | b'\t');
     if let Some(invalid_byte) = value_bytes.iter().find(is_byte_invalid) {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!(
-                "got invalid value in byte string: {:?} (bytes: {:?})",
-                utils::to_char(*invalid_byte),
-                value_bytes,
-            ),
-        ));
+        return Err(Error::syntax(format!(
+            "got invalid value in byte string: {:?} (bytes: {:?})",
+            utils::to_char(*invalid_byte),
+            value_bytes,
+        )));
+    }
+    unescape_string(&value_bytes).map(|bytes| bytes.into_owned())
+}
+
+/// Parse a hex block like `b|48656c6c 6f`, where each line starts with `b|` and
+/// contains pairs of hex digits, which can be separated by spaces and tabs. The
+/// block continues on the next line if it starts with `b|`, and ends at the first
+/// byte that is not part of a line, which is not consumed.
+pub fn parse_hex_block<R: BufRead>(reader: &mut PeekReader<R>) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    loop {
+        if (reader.read_byte()?, reader.read_byte()?) != (Some(b'b'), Some(b'|')) {
+            return Err(Error::syntax("hex block line did not start with 'b|'"));
+        }
+
+        let mut digits = Vec::new();
+        while let Some(next) = reader.peek()? {
+            if next.is_ascii_hexdigit() {
+                digits.push(next);
+            } else if !matches!(next, b' ' | b'\t' | b'\r') || digits.len() % 2 == 1 {
+                break;
+            }
+            reader.consume(1);
+        }
+        if digits.len() % 2 == 1 {
+            return Err(Error::syntax(format!(
+                "got an odd number of hex digits in hex block: {:?}",
+                String::from_utf8_lossy(&digits),
+            )));
+        }
+        for pair in digits.chunks_exact(2) {
+            bytes.push(hex::decode_hex([pair[0], pair[1]]).expect("digits should be hex"));
+        }
+
+        // only a line break and indentation can come between the lines of a block
+        let whitespace = reader.peek_until(|byte| !matches!(byte, b' ' | b'\t' | b'\r' | b'\n'))?;
+        let len = whitespace.len();
+        if !whitespace.contains(&b'\n') || !reader.peek_n(len + 2)?.ends_with(b"b|") {
+            return Ok(bytes);
+        }
+        reader.consume(len);
     }
-    unescape_string(&value_bytes)
-        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
-        .map(|bytes| bytes.into_owned())
 }
 
 #[cfg(test)]
@@ -174,6 +208,27 @@ mod tests {
         let data = r#""I am missing an end quote :("#;
         let mut reader = PeekReader::new(data.as_bytes());
         assert!(parse_string(&mut reader).is_err());
+
+        let data = r#""C:\\path\\", "next""#;
+        let mut reader = PeekReader::new(data.as_bytes());
+        assert_eq!(parse_string(&mut reader).unwrap(), r"C:\path\");
+
+        let data = "\"bell \x07 and escaped bell \\u0007\"";
+        let mut reader = PeekReader::new(data.as_bytes());
+        assert_eq!(
+            parse_string(&mut reader).unwrap(),
+            "bell \x07 and escaped bell \x07"
+        );
+
+        let mut reader = PeekReader::new(data.as_bytes());
+        let err = parse_string_literal(&mut reader, true, true).unwrap_err();
+        assert_eq!(err.position().map(|position| position.column), Some(7));
+
+        let data = "\"two\nlines\"";
+        let mut reader = PeekReader::new(data.as_bytes());
+        let err = parse_string(&mut reader).unwrap_err();
+        let position = err.position().unwrap();
+        assert_eq!((position.offset, position.line, position.column), (4, 1, 5));
     }
 
     #[test]
@@ -199,13 +254,24 @@ mod tests {
         let data = r###"r##"This "string" can fit so many #"quotes"# :)"##"###;
         let mut reader = PeekReader::new(data.as_bytes());
         assert_eq!(
-            parse_raw_string(&mut reader).unwrap(),
+            parse_raw_string(&mut reader, 255).unwrap(),
             "This \"string\" can fit so many #\"quotes\"# :)"
         );
 
         let data = r##"r#"I am not closed properly ""##;
         let mut reader = PeekReader::new(data.as_bytes());
-        assert!(parse_raw_string(&mut reader).is_err());
+        assert!(parse_raw_string(&mut reader, 255).is_err());
+
+        let data = r###"r#"ends with a quote""#"###;
+        let mut reader = PeekReader::new(data.as_bytes());
+        assert_eq!(
+            parse_raw_string(&mut reader, 255).unwrap(),
+            "ends with a quote\""
+        );
+
+        let data = r####"r###"too many hashes"###"####;
+        let mut reader = PeekReader::new(data.as_bytes());
+        assert!(parse_raw_string(&mut reader, 2).is_err());
     }
 
     #[test]
@@ -0,0 +1,115 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{ArcValue, Map, Number, Value, deserialize::ParseContext, value::mason_string};
+
+/// A type that [`parse_value`](super::parse_value) can parse a value into.
+pub trait ParseTarget: Sized {
+    /// The type of the entries of an object.
+    type Object: Default;
+
+    fn string(string: String, context: &mut ParseContext) -> Self;
+    fn byte_string(bytes: Vec<u8>) -> Self;
+    fn number(number: Number) -> Self;
+    fn bool(bool: bool) -> Self;
+    fn null() -> Self;
+    fn array(array: Vec<Self>) -> Self;
+    fn object(object: Self::Object) -> Self;
+    fn tagged(tag: String, value: Self, context: &mut ParseContext) -> Self;
+
+    /// Insert `value` into `object`, overwriting any earlier value of `key`.
+    fn insert(object: &mut Self::Object, key: String, value: Self, context: &mut ParseContext);
+    fn contains_key(object: &Self::Object, key: &str) -> bool;
+}
+
+impl ParseTarget for Value {
+    type Object = Map;
+
+    fn string(string: String, _context: &mut ParseContext) -> Self {
+        Self::String(mason_string(string))
+    }
+
+    fn byte_string(bytes: Vec<u8>) -> Self {
+        Self::ByteString(bytes)
+    }
+
+    fn number(number: Number) -> Self {
+        Self::Number(number)
+    }
+
+    fn bool(bool: bool) -> Self {
+        Self::Bool(bool)
+    }
+
+    fn null() -> Self {
+        Self::Null
+    }
+
+    fn array(array: Vec<Self>) -> Self {
+        Self::Array(array)
+    }
+
+    fn object(object: Map) -> Self {
+        Self::Object(object)
+    }
+
+    fn tagged(tag: String, value: Self, _context: &mut ParseContext) -> Self {
+        Self::Tagged(tag, Box::new(value))
+    }
+
+    fn insert(object: &mut Map, key: String, value: Self, _context: &mut ParseContext) {
+        object.insert(key, value);
+    }
+
+    fn contains_key(object: &Map, key: &str) -> bool {
+        object.contains_key(key)
+    }
+}
+
+/// The entries of an [`ArcValue::Object`].
+type ArcMap = HashMap<Arc<str>, ArcValue>;
+
+/// Strings, keys and tags are interned as they are parsed, so equal strings
+/// share one allocation, see [`ParseOptions::parse_reader_interned`](crate::ParseOptions::parse_reader_interned).
+impl ParseTarget for ArcValue {
+    type Object = ArcMap;
+
+    fn string(string: String, context: &mut ParseContext) -> Self {
+        Self::String(context.intern(&string))
+    }
+
+    fn byte_string(bytes: Vec<u8>) -> Self {
+        Self::ByteString(Arc::from(bytes))
+    }
+
+    fn number(number: Number) -> Self {
+        Self::Number(number)
+    }
+
+    fn bool(bool: bool) -> Self {
+        Self::Bool(bool)
+    }
+
+    fn null() -> Self {
+        Self::Null
+    }
+
+    fn array(array: Vec<Self>) -> Self {
+        Self::Array(Arc::new(array))
+    }
+
+    fn object(object: ArcMap) -> Self {
+        Self::Object(Arc::new(object))
+    }
+
+    fn tagged(tag: String, value: Self, context: &mut ParseContext) -> Self {
+        Self::Tagged(context.intern(&tag), Arc::new(value))
+    }
+
+    fn insert(object: &mut ArcMap, key: String, value: Self, context: &mut ParseContext) {
+        object.insert(context.intern(&key), value);
+    }
+
+    fn contains_key(object: &ArcMap, key: &str) -> bool {
+        object.contains_key(key)
+    }
+}
@@ -1,8 +1,32 @@
-use std::io::{self, BufRead, Read};
+use std::io::BufRead;
 
-use crate::peek_reader::PeekReader;
+use crate::{Error, Result, peek_reader::PeekReader};
 
-pub fn skip_whitespace<R: Read>(reader: &mut PeekReader<R>) -> io::Result<()> {
+/// Skip the UTF-8 byte order mark at the start of a document, if there is one.
+/// UTF-16 documents are rejected with a clear error, as MASON must be UTF-8.
+pub fn skip_byte_order_mark<R: BufRead>(reader: &mut PeekReader<R>) -> Result<()> {
+    match *reader.peek_n(3)? {
+        [0xEF, 0xBB, 0xBF] => reader.consume_byte_order_mark(),
+        [0xFE, 0xFF, ..] | [0xFF, 0xFE, ..] => {
+            return Err(Error::syntax(
+                "got UTF-16 input, but MASON must be UTF-8 (enable the `encoding` feature to transcode UTF-16)",
+            ));
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+pub fn skip_whitespace<R: BufRead>(reader: &mut PeekReader<R>) -> Result<()> {
+    skip_whitespace_collecting(reader, None)
+}
+
+/// Like [`skip_whitespace`], but the text of any skipped comments is added to
+/// `comments`.
+pub fn skip_whitespace_collecting<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    mut comments: Option<&mut Vec<String>>,
+) -> Result<()> {
     loop {
         let Some(next_byte) = reader.peek()? else {
             // We reached EOF, which means there is no more whitespace to skip
@@ -25,33 +49,46 @@ pub fn skip_whitespace<R: Read>(reader: &mut PeekReader<R>) -> io::Result<()> {
         match next_byte {
             b'/' => {
                 reader.consume(2);
-                reader.skip_until(b'\n')?;
+                skip_line_comment(reader, comments.as_deref_mut())?;
             }
             b'*' => {
                 reader.consume(2);
-                loop {
-                    reader.skip_until(b'*')?;
-                    let Some(next_byte) = reader.read_byte()? else {
-                        return Err(io::Error::new(
-                            io::ErrorKind::InvalidData,
-                            "unclosed block comment",
-                        ));
-                    };
-                    if next_byte == b'/' {
-                        break;
-                    }
-                }
+                skip_block_comment(reader, comments.as_deref_mut())?;
             }
             _ => return Ok(()),
         };
     }
 }
 
-pub fn parse_sep<R: Read>(reader: &mut PeekReader<R>) -> io::Result<bool> {
+pub fn parse_sep<R: BufRead>(reader: &mut PeekReader<R>) -> Result<bool> {
+    parse_sep_collecting(reader, None)
+}
+
+/// Like [`parse_sep`], but the text of any skipped comments is added to `comments`.
+pub fn parse_sep_collecting<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    comments: Option<&mut Vec<String>>,
+) -> Result<bool> {
+    Ok(parse_separator(reader, comments)?.is_some())
+}
+
+/// The separator between two values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Separator {
+    /// A newline, which may be part of a line comment.
+    Newline,
+    Comma,
+}
+
+/// Like [`parse_sep_collecting`], but returns which separator was parsed.
+pub fn parse_separator<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    mut comments: Option<&mut Vec<String>>,
+) -> Result<Option<Separator>> {
     // parse space
     loop {
         let Some(next_byte) = reader.peek()? else {
-            return Ok(false);
+            return Ok(None);
         };
 
         match next_byte {
@@ -60,32 +97,21 @@ pub fn parse_sep<R: Read>(reader: &mut PeekReader<R>) -> io::Result<bool> {
             }
             b'/' => {
                 let Some([_, next_byte]) = reader.peek2()? else {
-                    return Ok(false);
+                    return Ok(None);
                 };
                 match next_byte {
                     b'/' => {
                         // a line comment contains a newline,
                         // and is therefore a valid sep
                         reader.consume(2);
-                        reader.skip_until(b'\n')?;
-                        return Ok(true);
+                        skip_line_comment(reader, comments.as_deref_mut())?;
+                        return Ok(Some(Separator::Newline));
                     }
                     b'*' => {
                         reader.consume(2);
+                        skip_block_comment(reader, comments.as_deref_mut())?;
                     }
-                    _ => return Ok(false),
-                }
-                loop {
-                    reader.skip_until(b'*')?;
-                    let Some(byte) = reader.read_byte()? else {
-                        return Err(io::Error::new(
-                            io::ErrorKind::InvalidData,
-                            "unclosed block comment",
-                        ));
-                    };
-                    if byte == b'/' {
-                        break;
-                    }
+                    _ => return Ok(None),
                 }
             }
             _ => break,
@@ -93,27 +119,107 @@ pub fn parse_sep<R: Read>(reader: &mut PeekReader<R>) -> io::Result<bool> {
     }
 
     let Some(next_bytes) = reader.peek2()? else {
-        return Ok(false);
+        return Ok(None);
     };
     match &next_bytes {
         b"\r\n" => {
             reader.consume(2);
-            Ok(true)
+            Ok(Some(Separator::Newline))
+        }
+        &[b'\n', _] => {
+            reader.consume(1);
+            Ok(Some(Separator::Newline))
         }
-        &[b'\n', _] | &[b',', _] => {
+        &[b',', _] => {
             reader.consume(1);
-            Ok(true)
+            Ok(Some(Separator::Comma))
         }
         b"//" => {
-            reader.skip_until(b'\n')?;
-            Ok(true)
+            reader.consume(2);
+            skip_line_comment(reader, comments)?;
+            Ok(Some(Separator::Newline))
         }
-        _ => Ok(false),
+        _ => Ok(None),
+    }
+}
+
+/// Like [`skip_whitespace`], but only JSON whitespace is allowed, so a comment
+/// is an error.
+pub fn skip_json_whitespace<R: BufRead>(reader: &mut PeekReader<R>) -> Result<()> {
+    let len = reader
+        .peek_until(|byte| !matches!(byte, b' ' | b'\r' | b'\n' | b'\t'))?
+        .len();
+    reader.consume(len);
+    if reader.peek()? == Some(b'/') {
+        return Err(Error::syntax("comments are not allowed in JSON"));
+    }
+    Ok(())
+}
+
+/// Like [`parse_sep`], but only a comma is a valid separator, like in JSON.
+pub fn parse_json_sep<R: BufRead>(reader: &mut PeekReader<R>) -> Result<bool> {
+    skip_json_whitespace(reader)?;
+    if reader.peek()? == Some(b',') {
+        reader.consume(1);
+        Ok(true)
+    } else {
+        Ok(false)
     }
 }
 
+/// Skip the rest of a line comment whose opening `//` has already been consumed.
+fn skip_line_comment<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    comments: Option<&mut Vec<String>>,
+) -> Result<()> {
+    let Some(comments) = comments else {
+        reader.skip_until(b'\n')?;
+        return Ok(());
+    };
+
+    let mut bytes = Vec::new();
+    reader.read_until(b'\n', &mut bytes)?;
+    comments.push(comment_text(&bytes));
+    Ok(())
+}
+
+/// Skip the rest of a block comment whose opening `/*` has already been consumed.
+fn skip_block_comment<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    comments: Option<&mut Vec<String>>,
+) -> Result<()> {
+    let mut bytes = Vec::new();
+    loop {
+        if comments.is_some() {
+            reader.read_until(b'*', &mut bytes)?;
+        } else {
+            reader.skip_until(b'*')?;
+        }
+        let Some(next_byte) = reader.read_byte()? else {
+            return Err(Error::syntax("unclosed block comment"));
+        };
+        if next_byte == b'/' {
+            break;
+        }
+        bytes.push(next_byte);
+    }
+
+    if let Some(comments) = comments {
+        // remove the '*' of the closing "*/"
+        bytes.pop();
+        comments.push(comment_text(&bytes));
+    }
+    Ok(())
+}
+
+fn comment_text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim().to_owned()
+}
+
 #[cfg(test)]
 mod tests {
+    use std::io::Read;
+
     use super::*;
 
     #[test]
@@ -178,4 +284,39 @@ mod tests {
 
         assert!(parse_sep(&mut reader).unwrap());
     }
+
+    #[test]
+    fn test_collect_comments() {
+        let data = "\
+        // first\r
+        /* second\n line */ value /* third */ // fourth
+        next
+        ";
+
+        let mut comments = Vec::new();
+        let mut reader = PeekReader::new(data.as_bytes());
+        skip_whitespace_collecting(&mut reader, Some(&mut comments)).unwrap();
+        assert_eq!(comments, ["first", "second\n line"]);
+
+        let mut buf = [0; 5];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"value");
+
+        comments.clear();
+        assert!(parse_sep_collecting(&mut reader, Some(&mut comments)).unwrap());
+        assert_eq!(comments, ["third", "fourth"]);
+    }
+
+    #[test]
+    fn test_json_whitespace() {
+        let mut reader = PeekReader::new(" \r\n\t1 \n , 2\n3 // comment".as_bytes());
+        skip_json_whitespace(&mut reader).unwrap();
+        assert_eq!(reader.read_byte().unwrap(), Some(b'1'));
+        assert!(parse_json_sep(&mut reader).unwrap());
+        skip_json_whitespace(&mut reader).unwrap();
+        assert_eq!(reader.read_byte().unwrap(), Some(b'2'));
+        assert!(!parse_json_sep(&mut reader).unwrap());
+        assert_eq!(reader.read_byte().unwrap(), Some(b'3'));
+        assert!(parse_json_sep(&mut reader).is_err());
+    }
 }
@@ -0,0 +1,204 @@
+//! Find the structural differences between two MASON values.
+//!
+//! ```
+//! # use mason_rs::{Value, diff::{Change, diff}};
+//! # use std::str::FromStr;
+//! #
+//! let old = Value::from_str("port: 80, hosts: [\"a\", \"b\"], debug: true").unwrap();
+//! let new = Value::from_str("port: 8080, hosts: [\"a\"], tls: {}").unwrap();
+//!
+//! let changes: Vec<String> = diff(&old, &new).iter().map(Change::to_string).collect();
+//! assert_eq!(
+//!     changes,
+//!     [
+//!         "- debug: true",
+//!         "- hosts.1: \"b\"",
+//!         "~ port: 80 -> 8080",
+//!         "+ tls: {}",
+//!     ]
+//! );
+//! ```
+
+use std::fmt::{self, Display};
+
+use crate::{SerializeOptions, Value, serialize::write_value, utils::with_key};
+
+/// A difference between two values, found by [`diff`].
+///
+/// The path of a change is a list of keys separated by `.`, where array elements
+/// are identified by their index, like in [`Value::insert_path`]. The path of the
+/// top level value is the empty string.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// A key or array element that is only in the new value.
+    Added { path: String, value: Value },
+    /// A key or array element that is only in the old value.
+    Removed { path: String, value: Value },
+    /// A value that is different in the old and the new value.
+    Changed {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+impl Change {
+    /// The path of the value that changed.
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Added { path, .. } | Self::Removed { path, .. } | Self::Changed { path, .. } => {
+                path
+            }
+        }
+    }
+}
+
+impl Display for Change {
+    /// Write the change on a single line, starting with `+` for added values, `-`
+    /// for removed values and `~` for changed values. Values are written as
+    /// compact MASON, with braces around objects.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = SerializeOptions::new().compact(true).braced_root(true);
+        let path = match self.path() {
+            "" => "<root>",
+            path => path,
+        };
+        match self {
+            Self::Added { value, .. } => {
+                write!(f, "+ {path}: ")?;
+                write_value(value, f, &options)
+            }
+            Self::Removed { value, .. } => {
+                write!(f, "- {path}: ")?;
+                write_value(value, f, &options)
+            }
+            Self::Changed { old, new, .. } => {
+                write!(f, "~ {path}: ")?;
+                write_value(old, f, &options)?;
+                write!(f, " -> ")?;
+                write_value(new, f, &options)
+            }
+        }
+    }
+}
+
+/// Find the differences between `old` and `new`.
+///
+/// Objects are compared key by key, and arrays element by element, so a change
+/// deep inside a document is reported with the path to it. Any other values are
+/// compared as a whole. Changes are sorted by path, with array elements in order.
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_at(&mut String::new(), old, new, &mut changes);
+    changes
+}
+
+fn diff_at(path: &mut String, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let mut keys: Vec<&str> = old.keys().chain(new.keys()).map(|key| &**key).collect();
+            keys.sort_unstable();
+            keys.dedup();
+            for key in keys {
+                with_key(path, key, |path| match (old.get(key), new.get(key)) {
+                    (Some(old), Some(new)) => diff_at(path, old, new, changes),
+                    (Some(old), None) => changes.push(Change::Removed {
+                        path: path.clone(),
+                        value: old.clone(),
+                    }),
+                    (None, Some(new)) => changes.push(Change::Added {
+                        path: path.clone(),
+                        value: new.clone(),
+                    }),
+                    (None, None) => unreachable!("{key:?} is a key of either object"),
+                });
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for i in 0..old.len().max(new.len()) {
+                with_key(path, &i.to_string(), |path| {
+                    match (old.get(i), new.get(i)) {
+                        (Some(old), Some(new)) => diff_at(path, old, new, changes),
+                        (Some(old), None) => changes.push(Change::Removed {
+                            path: path.clone(),
+                            value: old.clone(),
+                        }),
+                        (None, Some(new)) => changes.push(Change::Added {
+                            path: path.clone(),
+                            value: new.clone(),
+                        }),
+                        (None, None) => unreachable!("{i} is an index of either array"),
+                    }
+                });
+            }
+        }
+        (Value::Tagged(old_tag, old), Value::Tagged(new_tag, new)) if old_tag == new_tag => {
+            diff_at(path, old, new, changes);
+        }
+        (old, new) if old != new => changes.push(Change::Changed {
+            path: path.clone(),
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// Call `f` with `key` appended to `path`.
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let value = |string| Value::from_str(string).unwrap();
+
+        let old = value("a: {b: [1, {c: null}], d: \"x\"}, e: [], f: b\"f\"");
+        assert_eq!(diff(&old, &old), []);
+
+        let new = value("a: {b: [1, {c: 2}, 3], d: \"x\"}, e: {}, g: false");
+        assert_eq!(
+            diff(&old, &new),
+            [
+                Change::Changed {
+                    path: "a.b.1.c".to_owned(),
+                    old: Value::Null,
+                    new: Value::from(2.0),
+                },
+                Change::Added {
+                    path: "a.b.2".to_owned(),
+                    value: Value::from(3.0),
+                },
+                Change::Changed {
+                    path: "e".to_owned(),
+                    old: value("[]"),
+                    new: value("{}"),
+                },
+                Change::Removed {
+                    path: "f".to_owned(),
+                    value: Value::ByteString(b"f".to_vec()),
+                },
+                Change::Added {
+                    path: "g".to_owned(),
+                    value: Value::Bool(false),
+                },
+            ]
+        );
+
+        let changes = diff(&Value::from(1.0), &Value::Null);
+        assert_eq!(changes[0].path(), "");
+        assert_eq!(changes[0].to_string(), "~ <root>: 1 -> null");
+
+        let tagged = |tag: &str, value| Value::Tagged(tag.to_owned(), Box::new(value));
+        let old = tagged("t", value("[1]"));
+        let changes = diff(&old, &tagged("t", value("[2]")));
+        assert_eq!(changes[0].to_string(), "~ 0: 1 -> 2");
+        let changes = diff(&old, &tagged("u", value("[1]")));
+        assert_eq!(changes[0].to_string(), "~ <root>: @t [1] -> @u [1]");
+
+        let changes = diff(&value("a: 1"), &value("a: {b: 2}"));
+        assert_eq!(changes[0].to_string(), "~ a: 1 -> {b: 2}");
+    }
+}
@@ -0,0 +1,176 @@
+//! Transcode UTF-16 input to UTF-8 before it is parsed.
+
+use std::io::{self, BufRead, BufReader, Read};
+
+use crate::utils::fill_buf_retrying;
+
+/// A reader of UTF-8, which is either read as is, or transcoded from UTF-16.
+pub(crate) enum Decoder<R: BufRead> {
+    Utf8(R),
+    Utf16(BufReader<Utf16Reader<R>>),
+}
+
+/// Detect the encoding of `reader` from its byte order mark. If it is UTF-16,
+/// the returned reader transcodes it to UTF-8, and the byte order mark is removed.
+/// Otherwise, `reader` is read as is.
+pub(crate) fn decode<R: BufRead>(mut reader: R) -> io::Result<Decoder<R>> {
+    let big_endian = match *fill_buf_retrying(&mut reader)? {
+        [0xFE, 0xFF, ..] => true,
+        [0xFF, 0xFE, ..] => false,
+        _ => return Ok(Decoder::Utf8(reader)),
+    };
+    reader.consume(2);
+    Ok(Decoder::Utf16(BufReader::new(Utf16Reader {
+        inner: reader,
+        big_endian,
+        pending: [0; 4],
+        pending_range: 0..0,
+    })))
+}
+
+impl<R: BufRead> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Utf8(reader) => reader.read(buf),
+            Self::Utf16(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl<R: BufRead> BufRead for Decoder<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self {
+            Self::Utf8(reader) => reader.fill_buf(),
+            Self::Utf16(reader) => reader.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self {
+            Self::Utf8(reader) => reader.consume(amt),
+            Self::Utf16(reader) => reader.consume(amt),
+        }
+    }
+}
+
+/// Reads UTF-16 from `inner`, and returns it as UTF-8.
+pub(crate) struct Utf16Reader<R> {
+    inner: R,
+    big_endian: bool,
+    /// The UTF-8 bytes of the last decoded character.
+    pending: [u8; 4],
+    /// The bytes in `pending` that have not been returned yet.
+    pending_range: std::ops::Range<usize>,
+}
+
+impl<R: Read> Utf16Reader<R> {
+    /// Read the next UTF-16 code unit, or `None` at EOF.
+    fn read_unit(&mut self) -> io::Result<Option<u16>> {
+        let mut bytes = [0; 2];
+        let mut read = 0;
+        while read < 2 {
+            match self.inner.read(&mut bytes[read..]) {
+                Ok(0) if read == 0 => return Ok(None),
+                Ok(0) => return Err(invalid_data("UTF-16 input has an odd number of bytes")),
+                Ok(n) => read += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(Some(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }))
+    }
+
+    /// Read the next character, or `None` at EOF.
+    fn read_char(&mut self) -> io::Result<Option<char>> {
+        let Some(unit) = self.read_unit()? else {
+            return Ok(None);
+        };
+        let units = if (0xD800..0xDC00).contains(&unit) {
+            let low = self.read_unit()?.unwrap_or(0);
+            vec![unit, low]
+        } else {
+            vec![unit]
+        };
+        match char::decode_utf16(units).next() {
+            Some(Ok(c)) => Ok(Some(c)),
+            _ => Err(invalid_data("UTF-16 input contains an unpaired surrogate")),
+        }
+    }
+}
+
+impl<R: Read> Read for Utf16Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            if self.pending_range.is_empty() {
+                let Some(c) = self.read_char()? else {
+                    break;
+                };
+                self.pending_range = 0..c.encode_utf8(&mut self.pending).len();
+            }
+
+            let pending = &self.pending[self.pending_range.clone()];
+            let amount = pending.len().min(buf.len() - written);
+            buf[written..written + amount].copy_from_slice(&pending[..amount]);
+            self.pending_range.start += amount;
+            written += amount;
+        }
+        Ok(written)
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(string: &str, big_endian: bool) -> Vec<u8> {
+        let mut bytes = if big_endian {
+            vec![0xFE, 0xFF]
+        } else {
+            vec![0xFF, 0xFE]
+        };
+        for unit in string.encode_utf16() {
+            if big_endian {
+                bytes.extend(unit.to_be_bytes());
+            } else {
+                bytes.extend(unit.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_decode() {
+        let string = "key: \"værdi 🎉\"";
+        for big_endian in [true, false] {
+            let bytes = utf16(string, big_endian);
+            let mut decoded = String::new();
+            decode(bytes.as_slice())
+                .unwrap()
+                .read_to_string(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, string);
+        }
+
+        let mut decoded = String::new();
+        decode(string.as_bytes())
+            .unwrap()
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, string);
+
+        for bytes in [&[0xFF, 0xFE, b'a'][..], &[0xFF, 0xFE, 0x00, 0xD8, b'a', 0]] {
+            let mut decoded = String::new();
+            let err = decode(bytes).unwrap().read_to_string(&mut decoded);
+            assert_eq!(err.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
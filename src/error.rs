@@ -0,0 +1,395 @@
+//! When parsing MASON goes wrong.
+
+use std::{
+    fmt::{self, Debug, Display},
+    io,
+};
+
+use crate::Position;
+
+/// Alias for a `Result` with the error type [`mason_rs::Error`](Error).
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The category of an [`Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The input ended before the value was complete.
+    UnexpectedEof,
+    /// A number was malformed.
+    InvalidNumber,
+    /// A string contained an invalid escape sequence.
+    InvalidEscape,
+    /// An object contained the same key more than once, and
+    /// [`ParseOptions::reject_duplicate_keys`](crate::ParseOptions::reject_duplicate_keys)
+    /// was set.
+    DuplicateKey,
+    /// The value was nested too deeply.
+    DepthExceeded,
+    /// The input was not valid MASON.
+    Syntax,
+    /// A [`Query`](crate::Query) was malformed.
+    InvalidQuery,
+    /// A [`Value`](crate::Value) could not be converted with
+    /// [`FromMason`](crate::FromMason), because it had the wrong type or was
+    /// missing a field.
+    InvalidType,
+    /// A placeholder could not be resolved by a [`Resolver`](crate::Resolver).
+    Resolve,
+    /// Parsing was cancelled with [`ParseOptions::cancel_token`](crate::ParseOptions::cancel_token).
+    Cancelled,
+    /// The profile given to [`Value::apply_profile`](crate::Value::apply_profile)
+    /// does not exist.
+    UnknownProfile,
+    /// A string was not valid base64, see `Value::byte_string_from_base64`.
+    InvalidBase64,
+    /// An error occurred while reading the input.
+    Io,
+}
+
+/// This type represents all possible errors that can occur when parsing MASON
+/// data into a [`Value`](crate::Value).
+pub struct Error {
+    /// This `Box` allows us to keep the size of `Error` as small as possible.
+    inner: Box<InnerError>,
+}
+
+#[derive(Debug)]
+struct InnerError {
+    kind: ErrorKind,
+    message: String,
+    position: Option<Position>,
+    source: Option<io::Error>,
+    /// The line of the input where the error occurred, see [`Error::with_source`].
+    snippet: Option<String>,
+    /// The path of the value that could not be converted, see [`Error::in_field`].
+    path: Option<String>,
+}
+
+impl Error {
+    pub(crate) fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            inner: Box::new(InnerError {
+                kind,
+                message: message.into(),
+                position: None,
+                source: None,
+                snippet: None,
+                path: None,
+            }),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn syntax(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Syntax, message)
+    }
+
+    #[inline]
+    pub(crate) fn eof(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::UnexpectedEof, message)
+    }
+
+    /// Create an [`ErrorKind::InvalidType`] error for a `value` that could not be
+    /// converted to `expected`, for use in [`FromMason`](crate::FromMason)
+    /// implementations.
+    ///
+    /// ```
+    /// # use mason_rs::{Error, Value};
+    /// #
+    /// let err = Error::invalid_type("a string", &Value::from(1.0));
+    /// assert_eq!(err.to_string(), "expected a string, got number");
+    /// ```
+    pub fn invalid_type(expected: &str, value: &crate::Value) -> Self {
+        Self::new(
+            ErrorKind::InvalidType,
+            format!("expected {expected}, got {}", value.value_type()),
+        )
+    }
+
+    /// Create an [`ErrorKind::InvalidType`] error for an object that is missing
+    /// `field`, for use in [`FromMason`](crate::FromMason) implementations.
+    pub fn missing_field(field: &str) -> Self {
+        Self::new(ErrorKind::InvalidType, format!("missing field {field:?}"))
+    }
+
+    /// Record that the error occurred inside `field`, which is a key or an array
+    /// index. Called from the innermost field outwards, this builds the path of
+    /// the value that could not be converted, which is shown when the error is
+    /// displayed.
+    ///
+    /// ```
+    /// # use mason_rs::{Error, Value};
+    /// #
+    /// let err = Error::invalid_type("bool", &Value::Null).in_field(0).in_field("flags");
+    /// assert_eq!(err.path(), Some("flags.0"));
+    /// assert_eq!(err.to_string(), "expected bool, got null in `flags.0`");
+    /// ```
+    pub fn in_field(mut self, field: impl Display) -> Self {
+        self.inner.path = Some(match self.inner.path.take() {
+            Some(path) => format!("{field}.{path}"),
+            None => field.to_string(),
+        });
+        self
+    }
+
+    /// Set the position of the error, unless it already has one.
+    pub(crate) fn at(mut self, position: Position) -> Self {
+        self.inner.position.get_or_insert(position);
+        self
+    }
+
+    /// Include the line of `source` where the error occurred when the error is
+    /// displayed. Does nothing if the position of the error is unknown.
+    pub(crate) fn with_source(mut self, source: &[u8]) -> Self {
+        if let Some(position) = self.inner.position {
+            self.inner.snippet = Some(snippet(source, position));
+        }
+        self
+    }
+
+    /// Display this error together with the line of `source` where it occurred,
+    /// with a caret under the position of the error, like rustc does. `source`
+    /// must be the input that was parsed. Errors parsed from a string with
+    /// [`Value::from_str`](std::str::FromStr) already include the line when displayed.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// #
+    /// let source = b"a: 1\nb: [1, 2, }";
+    /// let err = Value::from_slice(source).unwrap_err();
+    /// assert_eq!(
+    ///     err.display_with_source(source).to_string(),
+    ///     "\
+    /// key identifier starts with invalid char: '}' at line 2, column 11
+    ///   |
+    /// 2 | b: [1, 2, }
+    ///   |           ^",
+    /// );
+    /// ```
+    pub fn display_with_source<'a, S>(&'a self, source: &'a S) -> impl Display + 'a
+    where
+        S: AsRef<[u8]> + ?Sized,
+    {
+        DisplayWithSource {
+            error: self,
+            source: source.as_ref(),
+        }
+    }
+
+    /// The category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        self.inner.kind
+    }
+
+    /// The position in the input where this error occurred, if it is known.
+    pub fn position(&self) -> Option<Position> {
+        self.inner.position
+    }
+
+    /// The path of the value that could not be converted, for errors from
+    /// [`FromMason`](crate::FromMason) and [`Value::resolve`](crate::Value::resolve).
+    /// See [`Error::in_field`].
+    pub fn path(&self) -> Option<&str> {
+        self.inner.path.as_deref()
+    }
+
+    /// A description of the error, without the position.
+    pub fn message(&self) -> &str {
+        &self.inner.message
+    }
+
+    /// Returns true if this error was caused by the input ending too soon.
+    pub fn is_eof(&self) -> bool {
+        self.inner.kind == ErrorKind::UnexpectedEof
+    }
+
+    /// Returns the underlying I/O error, if this error was caused by one.
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.inner.source.as_ref()
+    }
+
+    /// Convert this error into an I/O error, keeping the underlying I/O
+    /// error if there is one.
+    pub fn into_io_error(mut self) -> io::Error {
+        if let Some(source) = self.inner.source.take() {
+            return source;
+        }
+
+        let kind = match self.inner.kind {
+            ErrorKind::UnexpectedEof => io::ErrorKind::UnexpectedEof,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, self)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        let kind = if value.kind() == io::ErrorKind::UnexpectedEof {
+            ErrorKind::UnexpectedEof
+        } else {
+            ErrorKind::Io
+        };
+        let mut error = Self::new(kind, value.to_string());
+        error.inner.source = Some(value);
+        error
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(value: Error) -> Self {
+        value.into_io_error()
+    }
+}
+
+impl Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.inner.position {
+            Some(position) => write!(formatter, "{} at {position}", self.inner.message)?,
+            None => formatter.write_str(&self.inner.message)?,
+        }
+        if let Some(path) = &self.inner.path {
+            write!(formatter, " in `{path}`")?;
+        }
+        match &self.inner.snippet {
+            Some(snippet) => write!(formatter, "\n{snippet}"),
+            None => Ok(()),
+        }
+    }
+}
+
+struct DisplayWithSource<'a> {
+    error: &'a Error,
+    source: &'a [u8],
+}
+
+impl Display for DisplayWithSource<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.error)?;
+        match self.error.inner.position {
+            Some(position) if self.error.inner.snippet.is_none() => {
+                write!(formatter, "\n{}", snippet(self.source, position))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Show the line of `source` that contains `position`, with a caret under the
+/// position and the line number in a gutter to the left.
+pub(crate) fn snippet(source: &[u8], position: Position) -> String {
+    let offset = position.offset.min(source.len());
+    let start = source[..offset]
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map_or(0, |newline| newline + 1);
+    let end = source[offset..]
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .map_or(source.len(), |newline| offset + newline);
+
+    let line = String::from_utf8_lossy(&source[start..end]);
+    let line = line.trim_end_matches('\r');
+    // keep tabs, so the caret lines up with the line above it
+    let padding: String = String::from_utf8_lossy(&source[start..offset])
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    let number = position.line.to_string();
+    let gutter = " ".repeat(number.len());
+    format!("{gutter} |\n{number} | {line}\n{gutter} | {padding}^")
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.inner
+            .source
+            .as_ref()
+            .map(|err| err as &(dyn std::error::Error + 'static))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{ParseOptions, Value};
+
+    #[test]
+    fn test_error_kind() {
+        let kind = |string: &str| Value::from_str(string).unwrap_err().kind();
+        assert_eq!(kind("[1, 2"), ErrorKind::UnexpectedEof);
+        assert_eq!(kind("0x"), ErrorKind::UnexpectedEof);
+        assert_eq!(kind("1.e4"), ErrorKind::InvalidNumber);
+        assert_eq!(kind(r#""\q""#), ErrorKind::InvalidEscape);
+        assert_eq!(kind(&"[".repeat(200)), ErrorKind::DepthExceeded);
+        assert_eq!(kind("[1 2]"), ErrorKind::Syntax);
+        assert_eq!(kind("nul"), ErrorKind::Syntax);
+
+        let err = ParseOptions::new()
+            .reject_duplicate_keys(true)
+            .parse_str("a: 1, a: 2")
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DuplicateKey);
+        assert_eq!(
+            Value::from_str("a: 1, a: 2").unwrap()["a"],
+            Value::from(2.0)
+        );
+    }
+
+    #[test]
+    fn test_error_position() {
+        let err = Value::from_str("a: 1\nb: [1, 2, }").unwrap_err();
+        let position = err.position().unwrap();
+        assert_eq!((position.line, position.column), (2, 11));
+        assert!(
+            err.to_string()
+                .lines()
+                .next()
+                .unwrap()
+                .ends_with("at line 2, column 11")
+        );
+
+        let err = Error::from(io::Error::other("oh no"));
+        assert_eq!(err.kind(), ErrorKind::Io);
+        assert!(err.io_error().is_some());
+        assert_eq!(err.into_io_error().to_string(), "oh no");
+    }
+
+    #[test]
+    fn test_snippet() {
+        let err = Value::from_str("a: 1\nb: [1, 2, }").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "key identifier starts with invalid char: '}' at line 2, column 11\n  |\n2 | b: [1, 2, }\n  |           ^"
+        );
+        // the snippet is only shown once
+        assert_eq!(
+            err.display_with_source("a: 1\nb: [1, 2, }").to_string(),
+            err.to_string()
+        );
+
+        let source = "// the ports\r\nports: [\n\t80,\n\t\"æøå\" 1\n]";
+        let err = Value::from_reader(source.as_bytes()).unwrap_err();
+        assert!(!err.to_string().contains('^'));
+        assert_eq!(
+            err.display_with_source(source).to_string(),
+            format!("{err}\n  |\n4 | \t\"æøå\" 1\n  | \t      ^")
+        );
+
+        let err = Value::from_str("[\n1,\n").unwrap_err();
+        assert!(err.to_string().ends_with("\n  |\n3 | \n  | ^"), "{err}");
+
+        let err = Error::syntax("no position");
+        assert_eq!(err.display_with_source("source").to_string(), "no position");
+    }
+}
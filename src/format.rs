@@ -0,0 +1,181 @@
+use std::ops::Range;
+
+use crate::{LineEnding, ParseOptions, Result, SerializeOptions};
+
+/// A change to a text, found by [`format_range`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Edit {
+    /// The byte range of the text that should be replaced.
+    pub range: Range<usize>,
+    /// The text that replaces the range.
+    pub new_text: String,
+}
+
+impl Edit {
+    /// Check if the edit changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty() && self.new_text.is_empty()
+    }
+
+    /// Apply the edit to `text`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range of the edit is out of bounds for `text`, or does not
+    /// start and end on character boundaries.
+    pub fn apply(&self, text: &mut String) {
+        text.replace_range(self.range.clone(), &self.new_text);
+    }
+}
+
+/// Format the part of `source` in `range`, like for the "format selection" command
+/// of an editor, and return the smallest edit that does it. Text outside of
+/// `range` is never changed.
+///
+/// The selected text must be a complete MASON document on its own, like a single
+/// value or some key-value pairs of an object. Lines after the first are indented
+/// like the line the range starts on, and whitespace around the selected value is
+/// kept. Selections with comments are left as they are, since formatting would
+/// remove the comments.
+///
+/// ```
+/// # use mason_rs::format_range;
+/// #
+/// let mut source = "name: \"demo\"\nlimits: {memory:   [1,2]}".to_owned();
+/// let edit = format_range(&source, 21..source.len()).unwrap();
+/// edit.apply(&mut source);
+/// assert_eq!(source, "name: \"demo\"\nlimits: {\n    memory: [1, 2]\n}");
+///
+/// // the selection is already formatted, so nothing changes
+/// assert!(format_range(&source, 21..source.len()).unwrap().is_empty());
+/// ```
+///
+/// # Errors
+///
+/// This function fails if the selected text is not a valid MASON document. The
+/// positions in the error are relative to the start of `range`.
+///
+/// # Panics
+///
+/// Panics if `range` is out of bounds for `source`, or does not start and end on
+/// character boundaries.
+pub fn format_range(source: &str, range: Range<usize>) -> Result<Edit> {
+    let selection = &source[range.clone()];
+    let unchanged = Edit {
+        range: range.start..range.start,
+        new_text: String::new(),
+    };
+
+    let content = selection.trim();
+    if content.is_empty() {
+        return Ok(unchanged);
+    }
+    let parsed = ParseOptions::new()
+        .capture_comments(true)
+        .capture_string_styles(true)
+        .parse_str(content)?;
+    if !parsed.comments.is_empty() {
+        return Ok(unchanged);
+    }
+
+    let line_start = source[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_indentation: &str = {
+        let line = &source[line_start..];
+        &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+    };
+    let line_ending = LineEnding::detect(source);
+    let options = SerializeOptions::new()
+        .indentation(if line_indentation.starts_with('\t') {
+            "\t"
+        } else {
+            "    "
+        })
+        .line_ending(line_ending)
+        // a selected object keeps its braces, and selected key-value pairs stay without
+        .braced_root(content.starts_with('{'));
+    let mut formatted = String::new();
+    parsed
+        .to_writer_with_options(&mut formatted, &options)
+        .expect("writing to a String does not fail");
+    let newline = line_ending.as_str();
+    let formatted = formatted.replace(newline, &format!("{newline}{line_indentation}"));
+
+    // only replace the part of the content that changed
+    let prefix_len = common_prefix_len(content, &formatted);
+    let suffix_len = common_prefix_len(
+        &content[prefix_len..].chars().rev().collect::<String>(),
+        &formatted[prefix_len..].chars().rev().collect::<String>(),
+    );
+    let content_start = range.start + (selection.len() - selection.trim_start().len());
+    Ok(Edit {
+        range: content_start + prefix_len..content_start + content.len() - suffix_len,
+        new_text: formatted[prefix_len..formatted.len() - suffix_len].to_owned(),
+    })
+}
+
+/// The length in bytes of the longest common prefix of `a` and `b`.
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(a.len().min(b.len()), |((i, _), _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(source: &str, range: Range<usize>) -> String {
+        let mut source = source.to_owned();
+        format_range(&source, range).unwrap().apply(&mut source);
+        source
+    }
+
+    #[test]
+    fn test_format_range() {
+        let source = "a: {\n    b: [1,2,\n    3]\n    c: true\n}";
+        let start = source.find('[').unwrap();
+        let end = source.find(']').unwrap() + 1;
+        assert_eq!(
+            format(source, start..end),
+            "a: {\n    b: [1, 2, 3]\n    c: true\n}"
+        );
+        let edit = format_range(source, start..end).unwrap();
+        assert_eq!(edit.range, start + 3..start + 9);
+        assert_eq!(edit.new_text, " 2,");
+
+        // key-value pairs keep their indentation and stay without braces
+        let source = "a: {\n\tc:{d:\"x\"}\n}";
+        assert_eq!(format(source, 6..15), "a: {\n\tc: {\n\t\td: \"x\"\n\t}\n}");
+
+        // strings keep the style they were written in
+        let source = "a: {b:[r\"x\",\"\\u{41}\"]}";
+        assert_eq!(
+            format(source, 3..source.len()),
+            "a: {\n    b: [r\"x\", \"\\u{41}\"]\n}"
+        );
+
+        // whitespace around the selection is kept
+        assert_eq!(format("a:  [1,2]  \nb: 1", 2..11), "a:  [1, 2]  \nb: 1");
+        assert_eq!(
+            format("a: 1\r\nb: {c:1}", 9..14),
+            "a: 1\r\nb: {\r\n    c: 1\r\n}"
+        );
+    }
+
+    #[test]
+    fn test_format_range_unchanged() {
+        for (source, range) in [
+            ("a: [1, 2]", 3..9),
+            ("a: [1,2] // one, two", 0..20),
+            ("a: [1,2]", 3..3),
+            ("a: [1,2]", 2..3),
+        ] {
+            let edit = format_range(source, range).unwrap();
+            assert!(edit.is_empty(), "{source:?} changed: {edit:?}");
+        }
+
+        // the selection is not a complete value
+        assert!(format_range("a: [1, 2]", 3..6).is_err());
+    }
+}
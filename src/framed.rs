@@ -0,0 +1,471 @@
+//! Read and write streams of MASON messages, like MASON used as a wire format for
+//! logs or remote procedure calls.
+//!
+//! ```
+//! # use mason_rs::{Value, framed::Reader};
+//! # use std::str::FromStr;
+//! #
+//! let stream = "{level: \"info\", message: \"started\"}\n\nlevel: \"warn\", message: \"slow\"\n";
+//! let messages: Vec<Value> = Reader::lines(stream.as_bytes()).collect::<Result<_, _>>().unwrap();
+//! assert_eq!(messages.len(), 2);
+//! assert_eq!(messages[1], Value::from_str("{level: \"warn\", message: \"slow\"}").unwrap());
+//! ```
+
+use std::io::{self, BufRead, Read, Write};
+
+use crate::{Error, ParseOptions, Result, SerializeOptions, Value};
+
+/// How the messages in a stream are separated from each other.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Framing {
+    /// Every line is a message, like in NDJSON. Lines with only whitespace are
+    /// skipped, so messages can not contain newlines.
+    #[default]
+    Lines,
+    /// Every message starts with its length in bytes, as a 32-bit big-endian
+    /// unsigned integer.
+    LengthPrefixed,
+}
+
+/// A reader for a stream of MASON messages. Each message is parsed as a
+/// document on its own, so the positions in parse errors are relative to the
+/// start of the message.
+///
+/// The reader is also an iterator over the values of the messages.
+#[derive(Debug)]
+pub struct Reader<R> {
+    reader: R,
+    framing: Framing,
+    options: ParseOptions,
+    /// The bytes of the current message, which is kept to reuse its allocation.
+    buffer: Vec<u8>,
+}
+
+impl<R: BufRead> Reader<R> {
+    /// Create a reader for the messages in `reader`, separated as described by
+    /// `framing`.
+    pub fn new(reader: R, framing: Framing) -> Self {
+        Self {
+            reader,
+            framing,
+            options: ParseOptions::new(),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Create a reader for messages that are separated by newlines.
+    pub fn lines(reader: R) -> Self {
+        Self::new(reader, Framing::Lines)
+    }
+
+    /// Create a reader for messages that start with their length.
+    pub fn length_prefixed(reader: R) -> Self {
+        Self::new(reader, Framing::LengthPrefixed)
+    }
+
+    /// The options every message is parsed with. The default is
+    /// [`ParseOptions::new`].
+    pub fn options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Get the reader that the messages are read from.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Read the next message, or None if the stream has ended.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if reading from the stream fails, if the stream ends
+    /// in the middle of a length-prefixed message, or if the message is not
+    /// valid MASON.
+    pub fn read_value(&mut self) -> Result<Option<Value>> {
+        if !self.read_message()? {
+            return Ok(None);
+        }
+        Ok(Some(self.options.parse_slice(&self.buffer)?.value))
+    }
+
+    /// Read the next message, and deserialize it as a `T`.
+    ///
+    /// ```
+    /// # use mason_rs::framed::Reader;
+    /// #
+    /// #[derive(serde::Deserialize)]
+    /// struct Request {
+    ///     id: u32,
+    ///     method: String,
+    /// }
+    ///
+    /// let mut stream = Vec::new();
+    /// for message in ["{id: 1, method: \"ping\"}", "id: 2, method: \"stop\""] {
+    ///     stream.extend((message.len() as u32).to_be_bytes());
+    ///     stream.extend(message.as_bytes());
+    /// }
+    ///
+    /// let mut reader = Reader::length_prefixed(stream.as_slice());
+    /// let request: Request = reader.read_as().unwrap().unwrap();
+    /// assert_eq!((request.id, request.method.as_str()), (1, "ping"));
+    /// let request: Request = reader.read_as().unwrap().unwrap();
+    /// assert_eq!((request.id, request.method.as_str()), (2, "stop"));
+    /// assert!(reader.read_as::<Request>().unwrap().is_none());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function fails if reading from the stream fails, if the stream ends
+    /// in the middle of a length-prefixed message, or if the message can not be
+    /// deserialized as a `T`.
+    #[cfg(feature = "serde")]
+    pub fn read_as<T: serde::de::DeserializeOwned>(
+        &mut self,
+    ) -> crate::serde::error::Result<Option<T>> {
+        if !self.read_message()? {
+            return Ok(None);
+        }
+        crate::from_slice(&self.buffer).map(Some)
+    }
+
+    /// Read the next message into the buffer, and return false if the stream
+    /// has ended.
+    fn read_message(&mut self) -> Result<bool> {
+        self.buffer.clear();
+        match self.framing {
+            Framing::Lines => loop {
+                if self.reader.read_until(b'\n', &mut self.buffer)? == 0 {
+                    return Ok(false);
+                }
+                if !self.buffer.iter().all(u8::is_ascii_whitespace) {
+                    return Ok(true);
+                }
+                self.buffer.clear();
+            },
+            Framing::LengthPrefixed => {
+                let mut prefix = [0; 4];
+                let read = read_fully(&mut self.reader, &mut prefix)?;
+                if read == 0 {
+                    return Ok(false);
+                } else if read < prefix.len() {
+                    return Err(Error::eof("Got EOF in the length of a message"));
+                }
+
+                let len = u32::from_be_bytes(prefix) as u64;
+                // the buffer grows as the message is read, so a wrong length does
+                // not allocate more than the stream contains
+                let read = (&mut self.reader).take(len).read_to_end(&mut self.buffer)?;
+                if (read as u64) < len {
+                    return Err(Error::eof(format!(
+                        "Got EOF after {read} of the {len} bytes of a message"
+                    )));
+                }
+                Ok(true)
+            }
+        }
+    }
+}
+
+/// Read into `buf` until it is full or the reader ends, and return the number of
+/// bytes read.
+fn read_fully(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(read)
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_value().transpose()
+    }
+}
+
+/// When a [`Writer`] flushes the stream it writes to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FlushPolicy {
+    /// Only flush when [`Writer::flush`] is called. This is the fastest, but
+    /// messages can stay in the buffer of a buffered stream for a long time.
+    #[default]
+    Manual,
+    /// Flush after every message, so that it can be read right away.
+    EveryMessage,
+}
+
+/// A writer for a stream of MASON messages, which can be read with a [`Reader`].
+/// Each message is written compactly on a single line.
+///
+/// ```
+/// # use mason_rs::{Value, framed::{FlushPolicy, Reader, Writer}};
+/// # use std::str::FromStr;
+/// #
+/// let mut writer = Writer::lines(Vec::new()).flush_policy(FlushPolicy::EveryMessage);
+/// writer.write_value(&Value::from_str("tags: [\"a\", \"b\"]").unwrap()).unwrap();
+/// writer.write_value(&Value::String("multi\nline".into())).unwrap();
+///
+/// let stream = writer.into_inner();
+/// assert_eq!(stream, b"{tags: [\"a\", \"b\"]}\n\"multi\\nline\"\n");
+/// assert_eq!(Reader::lines(stream.as_slice()).count(), 2);
+/// ```
+#[derive(Debug)]
+pub struct Writer<W> {
+    writer: W,
+    framing: Framing,
+    delimiter: String,
+    flush_policy: FlushPolicy,
+    options: SerializeOptions,
+    /// The text of the current message, which is kept to reuse its allocation.
+    buffer: String,
+}
+
+impl<W: Write> Writer<W> {
+    /// Create a writer that writes messages to `writer`, separated as described by
+    /// `framing`.
+    pub fn new(writer: W, framing: Framing) -> Self {
+        Self {
+            writer,
+            framing,
+            delimiter: "\n".to_owned(),
+            flush_policy: FlushPolicy::default(),
+            options: SerializeOptions::new().compact(true).braced_root(true),
+            buffer: String::new(),
+        }
+    }
+
+    /// Create a writer that ends every message with a newline.
+    pub fn lines(writer: W) -> Self {
+        Self::new(writer, Framing::Lines)
+    }
+
+    /// Create a writer that starts every message with its length.
+    pub fn length_prefixed(writer: W) -> Self {
+        Self::new(writer, Framing::LengthPrefixed)
+    }
+
+    /// The text written after every message with [`Framing::Lines`]. The default
+    /// is `"\n"`. Compact MASON has no newlines, so a delimiter with a newline
+    /// never appears inside a message.
+    pub fn delimiter(mut self, delimiter: impl Into<String>) -> Self {
+        self.delimiter = delimiter.into();
+        self
+    }
+
+    /// When the stream is flushed. The default is [`FlushPolicy::Manual`].
+    pub fn flush_policy(mut self, flush_policy: FlushPolicy) -> Self {
+        self.flush_policy = flush_policy;
+        self
+    }
+
+    /// The options every message is written with. [`SerializeOptions::compact`]
+    /// is always set. The default also sets [`SerializeOptions::braced_root`].
+    pub fn options(mut self, options: SerializeOptions) -> Self {
+        self.options = options.compact(true);
+        self
+    }
+
+    /// Get the writer that the messages are written to.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Write `value` as a message.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if writing to the stream fails, or if the message is
+    /// too long for its length to fit in the prefix.
+    pub fn write_value(&mut self, value: &Value) -> io::Result<()> {
+        self.buffer.clear();
+        value
+            .to_writer_with_options(&mut self.buffer, &self.options)
+            .expect("writing to a String does not fail");
+        self.write_message()
+    }
+
+    /// Serialize `value`, and write it as a message.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if `value` can not be serialized, if writing to the
+    /// stream fails, or if the message is too long for its length to fit in the
+    /// prefix.
+    #[cfg(feature = "serde")]
+    pub fn write_as<T: serde::Serialize>(&mut self, value: &T) -> crate::serde::error::Result<()> {
+        self.buffer.clear();
+        value.serialize(&mut crate::Serializer::with_options(
+            &mut self.buffer,
+            self.options.clone(),
+        ))?;
+        Ok(self.write_message()?)
+    }
+
+    /// Flush the stream.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if flushing the stream fails.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Write the message in the buffer, framed as described by the framing.
+    fn write_message(&mut self) -> io::Result<()> {
+        match self.framing {
+            Framing::Lines => {
+                self.writer.write_all(self.buffer.as_bytes())?;
+                self.writer.write_all(self.delimiter.as_bytes())?;
+            }
+            Framing::LengthPrefixed => {
+                let len = u32::try_from(self.buffer.len()).map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("message of {} bytes is too long", self.buffer.len()),
+                    )
+                })?;
+                self.writer.write_all(&len.to_be_bytes())?;
+                self.writer.write_all(self.buffer.as_bytes())?;
+            }
+        }
+        if self.flush_policy == FlushPolicy::EveryMessage {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn value(string: &str) -> Value {
+        Value::from_str(string).unwrap()
+    }
+
+    fn length_prefixed(messages: &[&str]) -> Vec<u8> {
+        let mut stream = Vec::new();
+        for message in messages {
+            stream.extend((message.len() as u32).to_be_bytes());
+            stream.extend(message.as_bytes());
+        }
+        stream
+    }
+
+    #[test]
+    fn test_lines() {
+        let stream = b"[1, 2]\r\n  \n\"a\" // comment\na: {b: 1}";
+        let values: Vec<Value> = Reader::lines(&stream[..]).map(Result::unwrap).collect();
+        assert_eq!(
+            values,
+            [value("[1, 2]"), value("\"a\""), value("a: {b: 1}")]
+        );
+
+        let mut reader = Reader::lines(&b"1\n[\n2\n"[..]);
+        assert_eq!(reader.read_value().unwrap(), Some(value("1")));
+        assert!(reader.read_value().unwrap_err().is_eof());
+        // the reader can continue after an invalid message
+        assert_eq!(reader.read_value().unwrap(), Some(value("2")));
+        assert_eq!(reader.read_value().unwrap(), None);
+    }
+
+    #[test]
+    fn test_length_prefixed() {
+        let stream = length_prefixed(&["{a: 1,\n b: 2}", "", "\"x\""]);
+        let mut reader = Reader::length_prefixed(stream.as_slice());
+        assert_eq!(reader.read_value().unwrap(), Some(value("a: 1, b: 2")));
+        // an empty message is invalid, like an empty document
+        assert!(reader.read_value().unwrap_err().is_eof());
+        assert_eq!(reader.read_value().unwrap(), Some(value("\"x\"")));
+        assert_eq!(reader.read_value().unwrap(), None);
+
+        let mut stream = length_prefixed(&["[1, 2]"]);
+        stream.truncate(stream.len() - 1);
+        let err = Reader::length_prefixed(stream.as_slice())
+            .read_value()
+            .unwrap_err();
+        assert!(err.is_eof());
+        assert_eq!(err.message(), "Got EOF after 5 of the 6 bytes of a message");
+
+        let err = Reader::length_prefixed(&[0, 0][..])
+            .read_value()
+            .unwrap_err();
+        assert!(err.is_eof());
+
+        let stream = length_prefixed(&["[1]", "{a: 1}"]);
+        let reader =
+            Reader::length_prefixed(stream.as_slice()).options(ParseOptions::new().json_only(true));
+        assert!(reader.collect::<Result<Vec<_>>>().is_err());
+    }
+
+    #[test]
+    fn test_writer() {
+        let values = [
+            value("a: 1, b: {c: [1, 2]}"),
+            value("\"line\\nbreak\""),
+            value("[]"),
+        ];
+        for framing in [Framing::Lines, Framing::LengthPrefixed] {
+            let mut writer = Writer::new(Vec::new(), framing);
+            for value in &values {
+                writer.write_value(value).unwrap();
+            }
+            let stream = writer.into_inner();
+            let read: Vec<Value> = Reader::new(stream.as_slice(), framing)
+                .map(Result::unwrap)
+                .collect();
+            assert_eq!(read, values, "{framing:?}");
+        }
+
+        let mut writer = Writer::lines(Vec::new())
+            .delimiter("\r\n")
+            .options(SerializeOptions::new().sort_keys(true));
+        writer.write_value(&value("b: 1, a: 2")).unwrap();
+        assert_eq!(writer.into_inner(), b"a: 2, b: 1\r\n");
+
+        let mut writer = Writer::length_prefixed(Vec::new());
+        writer.write_value(&value("\"abc\"")).unwrap();
+        assert_eq!(writer.into_inner(), b"\0\0\0\x05\"abc\"");
+    }
+
+    #[test]
+    fn test_writer_flush_policy() {
+        /// Counts how many times it is flushed.
+        #[derive(Default)]
+        struct Flushes(usize);
+
+        impl Write for Flushes {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                self.0 += 1;
+                Ok(())
+            }
+        }
+
+        let mut writer = Writer::lines(Flushes::default());
+        writer.write_value(&Value::Null).unwrap();
+        writer.write_value(&Value::Null).unwrap();
+        assert_eq!(writer.writer.0, 0);
+        writer.flush().unwrap();
+        assert_eq!(writer.writer.0, 1);
+
+        let mut writer = Writer::lines(Flushes::default()).flush_policy(FlushPolicy::EveryMessage);
+        writer.write_value(&Value::Null).unwrap();
+        writer.write_value(&Value::Null).unwrap();
+        assert_eq!(writer.into_inner().0, 2);
+    }
+}
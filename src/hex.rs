@@ -1,12 +1,47 @@
+//! Encoding and decoding of bytes as hex digits, like in byte string escapes
+//! such as `b"\xff"`.
+//!
+//! ```
+//! use mason_rs::hex;
+//!
+//! assert_eq!(hex::encode(b"\x00mason\xff"), "006d61736f6eff");
+//! assert_eq!(hex::decode("006D61736F6EFF"), Some(b"\x00mason\xff".to_vec()));
+//! assert_eq!(hex::decode("abc"), None);
+//! ```
+
 use crate::utils;
 
-/// Decode a pair of hex digits into a number.
-pub fn decode_hex(hex: [u8; 2]) -> Result<u8, ()> {
+/// Encode `bytes` as lowercase hex digits, two for each byte.
+pub fn encode(bytes: &[u8]) -> String {
+    let mut string = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let [high, low] = encode_hex(*byte);
+        string.push(utils::to_char(high));
+        string.push(utils::to_char(low));
+    }
+    string
+}
+
+/// Decode a string of hex digits, two for each byte, which can be uppercase or
+/// lowercase. Returns None if `hex` contains anything else, or has an odd length.
+pub fn decode(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.as_bytes();
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    hex.chunks_exact(2)
+        .map(|pair| decode_hex([pair[0], pair[1]]))
+        .collect()
+}
+
+/// Decode a pair of hex digits into a number. Returns None if either of them is
+/// not a hex digit.
+pub fn decode_hex(hex: [u8; 2]) -> Option<u8> {
     let (high, low) = (hex_to_num(hex[0])?, hex_to_num(hex[1])?);
-    Ok(low | (high << 4))
+    Some(low | (high << 4))
 }
 
-/// Encode a number into a pair of hex digits.
+/// Encode a number into a pair of lowercase hex digits.
 pub fn encode_hex(num: u8) -> [u8; 2] {
     let (high, low) = (num >> 4, num & 0b0000_1111);
     [
@@ -16,21 +51,21 @@ pub fn encode_hex(num: u8) -> [u8; 2] {
 }
 
 /// Convert a hex digit into a number.
-fn hex_to_num(hex: u8) -> Result<u8, ()> {
+fn hex_to_num(hex: u8) -> Option<u8> {
     match utils::to_char(hex) {
-        '0'..='9' => Ok(hex - b'0'),
-        'A'..='F' => Ok(hex - (b'A' - 10)),
-        'a'..='f' => Ok(hex - (b'a' - 10)),
-        _ => Err(()),
+        '0'..='9' => Some(hex - b'0'),
+        'A'..='F' => Some(hex - (b'A' - 10)),
+        'a'..='f' => Some(hex - (b'a' - 10)),
+        _ => None,
     }
 }
 
 /// Convert number into a hex digit.
-fn num_to_hex(num: u8) -> Result<u8, ()> {
+fn num_to_hex(num: u8) -> Option<u8> {
     match num {
-        0..=9 => Ok(b'0' + num),
-        10..=15 => Ok(b'a' + (num - 10)),
-        _ => Err(()),
+        0..=9 => Some(b'0' + num),
+        10..=15 => Some(b'a' + (num - 10)),
+        _ => None,
     }
 }
 
@@ -38,12 +73,29 @@ fn num_to_hex(num: u8) -> Result<u8, ()> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_encode_and_decode() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let hex = encode(&bytes);
+        assert_eq!(hex.len(), 512);
+        assert!(hex.starts_with("000102") && hex.ends_with("fdfeff"));
+        assert_eq!(decode(&hex), Some(bytes.clone()));
+        assert_eq!(decode(&hex.to_uppercase()), Some(bytes));
+
+        assert_eq!(encode(b""), "");
+        assert_eq!(decode(""), Some(Vec::new()));
+        assert_eq!(decode("0"), None);
+        assert_eq!(decode("0g"), None);
+        assert_eq!(decode("00 01"), None);
+        assert_eq!(decode("é"), None);
+    }
+
     #[test]
     fn test_decode_hex() {
         assert_eq!(decode_hex([b'f', b'F']).unwrap(), 255);
         assert_eq!(decode_hex([b'1', b'2']).unwrap(), 18);
         assert_eq!(decode_hex([b'c', b'3']).unwrap(), 195);
-        assert!(decode_hex([b'!', b'?']).is_err());
+        assert!(decode_hex([b'!', b'?']).is_none());
     }
 
     #[test]
@@ -58,7 +110,7 @@ mod tests {
         assert_eq!(hex_to_num(b'C').unwrap(), 12);
         assert_eq!(hex_to_num(b'a').unwrap(), 10);
         assert_eq!(hex_to_num(b'7').unwrap(), 7);
-        assert!(hex_to_num(b'!').is_err());
+        assert!(hex_to_num(b'!').is_none());
     }
 
     #[test]
@@ -66,6 +118,6 @@ mod tests {
         assert_eq!(num_to_hex(12).unwrap(), b'c');
         assert_eq!(num_to_hex(10).unwrap(), b'a');
         assert_eq!(num_to_hex(7).unwrap(), b'7');
-        assert!(num_to_hex(100).is_err());
+        assert!(num_to_hex(100).is_none());
     }
 }
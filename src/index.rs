@@ -1,6 +1,6 @@
-use std::{collections::HashMap, ops};
+use std::ops;
 
-use crate::Value;
+use crate::{Map, Value};
 
 /// A type that can be used to index into a `mason_rs::Value`.
 ///
@@ -30,7 +30,7 @@ use crate::Value;
 /// // Inner is a MASON array so it can be indexed with an integer.
 /// let first = &inner[0];
 ///
-/// assert_eq!(*first, Value::Number(1.0));
+/// assert_eq!(*first, Value::from(1.0));
 /// ```
 pub trait Index: private::Sealed {
     #[doc(hidden)]
@@ -99,7 +99,7 @@ impl Index for str {
     /// in the object, insert it with a value of null.
     fn index_or_insert<'v>(&self, v: &'v mut Value) -> &'v mut Value {
         if matches!(v, Value::Null) {
-            *v = Value::Object(HashMap::new());
+            *v = Value::Object(Map::new());
         }
         match v {
             Value::Object(map) => map.entry(self.to_owned()).or_insert(Value::Null),
@@ -135,6 +135,38 @@ where
     }
 }
 
+/// A range that can be used to get a slice of a MASON array with
+/// [`Value::get_slice`]. This trait is implemented for `Range<usize>`,
+/// `RangeFrom<usize>` and `RangeTo<usize>`.
+///
+/// This trait is sealed and cannot be implemented for types outside of
+/// `mason`.
+pub trait SliceIndex: private::Sealed {
+    #[doc(hidden)]
+    fn slice_into<'v>(&self, v: &'v Value) -> Option<&'v [Value]>;
+}
+
+macro_rules! impl_slice_index {
+    ($($range:ty),*) => {
+        $(
+            impl SliceIndex for $range {
+                fn slice_into<'v>(&self, v: &'v Value) -> Option<&'v [Value]> {
+                    match v {
+                        Value::Array(vec) => vec.get(self.clone()),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_slice_index!(
+    ops::Range<usize>,
+    ops::RangeFrom<usize>,
+    ops::RangeTo<usize>
+);
+
 // The usual semantics of Index is to panic on invalid indexing, but this
 // does not make much sense for indexing into a Value. For this reason,
 // invalid indexing returns `Value::Null`.
@@ -207,7 +239,7 @@ where
     /// let mut data = Value::from_str(r#"{ "x": 0 }"#).unwrap();
     ///
     /// // replace an existing key
-    /// data["x"] = Value::Number(1.0);
+    /// data["x"] = Value::from(1.0);
     ///
     /// // insert a new key
     /// data["y"] = Value::Array(vec![Value::Bool(false), Value::Bool(true)]);
@@ -216,7 +248,7 @@ where
     /// data["y"][0] = Value::Bool(true);
     ///
     /// // insert a new array value
-    /// data["y"][2] = Value::Number(1.3);
+    /// data["y"][2] = Value::from(1.3);
     ///
     /// // inserted a deeply nested key
     /// data["a"]["b"]["c"]["d"] = Value::Bool(true);
@@ -234,5 +266,8 @@ mod private {
     impl Sealed for usize {}
     impl Sealed for str {}
     impl Sealed for String {}
+    impl Sealed for std::ops::Range<usize> {}
+    impl Sealed for std::ops::RangeFrom<usize> {}
+    impl Sealed for std::ops::RangeTo<usize> {}
     impl<T> Sealed for &T where T: ?Sized + Sealed {}
 }
@@ -1,23 +1,75 @@
+mod convert;
 mod deserialize;
-mod hex;
+pub mod diff;
+#[cfg(feature = "encoding")]
+mod encoding;
+mod error;
+mod format;
+pub mod framed;
+pub mod hex;
 mod index;
+pub mod lint;
+pub mod map;
+pub mod merge;
 mod peek_reader;
+pub mod query;
+mod redact;
 mod serialize;
-mod unescape_string;
+pub mod text;
+pub mod typegen;
+pub mod types;
 mod utils;
 mod value;
 
+#[cfg(feature = "capi")]
+pub mod capi;
+
 #[cfg(feature = "serde")]
 pub mod serde;
 
+#[cfg(feature = "serde")]
+pub mod config;
+
+// watching needs threads and a file system, which wasm32-unknown-unknown lacks
+#[cfg(all(
+    feature = "watch",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub mod watch;
+
+#[cfg(feature = "wasm-bindgen")]
+pub mod wasm;
+
 #[cfg(test)]
 mod tests;
 
-pub use value::Value;
+// lets the derive macros refer to `::mason_rs` in this crate's own tests
+#[cfg(all(test, feature = "derive"))]
+extern crate self as mason_rs;
+
+pub use convert::{FromMason, ToMason};
+pub use deserialize::{
+    Comments, ParseOptions, ParseWarning, ParseWarningKind, Parsed, PushParser, StringStyle,
+    ValueComments,
+};
+pub use error::{Error, ErrorKind, Result};
+pub use format::{Edit, format_range};
+pub use map::Map;
+pub use peek_reader::Position;
+pub use query::Query;
+pub use redact::redact;
+pub use serialize::{LineEnding, SeparatorStyle, SerializeOptions};
+pub use value::{
+    ArcValue, ArrayBuilder, Document, MasonString, Number, NumberExactness, ObjectBuilder,
+    Resolver, Transform, Value, ValueKind,
+};
+
+#[cfg(feature = "derive")]
+pub use mason_rs_derive::{FromMason, ToMason};
 
 #[cfg(feature = "serde")]
 #[doc(inline)]
 pub use serde::{
     de::{Deserializer, from_reader, from_slice, from_str},
-    ser::{Serializer, to_string, to_writer},
+    ser::{Serializer, to_io_writer, to_string, to_string_with_options, to_writer},
 };
@@ -0,0 +1,442 @@
+//! Find things in MASON documents that are valid, but probably not intended.
+//!
+//! ```
+//! # use mason_rs::lint::{WarningKind, lint};
+//! #
+//! let warnings = lint("enabled: \"true\"\nlimits: {}".as_bytes()).unwrap();
+//! let kinds: Vec<_> = warnings.iter().map(|warning| warning.kind).collect();
+//! assert_eq!(kinds, [WarningKind::QuotedBoolean, WarningKind::EmptyObject]);
+//! assert_eq!(
+//!     warnings[0].to_string(),
+//!     "string \"true\" looks like a boolean at line 1, column 10",
+//! );
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+    io::{BufRead, Read},
+};
+
+use crate::{
+    Error, ErrorKind, Position, Result,
+    deserialize::{
+        DEFAULT_MAX_RAW_STRING_HASHES, ParsedNumber, Separator, parse_byte_string,
+        parse_identifier, parse_multi_line_string, parse_number, parse_raw_string, parse_separator,
+        parse_string, skip_byte_order_mark, skip_whitespace,
+    },
+    peek_reader::PeekReader,
+    utils,
+};
+
+/// Documents that are nested deeper than this are rejected, like when parsing
+/// with the default [`ParseOptions`](crate::ParseOptions).
+const MAX_DEPTH: usize = 100;
+
+/// Something suspicious in a MASON document, found by [`lint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Warning {
+    /// What kind of problem was found.
+    pub kind: WarningKind,
+    /// A description of the problem.
+    pub message: String,
+    /// Where the problem starts.
+    pub position: Position,
+}
+
+/// The category of a [`Warning`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum WarningKind {
+    /// An object contains the same key more than once. The parser keeps the last
+    /// value, unless [`ParseOptions::reject_duplicate_keys`](crate::ParseOptions::reject_duplicate_keys)
+    /// is set.
+    DuplicateKey,
+    /// An object contains keys that only differ by case, like `name` and `Name`.
+    KeyCaseConflict,
+    /// An object has no keys.
+    EmptyObject,
+    /// The last element of an array or object is followed by a comma.
+    TrailingSeparator,
+    /// A number has more digits than a 64-bit float can represent, so it is
+    /// rounded when it is parsed.
+    ExcessivePrecision,
+    /// A string is `"true"` or `"false"`, which was probably meant to be a boolean.
+    QuotedBoolean,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.position)
+    }
+}
+
+/// Check the MASON document in `reader` for suspicious, but valid, constructs.
+/// The warnings are returned in the order they appear in the document.
+///
+/// # Errors
+///
+/// This function fails if the document is not valid MASON, or if the reader fails.
+pub fn lint(reader: impl Read) -> Result<Vec<Warning>> {
+    let mut linter = Linter {
+        reader: PeekReader::new(reader),
+        warnings: Vec::new(),
+    };
+    linter
+        .document()
+        .map_err(|err| err.at(linter.reader.position()))?;
+    Ok(linter.warnings)
+}
+
+struct Linter<R: BufRead> {
+    reader: PeekReader<R>,
+    warnings: Vec<Warning>,
+}
+
+impl<R: BufRead> Linter<R> {
+    fn warn(&mut self, kind: WarningKind, position: Position, message: String) {
+        self.warnings.push(Warning {
+            kind,
+            message,
+            position,
+        });
+    }
+
+    fn document(&mut self) -> Result<()> {
+        skip_byte_order_mark(&mut self.reader)?;
+        skip_whitespace(&mut self.reader)?;
+        self.value(MAX_DEPTH, true)?;
+        skip_whitespace(&mut self.reader)?;
+        if let Some(garbage) = self.reader.peek()? {
+            return Err(Error::syntax(format!(
+                "Trailing garbage after document: {:?}",
+                utils::to_char(garbage)
+            )));
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, depth: usize, top_level: bool) -> Result<()> {
+        if depth == 0 {
+            return Err(Error::new(
+                ErrorKind::DepthExceeded,
+                "Reached maximum depth",
+            ));
+        }
+
+        let position = self.reader.position();
+        let Some(first_byte) = self.reader.peek()? else {
+            return Err(Error::eof("Got EOF when parsing value"));
+        };
+
+        match first_byte {
+            b'{' => {
+                self.reader.consume(1);
+                return self.object(depth, position, None);
+            }
+            b'[' => return self.array(depth),
+            b'"' => {
+                let string = parse_string(&mut self.reader)?;
+                if top_level {
+                    skip_whitespace(&mut self.reader)?;
+                    if self.reader.peek()? == Some(b':') {
+                        return self.object(depth, position, Some(string));
+                    }
+                }
+                self.check_string(&string, position);
+                return Ok(());
+            }
+            b'|' => {
+                parse_multi_line_string(&mut self.reader)?;
+                return Ok(());
+            }
+            b'r' | b'b' => match *self.reader.peek_n(2)? {
+                [b'r', b'"' | b'#'] => {
+                    let string = parse_raw_string(&mut self.reader, DEFAULT_MAX_RAW_STRING_HASHES)?;
+                    self.check_string(&string, position);
+                    return Ok(());
+                }
+                [b'b', b'"'] => {
+                    parse_byte_string(&mut self.reader)?;
+                    return Ok(());
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+
+        if first_byte.is_ascii_digit() || matches!(first_byte, b'+' | b'-' | b'.') {
+            return self.number(position);
+        }
+
+        let identifier = parse_identifier(&mut self.reader)?;
+        if top_level {
+            skip_whitespace(&mut self.reader)?;
+            if self.reader.peek()? == Some(b':') {
+                return self.object(depth, position, Some(identifier));
+            }
+        }
+        match identifier.as_str() {
+            "true" | "false" | "null" => Ok(()),
+            _ => Err(Error::syntax(format!("Malformed value: {identifier}"))),
+        }
+    }
+
+    /// Lint the rest of an object. If `first_key` is set, it has already been
+    /// parsed, and the object has no braces, otherwise the opening brace has
+    /// been consumed.
+    fn object(&mut self, depth: usize, start: Position, first_key: Option<String>) -> Result<()> {
+        let braced = first_key.is_none();
+        let mut next_key = first_key.map(|key| (key, start));
+        if braced {
+            skip_whitespace(&mut self.reader)?;
+            if self.reader.peek()? == Some(b'}') {
+                self.reader.consume(1);
+                self.warn(WarningKind::EmptyObject, start, "empty object".to_owned());
+                return Ok(());
+            }
+        }
+
+        let mut keys = HashSet::new();
+        // the first key of every lowercased key
+        let mut lowercase_keys = HashMap::new();
+        loop {
+            let (key, key_position) = match next_key.take() {
+                Some(key) => key,
+                None => {
+                    let position = self.reader.position();
+                    (parse_identifier(&mut self.reader)?, position)
+                }
+            };
+            if keys.contains(&key) {
+                self.warn(
+                    WarningKind::DuplicateKey,
+                    key_position,
+                    format!("duplicate key {key:?}"),
+                );
+            } else {
+                match lowercase_keys.get(&key.to_lowercase()) {
+                    Some(other) => self.warn(
+                        WarningKind::KeyCaseConflict,
+                        key_position,
+                        format!("key {key:?} only differs from {other:?} by case"),
+                    ),
+                    None => {
+                        lowercase_keys.insert(key.to_lowercase(), key.clone());
+                    }
+                }
+                keys.insert(key);
+            }
+
+            skip_whitespace(&mut self.reader)?;
+            match self.reader.read_byte()? {
+                Some(b':') => {}
+                Some(byte) => {
+                    return Err(Error::syntax(format!(
+                        "got unexpected byte {:?} after key",
+                        utils::to_char(byte)
+                    )));
+                }
+                None => return Err(Error::eof("Got EOF when parsing key-value pair")),
+            }
+            skip_whitespace(&mut self.reader)?;
+
+            let separator = self.element(depth)?;
+            let Some(next_byte) = self.reader.peek()? else {
+                return if braced {
+                    Err(Error::eof("got EOF while parsing object"))
+                } else {
+                    Ok(())
+                };
+            };
+            if next_byte == b'}' {
+                self.reader.consume(1);
+                return Ok(());
+            } else if separator.is_none() {
+                return Err(Error::syntax(format!(
+                    "invalid separator {}",
+                    utils::to_char(next_byte)
+                )));
+            }
+        }
+    }
+
+    /// Lint an array, starting at its opening bracket.
+    fn array(&mut self, depth: usize) -> Result<()> {
+        self.reader.consume(1);
+        skip_whitespace(&mut self.reader)?;
+        loop {
+            match self.reader.peek()? {
+                Some(b']') => {
+                    self.reader.consume(1);
+                    return Ok(());
+                }
+                Some(_) => {}
+                None => return Err(Error::eof("got EOF while parsing array")),
+            }
+
+            let separator = self.element(depth)?;
+            match self.reader.peek()? {
+                Some(b']') => {}
+                Some(byte) if separator.is_none() => {
+                    return Err(Error::syntax(format!(
+                        "invalid separator {}",
+                        utils::to_char(byte)
+                    )));
+                }
+                Some(_) => {}
+                None => return Err(Error::eof("got EOF while parsing array")),
+            }
+        }
+    }
+
+    /// Lint an element of an array or object, and the separator and whitespace
+    /// after it. A comma before the end of the container is a warning.
+    fn element(&mut self, depth: usize) -> Result<Option<Separator>> {
+        let is_multi_line_string = self.reader.peek()? == Some(b'|');
+        self.value(depth - 1, false)?;
+
+        let separator_position = self.reader.position();
+        let separator = if is_multi_line_string {
+            Some(Separator::Newline)
+        } else {
+            parse_separator(&mut self.reader, None)?
+        };
+        // the comma is the last byte of the separator
+        let comma_position = Position {
+            offset: self.reader.position().offset - 1,
+            column: self.reader.position().column - 1,
+            ..separator_position
+        };
+        skip_whitespace(&mut self.reader)?;
+
+        if separator == Some(Separator::Comma) && matches!(self.reader.peek()?, Some(b'}' | b']')) {
+            self.warn(
+                WarningKind::TrailingSeparator,
+                comma_position,
+                "trailing comma".to_owned(),
+            );
+        }
+        Ok(separator)
+    }
+
+    fn number(&mut self, position: Position) -> Result<()> {
+        let token = self.reader.peek_until(|byte| {
+            !(utils::is_identifier_char(byte) || matches!(byte, b'+' | b'-' | b'.' | b'\''))
+        })?;
+        let literal = String::from_utf8_lossy(token).into_owned();
+
+        let number = parse_number(&mut self.reader)?;
+        if !is_exact(&literal, number) {
+            self.warn(
+                WarningKind::ExcessivePrecision,
+                position,
+                format!(
+                    "{literal} can not be represented exactly, and is read as {}",
+                    number.as_f64()
+                ),
+            );
+        }
+        Ok(())
+    }
+
+    fn check_string(&mut self, string: &str, position: Position) {
+        if string.eq_ignore_ascii_case("true") || string.eq_ignore_ascii_case("false") {
+            self.warn(
+                WarningKind::QuotedBoolean,
+                position,
+                format!("string {string:?} looks like a boolean"),
+            );
+        }
+    }
+}
+
+/// Returns true if the number written as `literal` is exactly `number`.
+fn is_exact(literal: &str, number: ParsedNumber) -> bool {
+    match number {
+        // u128::MAX rounds up to 2^128, which saturates back to u128::MAX
+        ParsedNumber::PosInt(int) => int as f64 as u128 == int && int != u128::MAX,
+        ParsedNumber::NegInt(int) => int as f64 as i128 == int,
+        ParsedNumber::Float(float) => {
+            let literal = literal.trim_start_matches(['+', '-']);
+            if ["0x", "0o", "0b"]
+                .iter()
+                .any(|prefix| literal.starts_with(prefix))
+            {
+                // integers that are too big for a u128, which we do not check
+                return true;
+            }
+
+            // the shortest representation of a float that parses to the same float
+            // has the same significant digits as the literal if the literal is exact
+            let shortest = format!("{:e}", float.abs());
+            float.is_finite() && significant_digits(literal) == significant_digits(&shortest)
+        }
+    }
+}
+
+/// The digits of the mantissa of a number, without leading and trailing zeros.
+fn significant_digits(number: &str) -> String {
+    let mantissa = number.split(['e', 'E']).next().unwrap_or_default();
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    digits.trim_matches('0').to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warnings(data: &str) -> Vec<(WarningKind, usize, usize)> {
+        lint(data.as_bytes())
+            .unwrap()
+            .into_iter()
+            .map(|warning| (warning.kind, warning.position.line, warning.position.column))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint() {
+        use WarningKind::*;
+
+        assert_eq!(
+            warnings(
+                "\
+name: \"a\"
+Name: \"b\"
+name: \"c\"
+nested: {x: [1, {}, ], y: {z: \"False\",}}
+precise: [0.1, 1.50, -2.5e-3, 1'000, 0x10, 0.1000000000000000001, 9007199254740993]
+big: [1e400, 123456789012345678901234567890]
+strings: [r\"true\", \"truth\", b\"true\", |true
+]"
+            ),
+            [
+                (KeyCaseConflict, 2, 1),
+                (DuplicateKey, 3, 1),
+                (EmptyObject, 4, 17),
+                (TrailingSeparator, 4, 19),
+                (QuotedBoolean, 4, 31),
+                (TrailingSeparator, 4, 38),
+                (ExcessivePrecision, 5, 44),
+                (ExcessivePrecision, 5, 67),
+                (ExcessivePrecision, 6, 7),
+                (ExcessivePrecision, 6, 14),
+                (QuotedBoolean, 7, 11),
+            ]
+        );
+
+        assert_eq!(warnings("{}"), [(EmptyObject, 1, 1)]);
+        assert_eq!(
+            warnings("a: 1, A: {b: 2}, \"B\": [1,\n2, 3\n]"),
+            [(KeyCaseConflict, 1, 7)]
+        );
+        assert!(warnings("// nothing to see here\n[{a: 1, b: [true, null]}]").is_empty());
+
+        for data in ["[1 2]", "{a: 1", "a: nul", "[1] 2", &"[".repeat(101)] {
+            assert!(lint(data.as_bytes()).is_err(), "{data:?}");
+        }
+        let err = lint("a: [1,\n2,,]".as_bytes()).unwrap_err();
+        assert_eq!(err.position().map(|position| position.line), Some(2));
+    }
+}
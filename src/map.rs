@@ -0,0 +1,408 @@
+//! A map of MASON object keys to values.
+//!
+//! [`Map`] wraps the type that is used to store objects, so that it can change
+//! without affecting users of [`Value::Object`](crate::Value::Object).
+
+use std::{
+    borrow::Borrow,
+    collections::{HashMap, hash_map},
+    fmt,
+    hash::{DefaultHasher, Hash, Hasher},
+    iter::FusedIterator,
+};
+
+use crate::{MasonString, Value};
+
+/// Represents a MASON object. The order of the keys is unspecified.
+///
+/// Keys are stored as [`MasonString`]s, which are compact strings if the
+/// `compact_str` feature is enabled.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Map {
+    map: HashMap<MasonString, Value>,
+}
+
+impl Map {
+    /// Makes a new empty `Map`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes a new empty `Map` with room for at least `capacity` entries.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            map: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Clears the map, removing all entries.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    pub fn get<Q>(&self, key: &Q) -> Option<&Value>
+    where
+        MasonString: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.get(key)
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Value>
+    where
+        MasonString: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.get_mut(key)
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        MasonString: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.contains_key(key)
+    }
+
+    /// Inserts a key-value pair into the map. If the map already had a value for
+    /// the key, it is replaced, and the old value is returned.
+    ///
+    /// ```
+    /// # use mason_rs::{Map, Value};
+    /// #
+    /// let mut map = Map::new();
+    /// assert_eq!(map.insert("a".to_owned(), Value::Bool(true)), None);
+    /// assert_eq!(map.insert("a".to_owned(), Value::Null), Some(Value::Bool(true)));
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn insert(&mut self, key: impl Into<MasonString>, value: Value) -> Option<Value> {
+        self.map.insert(key.into(), value)
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key was
+    /// previously in the map.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Value>
+    where
+        MasonString: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.remove(key)
+    }
+
+    /// Gets the entry of the given key for in-place manipulation.
+    ///
+    /// ```
+    /// # use mason_rs::{Map, Value};
+    /// #
+    /// let mut map = Map::new();
+    /// for key in ["a", "b", "a"] {
+    ///     map.entry(key)
+    ///         .and_modify(|count| *count = Value::from(count.as_f64().unwrap() + 1.0))
+    ///         .or_insert(Value::from(1.0));
+    /// }
+    /// assert_eq!(map["a"], Value::from(2.0));
+    /// assert_eq!(map["b"], Value::from(1.0));
+    /// ```
+    pub fn entry(&mut self, key: impl Into<MasonString>) -> Entry<'_> {
+        Entry {
+            entry: self.map.entry(key.into()),
+        }
+    }
+
+    /// Retains only the entries for which the predicate returns true.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let mut value = Value::from_str("a: 1, b: null, c: null").unwrap();
+    /// let map = value.as_object_mut().unwrap();
+    /// map.retain(|_, value| !value.is_null());
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&MasonString, &mut Value) -> bool,
+    {
+        self.map.retain(f);
+    }
+
+    /// Gets an iterator over the entries of the map.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            iter: self.map.iter(),
+        }
+    }
+
+    /// Gets a mutable iterator over the entries of the map.
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut {
+            iter: self.map.iter_mut(),
+        }
+    }
+
+    /// Gets an iterator over the keys of the map.
+    pub fn keys(&self) -> Keys<'_> {
+        Keys {
+            iter: self.map.keys(),
+        }
+    }
+
+    /// Gets an iterator over the values of the map.
+    pub fn values(&self) -> Values<'_> {
+        Values {
+            iter: self.map.values(),
+        }
+    }
+
+    /// Gets a mutable iterator over the values of the map.
+    pub fn values_mut(&mut self) -> ValuesMut<'_> {
+        ValuesMut {
+            iter: self.map.values_mut(),
+        }
+    }
+}
+
+impl fmt::Debug for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.map.fmt(f)
+    }
+}
+
+impl Hash for Map {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // the order of the entries is unspecified, so each entry is hashed on its
+        // own, and the hashes are combined in a way that does not depend on the order
+        let entries = self
+            .map
+            .iter()
+            .map(|entry| {
+                let mut hasher = DefaultHasher::new();
+                entry.hash(&mut hasher);
+                hasher.finish()
+            })
+            .fold(0, u64::wrapping_add);
+        state.write_usize(self.map.len());
+        state.write_u64(entries);
+    }
+}
+
+impl<Q> std::ops::Index<&Q> for Map
+where
+    MasonString: Borrow<Q>,
+    Q: ?Sized + Hash + Eq,
+{
+    type Output = Value;
+
+    /// Panics if the key is not in the map.
+    fn index(&self, key: &Q) -> &Value {
+        self.map.index(key)
+    }
+}
+
+impl From<HashMap<MasonString, Value>> for Map {
+    fn from(map: HashMap<MasonString, Value>) -> Self {
+        Self { map }
+    }
+}
+
+impl From<Map> for HashMap<MasonString, Value> {
+    fn from(map: Map) -> Self {
+        map.map
+    }
+}
+
+impl<const N: usize> From<[(MasonString, Value); N]> for Map {
+    fn from(entries: [(MasonString, Value); N]) -> Self {
+        Self {
+            map: HashMap::from(entries),
+        }
+    }
+}
+
+impl FromIterator<(MasonString, Value)> for Map {
+    fn from_iter<T: IntoIterator<Item = (MasonString, Value)>>(iter: T) -> Self {
+        Self {
+            map: HashMap::from_iter(iter),
+        }
+    }
+}
+
+impl Extend<(MasonString, Value)> for Map {
+    fn extend<T: IntoIterator<Item = (MasonString, Value)>>(&mut self, iter: T) {
+        self.map.extend(iter);
+    }
+}
+
+// maps can also be built from `String` keys
+impl<const N: usize> From<[(String, Value); N]> for Map {
+    fn from(entries: [(String, Value); N]) -> Self {
+        entries.into_iter().collect()
+    }
+}
+
+impl FromIterator<(String, Value)> for Map {
+    fn from_iter<T: IntoIterator<Item = (String, Value)>>(iter: T) -> Self {
+        iter.into_iter()
+            .map(|(key, value)| (MasonString::from(key), value))
+            .collect()
+    }
+}
+
+impl Extend<(String, Value)> for Map {
+    fn extend<T: IntoIterator<Item = (String, Value)>>(&mut self, iter: T) {
+        self.map
+            .extend(iter.into_iter().map(|(key, value)| (key.into(), value)));
+    }
+}
+
+/// An entry in a [`Map`], which may be vacant or occupied. Created by
+/// [`Map::entry`].
+pub struct Entry<'a> {
+    entry: hash_map::Entry<'a, MasonString, Value>,
+}
+
+impl<'a> Entry<'a> {
+    /// Returns a reference to the key of this entry.
+    pub fn key(&self) -> &MasonString {
+        self.entry.key()
+    }
+
+    /// Ensures a value is in the entry by inserting `default` if it is vacant,
+    /// and returns a mutable reference to the value.
+    pub fn or_insert(self, default: Value) -> &'a mut Value {
+        self.entry.or_insert(default)
+    }
+
+    /// Ensures a value is in the entry by inserting the result of `default` if
+    /// it is vacant, and returns a mutable reference to the value.
+    pub fn or_insert_with<F: FnOnce() -> Value>(self, default: F) -> &'a mut Value {
+        self.entry.or_insert_with(default)
+    }
+
+    /// Calls `f` on the value if the entry is occupied.
+    pub fn and_modify<F: FnOnce(&mut Value)>(self, f: F) -> Self {
+        Self {
+            entry: self.entry.and_modify(f),
+        }
+    }
+}
+
+macro_rules! delegate_iterator {
+    ($name:ident<$lifetime:lifetime>, $iter:ty, $item:ty, $doc:literal) => {
+        #[doc = $doc]
+        pub struct $name<$lifetime> {
+            iter: $iter,
+        }
+
+        impl<$lifetime> Iterator for $name<$lifetime> {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                self.iter.next()
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.iter.size_hint()
+            }
+        }
+
+        impl ExactSizeIterator for $name<'_> {}
+
+        impl FusedIterator for $name<'_> {}
+    };
+}
+
+delegate_iterator!(
+    Iter<'a>,
+    hash_map::Iter<'a, MasonString, Value>,
+    (&'a MasonString, &'a Value),
+    "An iterator over the entries of a [`Map`]."
+);
+delegate_iterator!(
+    IterMut<'a>,
+    hash_map::IterMut<'a, MasonString, Value>,
+    (&'a MasonString, &'a mut Value),
+    "A mutable iterator over the entries of a [`Map`]."
+);
+delegate_iterator!(
+    Keys<'a>,
+    hash_map::Keys<'a, MasonString, Value>,
+    &'a MasonString,
+    "An iterator over the keys of a [`Map`]."
+);
+delegate_iterator!(
+    Values<'a>,
+    hash_map::Values<'a, MasonString, Value>,
+    &'a Value,
+    "An iterator over the values of a [`Map`]."
+);
+delegate_iterator!(
+    ValuesMut<'a>,
+    hash_map::ValuesMut<'a, MasonString, Value>,
+    &'a mut Value,
+    "A mutable iterator over the values of a [`Map`]."
+);
+
+/// An owning iterator over the entries of a [`Map`].
+pub struct IntoIter {
+    iter: hash_map::IntoIter<MasonString, Value>,
+}
+
+impl Iterator for IntoIter {
+    type Item = (MasonString, Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+impl FusedIterator for IntoIter {}
+
+impl IntoIterator for Map {
+    type Item = (MasonString, Value);
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            iter: self.map.into_iter(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Map {
+    type Item = (&'a MasonString, &'a Value);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Map {
+    type Item = (&'a MasonString, &'a mut Value);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
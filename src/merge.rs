@@ -0,0 +1,495 @@
+//! Deeply merge MASON values, like layering configuration files on top of each other.
+//!
+//! ```
+//! # use mason_rs::{Value, merge::{ArrayMerge, MergeOptions}};
+//! # use std::str::FromStr;
+//! #
+//! let mut config = Value::from_str("server: {host: \"localhost\", port: 80}, tags: [\"a\"]").unwrap();
+//! let overlay = Value::from_str("server: {port: 8080, host: null}, tags: [\"b\"]").unwrap();
+//!
+//! let options = MergeOptions::new().arrays(ArrayMerge::Append).null_deletes_key(true);
+//! options.merge(&mut config, overlay);
+//! assert_eq!(config, Value::from_str("server: {port: 8080}, tags: [\"a\", \"b\"]").unwrap());
+//! ```
+
+use std::{collections::HashMap, fs, ops::Range, path::Path, sync::Arc};
+
+use crate::{Map, ParseOptions, Position, Result, Value, utils::with_key};
+
+/// How arrays in an overlay are combined with arrays in the value it is merged
+/// into. See [`MergeOptions::arrays`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArrayMerge {
+    /// The overlay array replaces the array.
+    #[default]
+    Replace,
+    /// The elements of the overlay array are appended to the array.
+    Append,
+    /// Elements at the same index are merged, and elements past the end of the
+    /// array are appended to it.
+    ByIndex,
+}
+
+/// Options that change how values are merged by [`MergeOptions::merge`].
+///
+/// Objects are always merged key by key: keys that are only in the overlay are
+/// added, and the values of keys that are in both are merged. Any other value in
+/// the overlay replaces the value it is merged into, except for arrays, which
+/// are combined as described by [`MergeOptions::arrays`].
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    arrays: ArrayMerge,
+    null_deletes_key: bool,
+}
+
+impl MergeOptions {
+    /// Create the default options, which merge values the same way as [`merge`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How arrays are combined. The default is [`ArrayMerge::Replace`].
+    pub fn arrays(mut self, arrays: ArrayMerge) -> Self {
+        self.arrays = arrays;
+        self
+    }
+
+    /// Remove keys whose value is `null` in the overlay, like a JSON merge patch.
+    /// The default is `false`, which sets their value to `null`.
+    pub fn null_deletes_key(mut self, null_deletes_key: bool) -> Self {
+        self.null_deletes_key = null_deletes_key;
+        self
+    }
+
+    /// Merge `overlay` into `value` using these options.
+    pub fn merge(&self, value: &mut Value, overlay: Value) {
+        match (value, overlay) {
+            (Value::Object(object), Value::Object(overlay)) => {
+                for (key, overlay) in overlay {
+                    if self.null_deletes_key && overlay.is_null() {
+                        object.remove(key.as_str());
+                        continue;
+                    }
+                    match object.get_mut(key.as_str()) {
+                        Some(value) => self.merge(value, overlay),
+                        None => {
+                            object.insert(key, overlay);
+                        }
+                    }
+                }
+            }
+            (Value::Array(array), Value::Array(overlay)) => match self.arrays {
+                ArrayMerge::Replace => *array = overlay,
+                ArrayMerge::Append => array.extend(overlay),
+                ArrayMerge::ByIndex => {
+                    let mut overlay = overlay.into_iter();
+                    for (value, overlay) in array.iter_mut().zip(overlay.by_ref()) {
+                        self.merge(value, overlay);
+                    }
+                    array.extend(overlay);
+                }
+            },
+            (value, overlay) => *value = overlay,
+        }
+    }
+}
+
+/// Merge `overlay` into `value` with the default [`MergeOptions`]. Objects are
+/// merged key by key, and anything else in `overlay` replaces what is in `value`.
+pub fn merge(value: &mut Value, overlay: Value) {
+    MergeOptions::new().merge(value, overlay);
+}
+
+/// Where a value in a [`MergedDocument`] came from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Origin {
+    /// The name of the document the value was in, like the path of a file.
+    pub source: Arc<str>,
+    /// Where the value starts in the document.
+    pub position: Position,
+}
+
+/// Documents merged on top of each other, which remember the document and
+/// position each value came from.
+///
+/// The origin of every value that is not a non-empty array or object is
+/// recorded, so [`MergedDocument::origin`] can answer where a setting came from.
+///
+/// ```
+/// # use mason_rs::merge::MergedDocument;
+/// #
+/// let mut document = MergedDocument::new();
+/// document.merge_str("base.mason", "server: {\n    host: \"localhost\"\n    port: 80\n}").unwrap();
+/// document.merge_str("prod.mason", "server: {port: 443}").unwrap();
+///
+/// assert_eq!(document.value()["server"]["port"].as_f64(), Some(443.0));
+/// let origin = document.origin("server.port").unwrap();
+/// assert_eq!((&*origin.source, origin.position.line), ("prod.mason", 1));
+/// let origin = document.origin("server.host").unwrap();
+/// assert_eq!((&*origin.source, origin.position.line), ("base.mason", 2));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MergedDocument {
+    value: Value,
+    options: MergeOptions,
+    /// The origin of every value that is not a non-empty container, by path.
+    origins: HashMap<String, Origin>,
+}
+
+impl Default for MergedDocument {
+    fn default() -> Self {
+        Self::with_options(MergeOptions::new())
+    }
+}
+
+impl MergedDocument {
+    /// Create an empty object that documents are merged into with the default
+    /// [`MergeOptions`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty object that documents are merged into with `options`.
+    pub fn with_options(options: MergeOptions) -> Self {
+        Self {
+            value: Value::Object(Map::new()),
+            options,
+            origins: HashMap::new(),
+        }
+    }
+
+    /// Parse `string` and merge it into the document. `source` names the string
+    /// in the origins of its values.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if `string` is not valid MASON, in which case the
+    /// document is not changed.
+    pub fn merge_str(&mut self, source: &str, string: &str) -> Result<()> {
+        let parsed = ParseOptions::new().capture_spans(true).parse_str(string)?;
+
+        let layer = Layer {
+            source: source.into(),
+            spans: &parsed.spans,
+        };
+        layer.record(
+            &self.options,
+            &mut self.origins,
+            &self.value,
+            &parsed.value,
+            &mut String::new(),
+            &mut String::new(),
+        );
+        self.options.merge(&mut self.value, parsed.value);
+        Ok(())
+    }
+
+    /// Read the MASON file at `path` and merge it into the document, with the path
+    /// as the source of its values.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the file could not be read, or if it is not valid
+    /// MASON, in which case the document is not changed.
+    pub fn merge_file(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let string = fs::read_to_string(path)?;
+        self.merge_str(&path.display().to_string(), &string)
+    }
+
+    /// The merged value.
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Take the merged value, forgetting the origins.
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Find where the value at `path` came from. The path is a list of keys
+    /// separated by `.`, like in [`Value::insert_path`].
+    ///
+    /// Returns `None` if there is no value at `path`, or if it is a non-empty
+    /// array or object, which can be merged from several documents.
+    pub fn origin(&self, path: &str) -> Option<&Origin> {
+        self.origins.get(path)
+    }
+}
+
+/// A document that is being merged into a [`MergedDocument`].
+struct Layer<'a> {
+    source: Arc<str>,
+    /// Where each value in the document is.
+    spans: &'a HashMap<String, Range<Position>>,
+}
+
+impl Layer<'_> {
+    /// Update `origins` for merging `overlay`, which is at `from` in the layer,
+    /// into `value`, which is at `target` in the merged document. This follows
+    /// [`MergeOptions::merge`].
+    fn record(
+        &self,
+        options: &MergeOptions,
+        origins: &mut HashMap<String, Origin>,
+        value: &Value,
+        overlay: &Value,
+        target: &mut String,
+        from: &mut String,
+    ) {
+        match (value, overlay) {
+            (Value::Object(object), Value::Object(overlay)) => {
+                if !overlay.is_empty() {
+                    origins.remove(target.as_str());
+                }
+                for (key, overlay) in overlay {
+                    with_key(target, key, |target| {
+                        with_key(from, key, |from| {
+                            if options.null_deletes_key && overlay.is_null() {
+                                forget(origins, target);
+                                return;
+                            }
+                            match object.get(key.as_str()) {
+                                Some(value) => {
+                                    self.record(options, origins, value, overlay, target, from)
+                                }
+                                None => self.replace(origins, overlay, target, from),
+                            }
+                        })
+                    });
+                }
+            }
+            (Value::Array(array), Value::Array(overlay))
+                if options.arrays != ArrayMerge::Replace =>
+            {
+                if !overlay.is_empty() {
+                    origins.remove(target.as_str());
+                }
+                for (i, overlay) in overlay.iter().enumerate() {
+                    let index = match options.arrays {
+                        ArrayMerge::Append => array.len() + i,
+                        _ => i,
+                    };
+                    with_key(target, &index.to_string(), |target| {
+                        with_key(from, &i.to_string(), |from| {
+                            match (options.arrays, array.get(i)) {
+                                (ArrayMerge::ByIndex, Some(value)) => {
+                                    self.record(options, origins, value, overlay, target, from)
+                                }
+                                _ => self.replace(origins, overlay, target, from),
+                            }
+                        })
+                    });
+                }
+            }
+            _ => self.replace(origins, overlay, target, from),
+        }
+    }
+
+    /// Update `origins` for replacing the value at `target` with `overlay`, which
+    /// is at `from` in the layer.
+    fn replace(
+        &self,
+        origins: &mut HashMap<String, Origin>,
+        overlay: &Value,
+        target: &mut String,
+        from: &mut String,
+    ) {
+        forget(origins, target);
+        self.insert(origins, overlay, target, from);
+    }
+
+    /// Record that the values in `overlay` came from this layer.
+    fn insert(
+        &self,
+        origins: &mut HashMap<String, Origin>,
+        overlay: &Value,
+        target: &mut String,
+        from: &mut String,
+    ) {
+        match overlay {
+            Value::Object(object) if !object.is_empty() => {
+                for (key, value) in object {
+                    with_key(target, key, |target| {
+                        with_key(from, key, |from| self.insert(origins, value, target, from))
+                    });
+                }
+            }
+            Value::Array(array) if !array.is_empty() => {
+                for (i, value) in array.iter().enumerate() {
+                    let key = i.to_string();
+                    with_key(target, &key, |target| {
+                        with_key(from, &key, |from| self.insert(origins, value, target, from))
+                    });
+                }
+            }
+            _ => {
+                if let Some(span) = self.spans.get(from.as_str()) {
+                    let source = self.source.clone();
+                    let position = span.start;
+                    origins.insert(target.clone(), Origin { source, position });
+                }
+            }
+        }
+    }
+}
+
+/// Forget the origins of the value at `path` and the values inside it.
+fn forget(origins: &mut HashMap<String, Origin>, path: &str) {
+    if path.is_empty() {
+        origins.clear();
+        return;
+    }
+    let prefix = format!("{path}.");
+    origins.retain(|other, _| other != path && !other.starts_with(&prefix));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let value = |string| Value::from_str(string).unwrap();
+        let base = value("a: {b: [1, {c: 2}], d: 3}, e: \"e\"");
+        let overlay = value("a: {b: [{c: 4, f: 5}], d: null}, e: {}, g: true");
+        let merged = |options: MergeOptions| {
+            let mut merged = base.clone();
+            options.merge(&mut merged, overlay.clone());
+            merged
+        };
+
+        let mut default = base.clone();
+        merge(&mut default, overlay.clone());
+        assert_eq!(
+            default,
+            value("a: {b: [{c: 4, f: 5}], d: null}, e: {}, g: true")
+        );
+        assert_eq!(merged(MergeOptions::new()), default);
+
+        assert_eq!(
+            merged(MergeOptions::new().arrays(ArrayMerge::Append)),
+            value("a: {b: [1, {c: 2}, {c: 4, f: 5}], d: null}, e: {}, g: true")
+        );
+        assert_eq!(
+            merged(MergeOptions::new().arrays(ArrayMerge::ByIndex)),
+            value("a: {b: [{c: 4, f: 5}, {c: 2}], d: null}, e: {}, g: true")
+        );
+        assert_eq!(
+            merged(MergeOptions::new().null_deletes_key(true)),
+            value("a: {b: [{c: 4, f: 5}]}, e: {}, g: true")
+        );
+
+        // arrays of objects are merged element by element
+        let mut array = value("[{a: 1}, {b: 2}]");
+        let options = MergeOptions::new().arrays(ArrayMerge::ByIndex);
+        options.merge(&mut array, value("[{c: 3}, {b: 4}, 5]"));
+        assert_eq!(array, value("[{a: 1, c: 3}, {b: 4}, 5]"));
+
+        let mut scalar = Value::from(1.0);
+        merge(&mut scalar, value("{a: 1}"));
+        assert_eq!(scalar, value("{a: 1}"));
+    }
+
+    fn origins(document: &MergedDocument) -> Vec<(String, String, usize, usize)> {
+        let mut origins: Vec<_> = document
+            .origins
+            .iter()
+            .map(|(path, origin)| {
+                (
+                    path.clone(),
+                    origin.source.to_string(),
+                    origin.position.line,
+                    origin.position.column,
+                )
+            })
+            .collect();
+        origins.sort();
+        origins
+    }
+
+    fn origin(
+        path: &str,
+        source: &str,
+        line: usize,
+        column: usize,
+    ) -> (String, String, usize, usize) {
+        (path.to_owned(), source.to_owned(), line, column)
+    }
+
+    #[test]
+    fn test_merged_document() {
+        let mut document = MergedDocument::new();
+        document
+            .merge_str("a", "x: 1\ny: {z: [1, 2], w: {}}\nv: true")
+            .unwrap();
+        document
+            .merge_str("b", "y: {z: [3], w: {u: null}}")
+            .unwrap();
+        assert_eq!(
+            document.value(),
+            &Value::from_str("x: 1, y: {z: [3], w: {u: null}}, v: true").unwrap()
+        );
+        assert_eq!(
+            origins(&document),
+            [
+                origin("v", "a", 3, 4),
+                origin("x", "a", 1, 4),
+                origin("y.w.u", "b", 1, 20),
+                origin("y.z.0", "b", 1, 9),
+            ]
+        );
+
+        // a value replaces the values inside the value it replaces
+        document.merge_str("c", "{y: 2, x: []}").unwrap();
+        assert_eq!(
+            origins(&document),
+            [
+                origin("v", "a", 3, 4),
+                origin("x", "c", 1, 11),
+                origin("y", "c", 1, 5),
+            ]
+        );
+        assert_eq!(document.origin("y").unwrap().source.as_ref(), "c");
+        assert_eq!(document.origin("z"), None);
+
+        // the document is not changed if parsing fails
+        assert!(document.merge_str("d", "y: [").is_err());
+        assert_eq!(document.origin("y").unwrap().source.as_ref(), "c");
+    }
+
+    #[test]
+    fn test_merged_document_options() {
+        let options = MergeOptions::new()
+            .arrays(ArrayMerge::Append)
+            .null_deletes_key(true);
+        let mut document = MergedDocument::with_options(options);
+        document.merge_str("a", "x: [1], y: 2").unwrap();
+        document.merge_str("b", "x: [2, 3], y: null").unwrap();
+        assert_eq!(
+            origins(&document),
+            [
+                origin("x.0", "a", 1, 5),
+                origin("x.1", "b", 1, 5),
+                origin("x.2", "b", 1, 8),
+            ]
+        );
+
+        let options = MergeOptions::new().arrays(ArrayMerge::ByIndex);
+        let mut document = MergedDocument::with_options(options);
+        document.merge_str("a", "[{p: 1}, 2]").unwrap();
+        document.merge_str("b", "[{q: 3}]").unwrap();
+        assert_eq!(
+            origins(&document),
+            [
+                origin("0.p", "a", 1, 6),
+                origin("0.q", "b", 1, 6),
+                origin("1", "a", 1, 10),
+            ]
+        );
+    }
+}
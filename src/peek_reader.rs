@@ -1,42 +1,127 @@
-use std::io::{self, BufRead, BufReader, Read};
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, BufRead, BufReader, Read},
+};
 
-/// [`BufReader`] with the ability to peek two bytes. This is
+use crate::utils::fill_buf_retrying;
+
+/// A location in a MASON document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    /// The number of bytes before this position.
+    pub offset: usize,
+    /// The line of this position, starting at 1.
+    pub line: usize,
+    /// The column of this position, counted in characters and starting at 1.
+    pub column: usize,
+}
+
+impl Position {
+    pub(crate) fn advance(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.offset += 1;
+            if *byte == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else if byte & 0b1100_0000 != 0b1000_0000 {
+                // utf8 continuation bytes are part of the previous character
+                self.column += 1;
+            }
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+/// Keeps track of the bytes a [`PeekReader`] has consumed.
+#[derive(Debug, Default)]
+struct Consumed {
+    /// The position of the next byte that will be read.
+    position: Position,
+    /// The bytes consumed since recording started, if we are recording.
+    recording: Option<Vec<u8>>,
+}
+
+impl Consumed {
+    fn advance(&mut self, bytes: &[u8]) {
+        self.position.advance(bytes);
+        if let Some(recording) = &mut self.recording {
+            recording.extend_from_slice(bytes);
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// [`BufRead`] with the ability to peek an arbitrary number of bytes. This is
 /// necessary until <https://github.com/rust-lang/rust/issues/128405> is merged.
+///
+/// A `PeekReader` reads from the buffer of a [`BufRead`]. [`PeekReader::new`]
+/// wraps any reader in a [`BufReader`], while [`PeekReader::from_buf_read`]
+/// reads directly from the buffer of a reader that is already buffered, which
+/// avoids copying every byte from one buffer into another.
 #[derive(Debug)]
-pub struct PeekReader<R: Read> {
-    buf_reader: BufReader<R>,
-    /// A secondary buffer in case `peek2` is called,
-    /// but `buf_reader.buffer().len() == 1`. It is not (yet)
-    /// possible to fill the buffer before it is empty, so in this
-    /// case, we must put the one byte here, empty the buffer, and then
-    /// fill it again.
-    buffer2: Option<u8>,
+pub(crate) struct PeekReader<R: BufRead> {
+    buf_reader: R,
+    /// A secondary buffer in case we want to peek further than what is left in
+    /// `buf_reader`'s buffer. It is not (yet) possible to fill the buffer before
+    /// it is empty, so in this case, we must move the remaining bytes here, empty
+    /// the buffer, and then fill it again. The bytes in this buffer come before
+    /// the bytes in `buf_reader`.
+    lookahead: VecDeque<u8>,
+    consumed: Consumed,
 }
 
-impl<R: Read> PeekReader<R> {
-    /// Creates a new `PeekReader<R>` with a default buffer capacity. The default is currently 8 KiB,
-    /// but may change in the future.
+impl<R: Read> PeekReader<BufReader<R>> {
+    /// Creates a new `PeekReader` that reads from `inner` through a [`BufReader`]
+    /// with a default buffer capacity. The default is currently 8 KiB, but may
+    /// change in the future.
+    ///
+    /// If `inner` already implements [`BufRead`], [`PeekReader::from_buf_read`]
+    /// avoids the extra buffer.
     pub fn new(inner: R) -> Self {
-        Self {
-            buf_reader: BufReader::new(inner),
-            buffer2: None,
-        }
+        Self::from_buf_read(BufReader::new(inner))
     }
 
-    /// Creates a new `PeekReader<R>` with the specified buffer capacity.
+    /// Creates a new `PeekReader` with the specified buffer capacity.
     #[cfg(test)]
     pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        Self::from_buf_read(BufReader::with_capacity(capacity, inner))
+    }
+}
+
+impl<R: BufRead> PeekReader<R> {
+    /// Creates a new `PeekReader` that reads directly from the buffer of `inner`.
+    ///
+    /// Unlike [`PeekReader::new`], no extra buffer is added, so bytes are only
+    /// copied when peeking past the end of `inner`'s buffer. This makes parsing
+    /// faster when `inner` is a byte slice, or a reader that is already buffered.
+    pub fn from_buf_read(inner: R) -> Self {
         Self {
-            buf_reader: BufReader::with_capacity(capacity, inner),
-            buffer2: None,
+            buf_reader: inner,
+            lookahead: VecDeque::new(),
+            consumed: Consumed::default(),
         }
     }
 
     /// Read one value without discarding it.  Returns None if EOF is reached.
     pub fn peek(&mut self) -> io::Result<Option<u8>> {
-        if let Some(byte) = self.buffer2 {
-            Ok(Some(byte))
-        } else if let Some(byte) = self.buf_reader.fill_buf()?.first() {
+        if let Some(byte) = self.lookahead.front() {
+            Ok(Some(*byte))
+        } else if let Some(byte) = fill_buf_retrying(&mut self.buf_reader)?.first() {
             Ok(Some(*byte))
         } else {
             Ok(None)
@@ -45,27 +130,97 @@ impl<R: Read> PeekReader<R> {
 
     /// Read two values without discarding them.  Returns None if EOF is reached.
     pub fn peek2(&mut self) -> io::Result<Option<[u8; 2]>> {
-        let current_buf = self.buf_reader.fill_buf()?;
-        let Some(current_buf_first) = current_buf.first() else {
-            return Ok(None);
-        };
+        match *self.peek_n(2)? {
+            [first, second] => Ok(Some([first, second])),
+            _ => Ok(None),
+        }
+    }
 
-        if let Some(byte1) = self.buffer2 {
-            Ok(Some([byte1, *current_buf_first]))
-        } else if let Some(current_buf_second) = current_buf.get(1) {
-            Ok(Some([*current_buf_first, *current_buf_second]))
-        } else {
-            // buf_reader buffer is only one byte long! Put current first into
-            // buffer 2, consume buffer, and try to fetch new values
-            let current_buf_first = self.buffer2.insert(*current_buf_first);
-            self.buf_reader.consume(1);
-
-            if let Some(new_buf_first) = self.buf_reader.fill_buf()?.first() {
-                Ok(Some([*current_buf_first, *new_buf_first]))
-            } else {
-                Ok(None)
+    /// Read `n` values without discarding them. If EOF is reached, the returned
+    /// slice will be shorter than `n`.
+    pub fn peek_n(&mut self, n: usize) -> io::Result<&[u8]> {
+        // fast path: all the values are in `buf_reader`'s buffer. Filling a buffer
+        // that is not empty does not read anything, so the second call is cheap.
+        if self.lookahead.is_empty() && fill_buf_retrying(&mut self.buf_reader)?.len() >= n {
+            return Ok(&self.buf_reader.fill_buf()?[..n]);
+        }
+
+        while self.lookahead.len() < n {
+            let buf = fill_buf_retrying(&mut self.buf_reader)?;
+            if buf.is_empty() {
+                break;
             }
+            let amount = buf.len().min(n - self.lookahead.len());
+            self.lookahead.extend(&buf[..amount]);
+            self.buf_reader.consume(amount);
         }
+
+        let len = self.lookahead.len().min(n);
+        Ok(&self.lookahead.make_contiguous()[..len])
+    }
+
+    /// Read values without discarding them until `predicate` returns true. The
+    /// value that `predicate` returned true for is not included in the returned
+    /// slice. If EOF is reached, all remaining values are returned.
+    pub fn peek_until(&mut self, mut predicate: impl FnMut(u8) -> bool) -> io::Result<&[u8]> {
+        // fast path: the end is in `buf_reader`'s buffer
+        if self.lookahead.is_empty() {
+            let buf = fill_buf_retrying(&mut self.buf_reader)?;
+            if let Some(end) = buf.iter().position(|byte| predicate(*byte)) {
+                return Ok(&self.buf_reader.fill_buf()?[..end]);
+            }
+        }
+
+        let mut checked = 0;
+        let end = loop {
+            if let Some(position) = self.lookahead.range(checked..).position(|b| predicate(*b)) {
+                break checked + position;
+            }
+            checked = self.lookahead.len();
+
+            let buf = fill_buf_retrying(&mut self.buf_reader)?;
+            if buf.is_empty() {
+                break checked;
+            }
+            let amount = buf.len();
+            self.lookahead.extend(buf);
+            self.buf_reader.consume(amount);
+        };
+
+        Ok(&self.lookahead.make_contiguous()[..end])
+    }
+
+    /// Get a mutable reference to the underlying reader. Reading from it directly
+    /// would make the position wrong.
+    #[cfg(feature = "serde")]
+    pub(crate) fn get_mut(&mut self) -> &mut R {
+        &mut self.buf_reader
+    }
+
+    /// The position of the next value that will be read.
+    pub fn position(&self) -> Position {
+        self.consumed.position
+    }
+
+    /// Start recording the bytes that are consumed, until
+    /// [`stop_recording`](Self::stop_recording) is called.
+    #[cfg(any(feature = "serde", feature = "arbitrary_precision"))]
+    pub(crate) fn start_recording(&mut self) {
+        self.consumed.recording = Some(Vec::new());
+    }
+
+    /// Stop recording, and return the bytes that were consumed since
+    /// [`start_recording`](Self::start_recording) was called.
+    #[cfg(any(feature = "serde", feature = "arbitrary_precision"))]
+    pub(crate) fn stop_recording(&mut self) -> Vec<u8> {
+        self.consumed.recording.take().unwrap_or_default()
+    }
+
+    /// Consume a UTF-8 byte order mark, which is three bytes long. Editors do
+    /// not display it, so unlike other characters, it does not count as a column.
+    pub(crate) fn consume_byte_order_mark(&mut self) {
+        self.consume(3);
+        self.consumed.position.column -= 1;
     }
 
     /// Read a single byte. Returns None if EOF is reached.
@@ -84,48 +239,58 @@ impl<R: Read> PeekReader<R> {
     }
 }
 
-impl<R: Read> Read for PeekReader<R> {
+impl<R: BufRead> Read for PeekReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        if buf.is_empty() {
-            return Ok(0);
-        }
-
-        if let Some(byte) = self.buffer2.take() {
-            buf[0] = byte;
-            let read = 1 + self.buf_reader.read(&mut buf[1..])?;
-            Ok(read)
-        } else {
-            self.buf_reader.read(buf)
+        let mut read = self.lookahead.read(buf)?;
+        // the bytes from the lookahead are already taken, so an interrupted read
+        // is retried instead of returning the error and losing them
+        while read < buf.len() {
+            match self.buf_reader.read(&mut buf[read..]) {
+                Ok(n) => {
+                    read += n;
+                    break;
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
         }
+        self.consumed.advance(&buf[..read]);
+        Ok(read)
     }
 
     fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
-        if buf.is_empty() {
-            return Ok(());
-        }
-
-        if let Some(byte) = self.buffer2.take() {
-            buf[0] = byte;
-            self.buf_reader.read_exact(&mut buf[1..])
-        } else {
-            self.buf_reader.read_exact(buf)
-        }
+        let read = self.lookahead.read(buf)?;
+        self.consumed.advance(&buf[..read]);
+        self.buf_reader.read_exact(&mut buf[read..])?;
+        self.consumed.advance(&buf[read..]);
+        Ok(())
     }
 }
 
-impl<R: Read> BufRead for PeekReader<R> {
+impl<R: BufRead> BufRead for PeekReader<R> {
     fn fill_buf(&mut self) -> io::Result<&[u8]> {
-        self.buf_reader.fill_buf()
+        if self.lookahead.is_empty() {
+            fill_buf_retrying(&mut self.buf_reader)
+        } else {
+            Ok(self.lookahead.as_slices().0)
+        }
     }
 
     fn consume(&mut self, amt: usize) {
-        if amt == 0 {
-            return;
-        }
-        if self.buffer2.take().is_some() {
-            self.buf_reader.consume(amt - 1)
-        } else {
-            self.buf_reader.consume(amt)
+        let from_lookahead = amt.min(self.lookahead.len());
+        let (first, second) = self.lookahead.as_slices();
+        let split = from_lookahead.min(first.len());
+        self.consumed.advance(&first[..split]);
+        self.consumed.advance(&second[..from_lookahead - split]);
+        self.lookahead.drain(..from_lookahead);
+
+        if amt > from_lookahead {
+            // the caller has seen the bytes it consumes, so they are in the buffer,
+            // and filling it again does not read anything
+            let buffer = fill_buf_retrying(&mut self.buf_reader).unwrap_or_default();
+            let from_buffer = (amt - from_lookahead).min(buffer.len());
+            self.consumed.advance(&buffer[..from_buffer]);
+            self.buf_reader.consume(from_buffer);
         }
     }
 }
@@ -175,21 +340,144 @@ mod tests {
         assert_eq!(reader.buf_reader.buffer().len(), 1);
 
         assert_eq!(reader.peek().unwrap(), Some(7));
-        assert!(reader.buffer2.is_none());
+        assert!(reader.lookahead.is_empty());
         assert_eq!(reader.peek2().unwrap(), Some([7, 6]));
-        assert_eq!(reader.buffer2, Some(7));
-        assert_eq!(reader.buf_reader.buffer().len(), 3);
+        assert_eq!(reader.lookahead, [7, 6]);
+        assert_eq!(reader.buf_reader.buffer().len(), 2);
 
         assert_eq!(reader.peek().unwrap(), Some(7));
         assert_eq!(reader.peek2().unwrap(), Some([7, 6]));
-        assert_eq!(reader.buffer2, Some(7));
+        assert_eq!(reader.lookahead, [7, 6]);
 
         assert_eq!(reader.read_byte().unwrap(), Some(7));
-        assert!(reader.buffer2.is_none());
+        assert_eq!(reader.lookahead, [6]);
         assert_eq!(reader.peek2().unwrap(), Some([6, 5]));
-        assert!(reader.buffer2.is_none());
 
         reader.read_exact(&mut buf).unwrap();
         assert_eq!(buf, [6, 5]);
+        assert!(reader.lookahead.is_empty());
+    }
+
+    #[test]
+    fn test_from_buf_read() {
+        let data = b"0123456789";
+        let mut reader = PeekReader::from_buf_read(data.as_slice());
+        assert_eq!(reader.peek2().unwrap(), Some([b'0', b'1']));
+        assert!(reader.lookahead.is_empty());
+        assert_eq!(reader.peek_n(4).unwrap(), b"0123");
+        assert_eq!(reader.read_byte().unwrap(), Some(b'0'));
+        assert_eq!(reader.buf_reader, b"123456789");
+
+        let mut reader = PeekReader::from_buf_read(BufReader::with_capacity(3, data.as_slice()));
+        assert_eq!(reader.peek_n(5).unwrap(), b"01234");
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "0123456789");
+    }
+
+    #[test]
+    fn test_peek_n() {
+        let data = b"0123456789";
+        let mut reader = PeekReader::with_capacity(3, data.as_slice());
+        assert_eq!(reader.peek_n(2).unwrap(), b"01");
+        assert_eq!(reader.peek_n(7).unwrap(), b"0123456");
+
+        reader.consume(2);
+        assert_eq!(reader.peek_n(3).unwrap(), b"234");
+        assert_eq!(reader.fill_buf().unwrap().first(), Some(&b'2'));
+
+        let mut string = String::new();
+        reader.read_line(&mut string).unwrap();
+        assert_eq!(string, "23456789");
+        assert_eq!(reader.peek_n(3).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_peek_until() {
+        let data = b"0123456789";
+        let mut reader = PeekReader::with_capacity(3, data.as_slice());
+        assert_eq!(reader.peek_until(|byte| byte == b'1').unwrap(), b"0");
+        assert_eq!(reader.peek_until(|byte| byte == b'7').unwrap(), b"0123456");
+        assert_eq!(reader.peek_until(|byte| byte == b'5').unwrap(), b"01234");
+
+        reader.consume(8);
+        assert_eq!(reader.peek_until(|byte| byte == b'0').unwrap(), b"89");
+        assert_eq!(reader.read_byte().unwrap(), Some(b'8'));
+    }
+
+    #[test]
+    fn test_position() {
+        let data = "ab\nø: [\n  1]";
+        let mut reader = PeekReader::with_capacity(3, data.as_bytes());
+        assert_eq!(reader.position(), Position::default());
+
+        reader.peek_n(5).unwrap();
+        assert_eq!(reader.position().offset, 0);
+
+        reader.read_exact(&mut [0; 4]).unwrap();
+        let position = reader.position();
+        assert_eq!((position.offset, position.line, position.column), (4, 2, 2));
+
+        assert_eq!(reader.peek_n(3).unwrap(), b"\xb8: ");
+        reader.consume(3);
+        let position = reader.position();
+        assert_eq!((position.offset, position.line, position.column), (7, 2, 4));
+
+        let mut string = String::new();
+        reader.read_to_string(&mut string).unwrap();
+        let position = reader.position();
+        assert_eq!(
+            (position.offset, position.line, position.column),
+            (13, 3, 5)
+        );
+        assert_eq!(position.to_string(), "line 3, column 5");
+    }
+
+    /// A reader that is interrupted before every read.
+    struct Interrupting<'a> {
+        bytes: &'a [u8],
+        interrupt: bool,
+    }
+
+    impl Read for Interrupting<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            // read a single byte at a time, to be interrupted as often as possible
+            let n = buf.len().min(self.bytes.len()).min(1);
+            buf[..n].copy_from_slice(&self.bytes[..n]);
+            self.bytes = &self.bytes[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_interrupted() {
+        let interrupting = |bytes| Interrupting {
+            bytes,
+            interrupt: false,
+        };
+
+        let mut reader = PeekReader::with_capacity(1, interrupting(b"abcd"));
+        assert_eq!(reader.peek().unwrap(), Some(b'a'));
+        assert_eq!(reader.peek_n(3).unwrap(), b"abc");
+        assert_eq!(reader.peek_until(|byte| byte == b'd').unwrap(), b"abc");
+        let mut buf = [0; 3];
+        assert_eq!(reader.read(&mut buf).unwrap(), 3);
+        assert_eq!(&buf, b"abc");
+        assert_eq!(reader.read_byte().unwrap(), Some(b'd'));
+        assert_eq!(reader.read_byte().unwrap(), None);
+
+        let source = "a: [1, r\"x\"], b: {c: true}";
+        let value = crate::Value::from_reader(interrupting(source.as_bytes())).unwrap();
+        assert_eq!(value, source.parse().unwrap());
+        #[cfg(feature = "serde")]
+        {
+            let deserialized: crate::Value =
+                crate::from_reader(interrupting(source.as_bytes())).unwrap();
+            assert_eq!(deserialized, value);
+        }
     }
 }
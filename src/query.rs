@@ -0,0 +1,478 @@
+//! Select values inside a [`Value`] with a small path query language.
+//!
+//! A query is a list of segments, each of which selects values inside the values
+//! selected by the previous segments:
+//!
+//! | Segment             | Selects                                                       |
+//! |---------------------|---------------------------------------------------------------|
+//! | `key`, `.key`       | The value of `key` in an object                               |
+//! | `["any key"]`       | The value of a key that is not an identifier                  |
+//! | `[2]`, `[-1]`       | An array element, where negative indices count from the end   |
+//! | `[1:3]`, `[-2:]`    | A slice of an array, where both bounds are optional           |
+//! | `*`, `.*`, `[*]`    | Every element of an array, or every value of an object        |
+//! | `[?path]`           | Every element or value where `path` is neither null nor false |
+//! | `[?path == value]`  | Every element or value where `path` compares true to `value`  |
+//!
+//! The path in a filter is a query relative to the element, where `@` is the
+//! element itself. Filters can compare with `==`, `!=`, `<`, `<=`, `>` and `>=`,
+//! against numbers, strings, booleans and null. Only numbers and strings can be
+//! ordered, and a path that selects nothing is treated as null. An empty query
+//! selects the value itself.
+//!
+//! ```
+//! # use mason_rs::Value;
+//! # use std::str::FromStr;
+//! #
+//! let value = Value::from_str(r#"
+//! servers: [
+//!     {host: "a.example", port: 80, enabled: true}
+//!     {host: "b.example", port: 8080, enabled: false}
+//!     {host: "c.example", port: 443, enabled: true}
+//! ]
+//! "#).unwrap();
+//!
+//! let hosts = |query| -> Vec<_> {
+//!     value.select(query).unwrap().into_iter().map(|host| host.as_str().unwrap()).collect()
+//! };
+//! assert_eq!(hosts("servers[*].host"), ["a.example", "b.example", "c.example"]);
+//! assert_eq!(hosts("servers[?enabled].host"), ["a.example", "c.example"]);
+//! assert_eq!(hosts("servers[?port >= 443].host"), ["b.example", "c.example"]);
+//! assert_eq!(hosts("servers[-2:].host"), ["b.example", "c.example"]);
+//! ```
+
+use std::{cmp::Ordering, str::FromStr};
+
+use crate::{Error, ErrorKind, MasonString, Result, Value, utils, value::mason_string};
+
+/// A parsed query. See the [module documentation](self) for the syntax.
+///
+/// Parsing a query once is useful when it is used to select values from many
+/// documents.
+///
+/// ```
+/// # use mason_rs::{Query, Value};
+/// # use std::str::FromStr;
+/// #
+/// let query = Query::from_str("a[0]").unwrap();
+/// for document in ["a: [1, 2]", "a: [3]", "a: []"] {
+///     let value = Value::from_str(document).unwrap();
+///     assert!(query.select(&value).len() <= 1);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(MasonString),
+    Index(isize),
+    Slice(Option<isize>, Option<isize>),
+    Wildcard,
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    path: Query,
+    comparison: Option<(Comparison, Value)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Query {
+    /// Select the values in `value` that match this query. The values of an
+    /// object are selected in arbitrary order.
+    pub fn select<'v>(&self, value: &'v Value) -> Vec<&'v Value> {
+        let mut selected = vec![value];
+        for segment in &self.segments {
+            selected = selected
+                .into_iter()
+                .flat_map(|value| segment.select(value))
+                .collect();
+        }
+        selected
+    }
+}
+
+impl FromStr for Query {
+    type Err = Error;
+
+    fn from_str(query: &str) -> Result<Self> {
+        let mut parser = Parser { query, offset: 0 };
+        let parsed = parser.parse_query(true)?;
+        parser.skip_whitespace();
+        if parser.offset != query.len() {
+            return Err(parser.error("unexpected character"));
+        }
+        Ok(parsed)
+    }
+}
+
+impl Segment {
+    fn select<'v>(&self, value: &'v Value) -> Vec<&'v Value> {
+        match (self, value) {
+            (Self::Key(key), Value::Object(map)) => map.get(key.as_str()).into_iter().collect(),
+            (Self::Index(index), Value::Array(array)) => resolve_index(*index, array.len())
+                .and_then(|index| array.get(index))
+                .into_iter()
+                .collect(),
+            (Self::Slice(start, end), Value::Array(array)) => {
+                let len = array.len();
+                let start = start.map_or(0, |start| clamp_index(start, len));
+                let end = end.map_or(len, |end| clamp_index(end, len));
+                array
+                    .get(start..end.max(start))
+                    .unwrap_or_default()
+                    .iter()
+                    .collect()
+            }
+            (Self::Wildcard, _) => children(value),
+            (Self::Filter(filter), _) => children(value)
+                .into_iter()
+                .filter(|child| filter.matches(child))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Filter {
+    fn matches(&self, value: &Value) -> bool {
+        let found = self
+            .path
+            .select(value)
+            .into_iter()
+            .next()
+            .unwrap_or(&Value::Null);
+        let Some((comparison, expected)) = &self.comparison else {
+            return !matches!(found, Value::Null | Value::Bool(false));
+        };
+
+        let ordering = match (found, expected) {
+            (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            _ => None,
+        };
+        match comparison {
+            Comparison::Eq => found == expected,
+            Comparison::Ne => found != expected,
+            Comparison::Lt => ordering == Some(Ordering::Less),
+            Comparison::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            Comparison::Gt => ordering == Some(Ordering::Greater),
+            Comparison::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        }
+    }
+}
+
+fn children(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(array) => array.iter().collect(),
+        Value::Object(map) => map.values().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Turn a possibly negative index into an index from the start.
+fn resolve_index(index: isize, len: usize) -> Option<usize> {
+    if index < 0 {
+        len.checked_sub(index.unsigned_abs())
+    } else {
+        Some(index.unsigned_abs())
+    }
+}
+
+/// Like [`resolve_index`], but clamped to `0..=len`, like slice bounds in Python.
+fn clamp_index(index: isize, len: usize) -> usize {
+    resolve_index(index, len).unwrap_or(0).min(len)
+}
+
+struct Parser<'q> {
+    query: &'q str,
+    offset: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.query.as_bytes().get(self.offset).copied()
+    }
+
+    fn rest(&self) -> &str {
+        &self.query[self.offset..]
+    }
+
+    fn error(&self, message: &str) -> Error {
+        let found = match self.rest().chars().next() {
+            Some(c) => format!("{c:?}"),
+            None => "end of query".to_owned(),
+        };
+        Error::new(
+            ErrorKind::InvalidQuery,
+            format!(
+                "{message} at character {} of query, found {found}",
+                self.query[..self.offset].chars().count() + 1
+            ),
+        )
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<()> {
+        if self.peek() == Some(byte) {
+            self.offset += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {:?}", utils::to_char(byte))))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.offset += 1;
+        }
+    }
+
+    /// Parse segments until something that can not start a segment is found. In a
+    /// filter, a query can start with `@`, which refers to the current element.
+    fn parse_query(&mut self, top_level: bool) -> Result<Query> {
+        let mut segments = Vec::new();
+        if top_level {
+            self.skip_whitespace();
+        } else if self.peek() == Some(b'@') {
+            self.offset += 1;
+        }
+
+        loop {
+            match self.peek() {
+                Some(b'.') => {
+                    self.offset += 1;
+                    segments.push(self.parse_name()?);
+                }
+                Some(b'[') => {
+                    self.offset += 1;
+                    segments.push(self.parse_bracketed()?);
+                }
+                // the first key does not need a leading '.'
+                Some(byte)
+                    if segments.is_empty() && (byte == b'*' || utils::is_identifier_char(byte)) =>
+                {
+                    segments.push(self.parse_name()?);
+                }
+                _ => return Ok(Query { segments }),
+            }
+        }
+    }
+
+    /// Parse a key or `*` after a `.`, or at the start of the query.
+    fn parse_name(&mut self) -> Result<Segment> {
+        if self.peek() == Some(b'*') {
+            self.offset += 1;
+            return Ok(Segment::Wildcard);
+        }
+
+        let len = self
+            .rest()
+            .bytes()
+            .take_while(|byte| utils::is_identifier_char(*byte))
+            .count();
+        if len == 0 {
+            return Err(self.error("expected a key"));
+        }
+        let key = self.rest()[..len].to_owned();
+        self.offset += len;
+        Ok(Segment::Key(mason_string(key)))
+    }
+
+    /// Parse the inside of `[...]`, after the opening bracket.
+    fn parse_bracketed(&mut self) -> Result<Segment> {
+        self.skip_whitespace();
+        let segment = match self.peek() {
+            Some(b'*') => {
+                self.offset += 1;
+                Segment::Wildcard
+            }
+            Some(b'?') => {
+                self.offset += 1;
+                Segment::Filter(self.parse_filter()?)
+            }
+            Some(b'"') => match self.parse_literal()? {
+                Value::String(key) => Segment::Key(key),
+                _ => unreachable!("a quoted literal is a string"),
+            },
+            _ => {
+                let start = self.parse_int()?;
+                self.skip_whitespace();
+                if self.peek() == Some(b':') {
+                    self.offset += 1;
+                    self.skip_whitespace();
+                    Segment::Slice(start, self.parse_int()?)
+                } else {
+                    Segment::Index(start.ok_or_else(|| self.error("expected an index"))?)
+                }
+            }
+        };
+        self.skip_whitespace();
+        self.expect(b']')?;
+        Ok(segment)
+    }
+
+    fn parse_int(&mut self) -> Result<Option<isize>> {
+        let len = self
+            .rest()
+            .bytes()
+            .enumerate()
+            .take_while(|(i, byte)| byte.is_ascii_digit() || (*i == 0 && *byte == b'-'))
+            .count();
+        if len == 0 {
+            return Ok(None);
+        }
+        let int = self.rest()[..len]
+            .parse()
+            .map_err(|_| self.error("invalid index"))?;
+        self.offset += len;
+        Ok(Some(int))
+    }
+
+    /// Parse the inside of `[?...]`, after the question mark.
+    fn parse_filter(&mut self) -> Result<Filter> {
+        self.skip_whitespace();
+        let path = self.parse_query(false)?;
+        if path.segments.is_empty() && !self.query[..self.offset].ends_with('@') {
+            return Err(self.error("expected a path"));
+        }
+        self.skip_whitespace();
+
+        let comparisons = [
+            ("==", Comparison::Eq),
+            ("!=", Comparison::Ne),
+            ("<=", Comparison::Le),
+            (">=", Comparison::Ge),
+            ("<", Comparison::Lt),
+            (">", Comparison::Gt),
+        ];
+        let Some((operator, comparison)) = comparisons
+            .into_iter()
+            .find(|(operator, _)| self.rest().starts_with(operator))
+        else {
+            return Ok(Filter {
+                path,
+                comparison: None,
+            });
+        };
+        self.offset += operator.len();
+        self.skip_whitespace();
+
+        let value = self.parse_literal()?;
+        Ok(Filter {
+            path,
+            comparison: Some((comparison, value)),
+        })
+    }
+
+    /// Parse a scalar MASON value: a number, a quoted string, a boolean or null.
+    fn parse_literal(&mut self) -> Result<Value> {
+        let len = if self.peek() == Some(b'"') {
+            let mut escaped = false;
+            let end = self.rest().bytes().skip(1).position(|byte| {
+                let is_end = byte == b'"' && !escaped;
+                escaped = byte == b'\\' && !escaped;
+                is_end
+            });
+            end.map(|end| end + 2)
+                .ok_or_else(|| self.error("unclosed string"))?
+        } else {
+            self.rest()
+                .bytes()
+                .take_while(|byte| {
+                    utils::is_identifier_char(*byte) || matches!(byte, b'+' | b'-' | b'.')
+                })
+                .count()
+        };
+
+        let literal = &self.rest()[..len];
+        let value = Value::from_str(literal)
+            .ok()
+            .filter(|value| !matches!(value, Value::Object(_) | Value::Array(_)))
+            .ok_or_else(|| self.error("expected a number, string, boolean or null"))?;
+        self.offset += len;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select(value: &str, query: &str) -> Vec<String> {
+        let value = Value::from_str(value).unwrap();
+        let mut selected: Vec<String> = value
+            .select(query)
+            .unwrap()
+            .into_iter()
+            .map(Value::to_string)
+            .collect();
+        selected.sort();
+        selected
+    }
+
+    #[test]
+    fn test_select() {
+        let value = r#"a: {b: [1, 2, 3, 4], "c d": true}, e: [{f: 1}, {f: 2}, {g: 3}]"#;
+        assert_eq!(
+            Value::from_str(value).unwrap().select("").unwrap(),
+            [&Value::from_str(value).unwrap()]
+        );
+        assert_eq!(select(value, "a.b[0]"), ["1"]);
+        assert_eq!(select(value, ".a.b[-1]"), ["4"]);
+        assert_eq!(select(value, r#"a["c d"]"#), ["true"]);
+        assert_eq!(select(value, "a.b[1:3]"), ["2", "3"]);
+        assert_eq!(select(value, "a.b[:-3]"), ["1"]);
+        assert_eq!(select(value, "a.b[3:1]"), Vec::<String>::new());
+        assert_eq!(select(value, "a.b[-10:10]").len(), 4);
+        assert_eq!(select(value, "a.*").len(), 2);
+        assert_eq!(select(value, "*").len(), 2);
+        assert_eq!(select(value, "e[*].f"), ["1", "2"]);
+        assert_eq!(select(value, "e[?f].f"), ["1", "2"]);
+        assert_eq!(select(value, "e[?f != 1].f"), ["2"]);
+        assert_eq!(select(value, "e[?g == 3]"), ["g: 3"]);
+        assert_eq!(select(value, "a.b[?@ > 2]"), ["3", "4"]);
+        assert_eq!(select(value, "a.b[?@ <= 2]"), ["1", "2"]);
+        assert_eq!(select(value, "a[?@ == true]"), ["true"]);
+        assert_eq!(select(value, "missing.b[0]"), Vec::<String>::new());
+        assert_eq!(select(value, "a.b.c"), Vec::<String>::new());
+
+        let value = r#"[{name: "a]"}, {name: "b\""}]"#;
+        assert_eq!(select(value, r#"[?name == "a]"].name"#), [r#""a]""#]);
+        assert_eq!(select(value, r#"[?name == "b\""].name"#), [r#""b\"""#]);
+        assert_eq!(
+            select(value, r#"[?name > "a"].name"#),
+            [r#""a]""#, r#""b\"""#]
+        );
+    }
+
+    #[test]
+    fn test_invalid_query() {
+        for query in [
+            "a.",
+            "a[",
+            "a[1",
+            "a[x]",
+            "a[?]",
+            "a[?b ==]",
+            "a[?b == {}]",
+            r#"a["b]"#,
+            "a b",
+            "a..b",
+        ] {
+            let err = Query::from_str(query).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidQuery, "{query:?}");
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use crate::Value;
+
+/// What redacted values are replaced with.
+const REDACTED: &str = "***";
+
+/// Replace the value of every key in `value` that matches one of `patterns` with
+/// `"***"`, so the value can be logged without leaking secrets. Keys are matched at
+/// any depth, including inside arrays and tagged values, and the whole value of a
+/// matching key is replaced, even if it is an object or array.
+///
+/// Patterns are globs, where `*` matches any number of characters and `?` matches
+/// a single character. Matching ignores ASCII case, so `password` also matches
+/// `Password` and `PASSWORD`.
+///
+/// ```
+/// # use mason_rs::{Value, redact};
+/// # use std::str::FromStr;
+/// #
+/// let mut value = Value::from_str(
+///     r#"user: "admin", Password: "hunter2", db: {api_secret: "x", tokens: ["a", "b"]}"#,
+/// )
+/// .unwrap();
+/// redact(&mut value, &["password", "token*", "*_secret"]);
+///
+/// assert_eq!(
+///     value,
+///     Value::from_str(r#"user: "admin", Password: "***", db: {api_secret: "***", tokens: "***"}"#)
+///         .unwrap(),
+/// );
+/// ```
+pub fn redact(value: &mut Value, patterns: &[&str]) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if patterns.iter().any(|pattern| glob_matches(pattern, key)) {
+                    *value = Value::String(REDACTED.into());
+                } else {
+                    redact(value, patterns);
+                }
+            }
+        }
+        Value::Array(array) => {
+            for value in array {
+                redact(value, patterns);
+            }
+        }
+        Value::Tagged(_, value) => redact(value, patterns),
+        _ => {}
+    }
+}
+
+/// Check if `text` matches the glob `pattern`, ignoring ASCII case.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // the position of the last `*` in the pattern, and where in the text it
+    // started matching, so we can backtrack and let it match one more character
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c.eq_ignore_ascii_case(&text[t]) => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_glob_matches() {
+        for (pattern, text) in [
+            ("password", "password"),
+            ("password", "PassWord"),
+            ("*_secret", "db_secret"),
+            ("*_secret", "_secret"),
+            ("*", ""),
+            ("a*b*c", "aXbYbZc"),
+            ("t?ken", "token"),
+            ("**x", "abx"),
+            ("ø*", "øl"),
+        ] {
+            assert!(glob_matches(pattern, text), "{pattern} should match {text}");
+        }
+        for (pattern, text) in [
+            ("password", "passwords"),
+            ("*_secret", "secret"),
+            ("a*b*c", "aXbYbZ"),
+            ("t?ken", "tken"),
+            ("", "a"),
+        ] {
+            assert!(
+                !glob_matches(pattern, text),
+                "{pattern} should not match {text}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_redact() {
+        let mut value = Value::from_str(
+            "token: 1, servers: [{name: \"a\", key: {id: 2}}, {name: \"b\"}], nested: {tokens: []}",
+        )
+        .unwrap();
+        value["servers"][1]["secret"] =
+            Value::Tagged("env".into(), Box::new(Value::from_str("{key: 3}").unwrap()));
+
+        redact(&mut value, &["token", "key"]);
+
+        let mut expected = Value::from_str(
+            "token: \"***\", servers: [{name: \"a\", key: \"***\"}, {name: \"b\"}], nested: {tokens: []}",
+        )
+        .unwrap();
+        expected["servers"][1]["secret"] = Value::Tagged(
+            "env".into(),
+            Box::new(Value::from_str("{key: \"***\"}").unwrap()),
+        );
+        assert_eq!(value, expected);
+
+        let original = value.clone();
+        redact(&mut value, &[]);
+        assert_eq!(value, original);
+    }
+}
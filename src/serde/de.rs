@@ -1,6 +1,6 @@
 //! Deserialize MASON data to a Rust data structure.
 
-use std::io::{self, BufRead, Read};
+use std::io::{self, BufRead, BufReader, Read};
 
 use pastey::paste;
 use serde::Deserialize;
@@ -10,41 +10,210 @@ use serde::de::{
     Unexpected, VariantAccess, Visitor,
 };
 
-use crate::peek_reader::PeekReader;
+use crate::deserialize::ParsedNumber;
+use crate::peek_reader::{PeekReader, Position};
 use crate::{deserialize, utils};
 
-use super::error::{Error, Result};
+use super::{
+    error::{Error, Result},
+    raw,
+};
 
 /// A structure that deserializes MASON into Rust values.
-pub struct Deserializer<R: Read> {
-    reader: PeekReader<R>,
+pub struct Deserializer<R: BufRead> {
+    reader: PeekReader<LimitedReader<R>>,
     depth: usize,
+    human_readable: bool,
+    limits: Limits,
+    /// The number of array elements and object entries deserialized so far.
+    items: usize,
+}
+
+/// Limits on the input of a [`Deserializer`], which protect services that
+/// deserialize untrusted MASON from inputs that would take too much memory or
+/// time. See [`Deserializer::with_limits`].
+///
+/// Fields that are not set can be taken from [`Limits::default`], which sets
+/// no limits, except for the default depth limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Limits {
+    /// The maximum length of the input in bytes.
+    pub max_bytes: usize,
+    /// The maximum depth that arrays and objects can be nested. The default is 100.
+    pub max_depth: usize,
+    /// The maximum number of array elements and object entries in the input,
+    /// counted together. Values that are skipped, like unknown fields, are not
+    /// counted, but they are limited by `max_bytes` and `max_depth`.
+    pub max_items: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_bytes: usize::MAX,
+            max_depth: 100,
+            max_items: usize::MAX,
+        }
+    }
+}
+
+/// A reader that fails if more than `limit` bytes are read from it.
+struct LimitedReader<R: BufRead> {
+    inner: R,
+    limit: usize,
+    remaining: usize,
+}
+
+impl<R: BufRead> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let read = available.len().min(buf.len());
+        buf[..read].copy_from_slice(&available[..read]);
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for LimitedReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let buf = self.inner.fill_buf()?;
+        if self.remaining == 0 && !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("input is longer than the limit of {} bytes", self.limit),
+            ));
+        }
+        Ok(&buf[..buf.len().min(self.remaining)])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.remaining -= amt;
+        self.inner.consume(amt);
+    }
 }
 
-impl<R: Read> Deserializer<R> {
-    /// Creates a MASON deserializer from an `io::Read`.
+impl<R: Read> Deserializer<BufReader<R>> {
+    /// Creates a MASON deserializer from an `io::Read`, which is read through a
+    /// [`BufReader`].
     ///
     /// Reader-based deserializers do not support deserializing borrowed types
     /// like `&str`, since the `std::io::Read` trait has no non-copying methods
     /// -- everything it does involves copying bytes out of the data source.
+    ///
+    /// If `reader` already implements [`BufRead`], like a [`BufReader`] or
+    /// [`Stdin`](std::io::Stdin), [`Deserializer::from_buf_reader`] is faster,
+    /// as every byte is not copied through a second buffer.
     pub fn from_reader(reader: R) -> Self {
+        Self::from_buf_reader(BufReader::new(reader))
+    }
+}
+
+impl<R: BufRead> Deserializer<R> {
+    /// Creates a MASON deserializer from an `io::BufRead`, which is read from
+    /// directly, without adding another buffer like [`Deserializer::from_reader`] does.
+    ///
+    /// ```
+    /// # use mason_rs::Deserializer;
+    /// # use serde::Deserialize;
+    /// # use std::io::BufReader;
+    /// #
+    /// let reader = BufReader::new("[1, 2, 3]".as_bytes());
+    /// let mut deserializer = Deserializer::from_buf_reader(reader);
+    /// let numbers = Vec::<u8>::deserialize(&mut deserializer).unwrap();
+    /// assert_eq!(numbers, [1, 2, 3]);
+    /// ```
+    pub fn from_buf_reader(reader: R) -> Self {
         Self {
-            reader: PeekReader::new(reader),
+            reader: PeekReader::from_buf_read(LimitedReader {
+                inner: reader,
+                limit: usize::MAX,
+                remaining: usize::MAX,
+            }),
             depth: 0,
+            human_readable: true,
+            limits: Limits::default(),
+            items: 0,
         }
     }
+
+    /// Set whether types should expect their human-readable representation, which
+    /// is what [`serde::Deserializer::is_human_readable`] returns. This must match
+    /// the setting of the [`Serializer`](super::Serializer) that produced the input.
+    /// The default is `true`.
+    ///
+    /// ```
+    /// # use mason_rs::Deserializer;
+    /// # use serde::Deserialize;
+    /// # use std::net::Ipv4Addr;
+    /// #
+    /// let mut deserializer = Deserializer::from_str("[127, 0, 0, 1]").human_readable(false);
+    /// let address = Ipv4Addr::deserialize(&mut deserializer).unwrap();
+    /// assert_eq!(address, Ipv4Addr::LOCALHOST);
+    /// ```
+    pub fn human_readable(mut self, human_readable: bool) -> Self {
+        self.human_readable = human_readable;
+        self
+    }
+
+    /// Limit the size of the input, to protect against untrusted input that would
+    /// take too much memory or time to deserialize. Deserializing fails with a
+    /// descriptive error as soon as a limit is exceeded. See [`Limits`].
+    ///
+    /// ```
+    /// # use mason_rs::{Deserializer, serde::de::Limits};
+    /// # use serde::Deserialize;
+    /// # use std::collections::HashMap;
+    /// #
+    /// let limits = Limits { max_bytes: 1024, max_depth: 8, max_items: 4 };
+    ///
+    /// let mut deserializer = Deserializer::from_str("a: [1, 2], b: []").with_limits(limits);
+    /// assert!(HashMap::<String, Vec<u8>>::deserialize(&mut deserializer).is_ok());
+    ///
+    /// let mut deserializer = Deserializer::from_str("a: [1, 2, 3], b: []").with_limits(limits);
+    /// let err = HashMap::<String, Vec<u8>>::deserialize(&mut deserializer).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "input has more than the limit of 4 array elements and object entries",
+    /// );
+    /// ```
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        let reader = self.reader.get_mut();
+        reader.limit = limits.max_bytes;
+        reader.remaining = limits.max_bytes;
+        self.limits = limits;
+        self
+    }
+
+    /// The position in the input of the next value that will be deserialized.
+    pub fn position(&self) -> Position {
+        self.reader.position()
+    }
 }
 
 impl<'de> Deserializer<&'de [u8]> {
     /// Creates a MASON deserializer from a `&[u8]`.
+    ///
+    /// The input is read like any other [`BufRead`], so strings are always copied
+    /// out of it. Borrowed types like `&str` can not be deserialized, but
+    /// `Cow<str>` can, and is always owned.
+    ///
+    /// ```
+    /// # use mason_rs::Deserializer;
+    /// # use serde::Deserialize;
+    /// # use std::borrow::Cow;
+    /// #
+    /// let mut deserializer = Deserializer::from_slice(b"\"a string\"");
+    /// let string = Cow::<str>::deserialize(&mut deserializer).unwrap();
+    /// assert!(matches!(string, Cow::Owned(_)));
+    /// ```
     pub fn from_slice(input: &'de [u8]) -> Self {
-        Self::from_reader(input)
+        Self::from_buf_reader(input)
     }
 
     #[allow(clippy::should_implement_trait)]
     /// Creates a MASON deserializer from a `&str`.
     pub fn from_str(input: &'de str) -> Self {
-        Self::from_reader(input.as_bytes())
+        Self::from_slice(input.as_bytes())
     }
 }
 
@@ -142,16 +311,22 @@ where
     T: Deserialize<'de>,
     R: Read + 'de,
 {
-    let mut deserializer = Deserializer::from_reader(reader);
-    let t = T::deserialize(&mut deserializer)?;
+    deserialize_document(&mut Deserializer::from_reader(reader))
+}
+
+/// Deserialize a `T`, and check that nothing but whitespace comes after it.
+fn deserialize_document<'de, T, R>(deserializer: &mut Deserializer<R>) -> Result<T>
+where
+    T: Deserialize<'de>,
+    R: BufRead + 'de,
+{
+    deserialize::skip_byte_order_mark(&mut deserializer.reader)?;
+    let t = T::deserialize(&mut *deserializer)?;
     deserialize::skip_whitespace(&mut deserializer.reader)?;
     if let Some(garbage) = deserializer.reader.peek()? {
-        Err(Error::from(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!(
-                "Trailing garbage after document: {:?}",
-                utils::to_char(garbage)
-            ),
+        Err(Error::syntax(format!(
+            "Trailing garbage after document: {:?}",
+            utils::to_char(garbage)
         )))
     } else {
         Ok(t)
@@ -194,7 +369,7 @@ pub fn from_slice<'de, T>(bytes: &'de [u8]) -> Result<T>
 where
     T: Deserialize<'de>,
 {
-    from_reader(bytes)
+    deserialize_document(&mut Deserializer::from_slice(bytes))
 }
 
 /// Deserialize an instance of type `T` from a string of MASON text.
@@ -229,43 +404,107 @@ where
 /// is wrong with the data, for example required struct fields are missing from
 /// the MASON map or some number is too big to fit in the expected primitive
 /// type.
+///
+/// The error includes the line of `string` where deserialization failed when
+/// it is displayed.
 pub fn from_str<'de, T>(string: &'de str) -> Result<T>
 where
     T: Deserialize<'de>,
 {
-    from_reader(string.as_bytes())
+    let mut deserializer = Deserializer::from_str(string);
+    deserialize_document(&mut deserializer)
+        .map_err(|err| err.with_source(string.as_bytes(), deserializer.position()))
 }
 
-impl<R: Read> Deserializer<R> {
-    // read_byte, but return Error::Eof on EOF
-    fn expect_read_byte(&mut self) -> Result<u8> {
+impl<R: BufRead> Deserializer<R> {
+    // skip the next value, and give its text to the visitor as a string
+    fn deserialize_raw_value<'de, V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.depth >= self.limits.max_depth {
+            return Err(Error::depth_exceeded());
+        }
+
+        deserialize::skip_whitespace(&mut self.reader)?;
+        self.reader.start_recording();
+        let skipped =
+            deserialize::skip_value(&mut self.reader, self.limits.max_depth - self.depth, false);
+        let raw = self.reader.stop_recording();
+        skipped?;
+
+        let raw = String::from_utf8(raw).map_err(|_| Error::syntax("raw value is not UTF-8"))?;
+        visitor.visit_string(raw.trim_end().to_owned())
+    }
+
+    // go one level deeper into the input, and fail if that is deeper than the limit
+    fn enter(&mut self) -> Result<()> {
+        if self.depth >= self.limits.max_depth {
+            return Err(Error::depth_exceeded());
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    // count an array element or object entry, and fail if there are too many
+    fn count_item(&mut self) -> Result<()> {
+        self.items += 1;
+        if self.items > self.limits.max_items {
+            return Err(Error::custom(format!(
+                "input has more than the limit of {} array elements and object entries",
+                self.limits.max_items
+            )));
+        }
+        Ok(())
+    }
+
+    // read_byte, but fail with an EOF error that says what was expected on EOF
+    fn expect_read_byte(&mut self, expected: &str) -> Result<u8> {
         match self.reader.read_byte() {
             Ok(Some(byte)) => Ok(byte),
-            Ok(None) => Err(Error::eof()),
+            Ok(None) => Err(Error::eof_expecting(expected)),
             Err(err) => Err(Error::from(err)),
         }
     }
 
-    // peek, but return Error::Eof on EOF
-    fn expect_peek(&mut self) -> Result<u8> {
+    // peek, but fail with an EOF error that says what was expected on EOF
+    fn expect_peek(&mut self, expected: &str) -> Result<u8> {
         match self.reader.peek() {
             Ok(Some(byte)) => Ok(byte),
-            Ok(None) => Err(Error::eof()),
+            Ok(None) => Err(Error::eof_expecting(expected)),
             Err(err) => Err(Error::from(err)),
         }
     }
 
-    // peek2, but return Error::Eof on EOF
-    fn expect_peek2(&mut self) -> Result<[u8; 2]> {
-        match self.reader.peek2() {
-            Ok(Some(bytes)) => Ok(bytes),
-            Ok(None) => Err(Error::eof()),
-            Err(err) => Err(Error::from(err)),
+    // peek all the identifier characters at the start of the input
+    fn peek_identifier(&mut self) -> Result<&[u8]> {
+        Ok(self
+            .reader
+            .peek_until(|byte| !utils::is_identifier_char(byte))?)
+    }
+
+    // create an error for when the identifier at the start of the input was
+    // not what we expected
+    fn unexpected_identifier(&mut self, expected: &dyn de::Expected) -> Error {
+        let identifier = match self.peek_identifier() {
+            Ok(identifier) => identifier,
+            Err(err) => return err,
+        };
+        if !identifier.is_empty() {
+            Error::invalid_type(
+                Unexpected::Str(&String::from_utf8_lossy(identifier)),
+                expected,
+            )
+        } else {
+            match self.expect_peek("a value") {
+                Ok(byte) => Error::invalid_type(Unexpected::Char(utils::to_char(byte)), expected),
+                Err(err) => err,
+            }
         }
     }
 }
 
-/// Deserialize an f64, and see if it can be converted into the given type
+/// Deserialize a number, and see if it can be converted into the given type
 macro_rules! deserialize_integer {
     ($type:ty) => {
         paste! {
@@ -273,21 +512,48 @@ macro_rules! deserialize_integer {
             where
                 V: Visitor<'de>,
             {
-                let num = $crate::deserialize::parse_number(&mut self.reader)?;
-                if num.fract() != 0.0 || num >  $type::MAX as f64 || num <  $type::MIN as f64 {
-                    Err(Error::invalid_type(
-                        Unexpected::Float(num),
-                        &stringify!($type),
-                    ))
-                } else {
-                    visitor.[<visit_ $type>](num as  $type)
+                let num = deserialize::parse_number(&mut self.reader)?;
+                let converted = match num {
+                    ParsedNumber::PosInt(int) => $type::try_from(int).ok(),
+                    ParsedNumber::NegInt(int) => $type::try_from(int).ok(),
+                    ParsedNumber::Float(float) => {
+                        // MAX rounds up when it is converted to a float, so compare
+                        // with MAX + 1 instead, which is a power of two, like MIN
+                        let bits = $type::BITS - u32::from($type::MIN != 0);
+                        if float.fract() != 0.0
+                            || float >= 2f64.powi(bits as i32)
+                            || float < $type::MIN as f64
+                        {
+                            None
+                        } else {
+                            Some(float as $type)
+                        }
+                    }
+                };
+                match converted {
+                    Some(int) => visitor.[<visit_ $type>](int),
+                    None => Err(Error::invalid_type(unexpected_number(num), &stringify!($type))),
                 }
             }
         }
     };
 }
 
-impl<'de, R: Read + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
+fn unexpected_number(num: ParsedNumber) -> Unexpected<'static> {
+    match num {
+        ParsedNumber::PosInt(int) => match u64::try_from(int) {
+            Ok(int) => Unexpected::Unsigned(int),
+            Err(_) => Unexpected::Other("128-bit integer"),
+        },
+        ParsedNumber::NegInt(int) => match i64::try_from(int) {
+            Ok(int) => Unexpected::Signed(int),
+            Err(_) => Unexpected::Other("128-bit integer"),
+        },
+        ParsedNumber::Float(float) => Unexpected::Float(float),
+    }
+}
+
+impl<'de, R: BufRead + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
     type Error = Error;
 
     // Look at the input data to decide what Serde data model type to
@@ -297,13 +563,13 @@ impl<'de, R: Read + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
     where
         V: Visitor<'de>,
     {
-        if self.depth == 100 {
-            return Err(Error::custom("reached maximum depth"));
+        if self.depth >= self.limits.max_depth {
+            return Err(Error::depth_exceeded());
         }
 
         deserialize::skip_whitespace(&mut self.reader)?;
 
-        let first_byte = self.expect_peek()?;
+        let first_byte = self.expect_peek("a value")?;
         match first_byte {
             b'{' => return self.deserialize_map(visitor),
             b'[' => return self.deserialize_seq(visitor),
@@ -337,7 +603,20 @@ impl<'de, R: Read + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
         }
 
         if first_byte.is_ascii_digit() || matches!(first_byte, b'+' | b'-' | b'.') {
-            self.deserialize_f64(visitor)
+            match deserialize::parse_number(&mut self.reader)? {
+                // MASON numbers are floats, and most visitors do not implement the
+                // 128-bit methods, so bigger integers are only exact when an i128
+                // or u128 is asked for
+                ParsedNumber::PosInt(int) => match u64::try_from(int) {
+                    Ok(int) => visitor.visit_u64(int),
+                    Err(_) => visitor.visit_f64(int as f64),
+                },
+                ParsedNumber::NegInt(int) => match i64::try_from(int) {
+                    Ok(int) => visitor.visit_i64(int),
+                    Err(_) => visitor.visit_f64(int as f64),
+                },
+                ParsedNumber::Float(float) => visitor.visit_f64(float),
+            }
         } else {
             let identifier = deserialize::parse_identifier(&mut self.reader)?;
             if self.depth == 0 {
@@ -351,7 +630,7 @@ impl<'de, R: Read + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
                 "true" => visitor.visit_bool(true),
                 "false" => visitor.visit_bool(false),
                 "null" => visitor.visit_unit(),
-                _ => Err(Error::custom(format!("malformed value: {identifier}"))),
+                _ => Err(Error::syntax(format!("malformed value: {identifier}"))),
             }
         }
     }
@@ -360,38 +639,32 @@ impl<'de, R: Read + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
     where
         V: Visitor<'de>,
     {
-        let expected: &[u8] = if self.expect_peek()? == b't' {
-            b"true"
-        } else {
-            b"false"
+        let value = match self.peek_identifier()? {
+            b"true" => true,
+            b"false" => false,
+            _ => return Err(self.unexpected_identifier(&"bool")),
         };
-        for b in expected {
-            let byte = self.expect_read_byte()?;
-            if byte != *b {
-                return Err(Error::invalid_type(
-                    Unexpected::Char(utils::to_char(*b)),
-                    &"bool",
-                ));
-            }
-        }
-        visitor.visit_bool(expected == b"true")
+        self.reader.consume(if value { 4 } else { 5 });
+        visitor.visit_bool(value)
     }
 
     deserialize_integer!(i8);
     deserialize_integer!(i16);
     deserialize_integer!(i32);
     deserialize_integer!(i64);
+    deserialize_integer!(i128);
 
     deserialize_integer!(u8);
     deserialize_integer!(u16);
     deserialize_integer!(u32);
     deserialize_integer!(u64);
+    deserialize_integer!(u128);
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let num = deserialize::parse_number(&mut self.reader)?;
+        let num = deserialize::parse_number(&mut self.reader)?.as_f64();
         let num_f32 = num as f32;
 
         // se if num is representable as an f32
@@ -406,7 +679,7 @@ impl<'de, R: Read + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_f64(deserialize::parse_number(&mut self.reader)?)
+        visitor.visit_f64(deserialize::parse_number(&mut self.reader)?.as_f64())
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
@@ -433,10 +706,13 @@ impl<'de, R: Read + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
     where
         V: Visitor<'de>,
     {
-        let byte = self.expect_peek()?;
+        let byte = self.expect_peek("a string")?;
         match byte {
             b'"' => visitor.visit_string(deserialize::parse_string(&mut self.reader)?),
-            b'r' => visitor.visit_string(deserialize::parse_raw_string(&mut self.reader)?),
+            b'r' => visitor.visit_string(deserialize::parse_raw_string(
+                &mut self.reader,
+                deserialize::DEFAULT_MAX_RAW_STRING_HASHES,
+            )?),
             b'|' => visitor.visit_string(deserialize::parse_multi_line_string(&mut self.reader)?),
             _ => Err(Error::invalid_type(
                 Unexpected::Char(utils::to_char(byte)),
@@ -466,9 +742,9 @@ impl<'de, R: Read + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
     where
         V: Visitor<'de>,
     {
-        // TODO: the first two bytes being 'nu' does not actually guarantee
-        // that the value is 'null'
-        if &self.expect_peek2()? == b"nu" {
+        // We must check the whole identifier, as the value could be something
+        // like 'nullable'
+        if self.peek_identifier()? == b"null" {
             self.reader.consume(4);
             visitor.visit_none()
         } else {
@@ -481,15 +757,10 @@ impl<'de, R: Read + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
     where
         V: Visitor<'de>,
     {
-        for b in b"null" {
-            let byte = self.expect_read_byte()?;
-            if byte != *b {
-                return Err(Error::invalid_type(
-                    Unexpected::Char(utils::to_char(byte)),
-                    &"unit",
-                ));
-            }
+        if self.peek_identifier()? != b"null" {
+            return Err(self.unexpected_identifier(&"unit"));
         }
+        self.reader.consume(4);
         visitor.visit_unit()
     }
 
@@ -503,11 +774,15 @@ impl<'de, R: Read + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
 
     // As is done here, serializers are encouraged to treat newtype structs as
     // insignificant wrappers around the data they contain. That means not
-    // parsing anything other than the contained value.
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    // parsing anything other than the contained value. The exception is
+    // `RawValue`, which gets the raw text of the value instead.
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if name == raw::TOKEN {
+            return self.deserialize_raw_value(visitor);
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -519,12 +794,12 @@ impl<'de, R: Read + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
         V: Visitor<'de>,
     {
         // Parse the opening bracket of the sequence.
-        let byte = self.expect_read_byte()?;
+        let byte = self.expect_read_byte("'['")?;
         if byte == b'[' {
             // Give the visitor access to each element of the sequence.
             let value = visitor.visit_seq(SepSeparated::new(self, true))?;
             // Parse the closing bracket of the sequence.
-            let byte = self.expect_read_byte()?;
+            let byte = self.expect_read_byte("']'")?;
             if byte == b']' {
                 Ok(value)
             } else {
@@ -569,12 +844,12 @@ impl<'de, R: Read + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
         if self.depth == 0 {
             // If depth is 0, struct does not need to be surrounded by braces
             let mut has_opening_brace = false;
-            if self.expect_peek()? == b'{' {
+            if self.expect_peek("an object")? == b'{' {
                 self.reader.read_byte()?;
                 has_opening_brace = true;
             }
 
-            self.depth += 1;
+            self.enter()?;
             let value = visitor.visit_map(SepSeparated::new(self, has_opening_brace))?;
             self.depth -= 1;
 
@@ -583,23 +858,23 @@ impl<'de, R: Read + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
                     self.reader.read_byte()?;
                 }
                 (false, true) => {
-                    return Err(Error::custom(
+                    return Err(Error::syntax(
                         "got closing bracket without an opening bracket",
                     ));
                 }
-                (true, false) => return Err(Error::custom("unclosed bracket")),
+                (true, false) => return Err(Error::syntax("unclosed bracket")),
                 (false, false) => {}
             }
             return Ok(value);
         }
 
-        let byte = self.expect_read_byte()?;
+        let byte = self.expect_read_byte("'{'")?;
         if byte == b'{' {
-            self.depth += 1;
+            self.enter()?;
             let value = visitor.visit_map(SepSeparated::new(self, true))?;
             self.depth -= 1;
 
-            let byte = self.expect_read_byte()?;
+            let byte = self.expect_read_byte("'}'")?;
             if byte == b'}' {
                 Ok(value)
             } else {
@@ -638,20 +913,52 @@ impl<'de, R: Read + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
     where
         V: Visitor<'de>,
     {
+        // Variants with data are represented as objects with a single key, which
+        // only lack braces at the top level.
+        let braced = self.expect_peek("an enum variant")? == b'{';
+        if braced {
+            self.reader.consume(1);
+            deserialize::skip_whitespace(&mut self.reader)?;
+        }
+
         let variant = deserialize::parse_identifier(&mut self.reader)?;
-        deserialize::skip_whitespace(&mut self.reader)?;
+        // a newline after a unit variant separates it from the next value, like
+        // the content of an adjacently tagged enum, so it must not be skipped
+        while matches!(self.reader.peek()?, Some(b' ' | b'\t')) {
+            self.reader.consume(1);
+        }
 
         if self.reader.peek()? != Some(b':') {
+            if braced {
+                return Err(Error::syntax("expected ':' after enum variant"));
+            }
             // Visit a unit variant.
-            visitor.visit_enum(variant.into_deserializer())
-        } else if self.depth == 0 {
-            // skip colon
-            self.reader.read_byte()?;
-            deserialize::skip_whitespace(&mut self.reader)?;
+            return visitor.visit_enum(variant.into_deserializer());
+        } else if !braced && self.depth != 0 {
+            return Err(Error::syntax(
+                "enum variant with data must be surrounded by braces",
+            ));
+        }
 
-            Ok(visitor.visit_enum(Enum::new(self, variant))?)
-        } else {
-            todo!()
+        // skip colon
+        self.reader.read_byte()?;
+        deserialize::skip_whitespace(&mut self.reader)?;
+
+        if !braced {
+            return visitor.visit_enum(Enum::new(self, variant));
+        }
+
+        self.enter()?;
+        let value = visitor.visit_enum(Enum::new(self, variant))?;
+        self.depth -= 1;
+
+        deserialize::skip_whitespace(&mut self.reader)?;
+        match self.expect_read_byte("'}'")? {
+            b'}' => Ok(value),
+            byte => Err(Error::invalid_type(
+                Unexpected::Char(utils::to_char(byte)),
+                &"enum end",
+            )),
         }
     }
 
@@ -666,18 +973,34 @@ impl<'de, R: Read + 'de> de::Deserializer<'de> for &mut Deserializer<R> {
         visitor.visit_string(deserialize::parse_identifier(&mut self.reader)?)
     }
 
+    // Ignored values are skipped without being parsed into strings or
+    // containers, which makes unknown fields cheap.
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_any(visitor)
+        if self.depth >= self.limits.max_depth {
+            return Err(Error::depth_exceeded());
+        }
+
+        deserialize::skip_whitespace(&mut self.reader)?;
+        deserialize::skip_value(
+            &mut self.reader,
+            self.limits.max_depth - self.depth,
+            self.depth == 0,
+        )?;
+        visitor.visit_unit()
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.human_readable
     }
 }
 
 // In order to handle seps correctly when deserializing a MASON array or map,
 // we need to track whether we are on the first element or past the first
 // element.
-struct SepSeparated<'a, R: Read> {
+struct SepSeparated<'a, R: BufRead> {
     de: &'a mut Deserializer<R>,
     first: bool,
     // should we expect a closing bracket?
@@ -687,7 +1010,7 @@ struct SepSeparated<'a, R: Read> {
     previously_parsed_multi_line_string: bool,
 }
 
-impl<'a, R: Read> SepSeparated<'a, R> {
+impl<'a, R: BufRead> SepSeparated<'a, R> {
     fn new(de: &'a mut Deserializer<R>, expect_closing: bool) -> Self {
         SepSeparated {
             de,
@@ -715,7 +1038,7 @@ impl<'a, R: Read> SepSeparated<'a, R> {
 
 // `SeqAccess` is provided to the `Visitor` to give it the ability to iterate
 // through elements of the sequence.
-impl<'de, R: Read + 'de> SeqAccess<'de> for SepSeparated<'_, R> {
+impl<'de, R: BufRead + 'de> SeqAccess<'de> for SepSeparated<'_, R> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
@@ -728,10 +1051,10 @@ impl<'de, R: Read + 'de> SeqAccess<'de> for SepSeparated<'_, R> {
             deserialize::skip_whitespace(&mut self.de.reader)?;
 
             if !valid_sep {
-                if self.de.expect_peek()? == b']' {
+                if self.de.expect_peek("']'")? == b']' {
                     return Ok(None);
                 } else {
-                    return Err(Error::custom("array missing sep"));
+                    return Err(Error::syntax("array missing sep"));
                 }
             }
         }
@@ -740,12 +1063,13 @@ impl<'de, R: Read + 'de> SeqAccess<'de> for SepSeparated<'_, R> {
         deserialize::skip_whitespace(&mut self.de.reader)?;
 
         // Check if there are no more elements.
-        if self.de.expect_peek()? == b']' {
+        if self.de.expect_peek("an array element or ']'")? == b']' {
             return Ok(None);
         }
 
         // Deserialize an array element.
-        self.de.depth += 1;
+        self.de.count_item()?;
+        self.de.enter()?;
         self.previously_parsed_multi_line_string = self.de.reader.peek()? == Some(b'|');
         let result = seed.deserialize(&mut *self.de).map(Some);
         self.de.depth -= 1;
@@ -756,7 +1080,7 @@ impl<'de, R: Read + 'de> SeqAccess<'de> for SepSeparated<'_, R> {
 
 // `MapAccess` is provided to the `Visitor` to give it the ability to iterate
 // through entries of the map.
-impl<'de, R: Read + 'de> MapAccess<'de> for SepSeparated<'_, R> {
+impl<'de, R: BufRead + 'de> MapAccess<'de> for SepSeparated<'_, R> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
@@ -765,7 +1089,8 @@ impl<'de, R: Read + 'de> MapAccess<'de> for SepSeparated<'_, R> {
     {
         if let Some(key) = self.first_key.take() {
             self.first = false;
-            return seed.deserialize(key.into_deserializer()).map(Some);
+            self.de.count_item()?;
+            return seed.deserialize(MapKey { key }).map(Some);
         }
 
         let valid_sep = if !self.first {
@@ -778,26 +1103,27 @@ impl<'de, R: Read + 'de> MapAccess<'de> for SepSeparated<'_, R> {
         match (self.de.reader.peek()?, self.expect_closing) {
             (Some(b'}'), true) | (None, false) => return Ok(None),
             (Some(b'}'), false) => {
-                return Err(Error::custom(
+                return Err(Error::syntax(
                     "got closing bracket without an opening bracket",
                 ));
             }
-            (None, true) => return Err(Error::custom("unclosed bracket")),
+            (None, true) => return Err(Error::syntax("unclosed bracket")),
             _ => {}
         }
 
         if !valid_sep {
-            return Err(Error::custom("map missing sep"));
+            return Err(Error::syntax("map missing sep"));
         }
         self.first = false;
+        self.de.count_item()?;
 
-        let key = if self.de.expect_peek()? == b'"' {
+        let key = if self.de.expect_peek("a key")? == b'"' {
             deserialize::parse_string(&mut self.de.reader)?
         } else {
             deserialize::parse_identifier(&mut self.de.reader)?
         };
 
-        seed.deserialize(key.into_deserializer()).map(Some)
+        seed.deserialize(MapKey { key }).map(Some)
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
@@ -805,7 +1131,7 @@ impl<'de, R: Read + 'de> MapAccess<'de> for SepSeparated<'_, R> {
         V: DeserializeSeed<'de>,
     {
         deserialize::skip_whitespace(&mut self.de.reader)?;
-        let byte = self.de.expect_read_byte()?;
+        let byte = self.de.expect_read_byte("':'")?;
         if byte != b':' {
             return Err(Error::invalid_type(
                 Unexpected::Char(utils::to_char(byte)),
@@ -815,7 +1141,7 @@ impl<'de, R: Read + 'de> MapAccess<'de> for SepSeparated<'_, R> {
         deserialize::skip_whitespace(&mut self.de.reader)?;
 
         // Deserialize a map value.
-        self.de.depth += 1;
+        self.de.enter()?;
         self.previously_parsed_multi_line_string = self.de.reader.peek()? == Some(b'|');
         let result = seed.deserialize(&mut *self.de);
         self.de.depth -= 1;
@@ -824,12 +1150,98 @@ impl<'de, R: Read + 'de> MapAccess<'de> for SepSeparated<'_, R> {
     }
 }
 
-struct Enum<'a, R: Read> {
+/// Deserialize a map key as the given type by parsing its text, which is how
+/// `KeySerializer` writes keys that are not strings
+macro_rules! deserialize_parsed_key {
+    ($type:ty) => {
+        paste! {
+            fn [<deserialize_ $type>]<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                match self.key.parse::<$type>() {
+                    Ok(value) => visitor.[<visit_ $type>](value),
+                    Err(_) => Err(Error::invalid_type(Unexpected::Str(&self.key), &stringify!($type))),
+                }
+            }
+        }
+    };
+}
+
+// Map keys are always strings or identifiers in MASON, but maps can have keys of
+// other types, like `HashMap<u32, String>`. The serializer writes those keys as
+// strings, so here we parse the key back into the type that is asked for.
+struct MapKey {
+    key: String,
+}
+
+impl<'de> de::Deserializer<'de> for MapKey {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_string(self.key)
+    }
+
+    deserialize_parsed_key!(bool);
+
+    deserialize_parsed_key!(i8);
+    deserialize_parsed_key!(i16);
+    deserialize_parsed_key!(i32);
+    deserialize_parsed_key!(i64);
+    deserialize_parsed_key!(i128);
+
+    deserialize_parsed_key!(u8);
+    deserialize_parsed_key!(u16);
+    deserialize_parsed_key!(u32);
+    deserialize_parsed_key!(u64);
+    deserialize_parsed_key!(u128);
+
+    deserialize_parsed_key!(f32);
+    deserialize_parsed_key!(f64);
+    deserialize_parsed_key!(char);
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let deserializer: StringDeserializer<Error> = self.key.into_deserializer();
+        deserializer.deserialize_enum(name, variants, visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple tuple_struct map
+        struct identifier ignored_any
+    }
+}
+
+struct Enum<'a, R: BufRead> {
     de: &'a mut Deserializer<R>,
     variant: Option<String>,
 }
 
-impl<'a, R: Read> Enum<'a, R> {
+impl<'a, R: BufRead> Enum<'a, R> {
     fn new(de: &'a mut Deserializer<R>, variant: String) -> Self {
         Enum {
             de,
@@ -843,7 +1255,7 @@ impl<'a, R: Read> Enum<'a, R> {
 //
 // Note that all enum deserialization methods in Serde refer exclusively to the
 // "externally tagged" enum representation.
-impl<'de, R: Read + 'de> EnumAccess<'de> for Enum<'_, R> {
+impl<'de, R: BufRead + 'de> EnumAccess<'de> for Enum<'_, R> {
     type Error = Error;
     type Variant = Self;
 
@@ -862,7 +1274,7 @@ impl<'de, R: Read + 'de> EnumAccess<'de> for Enum<'_, R> {
 
 // `VariantAccess` is provided to the `Visitor` to give it the ability to see
 // the content of the single variant that it decided to deserialize.
-impl<'de, R: Read + 'de> VariantAccess<'de> for Enum<'_, R> {
+impl<'de, R: BufRead + 'de> VariantAccess<'de> for Enum<'_, R> {
     type Error = Error;
 
     // If the `Visitor` expected this variant to be a unit variant, the input
@@ -904,9 +1316,10 @@ impl<'de, R: Read + 'de> VariantAccess<'de> for Enum<'_, R> {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::{borrow::Cow, collections::HashMap};
 
     use super::*;
+    use crate::serde::error::Category;
 
     #[test]
     fn test_struct() {
@@ -956,6 +1369,129 @@ Struct: {
         assert_eq!(expected, from_str(j).unwrap());
     }
 
+    #[test]
+    fn test_enum_representations() {
+        use serde::Serialize;
+
+        use crate::serde::ser::to_string;
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        enum External {
+            Unit,
+            Newtype(u32),
+            Tuple(u32, String),
+            Struct { a: Vec<u8> },
+        }
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(tag = "type")]
+        enum Internal {
+            Unit,
+            Newtype(Inner),
+            Struct { a: Vec<u8> },
+        }
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Inner {
+            x: f64,
+        }
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(tag = "t", content = "c")]
+        enum Adjacent {
+            Unit,
+            Newtype(u32),
+            Tuple(u32, String),
+            Struct { a: Vec<u8> },
+        }
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(untagged)]
+        enum Untagged {
+            Newtype(u32),
+            Tuple(u32, String),
+            Struct { a: Vec<u8> },
+        }
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Test<T> {
+            first: T,
+            all: Vec<T>,
+        }
+
+        fn round_trip<T>(variants: Vec<T>)
+        where
+            T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug,
+        {
+            for variant in &variants {
+                let string = to_string(variant).unwrap();
+                assert_eq!(&from_str::<T>(&string).unwrap(), variant, "{string}");
+            }
+
+            let mut variants = variants.into_iter();
+            let test = Test {
+                first: variants.next().unwrap(),
+                all: variants.collect(),
+            };
+            let string = to_string(&test).unwrap();
+            assert_eq!(from_str::<Test<T>>(&string).unwrap(), test, "{string}");
+        }
+
+        round_trip(vec![
+            External::Unit,
+            External::Newtype(1),
+            External::Tuple(1, "a".to_owned()),
+            External::Struct { a: vec![1, 2] },
+            External::Unit,
+        ]);
+        round_trip(vec![
+            Internal::Unit,
+            Internal::Newtype(Inner { x: 1.5 }),
+            Internal::Struct { a: vec![1, 2] },
+            Internal::Unit,
+        ]);
+        round_trip(vec![
+            Adjacent::Unit,
+            Adjacent::Newtype(1),
+            Adjacent::Tuple(1, "a".to_owned()),
+            Adjacent::Struct { a: vec![1, 2] },
+            Adjacent::Unit,
+        ]);
+        round_trip(vec![
+            Untagged::Newtype(1),
+            Untagged::Tuple(1, "a".to_owned()),
+            Untagged::Struct { a: vec![1, 2] },
+        ]);
+
+        // the content of an adjacently tagged enum can come before its tag
+        let j = "c: [1, \"a\"], t: \"Tuple\"";
+        let expected = Adjacent::Tuple(1, "a".to_owned());
+        assert_eq!(expected, from_str(j).unwrap());
+    }
+
+    #[test]
+    fn test_cow() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Test<'a> {
+            #[serde(borrow)]
+            plain: Cow<'a, str>,
+            #[serde(borrow)]
+            escaped: Cow<'a, str>,
+            owned: Cow<'static, str>,
+        }
+
+        let j = "plain: \"a\"\nescaped: \"b\\n\"\nowned: r\"c\"";
+        let expected = Test {
+            plain: "a".into(),
+            escaped: "b\n".into(),
+            owned: "c".into(),
+        };
+        assert_eq!(expected, from_str(j).unwrap());
+
+        // strings are copied out of the input, so they can not be borrowed
+        assert!(from_str::<&str>("\"a\"").is_err());
+    }
+
     #[test]
     fn test_complicated() {
         #[derive(Deserialize, PartialEq, Debug)]
@@ -984,4 +1520,346 @@ nothing: null";
         };
         assert_eq!(expected, from_str(j).unwrap());
     }
+
+    #[test]
+    fn test_option() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        enum Food {
+            #[serde(rename = "nugget")]
+            Nugget,
+        }
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Test {
+            a: Option<Food>,
+            b: Option<Food>,
+            c: Option<HashMap<String, u32>>,
+        }
+
+        let j = "a: nugget, b: null, c: {nullable_key: 1}";
+        let expected = Test {
+            a: Some(Food::Nugget),
+            b: None,
+            c: Some(HashMap::from([("nullable_key".to_owned(), 1)])),
+        };
+        assert_eq!(expected, from_str(j).unwrap());
+
+        assert_eq!(from_str::<Option<u32>>("null").unwrap(), None);
+        assert_eq!(from_str::<Option<bool>>("true").unwrap(), Some(true));
+        assert!(from_str::<Option<bool>>("trueish").is_err());
+        assert!(from_str::<()>("nullish").is_err());
+        assert_eq!(
+            from_str::<Option<HashMap<String, u32>>>("nullable_key: 1").unwrap(),
+            Some(HashMap::from([("nullable_key".to_owned(), 1)]))
+        );
+    }
+
+    #[test]
+    fn test_integers() {
+        let j = "a: 1, b: -2, c: 1.5, d: 18446744073709551615, e: 0xff";
+        let map: HashMap<String, serde_json::Value> = from_str(j).unwrap();
+        assert_eq!(map["a"], serde_json::json!(1));
+        assert_eq!(map["b"], serde_json::json!(-2));
+        assert_eq!(map["c"], serde_json::json!(1.5));
+        assert_eq!(map["d"], serde_json::json!(u64::MAX));
+        assert_eq!(map["e"], serde_json::json!(255));
+
+        assert_eq!(from_str::<u64>("18446744073709551615").unwrap(), u64::MAX);
+        assert_eq!(from_str::<i64>("-9223372036854775808").unwrap(), i64::MIN);
+        assert_eq!(from_str::<u8>("2e2").unwrap(), 200);
+        assert!(from_str::<u8>("256").is_err());
+        assert!(from_str::<u32>("-1").is_err());
+
+        // integers that an f64 can not represent exactly are read into a `Value`
+        // like the parser reads them
+        for int in [
+            "9007199254740993",
+            "18446744073709551615",
+            "-9007199254740993",
+        ] {
+            assert_eq!(
+                from_str::<crate::Value>(int).unwrap(),
+                int.parse::<crate::Value>().unwrap(),
+            );
+            assert_eq!(
+                serde_json::from_str::<crate::Value>(int).unwrap(),
+                int.parse::<crate::Value>().unwrap(),
+            );
+        }
+        #[cfg(not(feature = "arbitrary_precision"))]
+        assert_eq!(
+            from_str::<crate::Value>("18446744073709551615").unwrap(),
+            crate::Value::from(18446744073709551615.0)
+        );
+        #[cfg(feature = "arbitrary_precision")]
+        assert_eq!(
+            from_str::<crate::Value>("18446744073709551615")
+                .unwrap()
+                .to_string(),
+            "18446744073709551615"
+        );
+    }
+
+    #[test]
+    fn test_128_bit_integers() {
+        assert_eq!(
+            from_str::<u128>("340282366920938463463374607431768211455").unwrap(),
+            u128::MAX
+        );
+        assert_eq!(
+            from_str::<i128>("-170141183460469231731687303715884105728").unwrap(),
+            i128::MIN
+        );
+        assert_eq!(
+            from_str::<u128>("0xffff_ffff_ffff_ffff_ffff".replace('_', "'").as_str()).unwrap(),
+            0xffff_ffff_ffff_ffff_ffff
+        );
+        // digits after 2^53 would be lost if the number went through an f64
+        assert_eq!(
+            from_str::<u128>("18446744073709551617").unwrap(),
+            18446744073709551617
+        );
+        assert_eq!(from_str::<i128>("1e3").unwrap(), 1000);
+
+        assert!(from_str::<u128>("340282366920938463463374607431768211456").is_err());
+        assert!(from_str::<u128>("-1").is_err());
+        assert!(from_str::<i128>("0.5").is_err());
+        assert!(from_str::<u64>("18446744073709551616").is_err());
+
+        // IDs in structs round-trip through the serializer
+        #[derive(Deserialize, serde::Serialize, PartialEq, Debug)]
+        struct Ids {
+            big: u128,
+            small: i128,
+        }
+        let ids = Ids {
+            big: u128::MAX - 1,
+            small: i128::MIN + 1,
+        };
+        assert_eq!(
+            from_str::<Ids>(&crate::to_string(&ids).unwrap()).unwrap(),
+            ids
+        );
+
+        let value: serde_json::Value = from_str("18446744073709551616").unwrap();
+        assert_eq!(value, serde_json::json!(18446744073709551616.0));
+    }
+
+    #[test]
+    fn test_map_keys() {
+        let map: HashMap<u32, String> = from_str("\"1\": \"one\", \"20\": \"twenty\"").unwrap();
+        assert_eq!(
+            map,
+            HashMap::from([(1, "one".to_owned()), (20, "twenty".to_owned())])
+        );
+
+        #[derive(Deserialize, PartialEq, Eq, Hash, Debug)]
+        enum Color {
+            Red,
+        }
+        #[derive(Deserialize, PartialEq, Eq, Hash, Debug)]
+        struct Id(u16);
+
+        let j = "\
+ints: {\"-1\": 1, \"170141183460469231731687303715884105727\": 2}
+bools: {true: 1, false: 0}
+chars: {\"a\": 1, \"é\": 2}
+enums: {Red: 1}
+ids: {\"7\": 1}";
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Keys {
+            ints: HashMap<i128, u8>,
+            bools: HashMap<bool, u8>,
+            chars: HashMap<char, u8>,
+            enums: HashMap<Color, u8>,
+            ids: HashMap<Id, u8>,
+        }
+        let keys: Keys = from_str(j).unwrap();
+        assert_eq!(keys.ints, HashMap::from([(-1, 1), (i128::MAX, 2)]));
+        assert_eq!(keys.bools, HashMap::from([(true, 1), (false, 0)]));
+        assert_eq!(keys.chars, HashMap::from([('a', 1), ('é', 2)]));
+        assert_eq!(keys.enums, HashMap::from([(Color::Red, 1)]));
+        assert_eq!(keys.ids, HashMap::from([(Id(7), 1)]));
+
+        assert!(from_str::<HashMap<u8, u8>>("\"256\": 1").is_err());
+        assert!(from_str::<HashMap<u8, u8>>("one: 1").is_err());
+        assert!(from_str::<HashMap<char, u8>>("ab: 1").is_err());
+
+        let map = HashMap::from([(-3_i64, vec![true]), (5, vec![])]);
+        let string = crate::to_string(&map).unwrap();
+        assert_eq!(from_str::<HashMap<i64, Vec<bool>>>(&string).unwrap(), map);
+    }
+
+    #[test]
+    fn test_ignored_fields() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Test {
+            int: u32,
+        }
+
+        let j = "\
+unknown: {a: [1, \"two\", r#\"th\"ree\"#, b\"four\"], b: null}
+int: 1
+text: |multi
+      |line
+other: [[{}], -0.5]";
+        assert_eq!(Test { int: 1 }, from_str(j).unwrap());
+
+        let j = "{int: 1, unknown: {a: [1, 2}}";
+        assert!(from_str::<Test>(j).is_err());
+        let j = "{int: 1, unknown: nul}";
+        assert!(from_str::<Test>(j).is_err());
+    }
+
+    #[test]
+    fn test_limits() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Test {
+            name: String,
+            tags: Vec<String>,
+        }
+
+        let input = "name: \"a\", tags: [\"b\", \"c\"], unknown: [[[{d: 1}]]]";
+        let deserialize = |max_bytes, max_depth, max_items| {
+            let limits = Limits {
+                max_bytes,
+                max_depth,
+                max_items,
+            };
+            let mut deserializer = Deserializer::from_str(input).with_limits(limits);
+            deserialize_document::<Test, _>(&mut deserializer).map_err(|err| err.to_string())
+        };
+
+        let expected = Test {
+            name: "a".to_owned(),
+            tags: vec!["b".to_owned(), "c".to_owned()],
+        };
+        assert_eq!(deserialize(input.len(), 7, 5), Ok(expected));
+
+        let err = deserialize(20, 7, 5).unwrap_err();
+        assert!(
+            err.starts_with("input is longer than the limit of 20 bytes"),
+            "{err}"
+        );
+
+        // the unknown field is skipped, but it is still too deep
+        let err = deserialize(input.len(), 6, 5).unwrap_err();
+        assert!(err.contains("maximum depth"), "{err}");
+
+        let err = deserialize(input.len(), 7, 4).unwrap_err();
+        assert!(
+            err.starts_with("input has more than the limit of 4 array elements and object entries"),
+            "{err}"
+        );
+
+        // a long string is rejected before all of it is read
+        let limits = Limits {
+            max_bytes: 1 << 16,
+            ..Limits::default()
+        };
+        let mut deserializer =
+            Deserializer::from_reader(b"\"".chain(io::repeat(b'a'))).with_limits(limits);
+        let err = String::deserialize(&mut deserializer).unwrap_err();
+        assert!(err.to_string().contains("limit of 65536 bytes"), "{err}");
+
+        // typed values are limited too, not only values that are deserialized
+        // with deserialize_any
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Node {
+            c: Vec<Node>,
+        }
+        let nested = |levels: usize| {
+            format!(
+                "{}c: []{}",
+                "c: [{".repeat(levels - 1),
+                "}]".repeat(levels - 1)
+            )
+        };
+        let deserialize = |input: &str, max_depth| {
+            let limits = Limits {
+                max_depth,
+                ..Limits::default()
+            };
+            let mut deserializer = Deserializer::from_str(input).with_limits(limits);
+            deserialize_document::<Node, _>(&mut deserializer).map_err(|err| err.to_string())
+        };
+        assert!(deserialize(&nested(2), 5).is_ok());
+        let err = deserialize(&nested(7), 5).unwrap_err();
+        assert!(err.contains("maximum depth"), "{err}");
+        let err = deserialize(&nested(200_000), Limits::default().max_depth).unwrap_err();
+        assert!(err.contains("maximum depth"), "{err}");
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        enum List {
+            Nil,
+            Cons(Box<List>),
+        }
+        let input = format!("{}Nil{}", "{Cons: ".repeat(200_000), "}".repeat(200_000));
+        let mut deserializer = Deserializer::from_str(&input).with_limits(Limits::default());
+        let err = deserialize_document::<List, _>(&mut deserializer).unwrap_err();
+        assert!(err.to_string().contains("maximum depth"), "{err}");
+    }
+
+    #[test]
+    fn test_error_snippet() {
+        #[derive(Deserialize, PartialEq, Debug)]
+        struct Test {
+            int: u32,
+        }
+
+        let err = from_str::<Test>("// the int\nint: \"one\"").unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("at line 2, column 6\n  |\n2 | int: \"one\"\n  |      ^"),
+            "{err}"
+        );
+
+        let err = from_str::<Test>("int: 1,\nint: 2").unwrap_err();
+        assert!(
+            err.to_string().ends_with("\n  |\n2 | int: 2\n  |    ^"),
+            "{err}"
+        );
+
+        let err = from_slice::<Test>(b"int: \"one\"").unwrap_err();
+        assert!(!err.to_string().contains('^'));
+    }
+
+    #[test]
+    fn test_error_category() {
+        let error = |input: &str| {
+            let err = from_slice::<HashMap<String, Vec<String>>>(input.as_bytes()).unwrap_err();
+            (err.classify(), err.to_string())
+        };
+
+        assert_eq!(
+            error("a: [\"1\", \"2\""),
+            (
+                Category::Eof,
+                "unexpected end of input, expected ']'".to_owned()
+            )
+        );
+        assert_eq!(
+            error("a: [r#\"text"),
+            (Category::Eof, "found no end of raw string".to_owned())
+        );
+        assert_eq!(
+            error("a: [\"1\"] b"),
+            (Category::Syntax, "map missing sep".to_owned())
+        );
+        assert_eq!(
+            error("{a: [\"1\"]} x"),
+            (
+                Category::Syntax,
+                "Trailing garbage after document: 'x'".to_owned()
+            )
+        );
+        assert_eq!(error("a: [true]").0, Category::Data);
+        assert_eq!(error("a: [[[[1]]]]").0, Category::Data);
+
+        let err = from_str::<Vec<String>>("[\"1\",\n\"2\",\n3]").unwrap_err();
+        assert_eq!(err.classify(), Category::Data);
+        assert_eq!(err.position().map(|position| position.line), Some(3));
+        assert!(err.is_data() && !err.is_syntax() && !err.is_eof() && !err.is_io());
+        assert_eq!((err.line(), err.column()), (3, 1));
+    }
 }
@@ -7,6 +7,8 @@ use std::{
 
 use serde::{de, ser};
 
+use crate::{ErrorKind, Position, error::snippet};
+
 /// Alias for a `Result` with the error type `mason_rs::serde::error::Error`.
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -23,23 +25,63 @@ impl Debug for Error {
     }
 }
 
+/// The category of an [`Error`], see [`Error::classify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// Reading the input or writing the output failed.
+    Io,
+    /// The input was not valid MASON.
+    Syntax,
+    /// The input was valid MASON, but did not match the type it was deserialized
+    /// into, or a value could not be serialized.
+    Data,
+    /// The input ended before the value was complete.
+    Eof,
+}
+
 #[derive(Debug)]
 enum InnerError {
     // Created by data structures through the `ser::Error` and `de::Error` traits.
     Message(String),
     Io(io::Error),
-    Eof,
+    Parse(crate::Error),
+    /// The input ended, and what was expected instead, if known.
+    Eof(Option<String>),
     Fmt,
+    /// An error that occurred at a known position, with the line of the input
+    /// where it occurred.
+    Located {
+        error: Error,
+        position: Position,
+        snippet: String,
+    },
 }
 
 impl Error {
     #[inline]
     pub fn eof() -> Self {
         Self {
-            inner: Box::new(InnerError::Eof),
+            inner: Box::new(InnerError::Eof(None)),
         }
     }
 
+    /// Create an error for input that ended where `expected` should have been.
+    pub(crate) fn eof_expecting(expected: &str) -> Self {
+        Self {
+            inner: Box::new(InnerError::Eof(Some(expected.to_owned()))),
+        }
+    }
+
+    /// Create an error for input that is nested deeper than the depth limit.
+    pub(crate) fn depth_exceeded() -> Self {
+        crate::Error::new(ErrorKind::DepthExceeded, "reached maximum depth").into()
+    }
+
+    /// Create an error for input that is not valid MASON.
+    pub(crate) fn syntax(message: impl Into<String>) -> Self {
+        crate::Error::syntax(message).into()
+    }
+
     #[inline]
     pub fn fmt() -> Self {
         Self {
@@ -48,6 +90,103 @@ impl Error {
     }
 }
 
+impl Error {
+    /// Find out what kind of error this is, like whether the input was not valid
+    /// MASON or did not match the type it was deserialized into.
+    ///
+    /// ```
+    /// # use mason_rs::serde::error::Category;
+    /// #
+    /// let classify = |input| mason_rs::from_str::<Vec<String>>(input).unwrap_err().classify();
+    /// assert_eq!(classify("[\"a\", \"b\""), Category::Eof);
+    /// assert_eq!(classify("[\"a\", \"b\"}"), Category::Syntax);
+    /// assert_eq!(classify("[\"a\", 2]"), Category::Data);
+    /// ```
+    pub fn classify(&self) -> Category {
+        match self.inner.as_ref() {
+            InnerError::Message(_) => Category::Data,
+            InnerError::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof => Category::Eof,
+            InnerError::Io(_) | InnerError::Fmt => Category::Io,
+            InnerError::Parse(error) => match error.kind() {
+                ErrorKind::UnexpectedEof => Category::Eof,
+                ErrorKind::Io => Category::Io,
+                ErrorKind::InvalidType => Category::Data,
+                _ => Category::Syntax,
+            },
+            InnerError::Eof(_) => Category::Eof,
+            InnerError::Located { error, .. } => error.classify(),
+        }
+    }
+
+    /// Where in the input the error occurred, if it is known.
+    pub fn position(&self) -> Option<Position> {
+        match self.inner.as_ref() {
+            InnerError::Parse(error) => error.position(),
+            InnerError::Located { position, .. } => Some(*position),
+            _ => None,
+        }
+    }
+
+    /// The line where the error occurred, starting at 1, or 0 if it is not known.
+    pub fn line(&self) -> usize {
+        self.position().map_or(0, |position| position.line)
+    }
+
+    /// The column where the error occurred, counted in characters and starting at
+    /// 1, or 0 if it is not known.
+    pub fn column(&self) -> usize {
+        self.position().map_or(0, |position| position.column)
+    }
+
+    /// Check if reading the input or writing the output failed, see [`Category::Io`].
+    pub fn is_io(&self) -> bool {
+        self.classify() == Category::Io
+    }
+
+    /// Check if the input was not valid MASON, see [`Category::Syntax`].
+    pub fn is_syntax(&self) -> bool {
+        self.classify() == Category::Syntax
+    }
+
+    /// Check if the input did not match the type it was deserialized into, see
+    /// [`Category::Data`].
+    pub fn is_data(&self) -> bool {
+        self.classify() == Category::Data
+    }
+
+    /// Check if the input ended too early, see [`Category::Eof`]. When reading
+    /// from a stream, this can mean that the rest of the value has not arrived yet.
+    ///
+    /// ```
+    /// let err = mason_rs::from_str::<Vec<u32>>("[1,\n2").unwrap_err();
+    /// assert!(err.is_eof());
+    /// assert_eq!((err.line(), err.column()), (2, 2));
+    ///
+    /// let err = mason_rs::from_slice::<Vec<u32>>(b"[1, 2").unwrap_err();
+    /// assert_eq!((err.line(), err.column()), (0, 0));
+    /// ```
+    pub fn is_eof(&self) -> bool {
+        self.classify() == Category::Eof
+    }
+
+    /// Include the position of the error and the line of `source` where it
+    /// occurred when the error is displayed. If the error was created by the
+    /// parser, its own position is used instead of `position`.
+    pub(crate) fn with_source(self, source: &[u8], position: Position) -> Self {
+        let position = match self.inner.as_ref() {
+            InnerError::Parse(error) => error.position().unwrap_or(position),
+            _ => position,
+        };
+        Self {
+            inner: Box::new(InnerError::Located {
+                snippet: snippet(source, position),
+                error: self,
+                position,
+            }),
+        }
+    }
+}
+
 impl From<fmt::Error> for Error {
     fn from(_value: fmt::Error) -> Self {
         Self {
@@ -58,14 +197,16 @@ impl From<fmt::Error> for Error {
 
 impl From<io::Error> for Error {
     fn from(value: io::Error) -> Self {
-        if matches!(value.kind(), io::ErrorKind::UnexpectedEof) {
-            Self {
-                inner: Box::new(InnerError::Eof),
-            }
-        } else {
-            Self {
-                inner: Box::new(InnerError::Io(value)),
-            }
+        Self {
+            inner: Box::new(InnerError::Io(value)),
+        }
+    }
+}
+
+impl From<crate::Error> for Error {
+    fn from(value: crate::Error) -> Self {
+        Self {
+            inner: Box::new(InnerError::Parse(value)),
         }
     }
 }
@@ -91,8 +232,25 @@ impl Display for Error {
         match self.inner.as_ref() {
             InnerError::Message(msg) => formatter.write_str(msg),
             InnerError::Io(error) => write!(formatter, "{error}"),
-            InnerError::Eof => formatter.write_str("unexpected end of input"),
+            InnerError::Parse(error) => write!(formatter, "{error}"),
+            InnerError::Eof(None) => formatter.write_str("unexpected end of input"),
+            InnerError::Eof(Some(expected)) => {
+                write!(formatter, "unexpected end of input, expected {expected}")
+            }
             InnerError::Fmt => formatter.write_str("failed to write to writer"),
+            InnerError::Located {
+                error,
+                position,
+                snippet,
+            } => {
+                // parse errors already include their position
+                match error.inner.as_ref() {
+                    InnerError::Parse(parse_error) if parse_error.position().is_some() => {
+                        write!(formatter, "{error}\n{snippet}")
+                    }
+                    _ => write!(formatter, "{error} at {position}\n{snippet}"),
+                }
+            }
         }
     }
 }
@@ -2,4 +2,7 @@
 
 pub mod de;
 pub mod error;
+mod raw;
 pub mod ser;
+
+pub use raw::RawValue;
@@ -0,0 +1,188 @@
+//! Capture MASON text without parsing it.
+
+use std::fmt::{self, Display};
+
+use serde::{
+    Deserialize, Serialize,
+    de::{self, Visitor},
+};
+
+use crate::{SerializeOptions, Value, deserialize, peek_reader::PeekReader};
+
+use super::error::{Error, Result};
+
+/// The name [`RawValue`] uses to tell the MASON serializer and deserializer that
+/// it wants raw text.
+pub(crate) const TOKEN: &str = "$mason_rs::private::RawValue";
+
+/// The raw MASON text of a single value, which is captured without being parsed.
+///
+/// Using a `RawValue` as a struct field is useful for passing data through without
+/// caring about its contents, or for delaying parsing until the type of the data is
+/// known. When deserialized with [`from_str`](super::de::from_str) and friends, the
+/// value is only checked for validity, and its text is kept as is, including comments.
+/// When serialized, the text is written as is.
+///
+/// Other formats do not know about `RawValue`, so when deserializing one from them,
+/// the value is parsed into a [`Value`] which is then written as MASON, and when
+/// serializing one, it is serialized as a string.
+///
+/// ```
+/// # use mason_rs::serde::RawValue;
+/// # use serde::{Deserialize, Serialize};
+/// #
+/// #[derive(Deserialize, Serialize)]
+/// struct Event {
+///     kind: String,
+///     payload: RawValue,
+/// }
+///
+/// let event: Event = mason_rs::from_str("kind: \"click\", payload: {x: 0x10, y: 8 /* px */}").unwrap();
+/// assert_eq!(event.payload.get(), "{x: 0x10, y: 8 /* px */}");
+///
+/// let output = mason_rs::to_string(&event).unwrap();
+/// assert!(output.contains("payload: {x: 0x10, y: 8 /* px */}"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RawValue {
+    mason: String,
+}
+
+impl RawValue {
+    /// Create a `RawValue` from MASON text, which must contain a single value,
+    /// optionally surrounded by whitespace and comments. The surrounding whitespace
+    /// and comments are removed.
+    ///
+    /// # Errors
+    ///
+    /// This function fails if the text is not a single valid MASON value.
+    pub fn from_string(mason: String) -> Result<Self> {
+        let mut reader = PeekReader::from_buf_read(mason.as_bytes());
+        deserialize::skip_whitespace(&mut reader)?;
+        let start = reader.position().offset;
+        deserialize::skip_value(&mut reader, 100, false)?;
+        let end = reader.position().offset;
+        deserialize::skip_whitespace(&mut reader)?;
+        if reader.peek()?.is_some() {
+            return Err(Error::syntax("trailing characters after raw value"));
+        }
+
+        if start == 0 && end == mason.len() {
+            Ok(Self { mason })
+        } else {
+            Ok(Self {
+                mason: mason[start..end].to_owned(),
+            })
+        }
+    }
+
+    /// Get the MASON text of the value.
+    pub fn get(&self) -> &str {
+        &self.mason
+    }
+
+    /// Convert the `RawValue` into its MASON text.
+    pub fn into_string(self) -> String {
+        self.mason
+    }
+}
+
+impl Display for RawValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.mason)
+    }
+}
+
+impl Serialize for RawValue {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_newtype_struct(TOKEN, self.mason.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for RawValue {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct RawValueVisitor;
+
+        impl<'de> Visitor<'de> for RawValueVisitor {
+            type Value = RawValue;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("any valid MASON value")
+            }
+
+            // the MASON deserializer gives us the raw text directly
+            fn visit_str<E>(self, mason: &str) -> std::result::Result<RawValue, E> {
+                Ok(RawValue {
+                    mason: mason.to_owned(),
+                })
+            }
+
+            fn visit_string<E>(self, mason: String) -> std::result::Result<RawValue, E> {
+                Ok(RawValue { mason })
+            }
+
+            // other deserializers do not know about raw values, so we
+            // have to write the value as MASON ourselves
+            fn visit_newtype_struct<D>(
+                self,
+                deserializer: D,
+            ) -> std::result::Result<RawValue, D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                let value = Value::deserialize(deserializer)?;
+                let options = SerializeOptions::new().compact(true).braced_root(true);
+                let mut mason = String::new();
+                value
+                    .to_writer_with_options(&mut mason, &options)
+                    .map_err(de::Error::custom)?;
+                Ok(RawValue { mason })
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(TOKEN, RawValueVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_raw_value() {
+        let data = "values: [\"a\\\"]\", r#\"]\"#, |multi\n |line\n {x: [1]}]\ncount: 3";
+        let raw: HashMap<String, RawValue> = crate::from_str(data).unwrap();
+        assert_eq!(
+            raw["values"].get(),
+            "[\"a\\\"]\", r#\"]\"#, |multi\n |line\n {x: [1]}]"
+        );
+        assert_eq!(raw["count"].get(), "3");
+
+        let values: Vec<Value> = crate::from_str(raw["values"].get()).unwrap();
+        assert_eq!(values.len(), 4);
+
+        let raw: Vec<RawValue> = crate::from_str("[0xff, // hex\n{a: 1}]").unwrap();
+        assert_eq!(raw[0].get(), "0xff");
+        assert_eq!(crate::to_string(&raw).unwrap(), "[0xff, {a: 1}]");
+
+        assert!(crate::from_str::<Vec<RawValue>>("[{a: 1]").is_err());
+
+        // other formats go through `Value`
+        let raw: RawValue = serde_json::from_str("{\"a\": [true]}").unwrap();
+        assert_eq!(raw.get(), "{a: [true]}");
+
+        let raw = RawValue::from_string(" // comment\n [1, 2] ".to_owned()).unwrap();
+        assert_eq!(raw.get(), "[1, 2]");
+        assert_eq!(raw.to_string(), "[1, 2]");
+        assert!(RawValue::from_string("1 2".to_owned()).is_err());
+        assert!(RawValue::from_string("a: 1".to_owned()).is_err());
+    }
+}
@@ -1,7 +1,10 @@
 //! Serialize a Rust data structure into MASON data.
 
 use core::fmt;
-use std::fmt::{Display, Write};
+use std::{
+    fmt::{Display, Write},
+    io, mem,
+};
 
 use pastey::paste;
 use serde::{
@@ -9,20 +12,136 @@ use serde::{
     ser::{self, Error as _, Impossible},
 };
 
-use crate::serialize;
+use crate::{SerializeOptions, deserialize, peek_reader::PeekReader, serialize};
 
-use super::error::{Error, Result};
+use super::{
+    error::{Error, Result},
+    raw,
+};
 
 /// A structure for serializing Rust values into MASON.
 pub struct Serializer<W: Write> {
     writer: W,
-    depth: usize,
+    options: SerializeOptions,
+    /// The indentation level of the line we are writing.
+    level: usize,
+    /// Whether the next value is the top level value, whose braces are left out
+    /// if it is an object.
+    top_level: bool,
+    human_readable: bool,
+    /// Whether the next string is the text of a `RawValue`, which is written as is.
+    raw_value: bool,
 }
 
 impl<W: Write> Serializer<W> {
     /// Creates a new MASON serializer.
     pub fn new(writer: W) -> Self {
-        Self { writer, depth: 0 }
+        Self::with_options(writer, SerializeOptions::default())
+    }
+
+    /// Creates a new MASON serializer, which formats its output as described by
+    /// `options`.
+    ///
+    /// ```
+    /// # use mason_rs::{SerializeOptions, Serializer};
+    /// # use serde::Serialize;
+    /// # use std::collections::BTreeMap;
+    /// #
+    /// let map = BTreeMap::from([("a", vec![1, 2]), ("b", vec![])]);
+    ///
+    /// let mut string = String::new();
+    /// let options = SerializeOptions::new().indentation("\t").inline_arrays(false);
+    /// map.serialize(&mut Serializer::with_options(&mut string, options)).unwrap();
+    /// assert_eq!(string, "a: [\n\t1\n\t2\n]\nb: []");
+    /// ```
+    pub fn with_options(writer: W, options: SerializeOptions) -> Self {
+        Self {
+            writer,
+            top_level: !options.braced_root,
+            options,
+            level: 0,
+            human_readable: true,
+            raw_value: false,
+        }
+    }
+
+    /// Set whether types should use their human-readable representation, which is
+    /// what [`serde::Serializer::is_human_readable`] returns. Types like UUIDs and
+    /// IP addresses serialize as strings when this is `true` (the default), and in
+    /// a compact form, often as a byte string, when it is `false`.
+    ///
+    /// A value that was serialized with this set to `false` must be deserialized
+    /// with [`Deserializer::human_readable`](super::Deserializer::human_readable)
+    /// set to `false`.
+    ///
+    /// ```
+    /// # use mason_rs::Serializer;
+    /// # use serde::Serialize;
+    /// # use std::net::Ipv4Addr;
+    /// #
+    /// let mut string = String::new();
+    /// let mut serializer = Serializer::new(&mut string).human_readable(false);
+    /// Ipv4Addr::LOCALHOST.serialize(&mut serializer).unwrap();
+    /// assert_eq!(string, "[127, 0, 0, 1]");
+    /// ```
+    pub fn human_readable(mut self, human_readable: bool) -> Self {
+        self.human_readable = human_readable;
+        self
+    }
+
+    /// Unwrap the serializer, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// An adapter that lets a [`Serializer`] write into an [`io::Write`], such as a file
+/// or a socket, without buffering the output. The serializer only writes `str`s, so
+/// the bytes written are always valid UTF-8.
+///
+/// [`to_io_writer`] takes care of creating the adapter. Since [`fmt::Write`] can not
+/// report why writing failed, the I/O error is stored in the adapter, and can be
+/// retrieved with [`IoWriter::take_error`].
+///
+/// ```
+/// # use mason_rs::serde::ser::{IoWriter, Serializer};
+/// # use serde::Serialize;
+/// #
+/// let mut serializer = Serializer::new(IoWriter::new(Vec::new()));
+/// [1, 2, 3].serialize(&mut serializer).unwrap();
+/// assert_eq!(serializer.into_inner().into_inner(), b"[1, 2, 3]");
+/// ```
+pub struct IoWriter<W: io::Write> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> IoWriter<W> {
+    /// Wrap `writer` in the adapter.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            error: None,
+        }
+    }
+
+    /// Take the error that made the last write fail, if any.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// Unwrap the adapter, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: io::Write> Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.writer.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
     }
 }
 
@@ -40,6 +159,36 @@ pub fn to_writer<T: Serialize, W: Write>(value: &T, writer: &mut W) -> Result<()
     Ok(())
 }
 
+/// Serialize the given data structure as MASON into the I/O stream, without
+/// buffering the output. The output is the same as that of [`to_writer`].
+///
+/// ```
+/// # use serde::Serialize;
+/// #
+/// #[derive(Serialize)]
+/// struct Config {
+///     port: u16,
+/// }
+///
+/// let mut bytes = Vec::new();
+/// mason_rs::to_io_writer(&Config { port: 8080 }, &mut bytes).unwrap();
+/// assert_eq!(bytes, b"port: 8080");
+/// ```
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, if `T` contains a map with non-string keys, or if writing to the
+/// I/O stream fails.
+pub fn to_io_writer<T: Serialize, W: io::Write>(value: &T, writer: &mut W) -> Result<()> {
+    let mut serializer = Serializer::new(IoWriter::new(writer));
+    let result = value.serialize(&mut serializer);
+    match serializer.into_inner().take_error() {
+        Some(err) => Err(err.into()),
+        None => result,
+    }
+}
+
 /// Serialize the given data structure as a String of MASON.
 ///
 /// # Errors
@@ -52,19 +201,111 @@ pub fn to_string<T: Serialize>(value: &T) -> Result<String> {
     Ok(string)
 }
 
+/// Serialize the given data structure as a String of MASON, formatted as
+/// described by `options`.
+///
+/// ```
+/// # use mason_rs::SerializeOptions;
+/// # use serde::Serialize;
+/// #
+/// #[derive(Serialize)]
+/// struct Server {
+///     host: &'static str,
+///     ports: Vec<u16>,
+/// }
+///
+/// let server = Server { host: "localhost", ports: vec![80, 443] };
+/// let options = SerializeOptions::new().compact(true);
+/// assert_eq!(
+///     mason_rs::to_string_with_options(&server, options).unwrap(),
+///     r#"host: "localhost", ports: [80, 443]"#
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with non-string keys.
+pub fn to_string_with_options<T: Serialize>(
+    value: &T,
+    options: SerializeOptions,
+) -> Result<String> {
+    let mut string = String::new();
+    value.serialize(&mut Serializer::with_options(&mut string, options))?;
+    Ok(string)
+}
+
 impl<W: Write> Serializer<W> {
-    fn as_compound(&mut self) -> Compound<'_, W> {
-        Compound {
+    fn write_indentation(&mut self) -> fmt::Result {
+        for _ in 0..self.level {
+            self.writer.write_str(&self.options.indentation)?;
+        }
+        Ok(())
+    }
+
+    /// Start a line at the current indentation level, unless the output is compact.
+    fn write_newline(&mut self) -> fmt::Result {
+        if self.options.compact {
+            return Ok(());
+        }
+        self.writer.write_str(self.options.line_ending.as_str())?;
+        self.write_indentation()
+    }
+
+    /// Start writing an object. The braces of the top level object are left out.
+    fn begin_object(&mut self) -> Result<Compound<'_, W>> {
+        let braced = !mem::take(&mut self.top_level);
+        if braced {
+            write!(self.writer, "{{")?;
+            self.level += 1;
+        }
+        Ok(Compound {
+            serializer: self,
+            first_item: true,
+            kind: CompoundKind::Object { braced },
+            variant: None,
+            sorted: None,
+        })
+    }
+
+    /// Start writing an array.
+    fn begin_array(&mut self) -> Result<Compound<'_, W>> {
+        self.top_level = false;
+        write!(self.writer, "[")?;
+        let multi_line = !(self.options.compact || self.options.inline_arrays);
+        if multi_line {
+            self.level += 1;
+        }
+        Ok(Compound {
             serializer: self,
             first_item: true,
+            kind: CompoundKind::Array { multi_line },
+            variant: None,
+            sorted: None,
+        })
+    }
+
+    /// Enum variants with data are represented as objects with a single key, the
+    /// name of the variant. Returns whether the object is braced.
+    fn begin_variant(&mut self, variant: &str) -> Result<bool> {
+        let braced = !mem::take(&mut self.top_level);
+        if braced {
+            write!(self.writer, "{{")?;
+            self.level += 1;
+            self.write_newline()?;
         }
+        serialize::serialize_key(&mut self.writer, variant, &self.options)?;
+        write!(self.writer, ": ")?;
+        Ok(braced)
     }
 
-    fn write_whitespace(&mut self, depth: usize) -> fmt::Result {
-        if depth == 0 {
-            return Ok(());
+    fn end_variant(&mut self, braced: bool) -> Result<()> {
+        if braced {
+            self.level -= 1;
+            self.write_newline()?;
+            write!(self.writer, "}}")?;
         }
-        write!(self.writer, "{}", "    ".repeat(depth))
+        Ok(())
     }
 }
 
@@ -106,23 +347,52 @@ impl<'s, W: Write> ser::Serializer for &'s mut Serializer<W> {
     write_displayed!(u32);
     // This has the same issue as serializing i64.
     write_displayed!(u64);
-    write_displayed!(f32);
-    write_displayed!(f64);
+    // These have the same issue, but are written in full so they round-trip
+    // through our own deserializer, which reads integers exactly.
+    write_displayed!(i128);
+    write_displayed!(u128);
+
+    // f32 is not converted to f64, as that would change how it is displayed.
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        if v.is_finite() {
+            Ok(write!(self.writer, "{v}")?)
+        } else {
+            self.serialize_none()
+        }
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        Ok(serialize::serialize_number(&mut self.writer, v)?)
+    }
 
     fn serialize_char(self, v: char) -> Result<()> {
         // just serialize the char as a string
         Ok(serialize::serialize_string(
             &mut self.writer,
             v.encode_utf8(&mut [0; 4]),
+            &self.options,
         )?)
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
-        Ok(serialize::serialize_string(&mut self.writer, v)?)
+        if mem::take(&mut self.raw_value) {
+            self.top_level = false;
+            return Ok(self.writer.write_str(v)?);
+        }
+        Ok(serialize::serialize_string(
+            &mut self.writer,
+            v,
+            &self.options,
+        )?)
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<()> {
-        Ok(serialize::serialize_bytes(&mut self.writer, v)?)
+        Ok(serialize::serialize_bytes_indented(
+            &mut self.writer,
+            v,
+            &self.options,
+            self.level,
+        )?)
     }
 
     // An absent optional is represented as the MASON `null`.
@@ -150,19 +420,26 @@ impl<'s, W: Write> ser::Serializer for &'s mut Serializer<W> {
         self.serialize_none()
     }
 
+    // Unit variants are represented in MASON as the name of the variant, as a
+    // string.
     fn serialize_unit_variant(
         self,
         _name: &'static str,
         _variant_index: u32,
         variant: &'static str,
     ) -> Result<()> {
-        Ok(serialize::serialize_key(&mut self.writer, variant)?)
+        Ok(serialize::serialize_string(
+            &mut self.writer,
+            variant,
+            &self.options,
+        )?)
     }
 
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
+        self.raw_value = name == raw::TOKEN;
         value.serialize(self)
     }
 
@@ -176,21 +453,13 @@ impl<'s, W: Write> ser::Serializer for &'s mut Serializer<W> {
     where
         T: ?Sized + Serialize,
     {
-        if self.depth != 0 {
-            writeln!(self.writer, "{{\n")?;
-        }
-        serialize::serialize_key(&mut self.writer, variant)?;
-        write!(self.writer, ": ")?;
+        let braced = self.begin_variant(variant)?;
         value.serialize(&mut *self)?;
-        if self.depth != 0 {
-            writeln!(self.writer, "\n}}")?;
-        }
-        Ok(())
+        self.end_variant(braced)
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        write!(self.writer, "[")?;
-        Ok(self.as_compound())
+        self.begin_array()
     }
 
     // Tuples look just like sequences in MASON.
@@ -215,28 +484,29 @@ impl<'s, W: Write> ser::Serializer for &'s mut Serializer<W> {
         variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        if self.depth != 0 {
-            writeln!(self.writer, "{{")?;
-        }
-        serialize::serialize_key(&mut self.writer, variant)?;
-        write!(self.writer, ": [")?;
-        Ok(self.as_compound())
+        let braced = self.begin_variant(variant)?;
+        let mut compound = self.begin_array()?;
+        compound.variant = Some(braced);
+        Ok(compound)
     }
 
     // Maps are represented in MASON as `{ K: V, K: V, ... }`.
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        if self.depth != 0 {
-            writeln!(self.writer, "{{")?;
+        let sort_keys = self.options.sort_keys;
+        let mut compound = self.begin_object()?;
+        if sort_keys {
+            compound.sorted = Some(SortedEntries::new(compound.serializer));
         }
-        Ok(self.as_compound())
+        Ok(compound)
     }
 
-    // Structs look just like maps in MASON.
-    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
-        self.serialize_map(Some(len))
+    // Structs look just like maps in MASON, but their fields are written in the
+    // order they are declared, even if keys are sorted.
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        self.begin_object()
     }
 
-    // Struct variants are represented in MASON as `NAME: { K: V, ... }`.
+    // Struct variants are represented in MASON as `{ NAME: { K: V, ... } }`.
     fn serialize_struct_variant(
         self,
         _name: &'static str,
@@ -244,13 +514,14 @@ impl<'s, W: Write> ser::Serializer for &'s mut Serializer<W> {
         variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        if self.depth != 0 {
-            write!(self.writer, "{{")?;
-        };
-        serialize::serialize_key(&mut self.writer, variant)?;
-        writeln!(self.writer, ": {{")?;
-        self.depth += 1;
-        Ok(self.as_compound())
+        let braced = self.begin_variant(variant)?;
+        let mut compound = self.begin_object()?;
+        compound.variant = Some(braced);
+        Ok(compound)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.human_readable
     }
 }
 
@@ -259,15 +530,120 @@ impl<'s, W: Write> ser::Serializer for &'s mut Serializer<W> {
 pub struct Compound<'s, W: Write> {
     serializer: &'s mut Serializer<W>,
     first_item: bool,
+    kind: CompoundKind,
+    /// If this is the data of an enum variant, whether the variant is braced.
+    variant: Option<bool>,
+    /// The entries of a map whose keys are sorted, which are written when the map
+    /// ends.
+    sorted: Option<SortedEntries>,
+}
+
+struct SortedEntries {
+    /// Serializes the entries into strings, at the indentation level of the map.
+    serializer: Serializer<String>,
+    /// The key of each entry, and the text of the entry.
+    entries: Vec<(String, String)>,
+}
+
+impl SortedEntries {
+    fn new<W: Write>(serializer: &Serializer<W>) -> Self {
+        Self {
+            serializer: Serializer {
+                writer: String::new(),
+                options: serializer.options.clone(),
+                level: serializer.level,
+                top_level: false,
+                human_readable: serializer.human_readable,
+                raw_value: false,
+            },
+            entries: Vec::new(),
+        }
+    }
+
+    /// Write the entries sorted by key into `compound`.
+    fn write<W: Write>(mut self, compound: &mut Compound<'_, W>) -> fmt::Result {
+        self.entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (_, entry) in self.entries {
+            compound.begin_item()?;
+            compound.serializer.writer.write_str(&entry)?;
+        }
+        Ok(())
+    }
+}
+
+enum CompoundKind {
+    Object { braced: bool },
+    Array { multi_line: bool },
 }
 
 impl<W: Write> Compound<'_, W> {
-    fn write_unless_first_item(&mut self, string: &'static str) -> fmt::Result {
-        if !self.first_item {
-            write!(self.serializer.writer, "{}", string)
-        } else {
-            self.first_item = false;
-            Ok(())
+    /// Write what comes before the next key-value pair or array element.
+    fn begin_item(&mut self) -> fmt::Result {
+        let first_item = mem::replace(&mut self.first_item, false);
+        if !first_item && self.is_multi_line() {
+            let separator = self.serializer.options.separator(false);
+            write!(self.serializer.writer, "{separator}")?;
+        }
+        let serializer = &mut *self.serializer;
+        match self.kind {
+            CompoundKind::Object { braced } if !serializer.options.compact => {
+                if braced || !first_item {
+                    let newline = serializer.options.line_ending.as_str();
+                    serializer.writer.write_str(newline)?;
+                }
+                serializer.write_indentation()
+            }
+            CompoundKind::Array { multi_line: true } => serializer.write_newline(),
+            _ if first_item => Ok(()),
+            _ => write!(serializer.writer, ", "),
+        }
+    }
+
+    /// Whether the items are written on separate lines.
+    fn is_multi_line(&self) -> bool {
+        match self.kind {
+            CompoundKind::Object { .. } => !self.serializer.options.compact,
+            CompoundKind::Array { multi_line } => multi_line,
+        }
+    }
+
+    fn end(mut self) -> Result<()> {
+        if let Some(sorted) = self.sorted.take() {
+            sorted.write(&mut self)?;
+        }
+        let braced = !matches!(self.kind, CompoundKind::Object { braced: false });
+        if !self.first_item && braced && self.is_multi_line() {
+            let separator = self.serializer.options.separator(true);
+            write!(self.serializer.writer, "{separator}")?;
+        }
+        let serializer = self.serializer;
+        match self.kind {
+            // An empty document is not valid, so the top level object needs
+            // braces if it is empty
+            CompoundKind::Object { braced: false } if self.first_item => {
+                write!(serializer.writer, "{{}}")?;
+            }
+            CompoundKind::Object { braced: false } => {}
+            CompoundKind::Object { braced: true } => {
+                serializer.level -= 1;
+                if !self.first_item {
+                    serializer.write_newline()?;
+                }
+                write!(serializer.writer, "}}")?;
+            }
+            CompoundKind::Array { multi_line } => {
+                if multi_line {
+                    serializer.level -= 1;
+                    if !self.first_item {
+                        serializer.write_newline()?;
+                    }
+                }
+                write!(serializer.writer, "]")?;
+            }
+        }
+        match self.variant {
+            Some(braced) => serializer.end_variant(braced),
+            None => Ok(()),
         }
     }
 }
@@ -280,13 +656,12 @@ impl<W: Write> ser::SerializeSeq for Compound<'_, W> {
     where
         T: ?Sized + Serialize,
     {
-        self.write_unless_first_item(", ")?;
+        self.begin_item()?;
         value.serialize(&mut *self.serializer)
     }
 
     fn end(self) -> Result<()> {
-        write!(self.serializer.writer, "]")?;
-        Ok(())
+        Compound::end(self)
     }
 }
 
@@ -302,7 +677,7 @@ impl<W: Write> ser::SerializeTuple for Compound<'_, W> {
     }
 
     fn end(self) -> Result<()> {
-        <Self as ser::SerializeSeq>::end(self)
+        Compound::end(self)
     }
 }
 
@@ -318,7 +693,7 @@ impl<W: Write> ser::SerializeTupleStruct for Compound<'_, W> {
     }
 
     fn end(self) -> Result<()> {
-        <Self as ser::SerializeSeq>::end(self)
+        Compound::end(self)
     }
 }
 
@@ -334,13 +709,7 @@ impl<W: Write> ser::SerializeTupleVariant for Compound<'_, W> {
     }
 
     fn end(self) -> Result<()> {
-        if self.serializer.depth > 0 {
-            // Here we must close the object in addition to the array
-            write!(self.serializer.writer, "]\n}}")?;
-        } else {
-            write!(self.serializer.writer, "]")?;
-        }
-        Ok(())
+        Compound::end(self)
     }
 }
 
@@ -354,8 +723,19 @@ impl<W: Write> ser::SerializeMap for Compound<'_, W> {
     where
         T: ?Sized + Serialize,
     {
-        self.write_unless_first_item("\n")?;
-        self.serializer.write_whitespace(self.serializer.depth)?;
+        if let Some(sorted) = &mut self.sorted {
+            key.serialize(KeySerializer {
+                ser: &mut sorted.serializer,
+            })?;
+            let text = mem::take(&mut sorted.serializer.writer);
+            // sort by the key itself, not by how it is written
+            let key = deserialize::parse_identifier(&mut PeekReader::new(text.as_bytes()))
+                .unwrap_or_else(|_| text.clone());
+            sorted.entries.push((key, text));
+            return Ok(());
+        }
+
+        self.begin_item()?;
         key.serialize(KeySerializer {
             ser: self.serializer,
         })
@@ -365,18 +745,21 @@ impl<W: Write> ser::SerializeMap for Compound<'_, W> {
     where
         T: ?Sized + Serialize,
     {
+        if let Some(sorted) = &mut self.sorted {
+            value.serialize(&mut sorted.serializer)?;
+            let text = mem::take(&mut sorted.serializer.writer);
+            if let Some((_, entry)) = sorted.entries.last_mut() {
+                write!(entry, ": {text}")?;
+            }
+            return Ok(());
+        }
+
         write!(self.serializer.writer, ": ")?;
-        self.serializer.depth += 1;
-        value.serialize(&mut *self.serializer)?;
-        self.serializer.depth -= 1;
-        Ok(())
+        value.serialize(&mut *self.serializer)
     }
 
     fn end(self) -> Result<()> {
-        if self.serializer.depth > 0 {
-            write!(self.serializer.writer, "\n}}")?;
-        }
-        Ok(())
+        Compound::end(self)
     }
 }
 
@@ -393,7 +776,7 @@ impl<W: Write> ser::SerializeStruct for Compound<'_, W> {
     }
 
     fn end(self) -> Result<()> {
-        <Self as ser::SerializeMap>::end(self)
+        Compound::end(self)
     }
 }
 
@@ -410,14 +793,7 @@ impl<W: Write> ser::SerializeStructVariant for Compound<'_, W> {
     }
 
     fn end(self) -> Result<()> {
-        if self.serializer.depth > 1 {
-            // here we must close both the inner and outer object
-            write!(self.serializer.writer, "\n}}\n}}")?;
-        } else {
-            write!(self.serializer.writer, "\n}}")?;
-        }
-        self.serializer.depth -= 1;
-        Ok(())
+        Compound::end(self)
     }
 }
 
@@ -440,7 +816,11 @@ impl<W: Write> ser::Serializer for KeySerializer<'_, W> {
 
     #[inline]
     fn serialize_str(self, value: &str) -> Result<()> {
-        Ok(serialize::serialize_key(&mut self.ser.writer, value)?)
+        Ok(serialize::serialize_key(
+            &mut self.ser.writer,
+            value,
+            &self.ser.options,
+        )?)
     }
 
     #[inline]
@@ -450,7 +830,11 @@ impl<W: Write> ser::Serializer for KeySerializer<'_, W> {
         _variant_index: u32,
         variant: &'static str,
     ) -> Result<()> {
-        Ok(serialize::serialize_key(&mut self.ser.writer, variant)?)
+        Ok(serialize::serialize_key(
+            &mut self.ser.writer,
+            variant,
+            &self.ser.options,
+        )?)
     }
 
     #[inline]
@@ -617,9 +1001,13 @@ impl<W: Write> ser::Serializer for KeySerializer<'_, W> {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::{
+        collections::{BTreeMap, HashMap},
+        str::FromStr,
+    };
 
     use super::*;
+    use crate::{LineEnding, SeparatorStyle};
 
     #[test]
     fn test_struct() {
@@ -650,7 +1038,7 @@ seq: [\"a\", \"b\"]";
         }
 
         let u = E::Unit;
-        let expected = r#"Unit"#;
+        let expected = r#""Unit""#;
         assert_eq!(to_string(&u).unwrap(), expected);
 
         let n = E::Newtype(1);
@@ -718,4 +1106,482 @@ nothing: null"
             )
         }
     }
+
+    #[test]
+    fn test_sort_keys() {
+        #[derive(Serialize)]
+        struct Test {
+            z: HashMap<&'static str, HashMap<u32, bool>>,
+            a: Vec<HashMap<&'static str, u32>>,
+        }
+
+        let test = Test {
+            z: HashMap::from([
+                ("b", HashMap::from([(10, true), (2, false), (1, true)])),
+                ("a b", HashMap::new()),
+                ("a", HashMap::from([(0, false)])),
+            ]),
+            a: vec![HashMap::from([("y", 1), ("x", 2)])],
+        };
+
+        let options = SerializeOptions::new().sort_keys(true);
+        let expected = "\
+z: {
+    a: {
+        \"0\": false
+    }
+    \"a b\": {}
+    b: {
+        \"1\": true
+        \"10\": true
+        \"2\": false
+    }
+}
+a: [{
+    x: 2
+    y: 1
+}]";
+        assert_eq!(
+            to_string_with_options(&test, options.clone()).unwrap(),
+            expected
+        );
+
+        let expected = r#"{z: {a: {"0": false}, "a b": {}, b: {"1": true, "10": true, "2": false}}, a: [{x: 2, y: 1}]}"#;
+        assert_eq!(
+            to_string_with_options(&test, options.clone().compact(true).braced_root(true)).unwrap(),
+            expected
+        );
+
+        let map = HashMap::from([("b", 1), ("c", 2), ("a", 3)]);
+        assert_eq!(
+            to_string_with_options(&map, options).unwrap(),
+            "a: 3\nb: 1\nc: 2"
+        );
+    }
+
+    /// Check that `value` serializes to valid MASON, which deserializes back to
+    /// `value`.
+    fn assert_round_trip<T>(value: T)
+    where
+        T: Serialize + serde::de::DeserializeOwned + PartialEq + fmt::Debug,
+    {
+        for options in [
+            SerializeOptions::new(),
+            SerializeOptions::new().compact(true),
+        ] {
+            let string = to_string_with_options(&value, options).unwrap();
+            if let Err(err) = crate::Value::from_str(&string) {
+                panic!("{value:?} serialized to invalid MASON {string:?}: {err}");
+            }
+            let deserialized: T = crate::from_str(&string)
+                .unwrap_or_else(|err| panic!("failed to deserialize {string:?}: {err}"));
+            assert_eq!(deserialized, value, "{string:?}");
+        }
+    }
+
+    #[test]
+    fn test_top_level() {
+        #[derive(Serialize, serde::Deserialize, PartialEq, Debug)]
+        struct Unit;
+
+        #[derive(Serialize, serde::Deserialize, PartialEq, Debug)]
+        struct Newtype(String);
+
+        #[derive(Serialize, serde::Deserialize, PartialEq, Debug)]
+        struct Tuple(u8, bool);
+
+        #[derive(Serialize, serde::Deserialize, PartialEq, Debug)]
+        struct Empty {}
+
+        #[derive(Serialize, serde::Deserialize, PartialEq, Debug)]
+        struct Struct {
+            a: Option<Box<Struct>>,
+            b: Vec<E>,
+        }
+
+        #[derive(Serialize, serde::Deserialize, PartialEq, Debug)]
+        enum E {
+            Unit,
+            Newtype(u32),
+            Tuple(u32, u32),
+            Struct { a: u32 },
+        }
+
+        // scalars
+        assert_round_trip(true);
+        assert_round_trip(-8i8);
+        assert_round_trip(16i16);
+        assert_round_trip(-32i32);
+        assert_round_trip(i64::MIN);
+        assert_round_trip(8u8);
+        assert_round_trip(16u16);
+        assert_round_trip(32u32);
+        assert_round_trip(u64::MAX);
+        assert_round_trip(1.5f32);
+        assert_round_trip(-1e-300f64);
+        assert_round_trip('c');
+        assert_round_trip("string".to_owned());
+        assert_round_trip(None::<u32>);
+        assert_round_trip(Some(1u32));
+        assert_round_trip(());
+        assert_round_trip(Unit);
+        assert_round_trip(Newtype("newtype".to_owned()));
+
+        // MASON can not represent these floats
+        assert_eq!(to_string(&f64::NAN).unwrap(), "null");
+        assert_eq!(to_string(&f32::NEG_INFINITY).unwrap(), "null");
+
+        // sequences
+        assert_round_trip(vec![1, 2, 3]);
+        assert_round_trip(Vec::<u32>::new());
+        assert_round_trip((1u8, "two".to_owned()));
+        assert_round_trip(Tuple(1, false));
+        assert_round_trip(vec![vec![Empty {}], vec![]]);
+
+        // maps
+        assert_round_trip(HashMap::from([("a".to_owned(), 1u32)]));
+        assert_round_trip(HashMap::<String, u32>::new());
+        assert_round_trip(Empty {});
+        assert_round_trip(Struct {
+            a: Some(Box::new(Struct {
+                a: None,
+                b: vec![E::Unit, E::Newtype(1)],
+            })),
+            b: vec![E::Tuple(2, 3), E::Struct { a: 4 }],
+        });
+
+        // enums
+        assert_round_trip(E::Unit);
+        assert_round_trip(E::Newtype(1));
+        assert_round_trip(E::Tuple(1, 2));
+        assert_round_trip(E::Struct { a: 1 });
+        assert_round_trip(vec![
+            E::Unit,
+            E::Newtype(1),
+            E::Tuple(1, 2),
+            E::Struct { a: 1 },
+        ]);
+        assert_round_trip(HashMap::from([("e".to_owned(), E::Unit)]));
+        assert_round_trip(HashMap::from([("e".to_owned(), E::Struct { a: 1 })]));
+    }
+
+    #[test]
+    fn test_options() {
+        #[derive(Serialize)]
+        enum E {
+            Newtype(u32),
+            Tuple(u32, u32),
+            Struct { a: u32 },
+        }
+
+        #[derive(Serialize)]
+        struct Inner {
+            seq: Vec<u32>,
+            empty: Vec<u32>,
+            variants: (E, E, E),
+        }
+
+        #[derive(Serialize)]
+        struct Outer {
+            name: &'static str,
+            inner: Inner,
+            nothing: HashMap<String, u32>,
+        }
+
+        let outer = Outer {
+            name: "outer",
+            inner: Inner {
+                seq: vec![1, 2],
+                empty: vec![],
+                variants: (E::Newtype(1), E::Tuple(2, 3), E::Struct { a: 4 }),
+            },
+            nothing: HashMap::new(),
+        };
+
+        let expected = "\
+name: \"outer\"
+inner: {
+    seq: [1, 2]
+    empty: []
+    variants: [{
+        Newtype: 1
+    }, {
+        Tuple: [2, 3]
+    }, {
+        Struct: {
+            a: 4
+        }
+    }]
+}
+nothing: {}";
+        assert_eq!(to_string(&outer).unwrap(), expected);
+
+        let options = SerializeOptions::new()
+            .indentation("\t")
+            .inline_arrays(false);
+        let expected = "\
+name: \"outer\"
+inner: {
+\tseq: [
+\t\t1
+\t\t2
+\t]
+\tempty: []
+\tvariants: [
+\t\t{
+\t\t\tNewtype: 1
+\t\t}
+\t\t{
+\t\t\tTuple: [
+\t\t\t\t2
+\t\t\t\t3
+\t\t\t]
+\t\t}
+\t\t{
+\t\t\tStruct: {
+\t\t\t\ta: 4
+\t\t\t}
+\t\t}
+\t]
+}
+nothing: {}";
+        assert_eq!(to_string_with_options(&outer, options).unwrap(), expected);
+
+        let options = SerializeOptions::new().compact(true);
+        let expected = "name: \"outer\", inner: {seq: [1, 2], empty: [], variants: \
+            [{Newtype: 1}, {Tuple: [2, 3]}, {Struct: {a: 4}}]}, nothing: {}";
+        let compact = to_string_with_options(&outer, options).unwrap();
+        assert_eq!(compact, expected);
+
+        let options = SerializeOptions::new().compact(true).braced_root(true);
+        let braced = to_string_with_options(&outer, options).unwrap();
+        assert_eq!(braced, format!("{{{expected}}}"));
+
+        let options = SerializeOptions::new().braced_root(true);
+        let expected = "{\n    Struct: {\n        a: 4\n    }\n}";
+        assert_eq!(
+            to_string_with_options(&E::Struct { a: 4 }, options).unwrap(),
+            expected
+        );
+
+        // all of them are valid MASON, and mean the same thing
+        let value = crate::Value::from_str(&compact).unwrap();
+        for options in [
+            SerializeOptions::new(),
+            SerializeOptions::new().indentation("").inline_arrays(false),
+            SerializeOptions::new().braced_root(true),
+        ] {
+            let string = to_string_with_options(&outer, options).unwrap();
+            assert_eq!(crate::Value::from_str(&string).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_line_ending() {
+        #[derive(Serialize)]
+        struct Test {
+            a: Vec<u32>,
+            b: BTreeMap<&'static str, u32>,
+        }
+
+        let test = Test {
+            a: vec![1, 2],
+            b: BTreeMap::from([("c", 3), ("d", 4)]),
+        };
+        let options = SerializeOptions::new()
+            .inline_arrays(false)
+            .line_ending(LineEnding::CrLf);
+        let expected = "a: [\r\n    1\r\n    2\r\n]\r\nb: {\r\n    c: 3\r\n    d: 4\r\n}";
+        assert_eq!(to_string_with_options(&test, options).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_hex_blocks() {
+        struct Bytes(Vec<u8>);
+
+        impl Serialize for Bytes {
+            fn serialize<S: ser::Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                serializer.serialize_bytes(&self.0)
+            }
+        }
+
+        #[derive(Serialize)]
+        struct Test {
+            a: Vec<Bytes>,
+        }
+
+        let test = Test {
+            a: vec![Bytes((0..6).collect()), Bytes(vec![7])],
+        };
+        let options = SerializeOptions::new()
+            .inline_arrays(false)
+            .hex_blocks(Some(4));
+        assert_eq!(
+            to_string_with_options(&test, options.clone()).unwrap(),
+            "a: [\n    b|00010203\n        b|0405\n    b\"\\x07\"\n]"
+        );
+        assert_eq!(
+            to_string_with_options(&test, options.compact(true)).unwrap(),
+            "a: [b\"\\x00\\x01\\x02\\x03\\x04\\x05\", b\"\\x07\"]"
+        );
+    }
+
+    #[test]
+    fn test_separator_style() {
+        #[derive(Serialize)]
+        struct Test {
+            a: Vec<u32>,
+            b: BTreeMap<&'static str, u32>,
+        }
+
+        let test = Test {
+            a: vec![1, 2],
+            b: BTreeMap::from([("c", 3), ("d", 4)]),
+        };
+        let to_string = |options: SerializeOptions, style| {
+            to_string_with_options(&test, options.separator_style(style)).unwrap()
+        };
+
+        let expected = "a: [1, 2],\nb: {\n    c: 3,\n    d: 4\n}";
+        assert_eq!(
+            to_string(SerializeOptions::new(), SeparatorStyle::Comma),
+            expected
+        );
+
+        let expected = "{\n    a: [\n        1,\n        2,\n    ],\n    b: {\n        c: 3,\n        d: 4,\n    },\n}";
+        let options = SerializeOptions::new()
+            .inline_arrays(false)
+            .braced_root(true);
+        let string = to_string(options, SeparatorStyle::TrailingComma);
+        assert_eq!(string, expected);
+        assert!(crate::Value::from_str(&string).is_ok());
+
+        let expected = "a: [1, 2],\nb: {\n    c: 3,\n    d: 4,\n}";
+        let string = to_string(SerializeOptions::new(), SeparatorStyle::TrailingComma);
+        assert_eq!(string, expected);
+        assert!(crate::Value::from_str(&string).is_ok());
+
+        let expected = "a: [1, 2], b: {c: 3, d: 4}";
+        let options = SerializeOptions::new().compact(true);
+        assert_eq!(to_string(options, SeparatorStyle::TrailingComma), expected);
+    }
+
+    #[test]
+    fn test_to_io_writer() {
+        #[derive(Serialize)]
+        struct Test {
+            name: &'static str,
+            nested: Vec<HashMap<&'static str, u32>>,
+        }
+
+        let test = Test {
+            name: "🏳️‍⚧️",
+            nested: vec![HashMap::from([("a", 1)]), HashMap::new()],
+        };
+        let mut bytes = Vec::new();
+        to_io_writer(&test, &mut bytes).unwrap();
+        assert_eq!(String::from_utf8(bytes).unwrap(), to_string(&test).unwrap());
+
+        /// A writer that fails after `remaining` bytes.
+        struct Limited {
+            remaining: usize,
+        }
+
+        impl io::Write for Limited {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.remaining == 0 {
+                    return Err(io::Error::new(io::ErrorKind::StorageFull, "full"));
+                }
+                let len = buf.len().min(self.remaining);
+                self.remaining -= len;
+                Ok(len)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let err = to_io_writer(&test, &mut Limited { remaining: 10 }).unwrap_err();
+        assert_eq!(err.to_string(), "full");
+    }
+
+    #[test]
+    fn test_human_readable() {
+        use crate::Deserializer;
+
+        /// Serializes as a string when human readable, and as bytes otherwise.
+        #[derive(Debug, PartialEq)]
+        struct Id([u8; 2]);
+
+        impl Serialize for Id {
+            fn serialize<S: ser::Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                if serializer.is_human_readable() {
+                    serializer.serialize_str(&format!("{:02x}{:02x}", self.0[0], self.0[1]))
+                } else {
+                    serializer.serialize_bytes(&self.0)
+                }
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for Id {
+            fn deserialize<D: serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> std::result::Result<Self, D::Error> {
+                use serde::de::Error as _;
+
+                if deserializer.is_human_readable() {
+                    let string = String::deserialize(deserializer)?;
+                    let byte =
+                        |i| u8::from_str_radix(&string[i..i + 2], 16).map_err(D::Error::custom);
+                    Ok(Self([byte(0)?, byte(2)?]))
+                } else {
+                    let bytes = deserialize_bytes(deserializer)?;
+                    Ok(Self(
+                        bytes
+                            .try_into()
+                            .map_err(|_| D::Error::custom("wrong length"))?,
+                    ))
+                }
+            }
+        }
+
+        fn deserialize_bytes<'de, D: serde::Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<Vec<u8>, D::Error> {
+            struct BytesVisitor;
+            impl serde::de::Visitor<'_> for BytesVisitor {
+                type Value = Vec<u8>;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str("bytes")
+                }
+
+                fn visit_byte_buf<E>(self, v: Vec<u8>) -> std::result::Result<Vec<u8>, E> {
+                    Ok(v)
+                }
+            }
+            deserializer.deserialize_byte_buf(BytesVisitor)
+        }
+
+        let id = Id([0xab, 0x01]);
+        for (human_readable, expected) in [(true, r#""ab01""#), (false, r#"b"\xab\x01""#)] {
+            let mut string = String::new();
+            let mut serializer = Serializer::new(&mut string).human_readable(human_readable);
+            id.serialize(&mut serializer).unwrap();
+            assert_eq!(string, expected);
+
+            let mut deserializer = Deserializer::from_str(&string).human_readable(human_readable);
+            assert_eq!(
+                <Id as serde::Deserialize>::deserialize(&mut deserializer).unwrap(),
+                id
+            );
+        }
+    }
 }
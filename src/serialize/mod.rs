@@ -1,55 +1,446 @@
+mod options;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod styled;
+
 use std::fmt::{self, Write};
 
-use crate::{Value, hex::encode_hex, utils};
+use crate::{
+    Map, MasonString, Number, Value,
+    hex::{self, encode_hex},
+    text, utils,
+};
+
+pub use options::{LineEnding, SeparatorStyle, SerializeOptions};
+#[cfg(feature = "rayon")]
+pub(crate) use parallel::write_value_parallel;
+pub(crate) use styled::write_styled_value;
+
+/// Write `value` as described by `options`.
+pub fn write_value<W: Write>(value: &Value, w: &mut W, options: &SerializeOptions) -> fmt::Result {
+    let top_level = !options.braced_root;
+    if options.compact {
+        write_compact_value(value, w, top_level, options)
+    } else {
+        write_indented_value(value, w, options, 0, top_level)
+    }
+}
 
-pub fn write_indented_value<W: Write>(
+/// Write `value` with one key-value pair or array element per line. Nested values
+/// are indented by `options.indentation`, repeated `indentation_level` times for
+/// the line the value starts on.
+fn write_indented_value<W: Write>(
     value: &Value,
     w: &mut W,
-    indentation: &str,
+    options: &SerializeOptions,
     indentation_level: usize,
+    top_level: bool,
 ) -> fmt::Result {
-    match value {
-        Value::Object(hash_map) => {
-            if indentation_level != 0 {
-                writeln!(w, "{{\n")?;
-            }
-            for (i, (key, value)) in hash_map.iter().enumerate() {
-                write!(w, "{}", indentation.repeat(indentation_level))?;
-                serialize_key(w, key)?;
-                write!(w, ": ")?;
-                write_indented_value(value, w, indentation, indentation_level + 1)?;
-                if i != hash_map.len() - 1 {
-                    writeln!(w)?;
+    write_indented_value_with(
+        value,
+        w,
+        options,
+        indentation_level,
+        top_level,
+        &mut NoHooks,
+    )
+}
+
+/// Like [`write_indented_value`], but `hooks` are called around the values inside
+/// `value`.
+pub(crate) fn write_indented_value_with<W: Write>(
+    value: &Value,
+    w: &mut W,
+    options: &SerializeOptions,
+    indentation_level: usize,
+    top_level: bool,
+    hooks: &mut impl WriteHooks<W>,
+) -> fmt::Result {
+    let layout = Layout {
+        options,
+        compact: false,
+    };
+    layout.write(value, w, indentation_level, top_level, hooks)
+}
+
+pub fn write_compact_value<W: Write>(
+    value: &Value,
+    w: &mut W,
+    top_level: bool,
+    options: &SerializeOptions,
+) -> fmt::Result {
+    write_compact_value_with(value, w, top_level, options, &mut NoHooks)
+}
+
+/// Like [`write_compact_value`], but `hooks` are called around the values inside
+/// `value`. The indentation level that is given to the hooks is always 0.
+fn write_compact_value_with<W: Write>(
+    value: &Value,
+    w: &mut W,
+    top_level: bool,
+    options: &SerializeOptions,
+    hooks: &mut impl WriteHooks<W>,
+) -> fmt::Result {
+    let layout = Layout {
+        options,
+        compact: true,
+    };
+    layout.write(value, w, 0, top_level, hooks)
+}
+
+/// Code that is called around the values inside the value that is being written,
+/// to write some of them differently, or to keep track of where they are written.
+pub(crate) trait WriteHooks<W> {
+    /// Called before `child` is written. Returns true if the hook wrote the value
+    /// itself, in which case it is not written again.
+    fn enter(&mut self, child: &Child, w: &mut W) -> Result<bool, fmt::Error>;
+
+    /// Called after a value inside `parent` has been written, unless
+    /// [`WriteHooks::enter`] wrote it.
+    fn exit(&mut self, parent: &Value, w: &mut W) -> fmt::Result {
+        let _ = (parent, w);
+        Ok(())
+    }
+}
+
+/// A value inside the value that is being written.
+pub(crate) struct Child<'a> {
+    /// The array, object or tagged value the value is inside of.
+    pub parent: &'a Value,
+    /// The index of the value in the order the values inside `parent` are written.
+    pub index: usize,
+    /// The key of the value, if `parent` is an object.
+    pub key: Option<&'a str>,
+    pub value: &'a Value,
+    /// The indentation level of the line the value starts on.
+    pub indentation_level: usize,
+    /// Whether the value starts on a line of its own, after its key if it has one.
+    pub own_line: bool,
+}
+
+/// Hooks that do nothing.
+struct NoHooks;
+
+impl<W> WriteHooks<W> for NoHooks {
+    fn enter(&mut self, _child: &Child, _w: &mut W) -> Result<bool, fmt::Error> {
+        Ok(false)
+    }
+}
+
+/// How values are laid out, either indented or compact.
+#[derive(Clone, Copy)]
+struct Layout<'o> {
+    options: &'o SerializeOptions,
+    compact: bool,
+}
+
+/// An array, object or tagged value whose inside is being written.
+struct Frame<'a> {
+    value: &'a Value,
+    /// The values inside `value` in the order they are written, with their keys if
+    /// `value` is an object.
+    children: Vec<(Option<&'a MasonString>, &'a Value)>,
+    /// The index of the next child to write.
+    next: usize,
+    /// The indentation level of the line `value` starts on.
+    indentation_level: usize,
+    top_level: bool,
+    /// Whether every child is written on a line of its own.
+    own_lines: bool,
+}
+
+impl Layout<'_> {
+    /// Write `value`. Arrays and objects are kept on an explicit stack instead of
+    /// being written recursively, so deeply nested values can not overflow the call
+    /// stack.
+    fn write<W: Write>(
+        self,
+        value: &Value,
+        w: &mut W,
+        indentation_level: usize,
+        top_level: bool,
+        hooks: &mut impl WriteHooks<W>,
+    ) -> fmt::Result {
+        let mut stack = Vec::new();
+        stack.extend(self.start(value, w, indentation_level, top_level)?);
+        while let Some(frame) = stack.last_mut() {
+            let Some(&(key, child)) = frame.children.get(frame.next) else {
+                self.end(frame, w)?;
+                stack.pop();
+                if let Some(parent) = stack.last() {
+                    hooks.exit(parent.value, w)?;
+                    self.after_child(parent, w)?;
                 }
+                continue;
+            };
+
+            let index = frame.next;
+            frame.next += 1;
+            let child_level = self.before_child(frame, key, w)?;
+            let entered = Child {
+                parent: frame.value,
+                index,
+                key: key.map(MasonString::as_str),
+                value: child,
+                indentation_level: child_level,
+                own_line: frame.own_lines,
+            };
+            if hooks.enter(&entered, w)? {
+                self.after_child(frame, w)?;
+                continue;
             }
-            if indentation_level != 0 {
-                write!(w, "\n{}}}", indentation.repeat(indentation_level - 1))
-            } else {
-                Ok(())
+            match self.start(child, w, child_level, false)? {
+                Some(child_frame) => stack.push(child_frame),
+                None => {
+                    hooks.exit(frame.value, w)?;
+                    self.after_child(frame, w)?;
+                }
             }
         }
-        Value::Array(vec) => {
-            write!(w, "[")?;
-            for (i, value) in vec.iter().enumerate() {
-                write_indented_value(value, w, indentation, indentation_level)?;
-                if i != vec.len() - 1 {
-                    write!(w, ", ")?;
+        Ok(())
+    }
+
+    /// Write the start of `value`, or all of it if nothing is inside it. Returns
+    /// the frame to write the inside of `value` with.
+    fn start<'a, W: Write>(
+        self,
+        value: &'a Value,
+        w: &mut W,
+        indentation_level: usize,
+        top_level: bool,
+    ) -> Result<Option<Frame<'a>>, fmt::Error> {
+        let options = self.options;
+        let newline = options.line_ending.as_str();
+        let (children, own_lines) = match value {
+            Value::Object(hash_map) if hash_map.is_empty() => {
+                w.write_str("{}")?;
+                return Ok(None);
+            }
+            Value::Object(hash_map) => {
+                // The top level object does not need braces, or to be indented
+                if !top_level {
+                    w.write_str("{")?;
+                    if !self.compact {
+                        w.write_str(newline)?;
+                    }
                 }
+                let entries = entries(hash_map, options)
+                    .into_iter()
+                    .map(|(key, value)| (Some(key), value))
+                    .collect();
+                (entries, !self.compact)
+            }
+            Value::Array(vec) if vec.is_empty() => {
+                w.write_str("[]")?;
+                return Ok(None);
+            }
+            Value::Array(vec) => {
+                // Arrays of simple values are kept on one line
+                let own_lines = !(self.compact
+                    || (options.inline_arrays && !vec.iter().any(is_non_empty_container)));
+                w.write_str("[")?;
+                if own_lines {
+                    w.write_str(newline)?;
+                }
+                (vec.iter().map(|value| (None, value)).collect(), own_lines)
+            }
+            Value::Tagged(tag, value) => {
+                write!(w, "@{tag} ")?;
+                (vec![(None, &**value)], false)
+            }
+            Value::ByteString(vec) if self.compact => {
+                serialize_bytes(w, vec)?;
+                return Ok(None);
             }
-            write!(w, "]")
+            Value::ByteString(vec) => {
+                serialize_bytes_indented(w, vec, options, indentation_level)?;
+                return Ok(None);
+            }
+            Value::String(string) => {
+                serialize_string(w, string, options)?;
+                return Ok(None);
+            }
+            Value::Number(num) => {
+                serialize_mason_number(w, num, options.normalize_numbers)?;
+                return Ok(None);
+            }
+            Value::Bool(b) => {
+                write!(w, "{b}")?;
+                return Ok(None);
+            }
+            Value::Null => {
+                w.write_str("null")?;
+                return Ok(None);
+            }
+        };
+        Ok(Some(Frame {
+            value,
+            children,
+            next: 0,
+            indentation_level,
+            top_level,
+            own_lines,
+        }))
+    }
+
+    /// Write what comes before the next child of `frame`, and return the
+    /// indentation level of the child.
+    fn before_child<W: Write>(
+        self,
+        frame: &Frame,
+        key: Option<&MasonString>,
+        w: &mut W,
+    ) -> Result<usize, fmt::Error> {
+        let options = self.options;
+        let first = frame.next == 1;
+        let child_level = match frame.value {
+            Value::Object(_) if frame.own_lines && !frame.top_level => frame.indentation_level + 1,
+            Value::Array(_) if frame.own_lines => frame.indentation_level + 1,
+            _ => frame.indentation_level,
+        };
+
+        if frame.own_lines {
+            if !first && matches!(frame.value, Value::Object(_)) {
+                w.write_str(options.line_ending.as_str())?;
+            }
+            w.write_str(&options.indentation.repeat(child_level))?;
+        } else if !first && !matches!(frame.value, Value::Tagged(..)) {
+            w.write_str(", ")?;
         }
-        Value::ByteString(vec) => serialize_bytes(w, vec),
-        Value::String(string) => serialize_string(w, string),
-        Value::Number(num) => write!(w, "{num}"),
-        Value::Bool(b) => write!(w, "{b}"),
-        Value::Null => write!(w, "null"),
+        if let Some(key) = key {
+            serialize_key(w, key, options)?;
+            w.write_str(": ")?;
+        }
+        Ok(child_level)
     }
+
+    /// Write what comes after the last child of `frame` that was written.
+    fn after_child<W: Write>(self, frame: &Frame, w: &mut W) -> fmt::Result {
+        if !frame.own_lines {
+            return Ok(());
+        }
+        let last = frame.next == frame.children.len();
+        match frame.value {
+            Value::Object(_) if frame.top_level && last => Ok(()),
+            Value::Object(_) => w.write_str(self.options.separator(last)),
+            _ => {
+                w.write_str(self.options.separator(last))?;
+                w.write_str(self.options.line_ending.as_str())
+            }
+        }
+    }
+
+    /// Write the end of the value of `frame`, after all of its children.
+    fn end<W: Write>(self, frame: &Frame, w: &mut W) -> fmt::Result {
+        let indentation = || self.options.indentation.repeat(frame.indentation_level);
+        match frame.value {
+            Value::Object(_) if frame.top_level => Ok(()),
+            Value::Object(_) if frame.own_lines => {
+                let newline = self.options.line_ending.as_str();
+                write!(w, "{newline}{}}}", indentation())
+            }
+            Value::Object(_) => w.write_str("}"),
+            Value::Array(_) if frame.own_lines => write!(w, "{}]", indentation()),
+            Value::Array(_) => w.write_str("]"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The entries of `map`, sorted by key if [`SerializeOptions::sort_keys`] is set.
+fn entries<'a>(map: &'a Map, options: &SerializeOptions) -> Vec<(&'a MasonString, &'a Value)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    if options.sort_keys {
+        entries.sort_unstable_by_key(|(key, _)| *key);
+    }
+    entries
+}
+
+/// The values inside `value`, in the order they are written.
+#[cfg(feature = "rayon")]
+fn children<'a>(value: &'a Value, options: &SerializeOptions) -> Vec<&'a Value> {
+    match value {
+        Value::Object(map) => entries(map, options)
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect(),
+        Value::Array(array) => array.iter().collect(),
+        Value::Tagged(_, value) => vec![value],
+        _ => Vec::new(),
+    }
+}
+
+fn is_non_empty_container(value: &Value) -> bool {
+    match value {
+        Value::Object(hash_map) => !hash_map.is_empty(),
+        Value::Array(vec) => !vec.is_empty(),
+        Value::Tagged(_, value) => is_non_empty_container(value),
+        _ => false,
+    }
+}
+
+// MASON can not represent NaN or infinity, so like in JSON, they are written as null
+pub(crate) fn serialize_number<W: Write>(w: &mut W, num: f64) -> fmt::Result {
+    if num.is_finite() {
+        write!(w, "{num}")
+    } else {
+        write!(w, "null")
+    }
+}
+
+// write numbers the same way they were parsed, unless they should be normalized,
+// in which case integers are written exactly, in decimal. Numbers only have a
+// literal with the `arbitrary_precision` feature.
+fn serialize_mason_number<W: Write>(w: &mut W, num: &Number, normalize: bool) -> fmt::Result {
+    let Some(literal) = num.literal() else {
+        return serialize_number(w, num.as_f64());
+    };
+    match (num.as_u128(), num.as_i128()) {
+        _ if !normalize => w.write_str(literal),
+        (Some(int), _) => write!(w, "{int}"),
+        (_, Some(int)) => write!(w, "{int}"),
+        _ => serialize_number(w, num.as_f64()),
+    }
+}
+
+/// Write `bytes` as a hex block if they are too long for one line of it, see
+/// [`SerializeOptions::hex_blocks`], and as a byte string otherwise. The bytes
+/// start on a line at `indentation_level`.
+pub(crate) fn serialize_bytes_indented<W: Write>(
+    w: &mut W,
+    bytes: &[u8],
+    options: &SerializeOptions,
+    indentation_level: usize,
+) -> fmt::Result {
+    let width = options
+        .hex_blocks
+        .filter(|width| *width > 0 && bytes.len() > *width);
+    let Some(width) = width.filter(|_| !options.compact) else {
+        return serialize_bytes(w, bytes);
+    };
+    let continuation = options.indentation.repeat(indentation_level + 1);
+    for (i, line) in bytes.chunks(width).enumerate() {
+        if i != 0 {
+            write!(w, "{}{continuation}", options.line_ending.as_str())?;
+        }
+        write!(w, "b|")?;
+        // group the bytes in fours, so that offsets are easy to count
+        for (j, group) in line.chunks(4).enumerate() {
+            if j != 0 {
+                write!(w, " ")?;
+            }
+            w.write_str(&hex::encode(group))?;
+        }
+    }
+    Ok(())
 }
 
 pub(crate) fn serialize_bytes<W: Write>(w: &mut W, bytes: &[u8]) -> fmt::Result {
     write!(w, "b\"")?;
     for byte in bytes {
-        if *byte > 31 && *byte < 127 {
+        if matches!(byte, b'"' | b'\\') {
+            write!(w, "\\{}", utils::to_char(*byte))?;
+        } else if *byte > 31 && *byte < 127 {
             // byte is normal, add it as char
             write!(w, "{}", utils::to_char(*byte))?;
         } else {
@@ -69,23 +460,28 @@ pub(crate) fn serialize_bytes<W: Write>(w: &mut W, bytes: &[u8]) -> fmt::Result
     write!(w, "\"")
 }
 
-// We must escape quotes and backslashes
-pub(crate) fn serialize_string<W: Write>(w: &mut W, string: &str) -> fmt::Result {
-    if !string.contains(['"', '\\']) {
-        Ok(write!(w, "\"{string}\"")?)
+/// Write `string` as a quoted string. Quotes, backslashes and control characters
+/// are escaped, so the output can always be parsed again, and with
+/// [`SerializeOptions::escape_non_ascii`], so are all non-ASCII characters.
+pub(crate) fn serialize_string<W: Write>(
+    w: &mut W,
+    string: &str,
+    options: &SerializeOptions,
+) -> fmt::Result {
+    let style = if options.braced_unicode_escapes {
+        text::UnicodeEscape::Braced
     } else {
-        let mut v = string;
-        write!(w, "\"")?;
-        while let Some(index) = v.find(['"', '\\']) {
-            write!(w, "{}\\{}", &v[..index], &v[index..=index])?;
-            v = &v[(index + 1)..];
-        }
-        write!(w, "{v}\"")?;
-        Ok(())
-    }
+        text::UnicodeEscape::Short
+    };
+    let escaped = text::escape(string, options.escape_non_ascii, style);
+    write!(w, "\"{escaped}\"")
 }
 
-pub(crate) fn serialize_key<W: Write>(w: &mut W, key: &str) -> fmt::Result {
+pub(crate) fn serialize_key<W: Write>(
+    w: &mut W,
+    key: &str,
+    options: &SerializeOptions,
+) -> fmt::Result {
     let mut chars = key.chars();
     let Some(first) = chars.next() else {
         return write!(w, "\"\"");
@@ -96,7 +492,7 @@ pub(crate) fn serialize_key<W: Write>(w: &mut W, key: &str) -> fmt::Result {
     {
         write!(w, "{key}")
     } else {
-        serialize_string(w, key)
+        serialize_string(w, key, options)
     }
 }
 
@@ -104,7 +500,36 @@ pub(crate) fn serialize_key<W: Write>(w: &mut W, key: &str) -> fmt::Result {
 mod tests {
     use std::str::FromStr;
 
-    use crate::Value;
+    use crate::{LineEnding, Map, ParseOptions, SeparatorStyle, SerializeOptions, Value};
+
+    fn to_string_with_options(value: &Value, options: &SerializeOptions) -> String {
+        let mut string = String::new();
+        value.to_writer_with_options(&mut string, options).unwrap();
+        string
+    }
+
+    /// Check that both the compact and the indented output of `value` parse
+    /// back to `value`.
+    fn assert_round_trip(value: &Value) {
+        let options = SerializeOptions::new()
+            .indentation("\t")
+            .inline_arrays(false);
+        let comma = SerializeOptions::new().separator_style(SeparatorStyle::Comma);
+        let trailing_comma = SerializeOptions::new()
+            .inline_arrays(false)
+            .separator_style(SeparatorStyle::TrailingComma);
+        for string in [
+            value.to_string(),
+            format!("{value:#}"),
+            to_string_with_options(value, &options),
+            to_string_with_options(value, &comma),
+            to_string_with_options(value, &trailing_comma),
+        ] {
+            let parsed = Value::from_str(&string)
+                .unwrap_or_else(|err| panic!("failed to parse {string:?}: {err}"));
+            assert_eq!(&parsed, value, "{string:?}");
+        }
+    }
 
     #[test]
     fn test_to_string() {
@@ -129,4 +554,249 @@ mod tests {
         let same_value = Value::from_str(&value.to_string()).unwrap();
         assert_eq!(value, same_value);
     }
+
+    #[test]
+    fn test_compact_and_pretty() {
+        let value = Value::from_str("a: {b: [1, {c: null}]}").unwrap();
+        assert_eq!(value.to_string(), "a: {b: [1, {c: null}]}");
+        assert_eq!(Value::from_str(&format!("{value:#}")).unwrap(), value);
+
+        let value = Value::from_str("a: 1, b: 2").unwrap();
+        assert!(!value.to_string().contains('\n'));
+        assert_eq!(Value::from_str(&value.to_string()).unwrap(), value);
+
+        let value = Value::from_str("{}").unwrap();
+        assert_eq!(value.to_string(), "{}");
+
+        let value = Value::Array(vec![Value::from(f64::NAN), Value::from(f64::INFINITY)]);
+        assert_eq!(value.to_string(), "[null, null]");
+    }
+
+    #[test]
+    fn test_indented() {
+        let value = Value::from_str("a: {b: {c: [1, 2]}}").unwrap();
+        assert_eq!(
+            format!("{value:#}"),
+            "a: {\n    b: {\n        c: [1, 2]\n    }\n}"
+        );
+
+        let value = Value::from_str("[{a: 1}, [], [true]]").unwrap();
+        assert_eq!(
+            format!("{value:#}"),
+            "[\n    {\n        a: 1\n    }\n    []\n    [true]\n]"
+        );
+
+        let value = Value::from_str("{}").unwrap();
+        assert_eq!(format!("{value:#}"), "{}");
+    }
+
+    #[test]
+    fn test_options() {
+        let value = Value::from_str("a: {b: [1, [2], []]}").unwrap();
+        let to_string = |options| to_string_with_options(&value, &options);
+
+        assert_eq!(to_string(SerializeOptions::new()), format!("{value:#}"));
+        assert_eq!(
+            to_string(SerializeOptions::new().compact(true)),
+            value.to_string()
+        );
+        assert_eq!(
+            to_string(SerializeOptions::new().indentation("  ")),
+            "a: {\n  b: [\n    1\n    [2]\n    []\n  ]\n}"
+        );
+        assert_eq!(
+            to_string(SerializeOptions::new().inline_arrays(false)),
+            "a: {\n    b: [\n        1\n        [\n            2\n        ]\n        []\n    ]\n}"
+        );
+        assert_eq!(
+            to_string(SerializeOptions::new().compact(true).inline_arrays(false)),
+            value.to_string()
+        );
+        assert_eq!(
+            to_string(SerializeOptions::new().braced_root(true)),
+            "{\n    a: {\n        b: [\n            1\n            [2]\n            []\n        ]\n    }\n}"
+        );
+        assert_eq!(
+            to_string(SerializeOptions::new().braced_root(true).compact(true)),
+            "{a: {b: [1, [2], []]}}"
+        );
+
+        let value = Value::from_str("[{a: 1}]").unwrap();
+        let options = SerializeOptions::new().braced_root(true);
+        assert_eq!(
+            to_string_with_options(&value, &options),
+            format!("{value:#}")
+        );
+    }
+
+    #[test]
+    fn test_canonical() {
+        let value = Value::from_str("d: 0x10, b: [{y: 1'000, x: +1.50}], a: 1e3, c: {}").unwrap();
+        let canonical = SerializeOptions::canonical();
+
+        assert_eq!(
+            to_string_with_options(&value, &canonical.clone().compact(true)),
+            "a: 1000, b: [{x: 1.5, y: 1000}], c: {}, d: 16"
+        );
+        assert_eq!(
+            to_string_with_options(&value, &canonical),
+            "a: 1000\nb: [\n    {\n        x: 1.5\n        y: 1000\n    }\n]\nc: {}\nd: 16"
+        );
+
+        let sorted = to_string_with_options(&value, &SerializeOptions::new().sort_keys(true));
+        assert!(sorted.starts_with("a: "), "{sorted:?}");
+        assert_eq!(Value::from_str(&sorted).unwrap(), value);
+    }
+
+    #[test]
+    fn test_line_ending() {
+        let value = Value::from_str("a: {b: [1, [2], []]}").unwrap();
+        let options = SerializeOptions::new().line_ending(LineEnding::CrLf);
+        for options in [options.clone(), options.clone().braced_root(true)] {
+            let lf = to_string_with_options(&value, &options.clone().line_ending(LineEnding::Lf));
+            let crlf = to_string_with_options(&value, &options);
+            assert_eq!(crlf, lf.replace('\n', "\r\n"));
+            assert_eq!(LineEnding::detect(&crlf), LineEnding::CrLf);
+            assert_eq!(Value::from_str(&crlf).unwrap(), value);
+        }
+        let compact = options.compact(true);
+        assert_eq!(to_string_with_options(&value, &compact), value.to_string());
+        assert_eq!(LineEnding::detect("a: \"\\r\"\nb: 1\r\n"), LineEnding::Lf);
+    }
+
+    #[test]
+    fn test_control_characters() {
+        let value = Value::from_str(
+            r#"["line\nbreak", "tab\tnull\x00", "bell\u0007 del\x7f", {"key\r\n": "\b\f"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            value.to_string(),
+            r#"["line\nbreak", "tab\tnull\x00", "bell\x07 del\x7F", {"key\r\n": "\b\f"}]"#
+        );
+
+        let strict = ParseOptions::new().reject_control_characters(true);
+        for options in [
+            SerializeOptions::new(),
+            SerializeOptions::new().compact(true),
+            SerializeOptions::new().escape_non_ascii(true),
+        ] {
+            let string = to_string_with_options(&value, &options);
+            assert!(
+                !string.contains(|c: char| c.is_control() && c != '\n'),
+                "{string:?}"
+            );
+            assert_eq!(strict.parse_str(&string).unwrap().value, value);
+        }
+    }
+
+    #[test]
+    fn test_escape_non_ascii() {
+        let value =
+            Value::from_str("[\"ascii \\\"only\\\"\", \"æ\\\\ø\", {\"ключ\": \"𝄞\"}]").unwrap();
+        let options = SerializeOptions::new().escape_non_ascii(true);
+        for options in [options.clone(), options.compact(true)] {
+            let string = to_string_with_options(&value, &options);
+            assert!(string.is_ascii(), "{string}");
+            assert_eq!(Value::from_str(&string).unwrap(), value);
+        }
+        assert_eq!(
+            to_string_with_options(
+                &value,
+                &SerializeOptions::new().escape_non_ascii(true).compact(true)
+            ),
+            r#"["ascii \"only\"", "\u00E6\\\u00F8", {"\u043A\u043B\u044E\u0447": "\U01D11E"}]"#
+        );
+    }
+
+    #[test]
+    fn test_braced_unicode_escapes() {
+        let value = Value::from_str("[\"æ\\u0085🙃\", {\"ключ\": 1}]").unwrap();
+        let options = SerializeOptions::new()
+            .braced_unicode_escapes(true)
+            .compact(true);
+        assert_eq!(
+            to_string_with_options(&value, &options),
+            r#"["æ\u{85}🙃", {"ключ": 1}]"#
+        );
+        let options = options.escape_non_ascii(true);
+        let string = to_string_with_options(&value, &options);
+        assert_eq!(
+            string,
+            r#"["\u{E6}\u{85}\u{1F643}", {"\u{43A}\u{43B}\u{44E}\u{447}": 1}]"#
+        );
+        assert_eq!(Value::from_str(&string).unwrap(), value);
+    }
+
+    #[test]
+    fn test_hex_blocks() {
+        let mut value = Value::Array(vec![
+            Value::ByteString((0..20).collect()),
+            Value::ByteString(b"short".to_vec()),
+            Value::from_str("{a: null}").unwrap(),
+        ]);
+        value[2]["a"] = Value::ByteString(vec![0xff; 9]);
+
+        let options = SerializeOptions::new()
+            .hex_blocks(Some(8))
+            .separator_style(SeparatorStyle::Comma);
+        let string = to_string_with_options(&value, &options);
+        assert_eq!(
+            string,
+            "[\n    b|00010203 04050607\n        b|08090a0b 0c0d0e0f\n        b|10111213,\n    \
+             b\"short\",\n    {\n        a: b|ffffffff ffffffff\n            b|ff\n    }\n]"
+        );
+        let parse = ParseOptions::new().hex_blocks(true);
+        assert_eq!(parse.parse_str(&string).unwrap().value, value);
+
+        let options = options.compact(true);
+        let string = to_string_with_options(&value, &options);
+        assert!(string.starts_with("[b\"\\x00\\x01"), "{string}");
+        let options = SerializeOptions::new().hex_blocks(Some(0));
+        assert_eq!(to_string_with_options(&value[1], &options), "b\"short\"");
+    }
+
+    #[test]
+    fn test_tagged() {
+        let options = ParseOptions::new().tags(true);
+        let value = options.parse_str("@t {a: @u [1, @v {}]}").unwrap().value;
+        assert_eq!(value.to_string(), "@t {a: @u [1, @v {}]}");
+        assert_eq!(format!("{value:#}"), "@t {\n    a: @u [1, @v {}]\n}");
+        for string in [value.to_string(), format!("{value:#}")] {
+            assert_eq!(options.parse_str(&string).unwrap().value, value);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Every combination of containers nested up to three levels deep
+        let mut level = vec![
+            Value::Null,
+            Value::from(-1.5),
+            Value::String("a \"string\"".into()),
+            Value::ByteString(b"\x00\"".to_vec()),
+        ];
+        let mut values = level.clone();
+        for _ in 0..3 {
+            let mut nested = vec![Value::Array(vec![]), Value::Object(Map::new())];
+            for value in &level {
+                nested.push(Value::Array(vec![value.clone()]));
+                nested.push(Value::Array(vec![value.clone(), Value::Bool(true)]));
+                nested.push(Value::Object(Map::from([(
+                    "key".to_owned(),
+                    value.clone(),
+                )])));
+                nested.push(Value::Object(Map::from([
+                    ("a key".to_owned(), value.clone()),
+                    ("other".to_owned(), Value::Bool(false)),
+                ])));
+            }
+            values.extend(nested.iter().cloned());
+            level = nested;
+        }
+
+        for value in &values {
+            assert_round_trip(value);
+        }
+    }
 }
@@ -0,0 +1,339 @@
+/// Options that change how MASON is written, shared by [`Value::to_writer_with_options`]
+/// and, with the `serde` feature, the serde [`Serializer`](crate::Serializer).
+///
+/// ```
+/// # use mason_rs::{SerializeOptions, Value};
+/// # use std::str::FromStr;
+/// #
+/// let value = Value::from_str("ports: [80, 443]").unwrap();
+/// let options = SerializeOptions::new().indentation("  ").inline_arrays(false);
+///
+/// let mut string = String::new();
+/// value.to_writer_with_options(&mut string, &options).unwrap();
+/// assert_eq!(string, "ports: [\n  80\n  443\n]");
+/// ```
+///
+/// [`Value::to_writer_with_options`]: crate::Value::to_writer_with_options
+#[derive(Debug, Clone)]
+pub struct SerializeOptions {
+    pub(crate) indentation: String,
+    pub(crate) compact: bool,
+    pub(crate) inline_arrays: bool,
+    pub(crate) braced_root: bool,
+    pub(crate) separator_style: SeparatorStyle,
+    pub(crate) escape_non_ascii: bool,
+    pub(crate) braced_unicode_escapes: bool,
+    pub(crate) hex_blocks: Option<usize>,
+    pub(crate) line_ending: LineEnding,
+    pub(crate) sort_keys: bool,
+    pub(crate) normalize_numbers: bool,
+}
+
+/// How key-value pairs and array elements that are on separate lines are
+/// separated. See [`SerializeOptions::separator_style`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SeparatorStyle {
+    /// Only separate items with line breaks.
+    #[default]
+    Newline,
+    /// End every item except the last one with a comma.
+    Comma,
+    /// End every item with a comma, including the last one. The top level object
+    /// has no closing brace, so there is no comma after its last item.
+    TrailingComma,
+}
+
+/// The line break that lines are ended with. See [`SerializeOptions::line_ending`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`, which is used on Unix.
+    #[default]
+    Lf,
+    /// `\r\n`, which is used on Windows.
+    CrLf,
+}
+
+impl LineEnding {
+    /// Detect the line ending of `text` from its first line break. If `text` has
+    /// no line breaks, this returns [`LineEnding::Lf`].
+    ///
+    /// ```
+    /// # use mason_rs::LineEnding;
+    /// #
+    /// assert_eq!(LineEnding::detect("a: 1\r\nb: 2\r\n"), LineEnding::CrLf);
+    /// assert_eq!(LineEnding::detect("a: 1\nb: 2\n"), LineEnding::Lf);
+    /// assert_eq!(LineEnding::detect("a: 1"), LineEnding::Lf);
+    /// ```
+    pub fn detect(text: &str) -> Self {
+        match text.find('\n') {
+            Some(i) if text[..i].ends_with('\r') => Self::CrLf,
+            _ => Self::Lf,
+        }
+    }
+
+    /// The line break, `"\n"` or `"\r\n"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        Self {
+            indentation: "    ".to_owned(),
+            compact: false,
+            inline_arrays: true,
+            braced_root: false,
+            separator_style: SeparatorStyle::Newline,
+            escape_non_ascii: false,
+            braced_unicode_escapes: false,
+            hex_blocks: None,
+            line_ending: LineEnding::Lf,
+            sort_keys: false,
+            normalize_numbers: false,
+        }
+    }
+}
+
+impl SerializeOptions {
+    /// Create the default options, which write MASON the same way as
+    /// [`Value::to_writer`](crate::Value::to_writer).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The string nested values are indented with. The default is four spaces.
+    pub fn indentation(mut self, indentation: impl Into<String>) -> Self {
+        self.indentation = indentation.into();
+        self
+    }
+
+    /// Write everything on a single line, separating key-value pairs and array
+    /// elements with commas, like `a: 1, b: [2, 3]`. This is the format of
+    /// [`Value`](crate::Value)'s `Display` implementation. The default is `false`,
+    /// which puts each key-value pair on its own line.
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    /// Write arrays on a single line, like `[1, 2, 3]`. If this is `false`, each
+    /// element of a non-empty array is put on its own line. The default is `true`.
+    ///
+    /// Since the [`Value`](crate::Value) serializer knows the elements of an
+    /// array before writing it, it still puts the elements of arrays that contain
+    /// non-empty arrays or objects on separate lines. Has no effect if
+    /// [`SerializeOptions::compact`] is set.
+    pub fn inline_arrays(mut self, inline_arrays: bool) -> Self {
+        self.inline_arrays = inline_arrays;
+        self
+    }
+
+    /// Surround the document with braces if it is an object. MASON allows the
+    /// braces of the top level object to be left out, which is done by default,
+    /// but some consumers require them.
+    ///
+    /// ```
+    /// # use mason_rs::{SerializeOptions, Value};
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str("port: 8080").unwrap();
+    ///
+    /// let mut string = String::new();
+    /// value.to_writer_with_options(&mut string, &SerializeOptions::new().braced_root(true)).unwrap();
+    /// assert_eq!(string, "{\n    port: 8080\n}");
+    /// ```
+    pub fn braced_root(mut self, braced_root: bool) -> Self {
+        self.braced_root = braced_root;
+        self
+    }
+
+    /// How key-value pairs and array elements that are on separate lines are
+    /// separated. MASON allows both line breaks and commas, as well as a trailing
+    /// comma after the last item. The default is [`SeparatorStyle::Newline`].
+    /// Items on a single line are always separated by commas.
+    ///
+    /// ```
+    /// # use mason_rs::{SeparatorStyle, SerializeOptions, Value};
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str("a: [1, 2]").unwrap();
+    /// let to_string = |style| {
+    ///     let options = SerializeOptions::new().inline_arrays(false).separator_style(style);
+    ///     let mut string = String::new();
+    ///     value.to_writer_with_options(&mut string, &options).unwrap();
+    ///     string
+    /// };
+    ///
+    /// assert_eq!(to_string(SeparatorStyle::Newline), "a: [\n    1\n    2\n]");
+    /// assert_eq!(to_string(SeparatorStyle::Comma), "a: [\n    1,\n    2\n]");
+    /// assert_eq!(to_string(SeparatorStyle::TrailingComma), "a: [\n    1,\n    2,\n]");
+    /// ```
+    pub fn separator_style(mut self, separator_style: SeparatorStyle) -> Self {
+        self.separator_style = separator_style;
+        self
+    }
+
+    /// Escape all non-ASCII characters in strings and keys, so the output is pure
+    /// ASCII. Characters in the basic multilingual plane are written as `\uXXXX`,
+    /// and the rest as `\UXXXXXX`. The default is `false`, which writes strings
+    /// as UTF-8.
+    ///
+    /// ```
+    /// # use mason_rs::{SerializeOptions, Value};
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str("\"blåbær\": \"🫐\"").unwrap();
+    ///
+    /// let mut string = String::new();
+    /// value.to_writer_with_options(&mut string, &SerializeOptions::new().escape_non_ascii(true)).unwrap();
+    /// assert_eq!(string, r#""bl\u00E5b\u00E6r": "\U01FAD0""#);
+    /// assert_eq!(Value::from_str(&string).unwrap(), value);
+    /// ```
+    pub fn escape_non_ascii(mut self, escape_non_ascii: bool) -> Self {
+        self.escape_non_ascii = escape_non_ascii;
+        self
+    }
+
+    /// Write escaped characters like in Rust, as `\u{XXXX}` with one to six hex
+    /// digits, instead of as `\uXXXX` or `\UXXXXXX`. This applies to the
+    /// characters escaped by [`SerializeOptions::escape_non_ascii`], and to control
+    /// characters that are not ASCII. The default is `false`.
+    ///
+    /// ```
+    /// # use mason_rs::{SerializeOptions, Value};
+    /// #
+    /// let value = Value::String("blåbær 🫐".into());
+    /// let options = SerializeOptions::new().escape_non_ascii(true).braced_unicode_escapes(true);
+    ///
+    /// let mut string = String::new();
+    /// value.to_writer_with_options(&mut string, &options).unwrap();
+    /// assert_eq!(string, r#""bl\u{E5}b\u{E6}r \u{1FAD0}""#);
+    /// ```
+    pub fn braced_unicode_escapes(mut self, braced_unicode_escapes: bool) -> Self {
+        self.braced_unicode_escapes = braced_unicode_escapes;
+        self
+    }
+
+    /// Write byte strings that are longer than `bytes_per_line` bytes as hex blocks,
+    /// with `bytes_per_line` bytes on each line, which keeps large binary values
+    /// readable. The lines after the first are indented one level deeper than the
+    /// line the byte string starts on. Compact output is always written on one
+    /// line, so it has no hex blocks. Hex blocks can only be parsed with
+    /// [`ParseOptions::hex_blocks`](crate::ParseOptions::hex_blocks). The default
+    /// is `None`.
+    ///
+    /// ```
+    /// # use mason_rs::{ParseOptions, SerializeOptions, Value};
+    /// # use std::str::FromStr;
+    /// #
+    /// let mut value = Value::from_str("key: null").unwrap();
+    /// value["key"] = Value::ByteString((0..10).collect());
+    /// let options = SerializeOptions::new().hex_blocks(Some(8));
+    ///
+    /// let mut string = String::new();
+    /// value.to_writer_with_options(&mut string, &options).unwrap();
+    /// assert_eq!(string, "key: b|00010203 04050607\n    b|0809");
+    ///
+    /// let parsed = ParseOptions::new().hex_blocks(true).parse_str(&string).unwrap();
+    /// assert_eq!(parsed.value, value);
+    /// ```
+    pub fn hex_blocks(mut self, bytes_per_line: Option<usize>) -> Self {
+        self.hex_blocks = bytes_per_line;
+        self
+    }
+
+    /// The line break that lines are ended with. The default is [`LineEnding::Lf`].
+    /// When rewriting an existing file, [`LineEnding::detect`] finds the line ending
+    /// it already uses, so files with Windows line endings keep them.
+    ///
+    /// ```
+    /// # use mason_rs::{LineEnding, SerializeOptions, Value};
+    /// # use std::str::FromStr;
+    /// #
+    /// let source = "a: 1\r\nb: [2, 3]\r\n";
+    /// let value = Value::from_str(source).unwrap();
+    /// let options = SerializeOptions::new()
+    ///     .inline_arrays(false)
+    ///     .line_ending(LineEnding::detect(source));
+    ///
+    /// let mut string = String::new();
+    /// value.to_writer_with_options(&mut string, &options).unwrap();
+    /// assert!(string.contains("b: [\r\n    2\r\n    3\r\n]"));
+    /// assert!(!string.replace("\r\n", "").contains('\n'));
+    /// ```
+    pub fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Write the keys of every object in sorted order. Objects are unordered, so by
+    /// default, their keys are written in an arbitrary order, which can change
+    /// between runs. Sorting them makes the output stable, so it can be diffed.
+    ///
+    /// The serde [`Serializer`](crate::Serializer) sorts the keys of maps, like
+    /// `HashMap`s, but writes the fields of structs in the order they are declared.
+    ///
+    /// ```
+    /// # use mason_rs::{SerializeOptions, Value};
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str("c: 3, a: {z: 1, y: 2}, b: 2").unwrap();
+    ///
+    /// let mut string = String::new();
+    /// value.to_writer_with_options(&mut string, &SerializeOptions::new().sort_keys(true)).unwrap();
+    /// assert_eq!(string, "a: {\n    y: 2\n    z: 1\n}\nb: 2\nc: 3");
+    /// ```
+    pub fn sort_keys(mut self, sort_keys: bool) -> Self {
+        self.sort_keys = sort_keys;
+        self
+    }
+
+    /// Write integers in decimal without digit separators, like `255` for `0xff`
+    /// and `1000000` for `1'000'000`, and other numbers in their shortest form.
+    /// The default is `false`.
+    ///
+    /// Numbers are only written differently from how they were parsed with the
+    /// `arbitrary_precision` feature, which remembers their literals, so without
+    /// it, this does nothing.
+    pub fn normalize_numbers(mut self, normalize_numbers: bool) -> Self {
+        self.normalize_numbers = normalize_numbers;
+        self
+    }
+
+    /// Options that write every value one canonical way, so documents with the same
+    /// values are written the same way, no matter how they were formatted. Keys are
+    /// sorted and numbers are normalized, see [`SerializeOptions::sort_keys`] and
+    /// [`SerializeOptions::normalize_numbers`]. Strings are always written the same
+    /// way, whether they were raw, multi-line or regular strings.
+    ///
+    /// ```
+    /// # use mason_rs::{SerializeOptions, Value};
+    /// # use std::str::FromStr;
+    /// #
+    /// let a = Value::from_str("b: r\"raw\", a: [1, 2]").unwrap();
+    /// let b = Value::from_str("{ a: [1,2,], b: \"raw\" }").unwrap();
+    /// let to_string = |value: &Value| {
+    ///     let mut string = String::new();
+    ///     value.to_writer_with_options(&mut string, &SerializeOptions::canonical()).unwrap();
+    ///     string
+    /// };
+    /// assert_eq!(to_string(&a), "a: [1, 2]\nb: \"raw\"");
+    /// assert_eq!(to_string(&a), to_string(&b));
+    /// ```
+    pub fn canonical() -> Self {
+        Self::new().sort_keys(true).normalize_numbers(true)
+    }
+
+    /// What to write after an item that is followed by a line break.
+    pub(crate) fn separator(&self, last: bool) -> &'static str {
+        match (self.separator_style, last) {
+            (SeparatorStyle::Newline, _) | (SeparatorStyle::Comma, true) => "",
+            (SeparatorStyle::Comma | SeparatorStyle::TrailingComma, _) => ",",
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt::{self, Write};
+
+use rayon::prelude::*;
+
+use super::{
+    Child, SerializeOptions, WriteHooks, children, write_compact_value, write_compact_value_with,
+    write_indented_value, write_indented_value_with,
+};
+use crate::Value;
+
+/// Containers with fewer values than this are written on the current thread, since
+/// splitting up small amounts of work costs more than it saves.
+const PARALLEL_THRESHOLD: usize = 64;
+
+/// Write `value` as described by `options`, like [`write_value`](super::write_value),
+/// but write the values inside large arrays and objects on several threads.
+pub(crate) fn write_value_parallel(
+    value: &Value,
+    w: &mut String,
+    options: &SerializeOptions,
+) -> fmt::Result {
+    let top_level = !options.braced_root;
+    let mut hooks = ParallelHooks {
+        options,
+        written: Vec::new(),
+    };
+    if options.compact {
+        write_compact_value_with(value, w, top_level, options, &mut hooks)
+    } else {
+        write_indented_value_with(value, w, options, 0, top_level, &mut hooks)
+    }
+}
+
+/// Hooks that write the values inside large containers on several threads, once
+/// the first of them is reached.
+struct ParallelHooks<'o> {
+    options: &'o SerializeOptions,
+    /// The large containers whose values are being written, with the values
+    /// written in advance.
+    written: Vec<(*const Value, Vec<String>)>,
+}
+
+impl WriteHooks<String> for ParallelHooks<'_> {
+    fn enter(&mut self, child: &Child, w: &mut String) -> Result<bool, fmt::Error> {
+        let &Child {
+            parent,
+            index,
+            indentation_level,
+            ..
+        } = child;
+        let options = self.options;
+        if index == 0 {
+            // the values inside a small container can still be large
+            let children = children(parent, options);
+            if children.len() < PARALLEL_THRESHOLD {
+                return Ok(false);
+            }
+            // all the values inside a container have the same indentation level,
+            // which is known once the first of them is reached
+            let written = write_all(&children, |child, w| {
+                if options.compact {
+                    write_compact_value(child, w, false, options)
+                } else {
+                    write_indented_value(child, w, options, indentation_level, false)
+                }
+            })?;
+            self.written.push((parent as *const Value, written));
+        }
+
+        let Some((container, written)) = self.written.last() else {
+            return Ok(false);
+        };
+        if !std::ptr::eq(*container, parent) {
+            return Ok(false);
+        }
+        w.write_str(&written[index])?;
+        if index + 1 == written.len() {
+            self.written.pop();
+        }
+        Ok(true)
+    }
+}
+
+/// Write each of `values` into its own string with `write`, in parallel.
+fn write_all<F>(values: &[&Value], write: F) -> Result<Vec<String>, fmt::Error>
+where
+    F: Fn(&Value, &mut String) -> fmt::Result + Sync,
+{
+    values
+        .par_iter()
+        .map(|value| {
+            let mut string = String::new();
+            write(value, &mut string).map(|()| string)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialize::write_value;
+
+    /// A value with large and small arrays and objects, nested inside each other.
+    fn large_value() -> Value {
+        let mut object = crate::Map::new();
+        for i in 0..200 {
+            let mut entry = crate::Map::new();
+            entry.insert("id", Value::from(f64::from(i)));
+            entry.insert(
+                "tags",
+                Value::Array(
+                    (0..i % 80)
+                        .map(|j| Value::String(format!("t{j}").into()))
+                        .collect(),
+                ),
+            );
+            object.insert(
+                format!("entry{i}"),
+                Value::Tagged("entry".into(), Box::new(Value::Object(entry))),
+            );
+        }
+        object.insert("empty", Value::Array(Vec::new()));
+        Value::Object(object)
+    }
+
+    #[test]
+    fn test_write_value_parallel() {
+        let value = large_value();
+        for options in [
+            SerializeOptions::new(),
+            SerializeOptions::new().compact(true),
+            SerializeOptions::new().braced_root(true),
+            SerializeOptions::new().inline_arrays(false),
+            SerializeOptions::new().sort_keys(true).indentation("\t"),
+            SerializeOptions::new().compact(true).sort_keys(true),
+        ] {
+            let mut expected = String::new();
+            write_value(&value, &mut expected, &options).unwrap();
+            let mut parallel = String::new();
+            write_value_parallel(&value, &mut parallel, &options).unwrap();
+            assert_eq!(parallel, expected, "{options:?}");
+
+            let array = Value::Array(vec![value.clone(), Value::Null]);
+            expected.clear();
+            write_value(&array, &mut expected, &options).unwrap();
+            parallel.clear();
+            write_value_parallel(&array, &mut parallel, &options).unwrap();
+            assert_eq!(parallel, expected, "{options:?}");
+        }
+    }
+}
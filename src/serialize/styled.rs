@@ -0,0 +1,214 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Write},
+};
+
+use crate::{
+    LineEnding, SeparatorStyle, SerializeOptions, StringStyle, Value, text::unescape_string,
+    utils::push_key,
+};
+
+use super::{Child, WriteHooks, write_compact_value_with, write_indented_value_with};
+
+/// Write `value` as described by `options`, but write the strings at the paths in
+/// `styles` in the style they were parsed with, where that is possible.
+pub(crate) fn write_styled_value<W: Write>(
+    value: &Value,
+    w: &mut W,
+    options: &SerializeOptions,
+    styles: &HashMap<String, StringStyle>,
+) -> fmt::Result {
+    let top_level = !options.braced_root;
+    let mut writer = StyledWriter {
+        options,
+        styles,
+        path: String::new(),
+        lengths: Vec::new(),
+    };
+    let continuation = (!options.compact).then_some("");
+    if writer.write_string(value, w, continuation, "")? {
+        return Ok(());
+    }
+    if options.compact {
+        write_compact_value_with(value, w, top_level, options, &mut writer)
+    } else {
+        write_indented_value_with(value, w, options, 0, top_level, &mut writer)
+    }
+}
+
+struct StyledWriter<'a> {
+    options: &'a SerializeOptions,
+    styles: &'a HashMap<String, StringStyle>,
+    /// The path of the value that is being written.
+    path: String,
+    /// The lengths of `path` before the values that are being written were entered.
+    lengths: Vec<usize>,
+}
+
+impl<W: Write> WriteHooks<W> for StyledWriter<'_> {
+    fn enter(&mut self, child: &Child, w: &mut W) -> Result<bool, fmt::Error> {
+        let options = self.options;
+        let length = self.path.len();
+        // if the value is on a line of its own, the lines after the first line of a
+        // multi-line string are indented like it
+        let continuation = match child.parent {
+            Value::Object(_) => {
+                push_key(&mut self.path, child.key.unwrap_or_default());
+                (!options.compact).then(|| options.indentation.repeat(child.indentation_level + 1))
+            }
+            Value::Array(_) => {
+                push_key(&mut self.path, &child.index.to_string());
+                child
+                    .own_line
+                    .then(|| options.indentation.repeat(child.indentation_level))
+            }
+            // the value inside a tagged value has the same path
+            _ => None,
+        };
+        if self.write_string(child.value, w, continuation.as_deref(), &self.path)? {
+            self.path.truncate(length);
+            return Ok(true);
+        }
+        self.lengths.push(length);
+        Ok(false)
+    }
+
+    fn exit(&mut self, _parent: &Value, _w: &mut W) -> fmt::Result {
+        if let Some(length) = self.lengths.pop() {
+            self.path.truncate(length);
+        }
+        Ok(())
+    }
+}
+
+impl StyledWriter<'_> {
+    /// If `value` is a string with a recorded style that can represent it here,
+    /// write it in that style and return true.
+    fn write_string<W: Write>(
+        &self,
+        value: &Value,
+        w: &mut W,
+        continuation: Option<&str>,
+        path: &str,
+    ) -> Result<bool, fmt::Error> {
+        let (Value::String(string), Some(style)) = (value, self.styles.get(path)) else {
+            return Ok(false);
+        };
+        let string: &str = string;
+        let options = self.options;
+        // escaping non-ASCII characters takes priority over the style
+        let ascii = |text: &str| !options.escape_non_ascii || text.is_ascii();
+
+        match style {
+            StringStyle::Escaped(literal) => {
+                let unchanged = unescape_string(literal.as_bytes())
+                    .is_ok_and(|unescaped| unescaped == string.as_bytes());
+                if !(unchanged && ascii(literal)) {
+                    return Ok(false);
+                }
+                write!(w, "\"{literal}\"")?;
+            }
+            StringStyle::Raw(hashes) => {
+                // line breaks in raw strings would change if the output is indented
+                let hashes = "#".repeat(*hashes);
+                let closes = string.contains(&format!("\"{hashes}"));
+                if closes || !ascii(string) || string.contains('\n') {
+                    return Ok(false);
+                }
+                write!(w, "r{hashes}\"{string}\"{hashes}")?;
+            }
+            StringStyle::MultiLine => {
+                // a separator or a carriage return after the last line would become
+                // part of the string
+                let Some(continuation) = continuation else {
+                    return Ok(false);
+                };
+                if options.separator_style != SeparatorStyle::Newline
+                    || options.line_ending != LineEnding::Lf
+                    || string.contains('\r')
+                    || !ascii(string)
+                {
+                    return Ok(false);
+                }
+                let mut lines = string.split('\n');
+                write!(w, "|{}", lines.next().unwrap_or_default())?;
+                for line in lines {
+                    write!(w, "\n{continuation}|{line}")?;
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseOptions;
+
+    fn reformat(source: &str, options: &SerializeOptions) -> String {
+        let parsed = ParseOptions::new()
+            .capture_string_styles(true)
+            .parse_str(source)
+            .unwrap();
+        let mut string = String::new();
+        parsed.to_writer_with_options(&mut string, options).unwrap();
+        assert_eq!(
+            ParseOptions::new().parse_str(&string).unwrap().value,
+            parsed.value,
+            "{string}"
+        );
+        string
+    }
+
+    #[test]
+    fn test_string_styles() {
+        let source =
+            "a: \"\\u00E6\\t\"\nb: [r##\"x\"#\"##, |one\n  |two\n]\nc: {d: |x\n|\n}\ne: \"plain\"";
+        let options = SerializeOptions::new().sort_keys(true).inline_arrays(false);
+        assert_eq!(
+            reformat(source, &options),
+            "a: \"\\u00E6\\t\"\nb: [\n    r##\"x\"#\"##\n    |one\n    |two\n]\nc: {\n    d: |x\n        |\n}\ne: \"plain\""
+        );
+
+        // multi-line strings can not be kept in arrays on one line, or with commas
+        let source = "a: [|x\n|y\n]";
+        assert_eq!(reformat(source, &SerializeOptions::new()), "a: [\"x\\ny\"]");
+        assert_eq!(
+            reformat(
+                "a: |x\n|y\nb: 1",
+                &SerializeOptions::new()
+                    .separator_style(SeparatorStyle::TrailingComma)
+                    .sort_keys(true)
+            ),
+            "a: \"x\\ny\",\nb: 1"
+        );
+
+        let options = SerializeOptions::new().compact(true).sort_keys(true);
+        assert_eq!(
+            reformat("a: |x\n|y\nb: r\"q\"\nc: \"\\x41\"", &options),
+            "a: \"x\\ny\", b: r\"q\", c: \"\\x41\""
+        );
+        let options = options.escape_non_ascii(true);
+        assert_eq!(
+            reformat("a: r\"æ\"\nb: \"æ\\n\"\nc: \"\\u{E6}\"", &options),
+            "a: \"\\u00E6\", b: \"\\u00E6\\n\", c: \"\\u{E6}\""
+        );
+    }
+
+    #[test]
+    fn test_changed_strings() {
+        let mut parsed = ParseOptions::new()
+            .capture_string_styles(true)
+            .parse_str("a: \"\\x41\"\nb: r#\"q\"#\nc: [\"\\n\"]")
+            .unwrap();
+        parsed.value["a"] = Value::String("B".into());
+        parsed.value["b"] = Value::String("\"#".into());
+        let mut string = String::new();
+        let options = SerializeOptions::new().sort_keys(true);
+        parsed
+            .to_writer_with_options(&mut string, &options)
+            .unwrap();
+        assert_eq!(string, "a: \"B\"\nb: \"\\\"#\"\nc: [\"\\n\"]");
+    }
+}
@@ -112,7 +112,7 @@ fn deep_equals(json: &JsonValue, mason: &MasonValue) -> bool {
         (JsonValue::Null, MasonValue::Null) => true,
         (JsonValue::Bool(bool1), MasonValue::Bool(bool2)) => bool1 == bool2,
         (JsonValue::Number(number1), MasonValue::Number(number2)) => {
-            number1.as_f64().is_some_and(|number1| number1 == *number2)
+            number1.as_f64() == Some(number2.as_f64())
         }
         (JsonValue::String(string1), MasonValue::String(string2)) => string1 == string2,
         (JsonValue::String(string1), MasonValue::ByteString(string2)) => {
@@ -134,7 +134,7 @@ fn deep_equals(json: &JsonValue, mason: &MasonValue) -> bool {
                 return false;
             }
             for (key, value1) in object1 {
-                let Some(value2) = object2.get(key) else {
+                let Some(value2) = object2.get(key.as_str()) else {
                     return false;
                 };
                 if !deep_equals(value1, value2) {
@@ -142,7 +142,7 @@ fn deep_equals(json: &JsonValue, mason: &MasonValue) -> bool {
                 }
             }
             for (key, value2) in object2 {
-                let Some(value1) = object1.get(key) else {
+                let Some(value1) = object1.get(key.as_str()) else {
                     return false;
                 };
                 if !deep_equals(value1, value2) {
@@ -0,0 +1,446 @@
+//! Escape and unescape the contents of MASON string literals, without parsing a
+//! whole document.
+//!
+//! ```
+//! # use mason_rs::text::{escape_string, unescape_string};
+//! #
+//! let escaped = escape_string("say \"hi\"\n");
+//! assert_eq!(escaped, r#"say \"hi\"\n"#);
+//! assert_eq!(unescape_string(escaped.as_bytes()).unwrap(), "say \"hi\"\n".as_bytes());
+//! ```
+
+use std::{
+    borrow::Cow,
+    fmt::{self, Write},
+};
+
+use crate::{Error, ErrorKind, Result, hex::decode_hex, utils};
+
+fn invalid_escape(message: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidEscape, message)
+}
+
+/// Returns a byte string where all escaped characters in the input byte string
+/// are unescaped. If there is nothing to unescape, the input is borrowed.
+///
+/// All of MASON's escape sequences are supported: `\n`, `\r`, `\t`, `\b`, `\f`,
+/// `\'`, `\"`, `\\`, `\/`, `\xXX` for a single byte, `\uXXXX` for a character
+/// in the basic multilingual plane, or a UTF-16 surrogate pair like
+/// `\uD83D\uDE43`, and `\UXXXXXX` or `\u{X}` with one to six hex digits, like in
+/// Rust, for any character. Since `\x` can produce any byte, the result is not
+/// necessarily valid UTF-8.
+///
+/// # Errors
+///
+/// This function fails with [`ErrorKind::InvalidEscape`] if an escape sequence
+/// is unknown, incomplete or does not describe a valid character.
+///
+/// ```
+/// # use mason_rs::text::unescape_string;
+/// #
+/// assert_eq!(unescape_string(br"\x41\u00E6\uD83D\uDE43").unwrap(), "Aæ🙃".as_bytes());
+/// assert_eq!(unescape_string(br"\xff").unwrap(), [0xff].as_slice());
+/// assert!(unescape_string(br"\uD83D").is_err());
+/// assert_eq!(unescape_string(br"\u{41}\u{1F643}").unwrap(), "A🙃".as_bytes());
+/// ```
+pub fn unescape_string(bytes: &[u8]) -> Result<Cow<'_, [u8]>> {
+    unescape(bytes, true)
+}
+
+/// Unescape `bytes` like [`unescape_string`], but only accept `\u{X}` escapes if
+/// `braced_unicode_escapes` is set.
+pub(crate) fn unescape(bytes: &[u8], braced_unicode_escapes: bool) -> Result<Cow<'_, [u8]>> {
+    if !bytes.contains(&b'\\') {
+        return Ok(Cow::Borrowed(bytes));
+    }
+    let mut new_bytes = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        if byte == b'\\' && i + 1 < bytes.len() {
+            match bytes[i + 1] {
+                b'n' => {
+                    new_bytes.push(b'\n');
+                    i += 2;
+                }
+                b'r' => {
+                    new_bytes.push(b'\r');
+                    i += 2;
+                }
+                b't' => {
+                    new_bytes.push(b'\t');
+                    i += 2;
+                }
+                b'b' => {
+                    // backspace
+                    new_bytes.push(0x8);
+                    i += 2;
+                }
+                b'f' => {
+                    // form feed
+                    new_bytes.push(0xC);
+                    i += 2;
+                }
+                b'\'' => {
+                    new_bytes.push(b'\'');
+                    i += 2;
+                }
+                b'\"' => {
+                    new_bytes.push(b'\"');
+                    i += 2;
+                }
+                b'\\' => {
+                    new_bytes.push(b'\\');
+                    i += 2;
+                }
+                b'/' => {
+                    new_bytes.push(b'/');
+                    i += 2;
+                }
+                b'x' => {
+                    if i + 3 >= bytes.len() {
+                        return Err(invalid_escape("Got incomplete hex escape sequence"));
+                    }
+
+                    match decode_hex([bytes[i + 2], bytes[i + 3]]) {
+                        Some(value) => {
+                            new_bytes.push(value);
+                            i += 4;
+                        }
+                        None => {
+                            return Err(invalid_escape(format!(
+                                "Got invalid \\x hex {}",
+                                String::from_utf8_lossy(&bytes[(i + 2)..=(i + 3)])
+                            )));
+                        }
+                    }
+                }
+                b'u' => {
+                    let (c, read) = parse_unicode_code_point(&bytes[i..], braced_unicode_escapes)?;
+                    i += read;
+
+                    let mut c_utf8 = vec![0; c.len_utf8()];
+                    c.encode_utf8(&mut c_utf8);
+                    new_bytes.append(&mut c_utf8);
+                }
+                b'U' => {
+                    if i + 7 >= bytes.len() {
+                        return Err(invalid_escape(
+                            "Got incomplete non-BMP unicode escape sequence",
+                        ));
+                    }
+
+                    let byte1 = decode_hex([bytes[i + 2], bytes[i + 3]]);
+                    let byte2 = decode_hex([bytes[i + 4], bytes[i + 5]]);
+                    let byte3 = decode_hex([bytes[i + 6], bytes[i + 7]]);
+
+                    match (byte1, byte2, byte3) {
+                        (Some(byte1), Some(byte2), Some(byte3)) => {
+                            let num = u32::from_be_bytes([0, byte1, byte2, byte3]);
+                            let Some(c) = char::from_u32(num) else {
+                                return Err(invalid_escape(format!(
+                                    "Got invalid unicode code point \\U{} = {num}",
+                                    String::from_utf8_lossy(&bytes[(i + 2)..=(i + 7)])
+                                )));
+                            };
+
+                            let mut c_utf8 = vec![0; c.len_utf8()];
+                            c.encode_utf8(&mut c_utf8);
+                            new_bytes.append(&mut c_utf8);
+                            i += 8;
+                        }
+                        _ => {
+                            return Err(invalid_escape(format!(
+                                "Got invalid \\U hex {}",
+                                String::from_utf8_lossy(&bytes[(i + 2)..=(i + 7)])
+                            )));
+                        }
+                    }
+                }
+                x => {
+                    return Err(invalid_escape(format!(
+                        "Unexpected escape sequence: \\{}",
+                        utils::to_char(x)
+                    )));
+                }
+            }
+        } else {
+            new_bytes.push(byte);
+            i += 1;
+        }
+    }
+
+    Ok(Cow::Owned(new_bytes))
+}
+
+/// Parse a `\uXXXX` escape at the start of `bytes`, or a surrogate pair of two
+/// of them, or a `\u{X}` escape if `braced` is set. Returns the character and the
+/// number of bytes it was escaped with.
+fn parse_unicode_code_point(bytes: &[u8], braced: bool) -> Result<(char, usize)> {
+    if braced && bytes.get(2) == Some(&b'{') {
+        return parse_braced_code_point(bytes);
+    }
+    if bytes.len() < 6 {
+        return Err(invalid_escape("Got incomplete unicode escape sequence"));
+    }
+
+    let byte1 = decode_hex([bytes[2], bytes[3]]);
+    let byte2 = decode_hex([bytes[4], bytes[5]]);
+    let (Some(byte1), Some(byte2)) = (byte1, byte2) else {
+        return Err(invalid_escape(format!(
+            "Got invalid \\u hex {}",
+            String::from_utf8_lossy(&bytes[2..=5])
+        )));
+    };
+
+    let num = u16::from_be_bytes([byte1, byte2]);
+    if let Some(c) = char::from_u32(num.into()) {
+        return Ok((c, 6));
+    }
+
+    // was this the first part of a utf16 surrogate pair?
+    let high = num;
+    if !(0xD800..0xDC00).contains(&high) || bytes.len() < 12 || &bytes[6..=7] != b"\\u" {
+        return Err(invalid_escape(format!(
+            "Got invalid unicode code point \\u{} = {num}",
+            String::from_utf8_lossy(&bytes[2..=5])
+        )));
+    }
+
+    let byte3 = decode_hex([bytes[8], bytes[9]]);
+    let byte4 = decode_hex([bytes[10], bytes[11]]);
+    let (Some(byte3), Some(byte4)) = (byte3, byte4) else {
+        return Err(invalid_escape(format!(
+            "Got invalid \\u hex {}",
+            String::from_utf8_lossy(&bytes[8..=11])
+        )));
+    };
+
+    let low = u16::from_be_bytes([byte3, byte4]);
+    match char::decode_utf16([high, low]).next() {
+        Some(Ok(c)) => Ok((c, 12)),
+        _ => Err(invalid_escape(format!(
+            "Got invalid utf16 surrogate pair \\u{}\\u{}",
+            String::from_utf8_lossy(&bytes[2..=5]),
+            String::from_utf8_lossy(&bytes[8..=11]),
+        ))),
+    }
+}
+
+/// Parse a `\u{X}` escape with one to six hex digits at the start of `bytes`.
+/// Returns the character and the number of bytes it was escaped with.
+fn parse_braced_code_point(bytes: &[u8]) -> Result<(char, usize)> {
+    let Some(len) = bytes.iter().position(|&byte| byte == b'}') else {
+        return Err(invalid_escape("Got incomplete unicode escape sequence"));
+    };
+    let digits = &bytes[3..len];
+    if digits.is_empty() || digits.len() > 6 || !digits.iter().all(u8::is_ascii_hexdigit) {
+        return Err(invalid_escape(format!(
+            "Got invalid \\u{{}} hex {}",
+            String::from_utf8_lossy(digits)
+        )));
+    }
+
+    let num = digits.iter().fold(0, |num, &digit| {
+        num * 16 + char::from(digit).to_digit(16).unwrap_or_default()
+    });
+    match char::from_u32(num) {
+        Some(c) => Ok((c, len + 1)),
+        None => Err(invalid_escape(format!(
+            "Got invalid unicode code point \\u{{{}}} = {num}",
+            String::from_utf8_lossy(digits)
+        ))),
+    }
+}
+
+/// Returns `string` with quotes, backslashes and control characters escaped, so
+/// it can be put between quotes to make a MASON string literal. If there is
+/// nothing to escape, the input is borrowed. [`unescape_string`] reverses this.
+///
+/// Newlines, carriage returns, tabs, backspaces and form feeds are written as
+/// `\n`, `\r`, `\t`, `\b` and `\f`, and other control characters as `\xXX`.
+///
+/// ```
+/// # use mason_rs::text::escape_string;
+/// #
+/// assert_eq!(escape_string("plain"), "plain");
+/// assert_eq!(escape_string("C:\\path\t\u{7}"), r"C:\\path\t\x07");
+/// ```
+pub fn escape_string(string: &str) -> Cow<'_, str> {
+    escape(string, false, UnicodeEscape::Short)
+}
+
+/// Like [`escape_string`], but all non-ASCII characters are escaped too, so the
+/// result is pure ASCII. Characters in the basic multilingual plane are written
+/// as `\uXXXX`, and the rest as `\UXXXXXX`, or as a UTF-16 surrogate pair
+/// `\uXXXX\uXXXX` if `surrogate_pairs` is true, which JSON can also read.
+///
+/// ```
+/// # use mason_rs::text::escape_ascii;
+/// #
+/// assert_eq!(escape_ascii("æ 🙃", false), r"\u00E6 \U01F643");
+/// assert_eq!(escape_ascii("æ 🙃", true), r"\u00E6 \uD83D\uDE43");
+/// ```
+pub fn escape_ascii(string: &str, surrogate_pairs: bool) -> Cow<'_, str> {
+    let style = if surrogate_pairs {
+        UnicodeEscape::SurrogatePairs
+    } else {
+        UnicodeEscape::Short
+    };
+    escape(string, true, style)
+}
+
+/// How characters that are not escaped as ASCII are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UnicodeEscape {
+    /// `\uXXXX` in the basic multilingual plane, and `\UXXXXXX` outside it.
+    Short,
+    /// `\uXXXX` in the basic multilingual plane, and `\uXXXX\uXXXX` outside it.
+    SurrogatePairs,
+    /// `\u{X}` with one to six hex digits, like in Rust.
+    Braced,
+}
+
+/// Escape `string`, and if `ascii` is true, escape non-ASCII characters too.
+pub(crate) fn escape(string: &str, ascii: bool, style: UnicodeEscape) -> Cow<'_, str> {
+    let needs_escape =
+        |c: char| matches!(c, '"' | '\\') || c.is_control() || (ascii && !c.is_ascii());
+    if !string.chars().any(needs_escape) {
+        return Cow::Borrowed(string);
+    }
+
+    let mut escaped = String::with_capacity(string.len() + 2);
+    for c in string.chars() {
+        push_escaped(&mut escaped, c, ascii, style);
+    }
+    Cow::Owned(escaped)
+}
+
+fn push_escaped(escaped: &mut String, c: char, ascii: bool, style: UnicodeEscape) {
+    // writing to a string can not fail
+    let _ = match c {
+        '"' | '\\' => write!(escaped, "\\{c}"),
+        '\n' => write!(escaped, "\\n"),
+        '\r' => write!(escaped, "\\r"),
+        '\t' => write!(escaped, "\\t"),
+        '\u{8}' => write!(escaped, "\\b"),
+        '\u{C}' => write!(escaped, "\\f"),
+        c if c.is_ascii_control() => write!(escaped, "\\x{:02X}", u32::from(c)),
+        // C1 control characters are more than one byte long in UTF-8
+        c if c.is_control() || (ascii && !c.is_ascii()) => push_unicode_escape(escaped, c, style),
+        c => write!(escaped, "{c}"),
+    };
+}
+
+fn push_unicode_escape(escaped: &mut String, c: char, style: UnicodeEscape) -> fmt::Result {
+    let code = u32::from(c);
+    match style {
+        UnicodeEscape::Braced => write!(escaped, "\\u{{{code:X}}}"),
+        _ if code <= 0xFFFF => write!(escaped, "\\u{code:04X}"),
+        UnicodeEscape::SurrogatePairs => {
+            let mut units = [0; 2];
+            let [high, low] = c.encode_utf16(&mut units) else {
+                unreachable!("characters outside the basic multilingual plane are two units");
+            };
+            write!(escaped, "\\u{high:04X}\\u{low:04X}")
+        }
+        UnicodeEscape::Short => write!(escaped, "\\U{code:06X}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unescape_string() {
+        let escaped_string = "this\\t is \\n a string \\x00 with \\\" special \
+        \\xf0\\x9f\\x8f\\xb3\\xef\\xb8\\x8f\\xe2\\x80\\x8d\\xe2\\x9a\\xa7\\xef\\xb8\\x8f \
+        characters! \\u3061\\U003053 \\uD83D\\uDE43";
+        let unescaped_string = "this\t is \n a string \0 with \" special 🏳️‍⚧️ characters! ちこ 🙃";
+        match unescape_string(escaped_string.as_bytes()) {
+            Ok(string) => assert_eq!(
+                String::from_utf8(string.to_vec()).unwrap(),
+                unescaped_string
+            ),
+            Err(err) => panic!("unescape_string failed: {err}"),
+        }
+
+        let simple_string = "this is a string with normal characters!";
+        match unescape_string(simple_string.as_bytes()) {
+            Ok(string) => assert_eq!(String::from_utf8(string.to_vec()).unwrap(), simple_string),
+            Err(err) => panic!("unescape_string failed: {err}"),
+        }
+    }
+
+    #[test]
+    fn test_unescape_surrogate_pairs() {
+        assert_eq!(unescape_string(br"\uD834\uDD1E").unwrap(), "𝄞".as_bytes());
+        for invalid in [
+            &br"\uD834"[..],
+            br"\uD834 ",
+            br"\uD834\u0041",
+            br"\uD834\uD834",
+            br"\uD834\uE000",
+            br"\uDD1E\uDD1E",
+            br"\uDD1E",
+            br"\U110000",
+            br"\uD83",
+        ] {
+            let err = unescape_string(invalid).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidEscape, "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_unescape_braced() {
+        assert_eq!(
+            unescape_string(br"\u{0}\u{e6}\u{00E6}\u{10FFFF}x").unwrap(),
+            "\0ææ\u{10FFFF}x".as_bytes()
+        );
+        for invalid in [
+            &br"\u{}"[..],
+            br"\u{",
+            br"\u{41",
+            br"\u{1234567}",
+            br"\u{g}",
+            br"\u{ 41}",
+            br"\u{D83D}",
+            br"\u{110000}",
+        ] {
+            let err = unescape_string(invalid).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidEscape, "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_escape_string() {
+        let string = "\"quoted\" C:\\path\n\r\t\u{8}\u{C}\0\u{1B}\u{7F}\u{85} æ 🙃";
+        let escaped = escape_string(string);
+        assert_eq!(
+            escaped,
+            r#"\"quoted\" C:\\path\n\r\t\b\f\x00\x1B\x7F\u0085 æ 🙃"#
+        );
+        assert_eq!(
+            escape_ascii(string, false),
+            r#"\"quoted\" C:\\path\n\r\t\b\f\x00\x1B\x7F\u0085 \u00E6 \U01F643"#
+        );
+        assert_eq!(
+            escape_ascii(string, true),
+            r#"\"quoted\" C:\\path\n\r\t\b\f\x00\x1B\x7F\u0085 \u00E6 \uD83D\uDE43"#
+        );
+
+        for surrogate_pairs in [false, true] {
+            for escaped in [escape_string(string), escape_ascii(string, surrogate_pairs)] {
+                assert_eq!(
+                    unescape_string(escaped.as_bytes()).unwrap(),
+                    string.as_bytes()
+                );
+            }
+        }
+
+        assert!(matches!(escape_string("æ 🙃"), Cow::Borrowed(_)));
+        assert!(matches!(escape_ascii("plain", true), Cow::Borrowed(_)));
+    }
+}
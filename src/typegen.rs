@@ -0,0 +1,356 @@
+//! Generate Rust type definitions from an example MASON document.
+//!
+//! This is meant for bootstrapping typed configuration loading from an existing
+//! configuration file: the generated structs derive serde's `Serialize` and
+//! `Deserialize`, so the file can be loaded with [`from_str`](crate::from_str)
+//! and friends. The types are inferred from the values in the document:
+//!
+//! - Objects become structs, named after their key, and arrays become `Vec`s.
+//!   Objects in the same array are merged into one struct, where fields that are
+//!   missing from some of the objects are optional.
+//! - Integers become `i64`, other numbers `f64`, and byte strings `Vec<u8>`.
+//! - Values that are `null` somewhere become `Option`s.
+//! - Values whose type can not be inferred, like tagged values, elements of
+//!   empty arrays and values of different types in the same array, become
+//!   [`mason_rs::Value`](crate::Value).
+//!
+//! The result is a starting point that will often need some editing, like
+//! replacing `i64` with a smaller integer type.
+//!
+//! ```
+//! # use mason_rs::{Value, typegen::generate};
+//! # use std::str::FromStr;
+//! #
+//! let value = Value::from_str(r#"
+//! name: "app"
+//! servers: [
+//!     {host: "a.example", port: 80}
+//!     {host: "b.example", port: 8080, "tls-cert": "b.pem"}
+//! ]
+//! "#).unwrap();
+//!
+//! assert_eq!(generate(&value, "Config"), "\
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, Clone, Serialize, Deserialize)]
+//! pub struct Config {
+//!     pub name: String,
+//!     pub servers: Vec<Server>,
+//! }
+//!
+//! #[derive(Debug, Clone, Serialize, Deserialize)]
+//! pub struct Server {
+//!     pub host: String,
+//!     pub port: i64,
+//!     #[serde(rename = \"tls-cert\")]
+//!     pub tls_cert: Option<String>,
+//! }
+//! ");
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write,
+    mem,
+};
+
+use crate::Value;
+
+/// The fields of an object, and whether they are in every example of it.
+type Fields = BTreeMap<String, (Shape, bool)>;
+
+/// The type of a value, inferred from one or more examples of it.
+#[derive(Debug, Clone, PartialEq)]
+enum Shape {
+    /// There are no examples, like for the elements of an empty array.
+    Unknown,
+    /// The examples have different types.
+    Any,
+    Null,
+    Bool,
+    Integer,
+    Float,
+    String,
+    Bytes,
+    Array(Box<Shape>),
+    Object(Fields),
+    Optional(Box<Shape>),
+}
+
+impl Shape {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => Self::Null,
+            Value::Bool(_) => Self::Bool,
+            Value::Number(number) => {
+                let number = number.as_f64();
+                if number.fract() == 0.0 && number.abs() < 2f64.powi(53) {
+                    Self::Integer
+                } else {
+                    Self::Float
+                }
+            }
+            Value::String(_) => Self::String,
+            Value::ByteString(_) => Self::Bytes,
+            Value::Array(vec) => Self::Array(Box::new(
+                vec.iter().map(Self::of).fold(Self::Unknown, Self::unify),
+            )),
+            Value::Object(map) => Self::Object(
+                map.iter()
+                    .map(|(key, value)| (key.to_string(), (Self::of(value), true)))
+                    .collect(),
+            ),
+            Value::Tagged(..) => Self::Any,
+        }
+    }
+
+    /// The shape of a value that can be an example of either `self` or `other`.
+    fn unify(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Unknown, shape) | (shape, Self::Unknown) => shape,
+            (Self::Null, Self::Null) => Self::Null,
+            (Self::Null, shape) | (shape, Self::Null) => shape.optional(),
+            (Self::Optional(a), b) | (b, Self::Optional(a)) => a.unify(b).optional(),
+            (Self::Integer, Self::Float) | (Self::Float, Self::Integer) => Self::Float,
+            (Self::Array(a), Self::Array(b)) => Self::Array(Box::new(a.unify(*b))),
+            (Self::Object(mut a), Self::Object(mut b)) => {
+                for (key, (shape, required)) in &mut a {
+                    match b.remove(key) {
+                        Some((other, other_required)) => {
+                            *shape = mem::replace(shape, Self::Unknown).unify(other);
+                            *required &= other_required;
+                        }
+                        None => *required = false,
+                    }
+                }
+                a.extend(b.into_iter().map(|(key, (shape, _))| (key, (shape, false))));
+                Self::Object(a)
+            }
+            (a, b) if a == b => a,
+            _ => Self::Any,
+        }
+    }
+
+    fn optional(self) -> Self {
+        match self {
+            Self::Optional(_) | Self::Any | Self::Null => self,
+            shape => Self::Optional(Box::new(shape)),
+        }
+    }
+}
+
+/// Generate Rust structs with serde derives that can hold `value`, and documents
+/// like it. The struct of the top level object is called `name`, and the other
+/// structs are named after the keys they are found in. If `value` is not an
+/// object, a type alias called `name` is generated instead.
+///
+/// See the [module documentation](self) for how types are inferred.
+pub fn generate(value: &Value, name: &str) -> String {
+    let mut generator = Generator::default();
+    let root = Shape::of(value);
+    let root_name = generator.struct_name(name);
+    let mut output = String::from("use serde::{Deserialize, Serialize};\n");
+    match root {
+        Shape::Object(fields) => generator.pending.push((root_name, fields)),
+        shape => {
+            let rust_type = generator.rust_type(&shape, &root_name);
+            let _ = write!(output, "\npub type {root_name} = {rust_type};\n");
+        }
+    }
+
+    // structs are written in the order they are found
+    let mut i = 0;
+    while let Some((name, fields)) = generator.pending.get(i).cloned() {
+        i += 1;
+        let _ = write!(
+            output,
+            "\n#[derive(Debug, Clone, Serialize, Deserialize)]\npub struct {name} {{\n"
+        );
+        for (key, (shape, required)) in &fields {
+            let field = field_name(key);
+            if field.trim_start_matches("r#") != key {
+                let _ = writeln!(output, "    #[serde(rename = {key:?})]");
+            }
+            let shape = if *required {
+                shape.clone()
+            } else {
+                shape.clone().optional()
+            };
+            let rust_type = generator.rust_type(&shape, key);
+            let _ = writeln!(output, "    pub {field}: {rust_type},");
+        }
+        output.push_str("}\n");
+    }
+    output
+}
+
+#[derive(Default)]
+struct Generator {
+    /// The structs that have been named, but not written yet.
+    pending: Vec<(String, Fields)>,
+    used_names: HashSet<String>,
+}
+
+impl Generator {
+    /// Get the Rust type of `shape`. Objects get a new struct, named after `key`,
+    /// or after its singular form for the elements of an array.
+    fn rust_type(&mut self, shape: &Shape, key: &str) -> String {
+        match shape {
+            Shape::Unknown | Shape::Any => "mason_rs::Value".to_owned(),
+            Shape::Null => "Option<mason_rs::Value>".to_owned(),
+            Shape::Bool => "bool".to_owned(),
+            Shape::Integer => "i64".to_owned(),
+            Shape::Float => "f64".to_owned(),
+            Shape::String => "String".to_owned(),
+            Shape::Bytes => "Vec<u8>".to_owned(),
+            Shape::Array(shape) => format!("Vec<{}>", self.rust_type(shape, &singular_name(key))),
+            Shape::Optional(shape) => format!("Option<{}>", self.rust_type(shape, key)),
+            Shape::Object(fields) => {
+                let name = self.struct_name(key);
+                self.pending.push((name.clone(), fields.clone()));
+                name
+            }
+        }
+    }
+
+    /// Turn `key` into a struct name that has not been used yet.
+    fn struct_name(&mut self, key: &str) -> String {
+        let mut base = String::new();
+        for word in key.split(|c: char| !c.is_alphanumeric()) {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                base.extend(first.to_uppercase());
+                base.push_str(chars.as_str());
+            }
+        }
+        if !base.starts_with(|c: char| c.is_alphabetic()) {
+            base.insert(0, 'T');
+        }
+
+        let mut name = base.clone();
+        let mut n = 1;
+        while !self.used_names.insert(name.clone()) {
+            n += 1;
+            name = format!("{base}{n}");
+        }
+        name
+    }
+}
+
+/// The singular form of `key`, which names the elements of an array, like
+/// `Server` for `servers`. This is only a guess.
+fn singular_name(key: &str) -> String {
+    if let Some(stem) = key.strip_suffix("ies") {
+        format!("{stem}y")
+    } else if key.ends_with('s') && !key.ends_with("ss") && key.len() > 1 {
+        key[..key.len() - 1].to_owned()
+    } else {
+        key.to_owned()
+    }
+}
+
+/// Turn `key` into a snake case field name.
+fn field_name(key: &str) -> String {
+    let mut name = String::new();
+    let mut previous_lowercase = false;
+    for c in key.chars() {
+        if c.is_uppercase() {
+            if previous_lowercase {
+                name.push('_');
+            }
+            name.extend(c.to_lowercase());
+            previous_lowercase = false;
+        } else if c.is_alphanumeric() || c == '_' {
+            name.push(c);
+            previous_lowercase = c.is_lowercase() || c.is_numeric();
+        } else {
+            if !name.is_empty() && !name.ends_with('_') {
+                name.push('_');
+            }
+            previous_lowercase = false;
+        }
+    }
+    if !name.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        name.insert(0, '_');
+    }
+    if KEYWORDS.contains(&name.as_str()) {
+        name.insert_str(0, "r#");
+    }
+    name
+}
+
+/// Rust keywords that can be used as field names if they are raw identifiers.
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while",
+    "abstract", "become", "box", "do", "final", "gen", "macro", "override", "priv", "try",
+    "typeof", "unsized", "virtual", "yield",
+];
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_shapes() {
+        let shape = |string| Shape::of(&Value::from_str(string).unwrap());
+        assert_eq!(shape("[1, 2.5]"), Shape::Array(Box::new(Shape::Float)));
+        assert_eq!(
+            shape("[null, \"a\"]"),
+            Shape::Array(Box::new(Shape::Optional(Box::new(Shape::String))))
+        );
+        assert_eq!(shape("[1, \"a\"]"), Shape::Array(Box::new(Shape::Any)));
+        assert_eq!(shape("[]"), Shape::Array(Box::new(Shape::Unknown)));
+        assert_eq!(
+            shape("[{a: 1}, {a: null, b: true}]"),
+            Shape::Array(Box::new(Shape::Object(BTreeMap::from([
+                (
+                    "a".to_owned(),
+                    (Shape::Optional(Box::new(Shape::Integer)), true)
+                ),
+                ("b".to_owned(), (Shape::Bool, false)),
+            ]))))
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(field_name("camelCase"), "camel_case");
+        assert_eq!(field_name("kebab-case key"), "kebab_case_key");
+        assert_eq!(field_name("type"), "r#type");
+        assert_eq!(field_name("2fa"), "_2fa");
+        assert_eq!(singular_name("entries"), "entry");
+        assert_eq!(singular_name("address"), "address");
+
+        let mut generator = Generator::default();
+        assert_eq!(generator.struct_name("http-server"), "HttpServer");
+        assert_eq!(generator.struct_name("http_server"), "HttpServer2");
+        assert_eq!(generator.struct_name("1"), "T1");
+    }
+
+    #[test]
+    fn test_generate() {
+        let value = Value::from_str(
+            r#"type: "web", limits: {"maxConnections": 100, ratio: 0.5}, data: b"", other: null"#,
+        )
+        .unwrap();
+        let output = generate(&value, "config");
+        assert!(output.contains("pub struct Config {"));
+        assert!(output.contains("    pub r#type: String,"));
+        assert!(output.contains("    pub limits: Limits,"));
+        assert!(output.contains("    pub data: Vec<u8>,"));
+        assert!(output.contains("    pub other: Option<mason_rs::Value>,"));
+        assert!(
+            output.contains(
+                "    #[serde(rename = \"maxConnections\")]\n    pub max_connections: i64,"
+            )
+        );
+        assert!(output.contains("    pub ratio: f64,"));
+
+        let value = Value::from_str("[[1], [2, 3]]").unwrap();
+        assert!(generate(&value, "Matrix").ends_with("\npub type Matrix = Vec<Vec<i64>>;\n"));
+    }
+}
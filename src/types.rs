@@ -0,0 +1,394 @@
+//! Wrapper types for values that are commonly written as strings in MASON.
+//!
+//! With the `serde` feature, these types serialize to and deserialize from
+//! human friendly strings, so they can be used directly in configuration structs.
+//!
+//! ```
+//! # use mason_rs::types::{Duration, Timestamp};
+//! # use serde::Deserialize;
+//! #
+//! #[derive(Deserialize)]
+//! struct Config {
+//!     timeout: Duration,
+//!     created: Timestamp,
+//! }
+//!
+//! let config: Config = mason_rs::from_str(r#"
+//! timeout: "1m 30s"
+//! created: "2024-02-29T12:00:00Z"
+//! "#).unwrap();
+//! assert_eq!(config.timeout.0, std::time::Duration::from_secs(90));
+//! assert_eq!(config.created.to_string(), "2024-02-29T12:00:00Z");
+//! ```
+
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+    time::{self, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Error, value::units};
+
+/// A point in time, written as an ISO-8601 (RFC 3339) string, like
+/// `"2024-02-29T12:00:00Z"` or `"2024-02-29T13:00:00.5+01:00"`.
+///
+/// Timestamps are always formatted in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(pub SystemTime);
+
+/// A length of time, written as a human friendly string, like `"30s"`,
+/// `"1.5h"` or `"1h 30m"`. When deserializing, plain numbers are interpreted as
+/// seconds.
+///
+/// See [`Value::as_duration`](crate::Value::as_duration) for the supported units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Duration(pub time::Duration);
+
+impl From<SystemTime> for Timestamp {
+    fn from(value: SystemTime) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Timestamp> for SystemTime {
+    fn from(value: Timestamp) -> Self {
+        value.0
+    }
+}
+
+impl From<time::Duration> for Duration {
+    fn from(value: time::Duration) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Duration> for time::Duration {
+    fn from(value: Duration) -> Self {
+        value.0
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (secs, nanos) = match self.0.duration_since(UNIX_EPOCH) {
+            Ok(duration) => (duration.as_secs() as i64, duration.subsec_nanos()),
+            Err(err) => {
+                let duration = err.duration();
+                let secs = -(duration.as_secs() as i64);
+                match duration.subsec_nanos() {
+                    0 => (secs, 0),
+                    nanos => (secs - 1, 1_000_000_000 - nanos),
+                }
+            }
+        };
+
+        let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+        let seconds_of_day = secs.rem_euclid(86400);
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+            seconds_of_day / 3600,
+            seconds_of_day / 60 % 60,
+            seconds_of_day % 60,
+        )?;
+        if nanos != 0 {
+            let fraction = format!("{nanos:09}");
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        write!(f, "Z")
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = Error;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        parse_timestamp(string)
+            .map(Self)
+            .ok_or_else(|| Error::syntax(format!("invalid timestamp: {string:?}")))
+    }
+}
+
+impl Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&units::format_duration(self.0))
+    }
+}
+
+impl FromStr for Duration {
+    type Err = Error;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        units::parse_duration(string)
+            .map(Self)
+            .ok_or_else(|| Error::syntax(format!("invalid duration: {string:?}")))
+    }
+}
+
+fn parse_timestamp(string: &str) -> Option<SystemTime> {
+    let string = string.trim();
+    let number = |start: usize, end: usize| -> Option<i64> {
+        let digits = string.get(start..end)?;
+        if !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        digits.parse().ok()
+    };
+    let separator = |index: usize, allowed: &[u8]| {
+        string
+            .as_bytes()
+            .get(index)
+            .is_some_and(|byte| allowed.contains(byte))
+    };
+
+    let year = number(0, 4)?;
+    let month = number(5, 7)?;
+    let day = number(8, 10)?;
+    let hour = number(11, 13)?;
+    let minute = number(14, 16)?;
+    let second = number(17, 19)?;
+    if !(separator(4, b"-")
+        && separator(7, b"-")
+        && separator(10, b"Tt ")
+        && separator(13, b":")
+        && separator(16, b":"))
+    {
+        return None;
+    }
+    if !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return None;
+    }
+
+    let mut rest = &string[19..];
+    let mut nanos = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(fraction.len());
+        if digits == 0 {
+            return None;
+        }
+        // digits beyond nanosecond precision are ignored
+        let padded = format!("{:0<9}", &fraction[..digits.min(9)]);
+        nanos = padded.parse().ok()?;
+        rest = &fraction[digits..];
+    }
+
+    let offset = match rest.as_bytes() {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+            let hours: i64 = rest[1..3].parse().ok()?;
+            let minutes: i64 = rest[4..6].parse().ok()?;
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = hours * 3600 + minutes * 60;
+            if *sign == b'-' { -offset } else { offset }
+        }
+        _ => return None,
+    };
+
+    let secs =
+        days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset;
+    let time = if secs >= 0 {
+        UNIX_EPOCH.checked_add(time::Duration::from_secs(secs as u64))?
+    } else {
+        UNIX_EPOCH.checked_sub(time::Duration::from_secs(secs.unsigned_abs()))?
+    };
+    time.checked_add(time::Duration::from_nanos(nanos))
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The number of days since 1970-01-01 of the given date in the proleptic
+/// Gregorian calendar. See <https://howardhinnant.github.io/date_algorithms.html>.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// The inverse of [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(feature = "serde")]
+mod serde {
+    use std::{fmt, time};
+
+    use serde::{
+        Deserialize, Deserializer, Serialize, Serializer,
+        de::{self, Visitor},
+    };
+
+    use super::{Duration, Timestamp};
+
+    impl Serialize for Timestamp {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Timestamp {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct TimestampVisitor;
+
+            impl Visitor<'_> for TimestampVisitor {
+                type Value = Timestamp;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("an ISO-8601 timestamp")
+                }
+
+                fn visit_str<E: de::Error>(self, value: &str) -> Result<Timestamp, E> {
+                    value.parse().map_err(E::custom)
+                }
+            }
+
+            deserializer.deserialize_str(TimestampVisitor)
+        }
+    }
+
+    impl Serialize for Duration {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Duration {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct DurationVisitor;
+
+            impl Visitor<'_> for DurationVisitor {
+                type Value = Duration;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("a duration string or a number of seconds")
+                }
+
+                fn visit_str<E: de::Error>(self, value: &str) -> Result<Duration, E> {
+                    value.parse().map_err(E::custom)
+                }
+
+                fn visit_u64<E: de::Error>(self, value: u64) -> Result<Duration, E> {
+                    Ok(Duration(time::Duration::from_secs(value)))
+                }
+
+                fn visit_i64<E: de::Error>(self, value: i64) -> Result<Duration, E> {
+                    match u64::try_from(value) {
+                        Ok(value) => self.visit_u64(value),
+                        Err(_) => Err(E::invalid_value(de::Unexpected::Signed(value), &self)),
+                    }
+                }
+
+                fn visit_f64<E: de::Error>(self, value: f64) -> Result<Duration, E> {
+                    time::Duration::try_from_secs_f64(value)
+                        .map(Duration)
+                        .map_err(|_| E::invalid_value(de::Unexpected::Float(value), &self))
+                }
+            }
+
+            deserializer.deserialize_any(DurationVisitor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp() {
+        let timestamp: Timestamp = "1970-01-01T00:00:00Z".parse().unwrap();
+        assert_eq!(timestamp.0, UNIX_EPOCH);
+
+        let timestamp: Timestamp = "2024-02-29T13:30:00.25+01:30".parse().unwrap();
+        assert_eq!(timestamp.to_string(), "2024-02-29T12:00:00.25Z");
+        assert_eq!(
+            timestamp.0.duration_since(UNIX_EPOCH).unwrap(),
+            time::Duration::new(1709208000, 250_000_000)
+        );
+
+        let timestamp: Timestamp = "1969-12-31 23:59:59.5z".parse().unwrap();
+        assert_eq!(timestamp.to_string(), "1969-12-31T23:59:59.5Z");
+        assert_eq!(
+            UNIX_EPOCH.duration_since(timestamp.0).unwrap(),
+            time::Duration::from_millis(500)
+        );
+
+        for invalid in [
+            "2023-02-29T00:00:00Z",
+            "2024-01-01T24:00:00Z",
+            "2024-01-01T00:00:00",
+            "2024-01-01T00:00:00.Z",
+            "2024-01-01",
+            "24-01-01T00:00:00Z",
+        ] {
+            assert!(invalid.parse::<Timestamp>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_civil_days() {
+        for days in -1_000_000..1_000_000 {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        use std::collections::HashMap;
+
+        let durations: HashMap<String, Duration> =
+            crate::from_str(r#"a: "1h 30m", b: 90, c: 0.5"#).unwrap();
+        assert_eq!(durations["a"].0, time::Duration::from_secs(5400));
+        assert_eq!(durations["b"].0, time::Duration::from_secs(90));
+        assert_eq!(durations["c"].0, time::Duration::from_millis(500));
+        assert!(crate::from_str::<Duration>("-1").is_err());
+
+        let duration = Duration(time::Duration::from_secs(5400));
+        assert_eq!(crate::to_string(&duration).unwrap(), r#""1h30m""#);
+
+        let timestamp: Timestamp = crate::from_str(r#""2000-01-01T00:00:00Z""#).unwrap();
+        assert_eq!(
+            crate::to_string(&timestamp).unwrap(),
+            r#""2000-01-01T00:00:00Z""#
+        );
+    }
+}
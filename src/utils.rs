@@ -1,82 +1,230 @@
-use std::io::{self, BufRead, Read};
+use std::io::{self, BufRead};
 
-use crate::peek_reader::PeekReader;
+use crate::{Error, Result, peek_reader::PeekReader};
 
 pub fn to_char(byte: u8) -> char {
     // Safety: all u8's are valid chars
     unsafe { char::from_u32_unchecked(byte.into()) }
 }
 
+/// Fill the buffer of `reader` like [`BufRead::fill_buf`], but retry if the read
+/// is interrupted, like [`Read::read_exact`](std::io::Read::read_exact) does.
+pub fn fill_buf_retrying<R: BufRead>(reader: &mut R) -> io::Result<&[u8]> {
+    loop {
+        // filling a buffer that is not empty does not read anything, so it is
+        // filled again to return it, but an empty buffer at EOF is returned as is
+        match reader.fill_buf().map(|buf| buf.is_empty()) {
+            Ok(true) => return Ok(&[]),
+            Ok(false) => return reader.fill_buf(),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Returns true if `byte` can be part of an identifier (after the first character).
+pub fn is_identifier_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-')
+}
+
 /// Read from `reader` until a not-escaped quote is reached. The final quote is read
 /// but not returned.
-pub fn read_until_unquote<R: Read>(reader: &mut PeekReader<R>) -> io::Result<Vec<u8>> {
+///
+/// A quote is escaped if it comes after an odd number of backslashes, so in
+/// `"C:\\"`, the backslashes escape each other, and the quote ends the string.
+pub fn read_until_unquote<R: BufRead>(reader: &mut PeekReader<R>) -> Result<Vec<u8>> {
     let mut value = Vec::new();
-    let mut buff = Vec::new();
+    // whether the next byte is escaped by a backslash
+    let mut escaped = false;
     loop {
-        reader.read_until(b'"', &mut buff)?;
-        if buff.len() >= 2 && buff[buff.len() - 2] == b'\\' {
-            // quote is escaped, continue
-            value.append(&mut buff);
-        } else {
-            // quote is not escaped, remove it from buff and break
-            if buff.pop().is_none_or(|end| end != b'"') {
-                return Err(io::Error::new(
-                    io::ErrorKind::UnexpectedEof,
-                    "found no unquote",
-                ));
-            }
-            value.append(&mut buff);
-            break;
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Err(Error::eof("found no unquote"));
         }
-    }
 
-    Ok(value)
+        let end = buf.iter().position(|byte| {
+            let is_unquote = *byte == b'"' && !escaped;
+            escaped = *byte == b'\\' && !escaped;
+            is_unquote
+        });
+        let consumed = end.map_or(buf.len(), |end| end + 1);
+        value.extend_from_slice(&buf[..end.unwrap_or(consumed)]);
+        reader.consume(consumed);
+        if end.is_some() {
+            return Ok(value);
+        }
+    }
 }
 
-/// Read from `reader` until a specified pattern (string of bytes) is reached. The pattern is read
-/// but not returned.
-pub fn read_until_pattern<R: Read>(
+/// Read the start of a raw string after the `r`: some hashes followed by a quote.
+/// Returns the number of hashes, which must be at most `max_hashes`.
+pub fn read_raw_string_start<R: BufRead>(
     reader: &mut PeekReader<R>,
-    pattern: &[u8],
-) -> io::Result<Vec<u8>> {
-    if pattern.is_empty() {
-        return Ok(Vec::new());
+    max_hashes: usize,
+) -> Result<usize> {
+    let mut hashes = 0;
+    // count the hashes one at a time, so a long run of them is never buffered
+    while reader.peek()? == Some(b'#') {
+        if hashes == max_hashes {
+            return Err(Error::syntax(format!(
+                "raw string starts with more than {max_hashes} hashes"
+            )));
+        }
+        reader.consume(1);
+        hashes += 1;
     }
 
-    let mut value = Vec::new();
-    let mut buff = Vec::new();
+    match reader.read_byte()? {
+        Some(b'"') => Ok(hashes),
+        Some(byte) => Err(Error::syntax(format!(
+            "got invalid char {:?} in beginning of raw string",
+            to_char(byte)
+        ))),
+        None => Err(Error::eof("got EOF while parsing raw string")),
+    }
+}
+
+/// Read from `reader` until a quote followed by `hashes` hashes, the end of a raw
+/// string, is reached. Every chunk that is read is passed to `on_chunk`, and the
+/// last chunk ends with the quote and hashes.
+///
+/// The end is matched incrementally, so every byte is only looked at once, no
+/// matter how many hashes there are or how many almost-ends the string contains.
+pub fn read_until_raw_string_end<R: BufRead>(
+    reader: &mut PeekReader<R>,
+    hashes: usize,
+    mut on_chunk: impl FnMut(&[u8]),
+) -> Result<()> {
+    // the number of hashes after the last quote, or None if a byte that is not
+    // a hash has been read since then
+    let mut matched = None;
     loop {
-        reader.read_until(pattern[0], &mut buff)?;
-        if buff.is_empty() {
-            return Err(io::Error::new(
-                io::ErrorKind::UnexpectedEof,
-                "pattern not found",
-            ));
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Err(Error::eof("found no end of raw string"));
         }
-        value.append(&mut buff);
-
-        let mut correct_chars = 1;
-        while correct_chars < pattern.len() {
-            let Some(next) = reader.read_byte()? else {
-                return Err(io::Error::new(
-                    io::ErrorKind::UnexpectedEof,
-                    "pattern not found",
-                ));
-            };
-            value.push(next);
 
-            if next == pattern[correct_chars] {
-                correct_chars += 1;
-            } else {
-                break;
-            }
+        let end = buf.iter().position(|byte| {
+            matched = match (byte, matched) {
+                (b'"', _) => Some(0),
+                (b'#', Some(matched)) => Some(matched + 1),
+                _ => None,
+            };
+            matched == Some(hashes)
+        });
+        let consumed = end.map_or(buf.len(), |end| end + 1);
+        on_chunk(&buf[..consumed]);
+        reader.consume(consumed);
+        if end.is_some() {
+            return Ok(());
         }
+    }
+}
+
+/// Call `f` with `key` appended to the `.` separated `path`, and restore `path` afterwards.
+pub fn with_key<T>(path: &mut String, key: &str, f: impl FnOnce(&mut String) -> T) -> T {
+    let len = path.len();
+    push_key(path, key);
+    let result = f(path);
+    path.truncate(len);
+    result
+}
 
-        if correct_chars == pattern.len() {
-            for _ in 0..pattern.len() {
-                value.pop();
+/// Append `key` to the `.` separated `path`.
+pub fn push_key(path: &mut String, key: &str) {
+    if !path.is_empty() {
+        path.push('.');
+    }
+    path.push_str(key);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn test_read_until_unquote() {
+        let read = |data: &str| {
+            let mut reader = PeekReader::new(data.as_bytes());
+            let value = read_until_unquote(&mut reader)?;
+            let mut rest = String::new();
+            reader.read_to_string(&mut rest).unwrap();
+            Ok::<_, Error>((String::from_utf8(value).unwrap(), rest))
+        };
+
+        // every combination of up to four escaped backslashes, escaped quotes and
+        // other characters
+        let mut strings = vec![String::new()];
+        for _ in 0..4 {
+            strings = strings
+                .iter()
+                .flat_map(|string| {
+                    [
+                        format!("{string}a"),
+                        format!("{string}\\\\"),
+                        format!("{string}\\\""),
+                    ]
+                })
+                .collect();
+            for string in &strings {
+                assert_eq!(
+                    read(&format!("{string}\" rest")).unwrap(),
+                    (string.clone(), " rest".to_owned()),
+                    "{string:?}"
+                );
             }
-            return Ok(value);
         }
+
+        assert_eq!(read(r#"C:\\path\\" rest"#).unwrap().0, r"C:\\path\\");
+        assert_eq!(read(r#"\\\" quote" rest"#).unwrap().0, r#"\\\" quote"#);
+        assert!(read(r#"escaped end\""#).is_err());
+        assert!(read(r"no end\\").is_err());
+    }
+
+    fn read_raw_string(data: &[u8], max_hashes: usize) -> Result<Vec<u8>> {
+        let mut reader = PeekReader::new(data);
+        let hashes = read_raw_string_start(&mut reader, max_hashes)?;
+        let mut value = Vec::new();
+        read_until_raw_string_end(&mut reader, hashes, |chunk| value.extend(chunk))?;
+        value.truncate(value.len() - hashes - 1);
+        Ok(value)
+    }
+
+    #[test]
+    fn test_read_raw_string() {
+        assert_eq!(read_raw_string(br#""""#, 0).unwrap(), b"");
+        assert_eq!(read_raw_string(br##"#"a""#"##, 1).unwrap(), b"a\"");
+        assert_eq!(read_raw_string(br###"#"a"""##"###, 1).unwrap(), b"a\"\"");
+        assert_eq!(
+            read_raw_string(br###"##"#"a"#""##"###, 2).unwrap(),
+            b"#\"a\"#\""
+        );
+
+        assert!(read_raw_string(br#"#"a""#, 0).is_err());
+        assert!(read_raw_string(br#"#a""#, 1).is_err());
+        assert!(read_raw_string(br##"##"a"#"##, 2).is_err());
+    }
+
+    #[test]
+    fn test_read_raw_string_pathological() {
+        // many almost-ends, each one hash short
+        let hashes = 1000;
+        let almost_end = format!("\"{}", "#".repeat(hashes - 1));
+        let data = format!("{0}\"{1}\"{0}", "#".repeat(hashes), almost_end.repeat(1000));
+        let value = read_raw_string(data.as_bytes(), hashes).unwrap();
+        assert_eq!(value, almost_end.repeat(1000).as_bytes());
+
+        // a long run of hashes is rejected without reading all of it
+        let data = "#".repeat(1_000_000);
+        let mut reader = PeekReader::new(data.as_bytes());
+        assert!(read_raw_string_start(&mut reader, 255).is_err());
+        assert_eq!(reader.position().offset, 255);
+
+        // the end is never found
+        let data = format!("{0}\"{1}", "#".repeat(10), "\"#########".repeat(10_000));
+        let err = read_raw_string(data.as_bytes(), 10).unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::UnexpectedEof);
     }
 }
@@ -0,0 +1,78 @@
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::{Map, Value};
+
+/// Generated values are nested at most this deep, which keeps them well within
+/// the depth limit of the parser.
+const MAX_DEPTH: usize = 8;
+
+/// Generate any `Value` that can be represented in MASON. Numbers are always
+/// finite, as NaN and infinity have no MASON representation.
+impl<'a> Arbitrary<'a> for Value {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        arbitrary_value(u, MAX_DEPTH)
+    }
+}
+
+fn arbitrary_value(u: &mut Unstructured<'_>, depth: usize) -> Result<Value> {
+    // Containers are only generated while we are allowed to nest deeper
+    let kinds = if depth == 0 { 5 } else { 7 };
+    let value = match u.choose_index(kinds)? {
+        0 => Value::Null,
+        1 => Value::Bool(u.arbitrary()?),
+        2 => {
+            let number: f64 = u.arbitrary()?;
+            Value::from(if number.is_finite() { number } else { 0.0 })
+        }
+        3 => Value::String(u.arbitrary::<&str>()?.into()),
+        4 => Value::ByteString(u.arbitrary()?),
+        5 => {
+            let mut array = Vec::new();
+            while u.arbitrary()? {
+                array.push(arbitrary_value(u, depth - 1)?);
+            }
+            Value::Array(array)
+        }
+        _ => {
+            let mut object = Map::new();
+            while u.arbitrary()? {
+                object.insert(u.arbitrary::<&str>()?, arbitrary_value(u, depth - 1)?);
+            }
+            Value::Object(object)
+        }
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    /// A small xorshift generator, so the test data is the same on every run.
+    fn random_bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state.to_le_bytes()[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for seed in 0..1000 {
+            let data = random_bytes(seed, 1024);
+            let value = Value::arbitrary(&mut Unstructured::new(&data)).unwrap();
+            for string in [value.to_string(), format!("{value:#}")] {
+                let parsed = Value::from_str(&string)
+                    .unwrap_or_else(|err| panic!("failed to parse {string:?}: {err}"));
+                assert_eq!(parsed, value, "{string:?}");
+            }
+        }
+    }
+}
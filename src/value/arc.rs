@@ -0,0 +1,276 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{Map, MasonString, Number, Value, ValueKind};
+
+/// A [`Value`] whose arrays, objects and strings are reference counted, so
+/// cloning it, or any value inside it, is O(1).
+///
+/// This is useful when the same values are cloned many times, like when layering
+/// configuration files on top of each other. Arrays and objects are copied on
+/// write: the mutable accessors only copy an array or object if it is shared
+/// with another `ArcValue`, and even then, the values inside it are not copied.
+///
+/// ```
+/// # use mason_rs::{ArcValue, Value};
+/// # use std::str::FromStr;
+/// #
+/// let defaults = ArcValue::from(Value::from_str("server: {port: 80}, users: [\"root\"]").unwrap());
+///
+/// let mut config = defaults.clone();
+/// let server = config.get_mut("server").unwrap().as_object_mut().unwrap();
+/// server.insert("port".into(), ArcValue::from(8080.0));
+///
+/// assert_eq!(defaults["server"]["port"], ArcValue::from(80.0));
+/// assert_eq!(config["server"]["port"], ArcValue::from(8080.0));
+/// // the users were never modified, so they are still shared
+/// assert!(config["users"].ptr_eq(&defaults["users"]));
+///
+/// let config = Value::from(config);
+/// assert_eq!(config, Value::from_str("server: {port: 8080}, users: [\"root\"]").unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ArcValue {
+    Object(Arc<HashMap<Arc<str>, ArcValue>>),
+    Array(Arc<Vec<ArcValue>>),
+    String(Arc<str>),
+    ByteString(Arc<[u8]>),
+    Number(Number),
+    Bool(bool),
+    #[default]
+    Null,
+    /// A value with a tag, like [`Value::Tagged`].
+    Tagged(Arc<str>, Arc<ArcValue>),
+}
+
+impl ArcValue {
+    /// Return the kind of the `ArcValue`, which is the same as the kind of
+    /// the corresponding [`Value`].
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Self::Null => ValueKind::Null,
+            Self::Bool(_) => ValueKind::Bool,
+            Self::Number(_) => ValueKind::Number,
+            Self::String(_) => ValueKind::String,
+            Self::ByteString(_) => ValueKind::ByteString,
+            Self::Array(_) => ValueKind::Array,
+            Self::Object(_) => ValueKind::Object,
+            Self::Tagged(..) => ValueKind::Tagged,
+        }
+    }
+
+    /// Returns the value of `key` if the `ArcValue` is an object, and `None` otherwise.
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        self.as_object()?.get(key)
+    }
+
+    /// Returns a mutable reference to the value of `key` if the `ArcValue` is an
+    /// object, and `None` otherwise. The object is copied first if it is shared.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Self> {
+        self.as_object_mut()?.get_mut(key)
+    }
+
+    /// If the `ArcValue` is an object, return a reference to its entries.
+    /// Returns `None` otherwise.
+    pub fn as_object(&self) -> Option<&HashMap<Arc<str>, Self>> {
+        match self {
+            Self::Object(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// If the `ArcValue` is an object, return a mutable reference to its entries.
+    /// The entries are copied first if the object is shared. Returns `None` otherwise.
+    pub fn as_object_mut(&mut self) -> Option<&mut HashMap<Arc<str>, Self>> {
+        match self {
+            Self::Object(map) => Some(Arc::make_mut(map)),
+            _ => None,
+        }
+    }
+
+    /// If the `ArcValue` is an array, return a reference to its elements.
+    /// Returns `None` otherwise.
+    pub fn as_array(&self) -> Option<&[Self]> {
+        match self {
+            Self::Array(vec) => Some(vec),
+            _ => None,
+        }
+    }
+
+    /// If the `ArcValue` is an array, return a mutable reference to its elements.
+    /// The elements are copied first if the array is shared. Returns `None` otherwise.
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Self>> {
+        match self {
+            Self::Array(vec) => Some(Arc::make_mut(vec)),
+            _ => None,
+        }
+    }
+
+    /// If the `ArcValue` is a string, return it. Returns `None` otherwise.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Returns true if both values are the same array, object, string, byte string
+    /// or tagged value, meaning one is a clone of the other, and neither has been
+    /// modified since. Scalars are never shared, so this returns false for them.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Object(a), Self::Object(b)) => Arc::ptr_eq(a, b),
+            (Self::Array(a), Self::Array(b)) => Arc::ptr_eq(a, b),
+            (Self::String(a), Self::String(b)) => Arc::ptr_eq(a, b),
+            (Self::ByteString(a), Self::ByteString(b)) => Arc::ptr_eq(a, b),
+            (Self::Tagged(_, a), Self::Tagged(_, b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    /// Convert the `ArcValue` into a plain [`Value`]. Arrays and objects that are
+    /// not shared are moved instead of copied.
+    pub fn into_value(self) -> Value {
+        match self {
+            Self::Object(map) => Value::Object(
+                Arc::unwrap_or_clone(map)
+                    .into_iter()
+                    .map(|(key, value)| (MasonString::from(key.as_ref()), value.into_value()))
+                    .collect::<Map>(),
+            ),
+            Self::Array(vec) => Value::Array(
+                Arc::unwrap_or_clone(vec)
+                    .into_iter()
+                    .map(Self::into_value)
+                    .collect(),
+            ),
+            Self::String(string) => Value::String(string.as_ref().into()),
+            Self::ByteString(bytes) => Value::ByteString(bytes.to_vec()),
+            Self::Number(number) => Value::Number(number),
+            Self::Bool(b) => Value::Bool(b),
+            Self::Null => Value::Null,
+            Self::Tagged(tag, value) => Value::Tagged(
+                tag.to_string(),
+                Box::new(Arc::unwrap_or_clone(value).into_value()),
+            ),
+        }
+    }
+}
+
+impl From<Value> for ArcValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Object(map) => Self::Object(Arc::new(
+                map.into_iter()
+                    .map(|(key, value)| (Arc::from(key.as_str()), Self::from(value)))
+                    .collect(),
+            )),
+            Value::Array(vec) => Self::Array(Arc::new(vec.into_iter().map(Self::from).collect())),
+            Value::String(string) => Self::String(Arc::from(string.as_str())),
+            Value::ByteString(bytes) => Self::ByteString(Arc::from(bytes)),
+            Value::Number(number) => Self::Number(number),
+            Value::Bool(b) => Self::Bool(b),
+            Value::Null => Self::Null,
+            Value::Tagged(tag, value) => Self::Tagged(Arc::from(tag), Arc::new(Self::from(*value))),
+        }
+    }
+}
+
+impl From<f64> for ArcValue {
+    fn from(number: f64) -> Self {
+        Self::Number(number.into())
+    }
+}
+
+impl From<ArcValue> for Value {
+    fn from(value: ArcValue) -> Self {
+        value.into_value()
+    }
+}
+
+impl std::ops::Index<&str> for ArcValue {
+    type Output = Self;
+
+    /// Panics if the `ArcValue` is not an object, or if the key is not in it.
+    fn index(&self, key: &str) -> &Self {
+        self.get(key)
+            .unwrap_or_else(|| panic!("cannot access key {key:?} in MASON {}", self.kind()))
+    }
+}
+
+impl std::ops::Index<usize> for ArcValue {
+    type Output = Self;
+
+    /// Panics if the `ArcValue` is not an array, or if the index is out of bounds.
+    fn index(&self, index: usize) -> &Self {
+        self.as_array()
+            .and_then(|array| array.get(index))
+            .unwrap_or_else(|| panic!("cannot access index {index} of MASON {}", self.kind()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseOptions;
+
+    #[test]
+    fn test_arc_value() {
+        let data = "a: {b: [1, \"two\", b\"3\"]}, c: @tag {d: null}, e: true";
+        let value = ParseOptions::new()
+            .tags(true)
+            .parse_str(data)
+            .unwrap()
+            .value;
+        let arc_value = ArcValue::from(value.clone());
+        assert_eq!(arc_value.kind(), ValueKind::Object);
+        assert_eq!(arc_value["a"]["b"][1].as_str(), Some("two"));
+        assert_eq!(Value::from(arc_value.clone()), value);
+
+        let mut modified = arc_value.clone();
+        assert!(modified["a"].ptr_eq(&arc_value["a"]));
+        let array = modified.get_mut("a").unwrap().get_mut("b").unwrap();
+        array.as_array_mut().unwrap().push(ArcValue::Null);
+
+        assert!(!modified["a"].ptr_eq(&arc_value["a"]));
+        assert!(modified["c"].ptr_eq(&arc_value["c"]));
+        assert_eq!(modified["a"]["b"][0], ArcValue::from(1.0));
+        assert!(modified["a"]["b"][1].ptr_eq(&arc_value["a"]["b"][1]));
+        assert_eq!(arc_value["a"]["b"].as_array().unwrap().len(), 3);
+        assert_eq!(modified["a"]["b"].as_array().unwrap().len(), 4);
+
+        // the original value is not affected by the modification
+        assert_eq!(arc_value.into_value(), value);
+        assert!(ArcValue::from(1.0).get_mut("a").is_none());
+        assert!(ArcValue::Bool(true).as_array_mut().is_none());
+    }
+
+    #[test]
+    fn test_interned() {
+        let data = "[{id: \"a\", tags: [\"a\", \"b\"]}, {id: \"b\", a: @id \"a\"}]";
+        let options = ParseOptions::new().tags(true);
+        let value = options.parse_str(data).unwrap().value;
+        let interned = options.parse_reader_interned(data.as_bytes()).unwrap();
+        assert_eq!(interned, ArcValue::from(value.clone()));
+        assert_eq!(Value::from(interned.clone()), value);
+
+        let a = &interned[0]["id"];
+        assert!(a.ptr_eq(&interned[0]["tags"][0]));
+        assert!(!a.ptr_eq(&interned[0]["tags"][1]));
+        let ArcValue::Tagged(tag, tagged) = &interned[1]["a"] else {
+            panic!("{:?} is not tagged", interned[1]["a"]);
+        };
+        assert!(a.ptr_eq(tagged));
+
+        // keys are shared with each other, and with equal strings
+        let key = |i: usize, key: &str| {
+            let (key, _) = interned[i].as_object().unwrap().get_key_value(key).unwrap();
+            Arc::clone(key)
+        };
+        assert!(Arc::ptr_eq(&key(0, "id"), &key(1, "id")));
+        assert!(Arc::ptr_eq(&key(1, "id"), tag));
+        let ArcValue::String(string) = a else {
+            panic!("{a:?} is not a string");
+        };
+        assert!(Arc::ptr_eq(&key(1, "a"), string));
+    }
+}
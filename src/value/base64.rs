@@ -0,0 +1,69 @@
+use base64::{Engine, prelude::BASE64_STANDARD};
+
+use crate::{Error, ErrorKind, Result, Value};
+
+impl Value {
+    /// Create a byte string from standard base64 with padding, which is how
+    /// binary data is usually stored in JSON.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// #
+    /// let value = Value::byte_string_from_base64("bWFzb24=").unwrap();
+    /// assert_eq!(value, Value::ByteString(b"mason".to_vec()));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`ErrorKind::InvalidBase64`] if `base64` is not valid base64.
+    pub fn byte_string_from_base64(base64: &str) -> Result<Self> {
+        BASE64_STANDARD
+            .decode(base64)
+            .map(Self::ByteString)
+            .map_err(|err| Error::new(ErrorKind::InvalidBase64, format!("invalid base64: {err}")))
+    }
+
+    /// If the `Value` is a byte string, returns it encoded as standard base64 with
+    /// padding. Returns None otherwise.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str("data: b\"mason\", text: \"mason\"").unwrap();
+    /// assert_eq!(value["data"].as_base64(), Some("bWFzb24=".to_owned()));
+    /// assert_eq!(value["text"].as_base64(), None);
+    /// ```
+    pub fn as_base64(&self) -> Option<String> {
+        match self {
+            Self::ByteString(bytes) => Some(BASE64_STANDARD.encode(bytes)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        for bytes in [
+            &b""[..],
+            b"\x00",
+            b"\xff\xfe",
+            b"abc",
+            b"\x00\x01\x02\x03\xfa",
+        ] {
+            let value = Value::ByteString(bytes.to_vec());
+            let base64 = value.as_base64().unwrap();
+            assert_eq!(Value::byte_string_from_base64(&base64).unwrap(), value);
+        }
+        assert_eq!(Value::Null.as_base64(), None);
+
+        for invalid in ["bWFzb24", "bWF*b24=", "bWFzb24=="] {
+            let err = Value::byte_string_from_base64(invalid).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidBase64, "{invalid}");
+        }
+    }
+}
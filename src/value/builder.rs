@@ -0,0 +1,149 @@
+use crate::{Map, MasonString, ToMason, Value};
+
+/// Builds a [`Value::Object`] one field at a time. Created with [`Value::object`].
+///
+/// Field values can be anything that implements [`ToMason`], like numbers,
+/// strings, booleans, options, vectors and other values.
+///
+/// ```
+/// # use mason_rs::Value;
+/// # use std::str::FromStr;
+/// #
+/// let value = Value::object()
+///     .field("name", "app")
+///     .field("port", 8080)
+///     .field("debug", false)
+///     .field("hosts", Value::array().element("a.example").element("b.example").build())
+///     .build();
+///
+/// assert_eq!(
+///     value,
+///     Value::from_str(r#"name: "app", port: 8080, debug: false, hosts: ["a.example", "b.example"]"#)
+///         .unwrap(),
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+#[must_use = "call `build` to get the value"]
+pub struct ObjectBuilder {
+    map: Map,
+}
+
+impl ObjectBuilder {
+    /// Set the value of `key`. If `key` has already been set, its value is replaced.
+    pub fn field(mut self, key: impl Into<MasonString>, value: impl ToMason) -> Self {
+        self.map.insert(key, value.to_mason());
+        self
+    }
+
+    /// Set the value of `key` if `value` is `Some`, and leave the key out otherwise.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// #
+    /// let value = Value::object().optional_field("a", Some(1)).optional_field("b", None::<i32>).build();
+    /// assert_eq!(value.as_object().unwrap().len(), 1);
+    /// ```
+    pub fn optional_field(self, key: impl Into<MasonString>, value: Option<impl ToMason>) -> Self {
+        match value {
+            Some(value) => self.field(key, value),
+            None => self,
+        }
+    }
+
+    /// Finish building, and return the object.
+    pub fn build(self) -> Value {
+        Value::Object(self.map)
+    }
+}
+
+/// Builds a [`Value::Array`] one element at a time. Created with [`Value::array`].
+///
+/// Elements can be anything that implements [`ToMason`].
+///
+/// ```
+/// # use mason_rs::Value;
+/// # use std::str::FromStr;
+/// #
+/// let value = Value::array().element(1).elements(["two", "three"]).element(None::<bool>).build();
+/// assert_eq!(value, Value::from_str(r#"[1, "two", "three", null]"#).unwrap());
+/// ```
+#[derive(Debug, Clone, Default)]
+#[must_use = "call `build` to get the value"]
+pub struct ArrayBuilder {
+    vec: Vec<Value>,
+}
+
+impl ArrayBuilder {
+    /// Append `value` to the array.
+    pub fn element(mut self, value: impl ToMason) -> Self {
+        self.vec.push(value.to_mason());
+        self
+    }
+
+    /// Append every value in `values` to the array.
+    pub fn elements<T: ToMason>(mut self, values: impl IntoIterator<Item = T>) -> Self {
+        self.vec
+            .extend(values.into_iter().map(|value| value.to_mason()));
+        self
+    }
+
+    /// Finish building, and return the array.
+    pub fn build(self) -> Value {
+        Value::Array(self.vec)
+    }
+}
+
+impl From<ObjectBuilder> for Value {
+    fn from(builder: ObjectBuilder) -> Self {
+        builder.build()
+    }
+}
+
+impl From<ArrayBuilder> for Value {
+    fn from(builder: ArrayBuilder) -> Self {
+        builder.build()
+    }
+}
+
+impl Value {
+    /// Start building an object. See [`ObjectBuilder`].
+    pub fn object() -> ObjectBuilder {
+        ObjectBuilder::default()
+    }
+
+    /// Start building an array. See [`ArrayBuilder`].
+    pub fn array() -> ArrayBuilder {
+        ArrayBuilder::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_builders() {
+        let value = Value::object()
+            .field("a", 1)
+            .field("b", true)
+            .field("a", "replaced")
+            .field(
+                "c",
+                Value::object()
+                    .field("d", Value::array().elements(vec![1.5, 2.5]).build())
+                    .build(),
+            )
+            .optional_field("e", None::<String>)
+            .optional_field("f", Some(Value::Null))
+            .build();
+        assert_eq!(
+            value,
+            Value::from_str(r#"a: "replaced", b: true, c: {d: [1.5, 2.5]}, f: null"#).unwrap()
+        );
+
+        assert_eq!(Value::from(Value::object()), Value::Object(Map::new()));
+        assert_eq!(Value::from(Value::array()), Value::Array(Vec::new()));
+    }
+}
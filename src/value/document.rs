@@ -0,0 +1,152 @@
+use std::{
+    fmt::{self, Display},
+    ops::Deref,
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{Error, Value};
+
+/// An immutable [`Value`] that can be shared cheaply, including across threads.
+///
+/// A `Document` is created with [`Value::freeze`], and can be turned back into a
+/// `Value` that can be modified with [`Document::thaw`]. Cloning a `Document` is
+/// O(1), and it dereferences to the `Value` inside it, so it can be read like one.
+///
+/// Since a `Document` is just an [`Arc<Value>`], it can also be converted to and
+/// from one for free, which makes it easy to swap a shared configuration
+/// atomically with types like `ArcSwap<Value>` from the `arc-swap` crate.
+///
+/// ```
+/// # use mason_rs::{Document, Value};
+/// # use std::{str::FromStr, thread};
+/// #
+/// let config = Value::from_str("port: 8080").unwrap().freeze();
+///
+/// let worker = {
+///     let config = config.clone();
+///     thread::spawn(move || config["port"].as_f64())
+/// };
+/// assert_eq!(worker.join().unwrap(), Some(8080.0));
+///
+/// let mut value = config.clone().thaw();
+/// value["port"] = Value::from(8081.0);
+/// assert_eq!(config["port"], Value::from(8080.0));
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Document {
+    value: Arc<Value>,
+}
+
+impl Document {
+    /// Convert the `Document` back into a `Value` that can be modified. The value is
+    /// only copied if the `Document` has been cloned and the clones are still alive.
+    pub fn thaw(self) -> Value {
+        Arc::unwrap_or_clone(self.value)
+    }
+
+    /// Returns true if both documents are clones of the same document.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.value, &other.value)
+    }
+
+    /// Convert the `Document` into the [`Arc`] it is backed by.
+    pub fn into_arc(self) -> Arc<Value> {
+        self.value
+    }
+}
+
+impl Value {
+    /// Make the `Value` immutable, so it can be shared cheaply. See [`Document`].
+    pub fn freeze(self) -> Document {
+        Document {
+            value: Arc::new(self),
+        }
+    }
+}
+
+impl Deref for Document {
+    type Target = Value;
+
+    fn deref(&self) -> &Value {
+        &self.value
+    }
+}
+
+impl AsRef<Value> for Document {
+    fn as_ref(&self) -> &Value {
+        &self.value
+    }
+}
+
+impl From<Value> for Document {
+    fn from(value: Value) -> Self {
+        value.freeze()
+    }
+}
+
+impl From<Arc<Value>> for Document {
+    fn from(value: Arc<Value>) -> Self {
+        Self { value }
+    }
+}
+
+impl From<Document> for Arc<Value> {
+    fn from(document: Document) -> Self {
+        document.value
+    }
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    /// Deserialize a `Document` from a MASON string, like [`Value::from_str`].
+    fn from_str(string: &str) -> Result<Self, Error> {
+        Value::from_str(string).map(Value::freeze)
+    }
+}
+
+impl Display for Document {
+    /// Serialize the `Document` as MASON, like the [`Value`] inside it.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_document() {
+        let document = Document::from_str("a: [1, 2], b: {c: null}").unwrap();
+        let clone = document.clone();
+        assert!(clone.ptr_eq(&document));
+        assert_eq!(document.to_string(), document.as_ref().to_string());
+
+        let sums: Vec<f64> = (0..4)
+            .map(|i| {
+                let document = document.clone();
+                thread::spawn(move || {
+                    let array = document["a"].as_array().unwrap();
+                    array.iter().filter_map(Value::as_f64).sum::<f64>() * i as f64
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+        assert_eq!(sums, [0.0, 3.0, 6.0, 9.0]);
+
+        // thawing a shared document copies it
+        let mut value = clone.thaw();
+        value["b"]["c"] = Value::Bool(true);
+        assert_eq!(document["b"]["c"], Value::Null);
+
+        let arc = document.into_arc();
+        assert_eq!(Arc::strong_count(&arc), 1);
+        assert_eq!(Document::from(arc).thaw()["a"][1], Value::from(2.0));
+    }
+}
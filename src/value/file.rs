@@ -0,0 +1,152 @@
+use std::{
+    ffi::OsString,
+    fmt,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::Path,
+    process,
+};
+
+use crate::{LineEnding, Parsed, Result, SerializeOptions, Value};
+
+impl Value {
+    /// Deserialize a [`Value`] from the MASON file at `path`.
+    ///
+    /// ```no_run
+    /// # use mason_rs::Value;
+    /// #
+    /// let value = Value::from_file("config.mason").unwrap();
+    /// println!("{value}");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function can fail if the file could not be read, or if it is not valid MASON.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_reader(File::open(path)?)
+    }
+
+    /// Serialize the [`Value`] to the file at `path`, as written by [`Value::to_writer`].
+    ///
+    /// The value is first written to a temporary file in the same directory, which then
+    /// replaces the file at `path`. This means that the file at `path` is never left
+    /// partially written, even if writing fails. If the file already exists, its line
+    /// endings are kept, so rewriting a file with Windows line endings does not change
+    /// every line.
+    ///
+    /// ```no_run
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str("port: 8080").unwrap();
+    /// value.write_to_file("config.mason").unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function can fail if the temporary file could not be written, or if it could
+    /// not replace the file at `path`.
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        write_file(path.as_ref(), |string, options| {
+            self.to_writer_with_options(string, options)
+        })
+    }
+}
+
+impl Parsed {
+    /// Serialize the parsed value to the file at `path`, like
+    /// [`Value::write_to_file`], but with the strings written in the styles they
+    /// were parsed with, as described in [`Parsed::to_writer_with_options`].
+    ///
+    /// # Errors
+    ///
+    /// This function can fail if the temporary file could not be written, or if it could
+    /// not replace the file at `path`.
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        write_file(path.as_ref(), |string, options| {
+            self.to_writer_with_options(string, options)
+        })
+    }
+}
+
+/// Replace the file at `path` with the text `write` writes, through a temporary
+/// file. `write` gets the options with the line ending of the file.
+fn write_file(
+    path: &Path,
+    write: impl FnOnce(&mut String, &SerializeOptions) -> fmt::Result,
+) -> io::Result<()> {
+    let Some(file_name) = path.file_name() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{path:?} is not a file path"),
+        ));
+    };
+
+    let options = SerializeOptions::new().line_ending(detect_line_ending(path));
+    let mut string = String::new();
+    write(&mut string, &options).map_err(io::Error::other)?;
+
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".{}.tmp", process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = File::create(&temp_path).and_then(|mut file| {
+        file.write_all(string.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Detect the line ending of the file at `path` from its start. Files that can not
+/// be read, like files that do not exist yet, get the default line ending.
+fn detect_line_ending(path: &Path) -> LineEnding {
+    let mut start = Vec::new();
+    match File::open(path).and_then(|file| file.take(8192).read_to_end(&mut start)) {
+        Ok(_) => LineEnding::detect(&String::from_utf8_lossy(&start)),
+        Err(_) => LineEnding::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_file_round_trip() {
+        let dir = std::env::temp_dir().join(format!("mason-file-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("value.mason");
+
+        let value = Value::from_str(r#"a: {b: [1, "two", b"3"]}, c: null"#).unwrap();
+        value.write_to_file(&path).unwrap();
+        assert_eq!(Value::from_file(&path).unwrap(), value);
+
+        // existing line endings are kept
+        fs::write(&path, "a: 1\r\nb: 2\r\n").unwrap();
+        value.write_to_file(&path).unwrap();
+        let string = fs::read_to_string(&path).unwrap();
+        assert_eq!(string.matches("\r\n").count(), string.matches('\n').count());
+        assert_eq!(Value::from_file(&path).unwrap(), value);
+
+        Value::Null.write_to_file(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "null");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        assert!(Value::from_file(dir.join("missing.mason")).is_err());
+        assert!(
+            value
+                .write_to_file(dir.join("missing").join("value.mason"))
+                .is_err()
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
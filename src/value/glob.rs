@@ -0,0 +1,205 @@
+use crate::{Value, utils::with_key};
+
+impl Value {
+    /// Find the values whose paths match `pattern`, and return their paths and
+    /// values. This is a light-weight alternative to [`Value::select`] for looking
+    /// up the same key in many places.
+    ///
+    /// The pattern is a list of keys separated by `.`, like the paths of
+    /// [`Value::insert_path`], where array elements are identified by their index.
+    /// A `*` in a key matches any part of a key, so `*` on its own matches every
+    /// key or index, and `db_*` matches every key that starts with `db_`. A key
+    /// that is only `**` matches any number of keys, including none. The value
+    /// inside a tagged value has the same path as the tagged value.
+    ///
+    /// Every value is returned at most once, and the paths are returned like
+    /// [`Value::transform`] gives them. The order of values in objects is
+    /// arbitrary.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str(
+    ///     "servers: {a: {port: 80}, b: {port: 443}}, db: {replicas: [{port: 5432}]}",
+    /// )
+    /// .unwrap();
+    ///
+    /// let mut ports: Vec<_> = value.get_glob("servers.*.port").collect();
+    /// ports.sort_by(|(a, _), (b, _)| a.cmp(b));
+    /// assert_eq!(
+    ///     ports,
+    ///     [
+    ///         ("servers.a.port".to_owned(), &Value::from(80.0)),
+    ///         ("servers.b.port".to_owned(), &Value::from(443.0)),
+    ///     ],
+    /// );
+    ///
+    /// assert_eq!(value.get_glob("**.port").count(), 3);
+    /// assert_eq!(value.get_glob("db.replicas.0.*").count(), 1);
+    /// ```
+    pub fn get_glob<'v>(
+        &'v self,
+        pattern: &str,
+    ) -> impl Iterator<Item = (String, &'v Self)> + use<'v> {
+        let segments: Vec<&str> = pattern.split('.').collect();
+        let mut matches = Vec::new();
+        glob(self, &segments, &[0], &mut String::new(), &mut matches);
+        matches.into_iter()
+    }
+}
+
+/// Add the values inside `value` that match `segments` to `matches`, where
+/// `states` are the indices of the segments that the key of `value` is matched
+/// against next.
+fn glob<'v>(
+    value: &'v Value,
+    segments: &[&str],
+    states: &[usize],
+    path: &mut String,
+    matches: &mut Vec<(String, &'v Value)>,
+) {
+    // `**` can match no keys, so the segment after it also applies here
+    let mut current = Vec::new();
+    for &state in states {
+        let mut state = state;
+        loop {
+            if !current.contains(&state) {
+                current.push(state);
+            }
+            if segments.get(state) != Some(&"**") {
+                break;
+            }
+            state += 1;
+        }
+    }
+    if current.contains(&segments.len()) {
+        matches.push((path.clone(), value));
+    }
+
+    let mut visit = |key: &str, child: &'v Value| {
+        let mut next = Vec::new();
+        for &state in &current {
+            let next_state = match segments.get(state) {
+                Some(&"**") => state,
+                Some(segment) if matches_key(segment, key) => state + 1,
+                _ => continue,
+            };
+            if !next.contains(&next_state) {
+                next.push(next_state);
+            }
+        }
+        if !next.is_empty() {
+            with_key(path, key, |path| {
+                glob(child, segments, &next, path, matches)
+            });
+        }
+    };
+    // the values inside a tagged value are matched as if they were in the tagged value
+    let mut value = value;
+    while let Value::Tagged(_, inner) = value {
+        value = inner;
+    }
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                visit(key, child);
+            }
+        }
+        Value::Array(array) => {
+            for (i, child) in array.iter().enumerate() {
+                visit(&i.to_string(), child);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Check if `key` matches `segment`, where a `*` matches any part of the key.
+fn matches_key(segment: &str, key: &str) -> bool {
+    let Some((prefix, rest)) = segment.split_once('*') else {
+        return segment == key;
+    };
+    let Some(mut key) = key.strip_prefix(prefix) else {
+        return false;
+    };
+    // the part after the last `*` must end the key, and the parts between stars
+    // are matched as early as possible
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let last = parts.pop().unwrap_or_default();
+    for part in parts {
+        match key.find(part) {
+            Some(i) => key = &key[i + part.len()..],
+            None => return false,
+        }
+    }
+    key.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn paths(value: &Value, pattern: &str) -> Vec<String> {
+        let mut paths: Vec<_> = value.get_glob(pattern).map(|(path, _)| path).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_get_glob() {
+        let value = Value::from_str(
+            "a: {b: [{c: 1}, {c: 2, d: 3}], db_main: {c: 4}, db_test: 5}, c: 6, e: []",
+        )
+        .unwrap();
+
+        assert_eq!(paths(&value, "a.b.*.c"), ["a.b.0.c", "a.b.1.c"]);
+        assert_eq!(paths(&value, "a.b.1.*"), ["a.b.1.c", "a.b.1.d"]);
+        assert_eq!(paths(&value, "a.db_*"), ["a.db_main", "a.db_test"]);
+        assert_eq!(paths(&value, "a.*_m*n.c"), ["a.db_main.c"]);
+        assert_eq!(paths(&value, "*"), ["a", "c", "e"]);
+        assert_eq!(paths(&value, "a.b.2"), Vec::<String>::new());
+        assert_eq!(paths(&value, "x.*"), Vec::<String>::new());
+
+        assert_eq!(
+            paths(&value, "**.c"),
+            ["a.b.0.c", "a.b.1.c", "a.db_main.c", "c"]
+        );
+        assert_eq!(paths(&value, "a.**.**.c"), paths(&value, "a.**.c"));
+        assert_eq!(
+            paths(&value, "a.b.**"),
+            ["a.b", "a.b.0", "a.b.0.c", "a.b.1", "a.b.1.c", "a.b.1.d"]
+        );
+        assert_eq!(paths(&value, "**").len(), 13);
+        assert_eq!(value.get_glob("**").next(), Some((String::new(), &value)));
+    }
+
+    #[test]
+    fn test_get_glob_tagged() {
+        let tagged = Value::Tagged(
+            "env".into(),
+            Box::new(Value::from_str("{port: 80}").unwrap()),
+        );
+        let mut value = Value::from_str("a: null").unwrap();
+        value["a"] = tagged.clone();
+
+        let matches: Vec<_> = value.get_glob("*").collect();
+        assert_eq!(matches, [("a".to_owned(), &tagged)]);
+        let matches: Vec<_> = value.get_glob("a.port").collect();
+        assert_eq!(matches, [("a.port".to_owned(), &Value::from(80.0))]);
+    }
+
+    #[test]
+    fn test_matches_key() {
+        assert!(matches_key("port", "port"));
+        assert!(!matches_key("port", "ports"));
+        assert!(matches_key("*", ""));
+        assert!(matches_key("a*", "a"));
+        assert!(matches_key("*a*b*", "xaxbx"));
+        assert!(!matches_key("*b*a", "ab"));
+        assert!(!matches_key("a*a", "a"));
+        assert!(matches_key("a*a", "aba"));
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt::{self, Display};
+
+/// The kind of a [`Value`](super::Value), as returned by
+/// [`Value::kind`](super::Value::kind).
+///
+/// ```
+/// # use mason_rs::{Value, ValueKind};
+/// # use std::str::FromStr;
+/// #
+/// let value = Value::from_str("[1, \"two\"]").unwrap();
+/// let kinds: Vec<ValueKind> = value.as_array().unwrap().iter().map(Value::kind).collect();
+/// assert_eq!(kinds, [ValueKind::Number, ValueKind::String]);
+/// assert_eq!(ValueKind::ByteString.to_string(), "byte string");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ValueKind {
+    Object,
+    Array,
+    String,
+    ByteString,
+    Number,
+    Bool,
+    Null,
+    Tagged,
+}
+
+impl ValueKind {
+    /// Return a string description of the kind, which is the same as
+    /// [`Value::value_type`](super::Value::value_type).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Bool => "boolean",
+            Self::Number => "number",
+            Self::String => "string",
+            Self::ByteString => "byte string",
+            Self::Array => "array",
+            Self::Object => "object",
+            Self::Tagged => "tagged value",
+        }
+    }
+}
+
+impl Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
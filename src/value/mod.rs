@@ -1,42 +1,191 @@
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+mod arc;
+#[cfg(feature = "base64")]
+mod base64;
+mod builder;
+mod document;
+// there is no file system on wasm32-unknown-unknown
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+mod file;
+mod glob;
+mod kind;
+mod number;
+mod ordering;
+mod profile;
+mod resolve;
 #[cfg(feature = "serde")]
 pub mod serde;
+mod string;
+mod transform;
+pub(crate) mod units;
 
 use std::{
-    collections::HashMap,
+    cmp::Ordering,
     fmt::{self, Display, Write},
-    io::{self, Read},
+    hash::{Hash, Hasher},
+    io::{BufRead, BufReader, Read},
     mem,
     str::FromStr,
+    time::Duration,
 };
 
-use crate::{deserialize, index::Index, peek_reader::PeekReader, serialize::write_indented_value};
+#[cfg(feature = "rayon")]
+use crate::serialize::write_value_parallel;
+use crate::{
+    Result, deserialize,
+    index::{Index, SliceIndex},
+    map::Map,
+    peek_reader::PeekReader,
+    query::Query,
+    serialize::{SerializeOptions, write_compact_value, write_value},
+};
+
+pub use arc::ArcValue;
+pub use builder::{ArrayBuilder, ObjectBuilder};
+pub use document::Document;
+pub use kind::ValueKind;
+pub use number::{Number, NumberExactness};
+pub use resolve::Resolver;
+pub use string::MasonString;
+pub use transform::Transform;
+
+/// Convert a `String` into a [`MasonString`]. This is free unless the `compact_str`
+/// feature is enabled.
+pub(crate) fn mason_string(string: String) -> MasonString {
+    string.into()
+}
 
 /// Represents any valid MASON value.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default)]
 pub enum Value {
-    Object(HashMap<String, Value>),
+    Object(Map),
     Array(Vec<Value>),
-    String(String),
+    String(MasonString),
     ByteString(Vec<u8>),
-    Number(f64),
+    Number(Number),
     Bool(bool),
+    #[default]
     Null,
+    /// A value with a tag, like `@date "2024-01-01"`. Tags are not part of MASON,
+    /// and are only parsed if [`ParseOptions::tags`](crate::ParseOptions::tags) is set.
+    Tagged(String, Box<Value>),
 }
 
-impl Default for Value {
-    fn default() -> Self {
-        Self::Null
+impl Display for Value {
+    /// Serialize the `Value` as MASON. By default, the output is compact and fits on a
+    /// single line. The alternate flag (`{:#}`) can be used to get indented output,
+    /// as written by [`Value::to_writer`].
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str("a: [1, 2], b: true").unwrap();
+    /// assert!(!value.to_string().contains('\n'));
+    /// assert!(format!("{value:#}").contains('\n'));
+    /// ```
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            self.to_writer(f)
+        } else {
+            write_compact_value(self, f, true, &SerializeOptions::new())
+        }
     }
 }
 
-impl Display for Value {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.to_writer(f)
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        // values are compared with an explicit stack, so deep values can not
+        // overflow the call stack
+        let mut stack = vec![(self, other)];
+        while let Some(pair) = stack.pop() {
+            match pair {
+                (Self::Object(a), Self::Object(b)) => {
+                    if a.len() != b.len() {
+                        return false;
+                    }
+                    for (key, a) in a {
+                        let Some(b) = b.get(key.as_str()) else {
+                            return false;
+                        };
+                        stack.push((a, b));
+                    }
+                }
+                (Self::Array(a), Self::Array(b)) => {
+                    if a.len() != b.len() {
+                        return false;
+                    }
+                    stack.extend(a.iter().zip(b));
+                }
+                (Self::Tagged(a_tag, a), Self::Tagged(b_tag, b)) => {
+                    if a_tag != b_tag {
+                        return false;
+                    }
+                    stack.push((a, b));
+                }
+                (Self::String(a), Self::String(b)) if a == b => {}
+                (Self::ByteString(a), Self::ByteString(b)) if a == b => {}
+                (Self::Number(a), Self::Number(b)) if a == b => {}
+                (Self::Bool(a), Self::Bool(b)) if a == b => {}
+                (Self::Null, Self::Null) => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+/// Numbers are compared like [`Number`]s, so NaN is equal to itself, and `Value`
+/// can implement [`Eq`].
+///
+/// ```
+/// # use mason_rs::Value;
+/// #
+/// assert_eq!(Value::from(f64::NAN), Value::from(f64::NAN));
+/// assert_eq!(Value::from(-0.0), Value::from(0.0));
+/// ```
+impl Eq for Value {}
+
+impl Hash for Value {
+    /// Hash the `Value`, so it can be used as a key in a
+    /// [`HashMap`](std::collections::HashMap) or deduplicated with a
+    /// [`HashSet`](std::collections::HashSet).
+    ///
+    /// Numbers are hashed by the bit pattern of their `f64`, except that `-0.0` is
+    /// hashed like `0.0`, since they are equal. With the `arbitrary_precision`
+    /// feature, numbers are hashed by their exact value. The order of object keys
+    /// does not affect the hash.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::{collections::HashSet, str::FromStr};
+    /// #
+    /// let values = ["{a: 1, b: [0]}", "{b: [-0.0], a: 1.0}", "{a: 2}"]
+    ///     .map(|value| Value::from_str(value).unwrap());
+    /// let unique: HashSet<_> = values.into_iter().collect();
+    /// assert_eq!(unique.len(), 2);
+    /// ```
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            Self::Object(map) => map.hash(state),
+            Self::Array(array) => array.hash(state),
+            Self::String(string) => string.hash(state),
+            Self::ByteString(bytes) => bytes.hash(state),
+            Self::Number(number) => number.hash(state),
+            Self::Bool(bool) => bool.hash(state),
+            Self::Null => {}
+            Self::Tagged(tag, value) => {
+                tag.hash(state);
+                value.hash(state);
+            }
+        }
     }
 }
 
 impl FromStr for Value {
-    type Err = io::Error;
+    type Err = crate::Error;
 
     /// Deserialize a [`Value`] from a MASON string.
     ///
@@ -47,15 +196,15 @@ impl FromStr for Value {
     /// # use std::str::FromStr;
     /// #
     /// let data = Value::from_str("[1.0, true, null]").unwrap();
-    /// assert_eq!(data, Value::Array(vec![Value::Number(1.0), Value::Bool(true), Value::Null]))
+    /// assert_eq!(data, Value::Array(vec![Value::from(1.0), Value::Bool(true), Value::Null]))
     ///
     /// ```
     ///
     /// # Errors
     ///
     /// This function can fail if the string is not valid MASON.
-    fn from_str(string: &str) -> io::Result<Self> {
-        Self::from_reader(string.as_bytes())
+    fn from_str(string: &str) -> Result<Self> {
+        Self::from_slice(string.as_bytes()).map_err(|err| err.with_source(string.as_bytes()))
     }
 }
 
@@ -88,9 +237,31 @@ impl Value {
     ///
     /// This function can fail if the I/O stream is not valid MASON, or if any errors were
     /// encountered while reading from the stream.
-    pub fn from_reader(reader: impl Read) -> io::Result<Self> {
-        let mut peek_reader = PeekReader::new(reader);
-        deserialize::parse_document(&mut peek_reader)
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        Self::from_buf_reader(BufReader::new(reader))
+    }
+
+    /// Deserialize a [`Value`] from a buffered I/O stream of MASON.
+    ///
+    /// Unlike [`Value::from_reader`], the stream is read directly from its own
+    /// buffer, so this is faster when the stream is already buffered, like a
+    /// [`BufReader`](std::io::BufReader) or [`Stdin`](std::io::Stdin).
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::io::BufReader;
+    /// #
+    /// let value = Value::from_buf_reader(BufReader::new("a: 1".as_bytes())).unwrap();
+    /// assert_eq!(value["a"], Value::from(1.0));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function can fail if the I/O stream is not valid MASON, or if any errors were
+    /// encountered while reading from the stream.
+    pub fn from_buf_reader(reader: impl BufRead) -> Result<Self> {
+        let mut peek_reader = deserialize::document_reader(reader)?;
+        deserialize::parse_document(&mut peek_reader, &mut Default::default())
     }
 
     /// Deserialize a [`Value`] from a slice of MASON bytes.
@@ -102,14 +273,14 @@ impl Value {
     /// # use std::str::FromStr;
     /// #
     /// let data = Value::from_slice(b"[1.0, true, null]").unwrap();
-    /// assert_eq!(data, Value::Array(vec![Value::Number(1.0), Value::Bool(true), Value::Null]))
+    /// assert_eq!(data, Value::Array(vec![Value::from(1.0), Value::Bool(true), Value::Null]))
     /// ```
     ///
     /// # Errors
     ///
     /// This function can fail if the byte slice is not valid MASON.
-    pub fn from_slice(bytes: &[u8]) -> io::Result<Self> {
-        Self::from_reader(bytes)
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        Self::from_buf_reader(bytes)
     }
 
     /// Serialize a [`Value`] using the given writer.
@@ -128,7 +299,7 @@ impl Value {
     /// assert_eq!(writer, value_string);
     /// ```
     ///
-    /// This is also the function used by `Value`'s display implementation:
+    /// This is also the function used by `Value`'s alternate display implementation:
     ///
     /// ```
     /// # use mason_rs::Value;
@@ -137,10 +308,96 @@ impl Value {
     /// let value_string = r#""some bytes": b"This \b \x0e\t is \x7f bytes!""#;
     /// let value = Value::from_str(value_string).unwrap();
     ///
-    /// assert_eq!(value.to_string(), value_string);
+    /// assert_eq!(format!("{value:#}"), value_string);
     /// ```
     pub fn to_writer<W: Write>(&self, writer: &mut W) -> fmt::Result {
-        write_indented_value(self, writer, "    ", 0)
+        write_value(self, writer, &SerializeOptions::default())
+    }
+
+    /// Serialize the [`Value`] as MASON into `writer`, formatted as described by
+    /// `options`.
+    ///
+    /// ```
+    /// # use mason_rs::{SerializeOptions, Value};
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str("a: {b: [1, 2]}").unwrap();
+    ///
+    /// let mut string = String::new();
+    /// value.to_writer_with_options(&mut string, &SerializeOptions::new().indentation("\t")).unwrap();
+    /// assert_eq!(string, "a: {\n\tb: [1, 2]\n}");
+    ///
+    /// let mut string = String::new();
+    /// value.to_writer_with_options(&mut string, &SerializeOptions::new().compact(true)).unwrap();
+    /// assert_eq!(string, value.to_string());
+    /// ```
+    pub fn to_writer_with_options<W: Write>(
+        &self,
+        writer: &mut W,
+        options: &SerializeOptions,
+    ) -> fmt::Result {
+        write_value(self, writer, options)
+    }
+
+    /// Serialize the [`Value`] as compact MASON on a single line, like its
+    /// `Display` implementation, and return the UTF-8 bytes.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str("a: [1, 2]").unwrap();
+    /// assert_eq!(value.to_vec(), b"a: [1, 2]");
+    /// ```
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.to_vec_with_options(&SerializeOptions::new().compact(true))
+    }
+
+    /// Serialize the [`Value`] as indented MASON, like [`Value::to_writer`], and
+    /// return the UTF-8 bytes.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str("a: {b: true}").unwrap();
+    /// assert_eq!(value.to_vec_pretty(), b"a: {\n    b: true\n}");
+    /// ```
+    pub fn to_vec_pretty(&self) -> Vec<u8> {
+        self.to_vec_with_options(&SerializeOptions::default())
+    }
+
+    fn to_vec_with_options(&self, options: &SerializeOptions) -> Vec<u8> {
+        let mut string = String::new();
+        write_value(self, &mut string, options).expect("writing to a String does not fail");
+        // the bytes of the string are reused, so they are not copied
+        string.into_bytes()
+    }
+
+    /// Serialize the [`Value`] as MASON into a string, formatted as described by
+    /// `options`, like [`Value::to_writer_with_options`], but on several threads.
+    /// The values inside large arrays and objects are serialized in parallel, and
+    /// then joined, so the result is the same as when serializing on one thread.
+    ///
+    /// This is faster for very large values, like generated configs with many
+    /// thousands of entries. Small values are faster to serialize on one thread.
+    ///
+    /// ```
+    /// # use mason_rs::{SerializeOptions, Value};
+    /// #
+    /// let value = Value::Array((0..1000).map(|i| Value::from(f64::from(i))).collect());
+    /// let options = SerializeOptions::new().inline_arrays(false);
+    ///
+    /// let mut string = String::new();
+    /// value.to_writer_with_options(&mut string, &options).unwrap();
+    /// assert_eq!(value.to_string_parallel(&options), string);
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn to_string_parallel(&self, options: &SerializeOptions) -> String {
+        let mut string = String::new();
+        write_value_parallel(self, &mut string, options)
+            .expect("writing to a String does not fail");
+        string
     }
 
     /// Return a string description of the `Value`.
@@ -155,15 +412,140 @@ impl Value {
     /// assert_eq!(value["b"].value_type(), "boolean");
     /// ```
     pub fn value_type(&self) -> &'static str {
+        self.kind().as_str()
+    }
+
+    /// Return the kind of the `Value`, which unlike [`Value::value_type`] can be
+    /// matched on.
+    ///
+    /// ```
+    /// # use mason_rs::{Value, ValueKind};
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str(r#"{a: 2, b: false}"#).unwrap();
+    /// assert_eq!(value.kind(), ValueKind::Object);
+    /// assert_eq!(value["a"].kind(), ValueKind::Number);
+    /// assert_eq!(value["b"].kind().to_string(), "boolean");
+    /// ```
+    pub fn kind(&self) -> ValueKind {
         match self {
-            Self::Null => "null",
-            Self::Bool(_) => "boolean",
-            Self::Number(_) => "number",
-            Self::String(_) => "string",
-            Self::ByteString(_) => "byte string",
-            Self::Array(_) => "array",
-            Self::Object(_) => "object",
+            Self::Null => ValueKind::Null,
+            Self::Bool(_) => ValueKind::Bool,
+            Self::Number(_) => ValueKind::Number,
+            Self::String(_) => ValueKind::String,
+            Self::ByteString(_) => ValueKind::ByteString,
+            Self::Array(_) => ValueKind::Array,
+            Self::Object(_) => ValueKind::Object,
+            Self::Tagged(..) => ValueKind::Tagged,
+        }
+    }
+
+    /// Return the nesting depth of the `Value`. Scalars have a depth of 0, and every
+    /// level of array or object nesting adds 1. Tags do not add to the depth.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// assert_eq!(Value::from_str("1").unwrap().depth(), 0);
+    /// assert_eq!(Value::from_str("[]").unwrap().depth(), 1);
+    /// assert_eq!(Value::from_str("a: [1, {b: [2]}]").unwrap().depth(), 4);
+    /// ```
+    pub fn depth(&self) -> usize {
+        // values are visited with an explicit stack, so deep values can not
+        // overflow the call stack
+        let mut max_depth = 0;
+        let mut stack = vec![(self, 0)];
+        while let Some((value, depth)) = stack.pop() {
+            match value {
+                Self::Array(array) => {
+                    max_depth = max_depth.max(depth + 1);
+                    stack.extend(array.iter().map(|value| (value, depth + 1)));
+                }
+                Self::Object(map) => {
+                    max_depth = max_depth.max(depth + 1);
+                    stack.extend(map.values().map(|value| (value, depth + 1)));
+                }
+                Self::Tagged(_, value) => stack.push((value, depth)),
+                _ => {}
+            }
         }
+        max_depth
+    }
+
+    /// Return the length of the `Value`. This is the number of elements of an array,
+    /// the number of entries of an object, the number of characters of a string,
+    /// and the number of bytes of a byte string.
+    ///
+    /// Returns `None` for numbers, booleans and null, as they have no length. The
+    /// length of a tagged value is the length of the value it tags.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str(r#"{a: [1, 2, 3], b: "hæ", c: b"h\xc3\xa6", d: 1}"#).unwrap();
+    /// assert_eq!(value.len(), Some(4));
+    /// assert_eq!(value["a"].len(), Some(3));
+    /// assert_eq!(value["b"].len(), Some(2));
+    /// assert_eq!(value["c"].len(), Some(3));
+    /// assert_eq!(value["d"].len(), None);
+    /// ```
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            Self::Object(map) => Some(map.len()),
+            Self::Array(array) => Some(array.len()),
+            Self::String(string) => Some(string.chars().count()),
+            Self::ByteString(bytes) => Some(bytes.len()),
+            Self::Tagged(_, value) => value.len(),
+            Self::Number(_) | Self::Bool(_) | Self::Null => None,
+        }
+    }
+
+    /// Returns true if the `Value` has a length of 0, false if it has a non-zero
+    /// length, and `None` if it has no length. See [`Value::len`].
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str(r#"{a: [], b: "b", c: null}"#).unwrap();
+    /// assert_eq!(value["a"].is_empty(), Some(true));
+    /// assert_eq!(value["b"].is_empty(), Some(false));
+    /// assert_eq!(value["c"].is_empty(), None);
+    /// ```
+    pub fn is_empty(&self) -> Option<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// Return the total number of values in the `Value`, including itself and all
+    /// nested values.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// assert_eq!(Value::from_str("null").unwrap().total_nodes(), 1);
+    /// assert_eq!(Value::from_str("[1, 2, 3]").unwrap().total_nodes(), 4);
+    /// assert_eq!(Value::from_str("a: [1, {b: 2}]").unwrap().total_nodes(), 5);
+    /// ```
+    pub fn total_nodes(&self) -> usize {
+        let mut total = 0;
+        let mut stack = vec![self];
+        while let Some(value) = stack.pop() {
+            match value {
+                Self::Array(array) => stack.extend(array),
+                Self::Object(map) => stack.extend(map.values()),
+                // the tag is not a value of its own
+                Self::Tagged(_, value) => {
+                    stack.push(value);
+                    continue;
+                }
+                _ => {}
+            }
+            total += 1;
+        }
+        total
     }
 
     /// Index into a MASON array or object. A string index can be used to access a
@@ -180,7 +562,7 @@ impl Value {
     /// # use std::str::FromStr;
     /// #
     /// let object = Value::from_str(r#"{ "A": 65, "B": 66, "C": 67 }"#).unwrap();
-    /// assert_eq!(*object.get("A").unwrap(), Value::Number(65.0));
+    /// assert_eq!(*object.get("A").unwrap(), Value::from(65.0));
     ///
     /// let array = Value::from_str(r#"[ "A", "B", "C" ]"#).unwrap();
     /// assert_eq!(*array.get(2).unwrap(), Value::String("C".into()));
@@ -224,7 +606,7 @@ impl Value {
     /// # use std::str::FromStr;
     /// #
     /// let mut object = Value::from_str(r#"{ "A": 65, "B": 66, "C": 67 }"#).unwrap();
-    /// *object.get_mut("A").unwrap() = Value::Number(69.0);
+    /// *object.get_mut("A").unwrap() = Value::from(69.0);
     ///
     /// let mut array = Value::from_str(r#"[ "A", "B", "C" ]"#).unwrap();
     /// *array.get_mut(2).unwrap() = Value::String("D".into());
@@ -233,6 +615,48 @@ impl Value {
         index.index_into_mut(self)
     }
 
+    /// Get a slice of the elements of a MASON array, like `0..10`, `10..` or `..10`.
+    ///
+    /// Returns `None` if `self` is not an array, or if the range is not within the
+    /// bounds of the array.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str("items: [1, 2, 3, 4, 5]").unwrap();
+    ///
+    /// let page = value["items"].get_slice(0..2).unwrap();
+    /// assert_eq!(page, [Value::from(1.0), Value::from(2.0)]);
+    /// assert_eq!(value["items"].get_slice(3..).unwrap().len(), 2);
+    /// assert_eq!(value["items"].get_slice(..5).unwrap().len(), 5);
+    ///
+    /// assert_eq!(value["items"].get_slice(4..6), None);
+    /// assert_eq!(value.get_slice(0..1), None);
+    /// ```
+    pub fn get_slice<I: SliceIndex>(&self, index: I) -> Option<&[Self]> {
+        index.slice_into(self)
+    }
+
+    /// Select the values inside the `Value` that match `query`. See the
+    /// [`query`](crate::query) module for the query syntax.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str("servers: [{host: \"a\"}, {host: \"b\", enabled: false}]").unwrap();
+    /// let hosts = value.select("servers[?enabled != false].host").unwrap();
+    /// assert_eq!(hosts, [&Value::String("a".into())]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function fails if `query` is not a valid query.
+    pub fn select(&self, query: &str) -> Result<Vec<&Self>> {
+        Ok(Query::from_str(query)?.select(self))
+    }
+
     /// Returns true if the `Value` is an Object. Returns false otherwise.
     ///
     /// For any Value on which `is_object` returns true, `as_object` and
@@ -269,7 +693,7 @@ impl Value {
     /// // The array `["an", "array"]` is not an object.
     /// assert_eq!(v["b"].as_object(), None);
     /// ```
-    pub fn as_object(&self) -> Option<&HashMap<String, Self>> {
+    pub fn as_object(&self) -> Option<&Map> {
         match self {
             Self::Object(map) => Some(map),
             _ => None,
@@ -288,13 +712,36 @@ impl Value {
     /// v["a"].as_object_mut().unwrap().clear();
     /// assert_eq!(v, Value::from_str(r#"{ "a": {} }"#).unwrap());
     /// ```
-    pub fn as_object_mut(&mut self) -> Option<&mut HashMap<String, Self>> {
+    pub fn as_object_mut(&mut self) -> Option<&mut Map> {
         match self {
             Self::Object(map) => Some(map),
             _ => None,
         }
     }
 
+    /// If the `Value` is an Object, returns the associated object without cloning
+    /// it. Returns the `Value` back otherwise.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let v = Value::from_str(r#"{ "a": { "nested": true }, "b": ["an", "array"] }"#).unwrap();
+    /// let mut object = v.into_object().unwrap();
+    ///
+    /// assert_eq!(object.remove("a").unwrap().into_object().unwrap().len(), 1);
+    ///
+    /// // The array `["an", "array"]` is not an object.
+    /// let b = object.remove("b").unwrap();
+    /// assert_eq!(b.clone().into_object(), Err(b));
+    /// ```
+    pub fn into_object(self) -> std::result::Result<Map, Self> {
+        match self {
+            Self::Object(map) => Ok(map),
+            _ => Err(self),
+        }
+    }
+
     /// Returns true if the `Value` is an Array. Returns false otherwise.
     ///
     /// For any Value on which `is_array` returns true, `as_array` and
@@ -357,6 +804,27 @@ impl Value {
         }
     }
 
+    /// If the `Value` is an Array, returns the associated vector without cloning
+    /// it. Returns the `Value` back otherwise.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let mut v = Value::from_str(r#"{ "a": ["an", "array"], "b": { "an": "object" } }"#).unwrap();
+    ///
+    /// assert_eq!(v["a"].take().into_array().unwrap().len(), 2);
+    ///
+    /// // The object `{"an": "object"}` is not an array.
+    /// assert!(v["b"].take().into_array().unwrap_err().is_object());
+    /// ```
+    pub fn into_array(self) -> std::result::Result<Vec<Self>, Self> {
+        match self {
+            Self::Array(array) => Ok(array),
+            _ => Err(self),
+        }
+    }
+
     /// Returns true if the `Value` is a String. Returns false otherwise.
     ///
     /// For any Value on which `is_string` returns true, `as_str` is guaranteed
@@ -398,6 +866,48 @@ impl Value {
         }
     }
 
+    /// If the `Value` is a String, returns the associated string without cloning
+    /// it. Returns the `Value` back otherwise.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let mut v = Value::from_str(r#"{ "a": "some string", "b": false }"#).unwrap();
+    ///
+    /// assert_eq!(v["a"].take().into_string().unwrap(), "some string");
+    ///
+    /// // The boolean `false` is not a string.
+    /// assert_eq!(v["b"].take().into_string(), Err(Value::Bool(false)));
+    /// ```
+    pub fn into_string(self) -> std::result::Result<MasonString, Self> {
+        match self {
+            Self::String(s) => Ok(s),
+            _ => Err(self),
+        }
+    }
+
+    /// If the `Value` is a ByteString, returns the associated bytes without
+    /// cloning them. Returns the `Value` back otherwise.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let mut v = Value::from_str(r#"{ "a": b"bytes", "b": "a string" }"#).unwrap();
+    ///
+    /// assert_eq!(v["a"].take().into_bytes().unwrap(), b"bytes");
+    ///
+    /// // A string is not a byte string.
+    /// assert!(v["b"].take().into_bytes().unwrap_err().is_string());
+    /// ```
+    pub fn into_bytes(self) -> std::result::Result<Vec<u8>, Self> {
+        match self {
+            Self::ByteString(bytes) => Ok(bytes),
+            _ => Err(self),
+        }
+    }
+
     /// Returns true if the `Value` is a Number. Returns false otherwise.
     ///
     /// ```
@@ -415,8 +925,8 @@ impl Value {
         self.as_number().is_some()
     }
 
-    /// If the `Value` is a Number, returns the associated double. Returns
-    /// None otherwise.
+    /// If the `Value` is a Number, returns the associated [`Number`].
+    /// Returns None otherwise.
     ///
     /// ```
     /// # use mason_rs::Value;
@@ -424,18 +934,58 @@ impl Value {
     /// #
     /// let v = Value::from_str(r#"{ "a": 1, "b": "2" }"#).unwrap();
     ///
-    /// assert_eq!(v["a"].as_number(), Some(&1.0));
+    /// assert_eq!(v["a"].as_number(), Some(&1.0.into()));
     ///
     /// // The string `"2"` is not a number.
     /// assert_eq!(v["d"].as_number(), None);
     /// ```
-    pub fn as_number(&self) -> Option<&f64> {
+    pub fn as_number(&self) -> Option<&Number> {
         match self {
             Self::Number(number) => Some(number),
             _ => None,
         }
     }
 
+    /// If the `Value` is a Number, returns it as an `f64`. Returns None otherwise.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let v = Value::from_str(r#"{ "a": 0x10, "b": "2" }"#).unwrap();
+    ///
+    /// assert_eq!(v["a"].as_f64(), Some(16.0));
+    /// assert_eq!(v["b"].as_f64(), None);
+    /// ```
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(Number::as_f64)
+    }
+
+    /// If the `Value` is a Number, returns whether its `f64` is exactly the number
+    /// it was written as. Returns None otherwise. Integers above 2^53, like large
+    /// IDs, are rounded to the closest `f64` when they are parsed, which this can
+    /// be used to warn about.
+    ///
+    /// Without the `arbitrary_precision` feature, the literal a number was written
+    /// as is not kept, so an integer `f64` below 2^53 is assumed to be exact, and
+    /// anything else is [`NumberExactness::Unknown`]. With the feature, integers
+    /// of any size are [`NumberExactness::Exact`] or
+    /// [`NumberExactness::RoundedInteger`].
+    ///
+    /// ```
+    /// # use mason_rs::{NumberExactness, Value};
+    /// # use std::str::FromStr;
+    /// #
+    /// let v = Value::from_str("id: 9007199254740993, port: 80, ratio: 0.1").unwrap();
+    ///
+    /// assert_eq!(v["port"].number_exactness(), Some(NumberExactness::Exact));
+    /// assert_ne!(v["id"].number_exactness(), Some(NumberExactness::Exact));
+    /// assert_eq!(v["ratio"].number_exactness(), Some(NumberExactness::Unknown));
+    /// ```
+    pub fn number_exactness(&self) -> Option<NumberExactness> {
+        self.as_number().map(Number::exactness)
+    }
+
     /// Returns true if the `Value` is a Boolean. Returns false otherwise.
     ///
     /// For any Value on which `is_boolean` returns true, `as_bool` is
@@ -517,6 +1067,165 @@ impl Value {
         }
     }
 
+    /// Interpret the `Value` as a number, converting it if needed:
+    ///
+    /// - Numbers are returned as they are.
+    /// - Strings are parsed as MASON numbers, ignoring leading and trailing
+    ///   whitespace, so `"8080"`, `" -1.5e3 "` and `"0xff"` are all accepted.
+    /// - Booleans are converted to `1.0` and `0.0`.
+    ///
+    /// Returns `None` for all other values, and for strings that are not numbers.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str(r#"{a: "8080", b: 1.5, c: true, d: "port"}"#).unwrap();
+    /// assert_eq!(value["a"].coerce_f64(), Some(8080.0));
+    /// assert_eq!(value["b"].coerce_f64(), Some(1.5));
+    /// assert_eq!(value["c"].coerce_f64(), Some(1.0));
+    /// assert_eq!(value["d"].coerce_f64(), None);
+    /// ```
+    pub fn coerce_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(number) => Some(number.as_f64()),
+            Self::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+            Self::String(string) => {
+                let string = string.trim();
+                if string.is_empty() {
+                    return None;
+                }
+                let mut reader = PeekReader::new(string.as_bytes());
+                let number = deserialize::parse_number(&mut reader).ok()?;
+                match reader.peek() {
+                    Ok(None) => Some(number.as_f64()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Interpret the `Value` as a boolean, converting it if needed:
+    ///
+    /// - Booleans are returned as they are.
+    /// - The numbers `1` and `0` are converted to `true` and `false`.
+    /// - The strings `"true"`, `"1"`, `"false"` and `"0"` are converted to the
+    ///   matching boolean, ignoring ASCII case and leading and trailing whitespace.
+    ///
+    /// Returns `None` for all other values.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str(r#"{a: "True", b: 0, c: false, d: "yes"}"#).unwrap();
+    /// assert_eq!(value["a"].coerce_bool(), Some(true));
+    /// assert_eq!(value["b"].coerce_bool(), Some(false));
+    /// assert_eq!(value["c"].coerce_bool(), Some(false));
+    /// assert_eq!(value["d"].coerce_bool(), None);
+    /// ```
+    pub fn coerce_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            Self::Number(number) if number.as_f64() == 1.0 => Some(true),
+            Self::Number(number) if number.as_f64() == 0.0 => Some(false),
+            Self::String(string) => {
+                let string = string.trim();
+                if string == "1" || string.eq_ignore_ascii_case("true") {
+                    Some(true)
+                } else if string == "0" || string.eq_ignore_ascii_case("false") {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Interpret the `Value` as a string, converting it if needed:
+    ///
+    /// - Strings are returned as they are.
+    /// - Numbers and booleans are formatted the same way as when they are
+    ///   serialized, so `8080` becomes `"8080"`.
+    /// - Byte strings are converted if they are valid UTF-8.
+    ///
+    /// Returns `None` for all other values.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str(r#"{a: 8080, b: true, c: b"bytes", d: null}"#).unwrap();
+    /// assert_eq!(value["a"].coerce_string().as_deref(), Some("8080"));
+    /// assert_eq!(value["b"].coerce_string().as_deref(), Some("true"));
+    /// assert_eq!(value["c"].coerce_string().as_deref(), Some("bytes"));
+    /// assert_eq!(value["d"].coerce_string(), None);
+    /// ```
+    pub fn coerce_string(&self) -> Option<String> {
+        match self {
+            Self::String(string) => Some(string.to_string()),
+            Self::Number(number) => Some(number.to_string()),
+            Self::Bool(b) => Some(b.to_string()),
+            Self::ByteString(bytes) => String::from_utf8(bytes.clone()).ok(),
+            _ => None,
+        }
+    }
+
+    /// Interpret the `Value` as a duration. Numbers are interpreted as seconds, and
+    /// strings are parsed as human friendly durations, like `"30s"`, `"1.5h"` or
+    /// `"1h 30m"`. The supported units are `ns`, `us` (or `µs`), `ms`, `s`, `m`, `h`
+    /// and `d`.
+    ///
+    /// Returns `None` for all other values, for strings that are not durations,
+    /// and for negative durations.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// use std::time::Duration;
+    ///
+    /// let value = Value::from_str(r#"{a: "5m", b: "1h 30m", c: 2.5, d: "soon"}"#).unwrap();
+    /// assert_eq!(value["a"].as_duration(), Some(Duration::from_secs(300)));
+    /// assert_eq!(value["b"].as_duration(), Some(Duration::from_secs(5400)));
+    /// assert_eq!(value["c"].as_duration(), Some(Duration::from_millis(2500)));
+    /// assert_eq!(value["d"].as_duration(), None);
+    /// ```
+    pub fn as_duration(&self) -> Option<Duration> {
+        match self {
+            Self::Number(secs) => Duration::try_from_secs_f64(secs.as_f64()).ok(),
+            Self::String(string) => units::parse_duration(string),
+            _ => None,
+        }
+    }
+
+    /// Interpret the `Value` as a number of bytes. Numbers are interpreted as
+    /// bytes, and strings are parsed as human friendly byte sizes, like `"512"`,
+    /// `"10kB"` or `"2GiB"`. Both decimal (`kB`, `MB`, `GB`, `TB`, `PB`) and binary
+    /// (`KiB`, `MiB`, `GiB`, `TiB`, `PiB`) units are supported.
+    ///
+    /// Returns `None` for all other values, for strings that are not byte sizes,
+    /// and for sizes that are not a whole number of bytes.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str(r#"{a: "2GiB", b: "10 kB", c: 512, d: 0.5}"#).unwrap();
+    /// assert_eq!(value["a"].as_byte_size(), Some(2 * 1024 * 1024 * 1024));
+    /// assert_eq!(value["b"].as_byte_size(), Some(10_000));
+    /// assert_eq!(value["c"].as_byte_size(), Some(512));
+    /// assert_eq!(value["d"].as_byte_size(), None);
+    /// ```
+    pub fn as_byte_size(&self) -> Option<u64> {
+        match self {
+            Self::Number(bytes) => units::whole_bytes(bytes.as_f64()),
+            Self::String(string) => units::parse_byte_size(string),
+            _ => None,
+        }
+    }
+
     /// Takes the value out of the `Value`, leaving a `Null` in its place.
     ///
     /// ```
@@ -530,4 +1239,453 @@ impl Value {
     pub fn take(&mut self) -> Self {
         mem::replace(self, Self::Null)
     }
+
+    /// Insert a value at the given key path, returning the previous value at that
+    /// path, if any.
+    ///
+    /// The path is a list of keys separated by `.`. When indexing into an array,
+    /// the key is parsed as an index. Intermediate objects are created as needed,
+    /// and null values along the path are treated like empty objects. If the last
+    /// index of the path is equal to the length of an array, the value is pushed
+    /// to the array.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let mut value = Value::from_str(r#"{ a: { b: [1, 2] } }"#).unwrap();
+    ///
+    /// assert_eq!(value.insert_path("a.b.0", Value::Bool(true)), Some(Value::from(1.0)));
+    /// assert_eq!(value.insert_path("a.b.2", Value::Null), None);
+    /// assert_eq!(value.insert_path("x.y.z", Value::from(3.0)), None);
+    ///
+    /// assert_eq!(
+    ///     value,
+    ///     Value::from_str(r#"{ a: { b: [true, 2, null] }, x: { y: { z: 3 } } }"#).unwrap(),
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Like [square-bracket indexing](Value#impl-IndexMut%3CI%3E-for-Value), this
+    /// panics if the path goes through a value that is neither an object, an
+    /// array, nor null, or if an array index is bigger than the length of the array.
+    pub fn insert_path(&mut self, path: &str, value: Self) -> Option<Self> {
+        let (parent, last) = match path.rsplit_once('.') {
+            Some((parent, last)) => (
+                parent
+                    .split('.')
+                    .fold(self, |current, key| match path_index(current, key) {
+                        Some(index) => index.index_or_insert(current),
+                        None => key.index_or_insert(current),
+                    }),
+                last,
+            ),
+            None => (self, path),
+        };
+
+        match (path_index(parent, last), parent) {
+            (Some(index), Self::Array(array)) if index < array.len() => {
+                Some(mem::replace(&mut array[index], value))
+            }
+            (Some(index), parent) => {
+                *index.index_or_insert(parent) = value;
+                None
+            }
+            (None, parent) => {
+                if parent.is_null() {
+                    *parent = Self::Object(Map::new());
+                }
+                match parent {
+                    Self::Object(map) => map.insert(last.to_owned(), value),
+                    _ => panic!(
+                        "cannot access key {last:?} in MASON {}",
+                        parent.value_type()
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Remove the value at the given key path, returning it if it existed.
+    ///
+    /// The path is a list of keys separated by `.`. When indexing into an array,
+    /// the key is parsed as an index, and the removed element is shifted out of
+    /// the array.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let mut value = Value::from_str(r#"{ a: { b: [1, 2] }, c: "c" }"#).unwrap();
+    ///
+    /// assert_eq!(value.remove_path("a.b.0"), Some(Value::from(1.0)));
+    /// assert_eq!(value.remove_path("c"), Some(Value::String("c".into())));
+    /// assert_eq!(value.remove_path("a.x.y"), None);
+    ///
+    /// assert_eq!(value, Value::from_str(r#"{ a: { b: [2] } }"#).unwrap());
+    /// ```
+    pub fn remove_path(&mut self, path: &str) -> Option<Self> {
+        let (parent, last) = match path.rsplit_once('.') {
+            Some((parent, last)) => (
+                parent.split('.').try_fold(self, |current, key| {
+                    match path_index(current, key) {
+                        Some(index) => current.get_mut(index),
+                        None => current.get_mut(key),
+                    }
+                })?,
+                last,
+            ),
+            None => (self, path),
+        };
+
+        match parent {
+            Self::Object(map) => map.remove(last),
+            Self::Array(array) => match last.parse::<usize>() {
+                Ok(index) if index < array.len() => Some(array.remove(index)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Get the value at the given key path, distinguishing between a missing value,
+    /// which gives `None`, and an explicit `null`, which gives `Some(None)`. A path
+    /// that goes through a `null` is missing.
+    ///
+    /// The path is a list of keys separated by `.`. When indexing into an array,
+    /// the key is parsed as an index.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str("a: {b: [1, null]}, c: null").unwrap();
+    ///
+    /// assert_eq!(value.get_opt("a.b.0"), Some(Some(&Value::from(1.0))));
+    /// assert_eq!(value.get_opt("a.b.1"), Some(None));
+    /// assert_eq!(value.get_opt("c"), Some(None));
+    /// assert_eq!(value.get_opt("a.b.2"), None);
+    /// assert_eq!(value.get_opt("c.d"), None);
+    /// ```
+    pub fn get_opt(&self, path: &str) -> Option<Option<&Self>> {
+        let value =
+            path.split('.')
+                .try_fold(self, |current, key| match path_index(current, key) {
+                    Some(index) => current.get(index),
+                    None => current.get(key),
+                })?;
+        Some(if value.is_null() { None } else { Some(value) })
+    }
+
+    /// Remove every key whose value is `null` from the objects in the `Value`,
+    /// including objects nested inside other arrays and objects. Elements of
+    /// arrays are kept, even if they are `null`.
+    ///
+    /// This is useful for configuration overlays, where `key: null` commonly
+    /// means that the key should be deleted.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let mut value = Value::from_str("a: null, b: {c: null, d: [null, {e: null}]}").unwrap();
+    /// value.prune_nulls();
+    /// assert_eq!(value, Value::from_str("b: {d: [null, {}]}").unwrap());
+    /// ```
+    pub fn prune_nulls(&mut self) {
+        match self {
+            Self::Object(map) => {
+                map.retain(|_, value| !value.is_null());
+                for value in map.values_mut() {
+                    value.prune_nulls();
+                }
+            }
+            Self::Array(array) => {
+                for value in array {
+                    value.prune_nulls();
+                }
+            }
+            Self::Tagged(_, value) => value.prune_nulls(),
+            _ => {}
+        }
+    }
+
+    /// Sort every array in the `Value` with the comparator function `compare`,
+    /// including arrays nested inside other arrays and objects. The sort is
+    /// stable. Objects are unordered, so they are left as they are.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let mut value = Value::from_str("a: [3, 1, 2], b: {c: [2.5, -1]}").unwrap();
+    /// value.sort_arrays_by(|a, b| {
+    ///     let a = a.as_f64().unwrap_or(f64::NAN);
+    ///     a.total_cmp(&b.as_f64().unwrap_or(f64::NAN))
+    /// });
+    /// assert_eq!(value, Value::from_str("a: [1, 2, 3], b: {c: [-1, 2.5]}").unwrap());
+    /// ```
+    pub fn sort_arrays_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&Self, &Self) -> Ordering,
+    {
+        self.sort_arrays_by_inner(&mut compare);
+    }
+
+    fn sort_arrays_by_inner<F>(&mut self, compare: &mut F)
+    where
+        F: FnMut(&Self, &Self) -> Ordering,
+    {
+        match self {
+            Self::Array(array) => {
+                for value in array.iter_mut() {
+                    value.sort_arrays_by_inner(compare);
+                }
+                array.sort_by(|a, b| compare(a, b));
+            }
+            Self::Object(map) => {
+                for value in map.values_mut() {
+                    value.sort_arrays_by_inner(compare);
+                }
+            }
+            Self::Tagged(_, value) => value.sort_arrays_by_inner(compare),
+            _ => {}
+        }
+    }
+
+    /// Sort every array in the `Value` by the key extracted with `f`, including
+    /// arrays nested inside other arrays and objects. The sort is stable. Objects
+    /// are unordered, so they are left as they are.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let mut value = Value::from_str(r#"[{name: "b"}, {name: "c"}, {name: "a"}]"#).unwrap();
+    /// value.sort_arrays_by_key(|value| value["name"].as_str().map(String::from));
+    /// assert_eq!(
+    ///     value,
+    ///     Value::from_str(r#"[{name: "a"}, {name: "b"}, {name: "c"}]"#).unwrap()
+    /// );
+    /// ```
+    pub fn sort_arrays_by_key<K, F>(&mut self, mut f: F)
+    where
+        K: Ord,
+        F: FnMut(&Self) -> K,
+    {
+        self.sort_arrays_by(|a, b| f(a).cmp(&f(b)));
+    }
+
+    /// Remove duplicate elements from every array in the `Value`, including arrays
+    /// nested inside other arrays and objects. The first occurrence of each element
+    /// is kept. Nested arrays are deduplicated before they are compared.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let mut value = Value::from_str("a: [1, 2, 1, 3, 2], b: [[1, 1], [1]]").unwrap();
+    /// value.dedup_arrays();
+    /// assert_eq!(value, Value::from_str("a: [1, 2, 3], b: [[1]]").unwrap());
+    /// ```
+    pub fn dedup_arrays(&mut self) {
+        match self {
+            Self::Array(array) => {
+                for value in array.iter_mut() {
+                    value.dedup_arrays();
+                }
+                let mut unique: Vec<Self> = Vec::with_capacity(array.len());
+                for value in array.drain(..) {
+                    if !unique.contains(&value) {
+                        unique.push(value);
+                    }
+                }
+                *array = unique;
+            }
+            Self::Object(map) => {
+                for value in map.values_mut() {
+                    value.dedup_arrays();
+                }
+            }
+            Self::Tagged(_, value) => value.dedup_arrays(),
+            _ => {}
+        }
+    }
+
+    /// Compare two values deeply, like `==`, except that numbers are considered
+    /// equal if they differ by at most `epsilon`, and NaN is not equal to anything. This is useful when comparing
+    /// documents whose numbers have been through floating point arithmetic or
+    /// formatting.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let value = Value::from_str("a: [0.3, {b: 1e-10}]").unwrap();
+    /// let other = Value::from_str(&format!("a: [{}, {{b: 0}}]", 0.1 + 0.2)).unwrap();
+    /// assert_ne!(value, other);
+    /// assert!(value.approx_eq(&other, 1e-9));
+    /// assert!(!value.approx_eq(&other, 1e-12));
+    /// ```
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        match (self, other) {
+            (Self::Number(a), Self::Number(b)) => {
+                // compare the f64s with `==`, so NaN is not approximately anything
+                let (a, b) = (a.as_f64(), b.as_f64());
+                a == b || (a - b).abs() <= epsilon
+            }
+            (Self::Array(a), Self::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.approx_eq(b, epsilon))
+            }
+            (Self::Object(a), Self::Object(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .all(|(key, a)| b.get(key).is_some_and(|b| a.approx_eq(b, epsilon)))
+            }
+            (Self::Tagged(a_tag, a), Self::Tagged(b_tag, b)) => {
+                a_tag == b_tag && a.approx_eq(b, epsilon)
+            }
+            _ => self == other,
+        }
+    }
+}
+
+/// Parse a key in a key path as an array index, if `value` is an array.
+fn path_index(value: &Value, key: &str) -> Option<usize> {
+    match value {
+        Value::Array(_) => key.parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, hash::DefaultHasher};
+
+    use super::*;
+
+    fn hash(value: &Value) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_hash() {
+        let value = |string| Value::from_str(string).unwrap();
+        let tagged = |tag: &str, value| Value::Tagged(tag.into(), Box::new(value));
+
+        for (a, b) in [
+            (
+                value("{a: 1, b: {c: [1, 2], d: null}}"),
+                value("b: {d: null, c: [1, 2]}, a: 1"),
+            ),
+            (value("0"), value("-0.0")),
+            (value("b\"ab\""), value("b\"\\x61\\x62\"")),
+            (
+                tagged("env", value("\"HOME\"")),
+                tagged("env", value("\"HOME\"")),
+            ),
+        ] {
+            assert_eq!(a, b);
+            assert_eq!(hash(&a), hash(&b), "{a} and {b} should have the same hash");
+        }
+        for (a, b) in [
+            (value("{a: 1}"), value("{a: 2}")),
+            (value("[[1], []]"), value("[[], [1]]")),
+            (value("\"a\""), value("b\"a\"")),
+            (value("null"), value("{}")),
+            (tagged("env", value("1")), tagged("secret", value("1"))),
+        ] {
+            assert_ne!(
+                hash(&a),
+                hash(&b),
+                "{a} and {b} should have different hashes"
+            );
+        }
+
+        let mut cache = HashMap::new();
+        cache.insert(value("{x: 1, y: 2}"), "point");
+        assert_eq!(cache.get(&value("{y: 2.0, x: 1}")), Some(&"point"));
+
+        let nan = Value::from(f64::NAN);
+        assert_eq!(nan, nan);
+        assert_eq!(hash(&nan), hash(&nan));
+        assert_ne!(nan, Value::from(-f64::NAN));
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        let approx_eq = |a: &str, b: &str, epsilon| {
+            let (a, b) = (Value::from_str(a).unwrap(), Value::from_str(b).unwrap());
+            a.approx_eq(&b, epsilon) && b.approx_eq(&a, epsilon)
+        };
+
+        assert!(approx_eq("1", "1.05", 0.1));
+        assert!(!approx_eq("1", "1.2", 0.1));
+        assert!(approx_eq("[1, \"a\", null]", "[1.01, \"a\", null]", 0.1));
+        assert!(!approx_eq("[1, 2]", "[1]", 0.1));
+        assert!(!approx_eq("[1]", "[\"1\"]", 0.1));
+        assert!(approx_eq("a: 1, b: {c: 2}", "b: {c: 2.01}, a: 1", 0.1));
+        assert!(!approx_eq("a: 1, b: 2", "a: 1, c: 2", 0.1));
+        assert!(!approx_eq("a: 1", "a: 1, b: 2", 0.1));
+        assert!(approx_eq("b\"bytes\"", "b\"bytes\"", 0.0));
+
+        let infinity = Value::from(f64::INFINITY);
+        assert!(infinity.approx_eq(&infinity, 0.0));
+        assert!(!Value::from(f64::NAN).approx_eq(&Value::from(f64::NAN), 1.0));
+    }
+
+    #[test]
+    #[cfg(not(feature = "arbitrary_precision"))]
+    fn test_number_exactness() {
+        let value = Value::from_str("[9007199254740991, 9007199254740993, -1e3, 0.5]").unwrap();
+        let exactness: Vec<_> = value
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|number| number.number_exactness().unwrap())
+            .collect();
+        assert_eq!(
+            exactness,
+            [
+                NumberExactness::Exact,
+                NumberExactness::Unknown,
+                NumberExactness::Exact,
+                NumberExactness::Unknown,
+            ]
+        );
+        assert_eq!(Value::Null.number_exactness(), None);
+    }
+
+    #[test]
+    fn test_byte_order_mark() {
+        let value = Value::from_slice(b"\xEF\xBB\xBFa: [1]").unwrap();
+        assert_eq!(value["a"][0], Value::from(1.0));
+        assert_eq!(Value::from_str("\u{FEFF}true").unwrap(), Value::Bool(true));
+
+        // errors point to the same column as if there was no byte order mark
+        let err = Value::from_slice(b"\xEF\xBB\xBFa: ]").unwrap_err();
+        let position = err.position().unwrap();
+        assert_eq!((position.offset, position.column), (6, 4));
+
+        // a byte order mark is only allowed at the start of the document
+        assert!(Value::from_str("a: \u{FEFF}1").is_err());
+
+        let mut utf16le = vec![0xFF, 0xFE];
+        let mut utf16be = vec![0xFE, 0xFF];
+        for unit in "a: \"π 🎉\"".encode_utf16() {
+            utf16le.extend(unit.to_le_bytes());
+            utf16be.extend(unit.to_be_bytes());
+        }
+        for bytes in [utf16le, utf16be] {
+            let value = Value::from_reader(bytes.as_slice());
+            if cfg!(feature = "encoding") {
+                assert_eq!(value.unwrap()["a"].as_str(), Some("π 🎉"));
+            } else {
+                assert!(value.unwrap_err().message().contains("UTF-16"));
+            }
+        }
+    }
 }
@@ -0,0 +1,460 @@
+use std::{
+    fmt::{self, Display},
+    hash::{Hash, Hasher},
+};
+
+use crate::Value;
+use crate::deserialize::ParsedNumber;
+#[cfg(feature = "arbitrary_precision")]
+use crate::{deserialize::parse_number, peek_reader::PeekReader};
+
+/// A MASON number, which is the type of [`Value::Number`](super::Value::Number).
+///
+/// A number is stored as an `f64`, which [`Number::as_f64`] returns. With the
+/// `arbitrary_precision` feature, a number that was parsed also keeps its literal,
+/// like `18446744073709551617`, `0.1000000000000000000001` or `0xff`, which is
+/// written when the number is serialized, so parsing and writing a document does
+/// not change its numbers, not even their radix or digit separators. A number that
+/// is replaced is written in decimal, like any other `f64`. [`Number::as_u128`]
+/// and [`Number::as_i128`] then read integers exactly, even when they are too big
+/// for an `f64`. The type is the same with or without the feature, so the feature
+/// does not change the API.
+///
+/// Two numbers are equal if they have the same value, where integers are compared
+/// exactly, so `0x10 == 16.0`. Other numbers are compared by the bit pattern of
+/// their `f64`, except that `-0.0` is equal to `0.0`, so NaN is equal to itself.
+///
+/// ```
+/// # use mason_rs::{Number, Value};
+/// # use std::str::FromStr;
+/// #
+/// let value = Value::from_str("[0x10, 2.5]").unwrap();
+/// let Value::Number(number) = &value[0] else { unreachable!() };
+/// assert_eq!(number.as_u128(), Some(16));
+/// assert_eq!(*number, Number::from(16.0));
+/// assert_eq!(value[1].as_f64(), Some(2.5));
+/// ```
+///
+/// With the `arbitrary_precision` feature:
+///
+/// ```
+/// # use mason_rs::Value;
+/// # use std::str::FromStr;
+/// #
+/// # #[cfg(feature = "arbitrary_precision")] {
+/// let value = Value::from_str("[340282366920938463463374607431768211455, 0.1000000000000000000001]").unwrap();
+/// let Value::Number(max) = &value[0] else { unreachable!() };
+/// assert_eq!(max.as_u128(), Some(u128::MAX));
+/// assert_eq!(value.to_string(), "[340282366920938463463374607431768211455, 0.1000000000000000000001]");
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Number {
+    value: f64,
+    /// The text the number was parsed from, or the digits of an integer it was created from.
+    #[cfg(feature = "arbitrary_precision")]
+    literal: Option<Box<str>>,
+    /// The number as exactly as we know it, see [`Number::exact`]. It is parsed
+    /// from the literal when the number is created, so comparing and hashing
+    /// numbers does not parse them again.
+    #[cfg(feature = "arbitrary_precision")]
+    exact: ParsedNumber,
+}
+
+/// Whether the `f64` of a number is exactly the number it was written as, see
+/// [`Value::number_exactness`](super::Value::number_exactness).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NumberExactness {
+    /// The `f64` is exactly the number.
+    Exact,
+    /// The number is an integer that an `f64` can not represent, so the `f64` is
+    /// the closest `f64` to it instead.
+    RoundedInteger,
+    /// It is not known if the `f64` is exact, because the number is not an
+    /// integer, or because it is too large for every integer of its size to be an
+    /// `f64` and the literal it was written as is not known.
+    Unknown,
+}
+
+impl Number {
+    /// Create a number that was parsed from `literal`.
+    #[cfg(feature = "arbitrary_precision")]
+    pub(crate) fn from_literal(literal: String, value: f64) -> Self {
+        Self {
+            value,
+            exact: parse_exact(&literal, value),
+            literal: Some(literal.into_boxed_str()),
+        }
+    }
+
+    /// The number as an `f64`, which might not be exact.
+    pub fn as_f64(&self) -> f64 {
+        self.value
+    }
+
+    /// The literal the number was parsed from, or the digits of the integer it was
+    /// created from. Numbers created from an `f64` have no literal, and literals
+    /// are only kept with the `arbitrary_precision` feature.
+    pub fn literal(&self) -> Option<&str> {
+        #[cfg(feature = "arbitrary_precision")]
+        return self.literal.as_deref();
+        #[cfg(not(feature = "arbitrary_precision"))]
+        None
+    }
+
+    /// The number as a `u128`, if it is a non-negative integer that fits in one.
+    ///
+    /// ```
+    /// # use mason_rs::Number;
+    /// #
+    /// assert_eq!(Number::from(1_u64 << 60).as_u128(), Some(1 << 60));
+    /// assert_eq!(Number::from(2.0).as_u128(), Some(2));
+    /// assert_eq!(Number::from(-2).as_u128(), None);
+    /// assert_eq!(Number::from(2.5).as_u128(), None);
+    /// ```
+    pub fn as_u128(&self) -> Option<u128> {
+        match self.exact() {
+            ParsedNumber::PosInt(int) => Some(int),
+            _ => None,
+        }
+    }
+
+    /// The number as an `i128`, if it is an integer that fits in one.
+    ///
+    /// ```
+    /// # use mason_rs::Number;
+    /// #
+    /// assert_eq!(Number::from(i64::MIN).as_i128(), Some(i64::MIN.into()));
+    /// assert_eq!(Number::from(f64::MAX).as_i128(), None);
+    /// ```
+    pub fn as_i128(&self) -> Option<i128> {
+        match self.exact() {
+            ParsedNumber::PosInt(int) => i128::try_from(int).ok(),
+            ParsedNumber::NegInt(int) => Some(int),
+            ParsedNumber::Float(_) => None,
+        }
+    }
+
+    /// The number as an integer of type `T`, if it is an integer that fits in a `T`.
+    pub(crate) fn as_integer<T: TryFrom<i128> + TryFrom<u128>>(&self) -> Option<T> {
+        match self.as_u128() {
+            Some(int) => T::try_from(int).ok(),
+            None => T::try_from(self.as_i128()?).ok(),
+        }
+    }
+
+    /// Whether the number is an integer, and its `f64` is exactly that integer. An
+    /// integer that does not fit in an `f64`, like an ID above 2^53, is rounded
+    /// when it is parsed, so this is false for it.
+    ///
+    /// ```
+    /// # use mason_rs::Number;
+    /// #
+    /// assert!(Number::from(9_007_199_254_740_991_u64).is_exact_integer());
+    /// assert!(!Number::from(9_007_199_254_740_993_u64).is_exact_integer());
+    /// assert!(Number::from(3.0).is_exact_integer());
+    /// assert!(!Number::from(0.5).is_exact_integer());
+    /// ```
+    pub fn is_exact_integer(&self) -> bool {
+        self.value.fract() == 0.0 && self.exactness() == NumberExactness::Exact
+    }
+
+    /// How exactly the `f64` represents the number. The literal of the number is
+    /// not kept, so an integer `f64` is assumed to have been written as an integer.
+    #[cfg(not(feature = "arbitrary_precision"))]
+    pub(crate) fn exactness(&self) -> NumberExactness {
+        // every integer below 2^53 is an f64, so an f64 below it was not rounded, but
+        // 2^53 itself could be 2^53 + 1 rounded down
+        if self.value.fract() == 0.0 && self.value.abs() < 2f64.powi(53) {
+            NumberExactness::Exact
+        } else {
+            NumberExactness::Unknown
+        }
+    }
+
+    /// How exactly the `f64` represents the literal of the number. Numbers that
+    /// were created from an `f64` are exact, since the `f64` is the number.
+    #[cfg(feature = "arbitrary_precision")]
+    pub(crate) fn exactness(&self) -> NumberExactness {
+        let Some(literal) = self.literal() else {
+            return NumberExactness::Exact;
+        };
+        let value = self.value;
+        let exact = match parse_number(&mut PeekReader::from_buf_read(literal.as_bytes())) {
+            Ok(ParsedNumber::PosInt(int)) => {
+                (0.0..2f64.powi(128)).contains(&value) && value as u128 == int
+            }
+            Ok(ParsedNumber::NegInt(int)) => {
+                (-2f64.powi(127)..0.0).contains(&value) && value as i128 == int
+            }
+            _ => return NumberExactness::Unknown,
+        };
+        if exact {
+            NumberExactness::Exact
+        } else {
+            NumberExactness::RoundedInteger
+        }
+    }
+
+    /// The number as exactly as we know it, which is its `f64`.
+    #[cfg(not(feature = "arbitrary_precision"))]
+    fn exact(&self) -> ParsedNumber {
+        integer_f64(self.value)
+    }
+
+    /// The number as exactly as we know it, which is the number it is written as.
+    /// Integers are always returned as integers, even if they were written like
+    /// `1.0` or `1e3`.
+    #[cfg(feature = "arbitrary_precision")]
+    fn exact(&self) -> ParsedNumber {
+        self.exact
+    }
+}
+
+/// The number `literal` is written as, where `value` is its `f64`, with integers
+/// returned as integers, like [`Number::exact`].
+#[cfg(feature = "arbitrary_precision")]
+fn parse_exact(literal: &str, value: f64) -> ParsedNumber {
+    match parse_number(&mut PeekReader::from_buf_read(literal.as_bytes()))
+        .unwrap_or(ParsedNumber::Float(value))
+    {
+        ParsedNumber::Float(float) => integer_f64(float),
+        parsed => parsed,
+    }
+}
+
+/// The number an `f64` without a literal is written as, like [`Number::exact`].
+#[cfg(feature = "arbitrary_precision")]
+fn exact_f64(value: f64) -> ParsedNumber {
+    // every integer below 2^53 is written exactly
+    if value.fract() != 0.0 || value.abs() < 2f64.powi(53) {
+        return integer_f64(value);
+    }
+    // larger numbers are written with the shortest digits that round trip, so
+    // `1e33` is written as `1000000000000000000000000000000000`, even though the
+    // f64 is really 1000000000000000042420637374017961984
+    parse_exact(&value.to_string(), value)
+}
+
+/// `float` as an integer, if it is an integer that fits in an `i128` or `u128`.
+fn integer_f64(float: f64) -> ParsedNumber {
+    if float.fract() != 0.0 {
+        ParsedNumber::Float(float)
+    } else if (0.0..2f64.powi(128)).contains(&float) {
+        ParsedNumber::PosInt(float as u128)
+    } else if (-2f64.powi(127)..0.0).contains(&float) {
+        ParsedNumber::NegInt(float as i128)
+    } else {
+        ParsedNumber::Float(float)
+    }
+}
+
+/// Compare the bit patterns of two `f64`s, except that `-0.0` is equal to `0.0`.
+/// Unlike `==`, this makes NaN equal to itself, which is consistent with
+/// [`hash_f64`].
+fn f64_eq(a: f64, b: f64) -> bool {
+    a == b || a.to_bits() == b.to_bits()
+}
+
+/// Hash the bit pattern of `number`, except that `-0.0` is hashed like `0.0`, since
+/// they are equal.
+fn hash_f64<H: Hasher>(number: f64, state: &mut H) {
+    let number = if number == 0.0 { 0.0 } else { number };
+    number.to_bits().hash(state);
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.exact(), other.exact()) {
+            (ParsedNumber::Float(a), ParsedNumber::Float(b)) => f64_eq(a, b),
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl Eq for Number {}
+
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self.exact() {
+            ParsedNumber::PosInt(int) => int.hash(state),
+            ParsedNumber::NegInt(int) => int.hash(state),
+            ParsedNumber::Float(float) => hash_f64(float, state),
+        }
+    }
+}
+
+impl fmt::Debug for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.literal() {
+            Some(literal) => f.write_str(literal),
+            None => fmt::Debug::fmt(&self.value, f),
+        }
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.literal() {
+            Some(literal) => f.write_str(literal),
+            None => write!(f, "{}", self.value),
+        }
+    }
+}
+
+impl From<f64> for Number {
+    fn from(value: f64) -> Self {
+        Self {
+            value,
+            #[cfg(feature = "arbitrary_precision")]
+            literal: None,
+            #[cfg(feature = "arbitrary_precision")]
+            exact: exact_f64(value),
+        }
+    }
+}
+
+macro_rules! impl_from_integer {
+    ($($int:ident),*) => {
+        $(
+            impl From<$int> for Number {
+                fn from(int: $int) -> Self {
+                    #[cfg(feature = "arbitrary_precision")]
+                    return Self::from_literal(int.to_string(), int as f64);
+                    #[cfg(not(feature = "arbitrary_precision"))]
+                    Self { value: int as f64 }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_integer!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
+);
+
+impl From<f64> for Value {
+    fn from(number: f64) -> Self {
+        Self::Number(number.into())
+    }
+}
+
+#[cfg(all(test, feature = "arbitrary_precision"))]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_arbitrary_precision() {
+        let literals = [
+            "18446744073709551617",
+            "-170141183460469231731687303715884105728",
+            "3.14159265358979323846264338327950288",
+            "0x1'0000'0000'0000'0001",
+            "1e400",
+        ];
+        for literal in literals {
+            let value = Value::from_str(literal).unwrap();
+            assert_eq!(value.to_string(), literal);
+            let Value::Number(number) = &value else {
+                panic!("{literal} is not a number");
+            };
+            assert_eq!(number.literal(), Some(literal));
+        }
+
+        let number = |literal: &str| match Value::from_str(literal).unwrap() {
+            Value::Number(number) => number,
+            value => panic!("{value} is not a number"),
+        };
+        assert_eq!(
+            number("18446744073709551617").as_u128(),
+            Some(u64::MAX as u128 + 2)
+        );
+        assert_eq!(
+            number("0x1'0000'0000'0000'0001").as_u128(),
+            Some(u64::MAX as u128 + 2)
+        );
+        assert_eq!(number("-0b11").as_i128(), Some(-3));
+        assert_eq!(number("1.5").as_i128(), None);
+
+        assert_eq!(number("0x10"), Number::from(16.0));
+        assert_eq!(number("1e3"), Number::from(1000));
+        assert_eq!(number("1.50"), Number::from(1.5));
+        assert_ne!(
+            number("18446744073709551617"),
+            number("18446744073709551616")
+        );
+        assert_eq!(Number::from(f64::NAN), Number::from(f64::NAN));
+        assert_eq!(Number::from(-0.0), Number::from(0.0));
+
+        // numbers that were not parsed are written like floats
+        assert_eq!(Value::from(0.5).to_string(), "0.5");
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            serde_json::to_string(&Value::from_str("[18446744073709551617, 0x10, 2.5]").unwrap())
+                .unwrap(),
+            "[18446744073709551617,16.0,2.5]"
+        );
+
+        assert_eq!(
+            Value::Number(Number::from(u128::MAX)).to_string(),
+            u128::MAX.to_string()
+        );
+    }
+
+    #[test]
+    fn test_exactness() {
+        let exactness = |literal: &str| Value::from_str(literal).unwrap().number_exactness();
+        assert_eq!(
+            exactness("9007199254740993"),
+            Some(NumberExactness::RoundedInteger)
+        );
+        assert_eq!(
+            exactness("-9007199254740993"),
+            Some(NumberExactness::RoundedInteger)
+        );
+        assert_eq!(exactness("9007199254740992"), Some(NumberExactness::Exact));
+        assert_eq!(
+            exactness("0x20'0000'0000'0001"),
+            Some(NumberExactness::RoundedInteger)
+        );
+        assert_eq!(exactness("1e20"), Some(NumberExactness::Unknown));
+        assert_eq!(
+            Value::Number(Number::from(1e300)).number_exactness(),
+            Some(NumberExactness::Exact)
+        );
+        assert!(!Number::from(u128::MAX).is_exact_integer());
+        assert!(Number::from(i128::MIN).is_exact_integer());
+    }
+
+    #[test]
+    fn test_literal_formatting() {
+        let mut value = Value::from_str(
+            "mask: 0xFF, size: 1'000'000, mode: 0o755, flags: 0b1010, rate: +1.5e3",
+        )
+        .unwrap();
+        crate::merge::merge(
+            &mut value,
+            Value::from_str("extra: {limit: 0x7fff'ffff}").unwrap(),
+        );
+
+        for string in [value.to_string(), format!("{value:#}")] {
+            for literal in [
+                "0xFF",
+                "1'000'000",
+                "0o755",
+                "0b1010",
+                "+1.5e3",
+                "0x7fff'ffff",
+            ] {
+                assert!(string.contains(literal), "{literal} not in {string:?}");
+            }
+            assert_eq!(Value::from_str(&string).unwrap(), value);
+        }
+
+        value["mask"] = Value::from(255.0);
+        assert_eq!(value["mask"].to_string(), "255");
+    }
+}
@@ -0,0 +1,186 @@
+use std::cmp::Ordering;
+
+use crate::Value;
+
+impl Value {
+    /// Compare two values with a total order, which can be used to sort arrays of
+    /// values of different types, or to get deterministic output.
+    ///
+    /// Values of different types are ordered like
+    /// `null < bool < number < string < byte string < array < object < tagged value`.
+    /// Values of the same type are ordered like this:
+    ///
+    /// - `false` is less than `true`.
+    /// - Numbers are ordered by their `f64` value with [`f64::total_cmp`], except that
+    ///   `-0.0` is equal to `0.0`. This puts negative NaN first and positive NaN last.
+    ///   With the `arbitrary_precision` feature, numbers with the same `f64` value
+    ///   are equal, even if their exact values differ.
+    /// - Strings and byte strings are ordered by their bytes.
+    /// - Arrays are ordered element by element, and a shorter array is less than a
+    ///   longer array that starts with the same elements.
+    /// - Objects are ordered like arrays of their key-value pairs sorted by key.
+    /// - Tagged values are ordered by their tag, and then by their value.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let mut value = Value::from_str(r#"[{a: 1}, "b", [2], 1.5, "a", true, null, [1, 2], -3]"#).unwrap();
+    /// value.sort_arrays_by(Value::cmp_total);
+    /// assert_eq!(
+    ///     value,
+    ///     Value::from_str(r#"[null, true, -3, 1.5, "a", "b", [1, 2], [2], {a: 1}]"#).unwrap(),
+    /// );
+    /// ```
+    pub fn cmp_total(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
+            (Self::Number(a), Self::Number(b)) => {
+                // -0.0 is equal to 0.0, so they should not be ordered
+                let normalize = |number| if number == 0.0 { 0.0 } else { number };
+                normalize(a.as_f64()).total_cmp(&normalize(b.as_f64()))
+            }
+            (Self::String(a), Self::String(b)) => a.cmp(b),
+            (Self::ByteString(a), Self::ByteString(b)) => a.cmp(b),
+            (Self::Array(a), Self::Array(b)) => cmp_elements(a.iter(), b.iter(), Self::cmp_total),
+            (Self::Object(a), Self::Object(b)) => {
+                let mut a: Vec<_> = a.iter().collect();
+                let mut b: Vec<_> = b.iter().collect();
+                a.sort_unstable_by_key(|(key, _)| *key);
+                b.sort_unstable_by_key(|(key, _)| *key);
+                cmp_elements(a.into_iter(), b.into_iter(), |(a_key, a), (b_key, b)| {
+                    a_key.cmp(b_key).then_with(|| a.cmp_total(b))
+                })
+            }
+            (Self::Tagged(a_tag, a), Self::Tagged(b_tag, b)) => {
+                a_tag.cmp(b_tag).then_with(|| a.cmp_total(b))
+            }
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+
+    /// The position of the type of the value in the order used by [`Value::cmp_total`].
+    fn type_rank(&self) -> u8 {
+        match self {
+            Self::Null => 0,
+            Self::Bool(_) => 1,
+            Self::Number(_) => 2,
+            Self::String(_) => 3,
+            Self::ByteString(_) => 4,
+            Self::Array(_) => 5,
+            Self::Object(_) => 6,
+            Self::Tagged(..) => 7,
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    /// Compare two values with [`Value::cmp_total`]. Values that `cmp_total`
+    /// considers equal, but that are not equal with `==`, like numbers with the
+    /// same `f64` but different exact values with the `arbitrary_precision` feature,
+    /// are not comparable.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// assert!(Value::from_str("[1, 2]").unwrap() < Value::from_str("[1, 3]").unwrap());
+    /// assert!(Value::from(1.0) < Value::String("1".into()));
+    /// assert!(Value::from(f64::NAN).partial_cmp(&Value::from(f64::NAN)).unwrap().is_eq());
+    /// ```
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.cmp_total(other) {
+            Ordering::Equal if self != other => None,
+            ordering => Some(ordering),
+        }
+    }
+}
+
+/// Compare two sequences element by element with `compare`, where a sequence that
+/// ends first is less.
+fn cmp_elements<T, F>(
+    mut a: impl Iterator<Item = T>,
+    mut b: impl Iterator<Item = T>,
+    mut compare: F,
+) -> Ordering
+where
+    F: FnMut(T, T) -> Ordering,
+{
+    loop {
+        match (a.next(), b.next()) {
+            (Some(a), Some(b)) => match compare(a, b) {
+                Ordering::Equal => {}
+                ordering => return ordering,
+            },
+            (a, b) => return a.is_some().cmp(&b.is_some()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_cmp_total() {
+        let value = |string| Value::from_str(string).unwrap();
+
+        let ordered = [
+            value("null"),
+            value("false"),
+            value("true"),
+            Value::from(-f64::NAN),
+            Value::from(f64::NEG_INFINITY),
+            value("-1"),
+            value("0"),
+            value("1e3"),
+            Value::from(f64::NAN),
+            value("\"\""),
+            value("\"B\""),
+            value("\"a\""),
+            value("\"ab\""),
+            value("b\"\""),
+            value("b\"\\x00\""),
+            value("[]"),
+            value("[1]"),
+            value("[1, null]"),
+            value("[2]"),
+            value("{}"),
+            value("{a: 1}"),
+            value("{a: 1, b: 0}"),
+            value("{a: 2}"),
+            value("{b: 0}"),
+            Value::Tagged("a".into(), Box::new(value("2"))),
+            Value::Tagged("b".into(), Box::new(value("1"))),
+        ];
+        for (i, a) in ordered.iter().enumerate() {
+            for (j, b) in ordered.iter().enumerate() {
+                assert_eq!(a.cmp_total(b), i.cmp(&j), "comparing {a} and {b}");
+            }
+        }
+
+        assert_eq!(value("-0.0").cmp_total(&value("0")), Ordering::Equal);
+        assert_eq!(
+            value("{b: [1], a: 2}").cmp_total(&value("{a: 2, b: [1.0]}")),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_partial_cmp() {
+        let value = |string| Value::from_str(string).unwrap();
+
+        assert_eq!(
+            value("[1, 2]").partial_cmp(&value("[1, 2]")),
+            Some(Ordering::Equal)
+        );
+        assert!(value("\"a\"") > value("1"));
+        assert!(value("{b: 1}") > value("{a: 2}"));
+
+        let nan = Value::Array(vec![Value::from(f64::NAN)]);
+        assert_eq!(nan.partial_cmp(&nan), Some(Ordering::Equal));
+        assert_eq!(nan.cmp_total(&nan), Ordering::Equal);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{Error, ErrorKind, Result, Value, merge::merge};
+
+/// The key of the object that contains the profiles, see [`Value::apply_profile`].
+const PROFILES: &str = "profiles";
+
+impl Value {
+    /// Merge the profile called `profile` over the rest of the `Value`, and remove
+    /// the profiles. The profiles are the values of the top level `profiles`
+    /// object, and are merged like [`merge`](crate::merge::merge) does, so objects
+    /// are merged key by key, and anything else in the profile replaces what is in
+    /// the document.
+    ///
+    /// This supports the common convention of keeping the settings of every
+    /// environment in one file, with the shared settings outside of `profiles`.
+    ///
+    /// ```
+    /// # use mason_rs::Value;
+    /// # use std::str::FromStr;
+    /// #
+    /// let mut config = Value::from_str(r#"
+    ///     server: {host: "localhost", port: 8080}
+    ///     debug: true
+    ///     profiles: {
+    ///         prod: {server: {host: "example.com"}, debug: false}
+    ///         dev: {}
+    ///     }
+    /// "#).unwrap();
+    ///
+    /// config.apply_profile("prod").unwrap();
+    /// assert_eq!(
+    ///     config,
+    ///     Value::from_str(r#"server: {host: "example.com", port: 8080}, debug: false"#).unwrap(),
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`ErrorKind::UnknownProfile`] if there is no profile called
+    /// `profile`, and with [`ErrorKind::InvalidType`] if the `Value` is not an
+    /// object, or if `profiles` or the chosen profile is not an object. The `Value`
+    /// is left unchanged if this fails.
+    pub fn apply_profile(&mut self, profile: &str) -> Result<()> {
+        let Self::Object(map) = self else {
+            return Err(Error::invalid_type("object", self));
+        };
+        match map.get(PROFILES) {
+            Some(Self::Object(profiles)) => match profiles.get(profile) {
+                Some(Self::Object(_)) => {}
+                Some(value) => {
+                    return Err(Error::invalid_type("object", value)
+                        .in_field(profile)
+                        .in_field(PROFILES));
+                }
+                None => {
+                    let mut names: Vec<&str> = profiles.keys().map(|name| name.as_ref()).collect();
+                    names.sort_unstable();
+                    return Err(Error::new(
+                        ErrorKind::UnknownProfile,
+                        format!(
+                            "unknown profile {profile:?}, expected one of: {}",
+                            names.join(", ")
+                        ),
+                    ));
+                }
+            },
+            Some(value) => return Err(Error::invalid_type("object", value).in_field(PROFILES)),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::UnknownProfile,
+                    format!("unknown profile {profile:?}, the document has no profiles"),
+                ));
+            }
+        }
+
+        // the profile was checked above, so it is always found
+        if let Some(Self::Object(mut profiles)) = map.remove(PROFILES) {
+            if let Some(overlay) = profiles.remove(profile) {
+                merge(self, overlay);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_apply_profile() {
+        let config = Value::from_str(
+            "a: {b: 1, c: [1, 2]}, d: \"d\", profiles: {x: {a: {c: [3], e: true}}, y: {}}",
+        )
+        .unwrap();
+
+        let mut x = config.clone();
+        x.apply_profile("x").unwrap();
+        assert_eq!(
+            x,
+            Value::from_str("a: {b: 1, c: [3], e: true}, d: \"d\"").unwrap()
+        );
+
+        let mut y = config.clone();
+        y.apply_profile("y").unwrap();
+        assert_eq!(
+            y,
+            Value::from_str("a: {b: 1, c: [1, 2]}, d: \"d\"").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_apply_profile_errors() {
+        let apply = |string: &str, profile: &str| {
+            let mut value = Value::from_str(string).unwrap();
+            let original = value.clone();
+            let err = value.apply_profile(profile).unwrap_err();
+            assert_eq!(value, original);
+            (err.kind(), err.to_string())
+        };
+
+        assert_eq!(
+            apply("a: 1, profiles: {x: {}, w: {}}", "z"),
+            (
+                ErrorKind::UnknownProfile,
+                "unknown profile \"z\", expected one of: w, x".to_owned()
+            )
+        );
+        assert_eq!(
+            apply("a: 1", "z"),
+            (
+                ErrorKind::UnknownProfile,
+                "unknown profile \"z\", the document has no profiles".to_owned()
+            )
+        );
+        assert_eq!(
+            apply("a: 1, profiles: {x: 2}", "x"),
+            (
+                ErrorKind::InvalidType,
+                "expected object, got number in `profiles.x`".to_owned()
+            )
+        );
+        assert_eq!(
+            apply("a: 1, profiles: [{}]", "x"),
+            (
+                ErrorKind::InvalidType,
+                "expected object, got array in `profiles`".to_owned()
+            )
+        );
+        assert_eq!(
+            apply("[1]", "x"),
+            (
+                ErrorKind::InvalidType,
+                "expected object, got array".to_owned()
+            )
+        );
+    }
+}
@@ -3,9 +3,10 @@ use std::{collections::HashMap, fmt};
 use serde::{
     Deserialize, Serialize,
     de::{MapAccess, SeqAccess, Visitor},
+    ser::SerializeMap,
 };
 
-use crate::Value;
+use crate::{Map, MasonString, Number, Value, value::mason_string};
 
 impl Serialize for Value {
     #[inline]
@@ -16,12 +17,45 @@ impl Serialize for Value {
         match self {
             Self::Null => serializer.serialize_unit(),
             Self::Bool(b) => serializer.serialize_bool(*b),
-            Self::Number(f) => serializer.serialize_f64(*f),
+            Self::Number(number) => serialize_number(number, serializer),
             Self::String(s) => serializer.serialize_str(s),
             Self::ByteString(v) => serializer.serialize_bytes(v),
             Self::Array(v) => v.serialize(serializer),
             Self::Object(m) => m.serialize(serializer),
+            // serde has no concept of tags, so a tagged value is serialized like
+            // an object with the tag, prefixed with '@', as its only key
+            Self::Tagged(tag, value) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(&format!("@{tag}"), value)?;
+                map.end()
+            }
+        }
+    }
+}
+
+// integers that an f64 can not represent are serialized as integers, so they are
+// not rounded, but everything else is still serialized as an f64. Numbers only
+// have a literal with the `arbitrary_precision` feature.
+fn serialize_number<S: serde::Serializer>(
+    number: &Number,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let float = number.as_f64();
+    if number.literal().is_none() {
+        return serializer.serialize_f64(float);
+    }
+    match (number.as_u128(), number.as_i128()) {
+        (Some(int), _) if float >= 2f64.powi(128) || float as u128 != int => {
+            match u64::try_from(int) {
+                Ok(int) => serializer.serialize_u64(int),
+                Err(_) => serializer.serialize_u128(int),
+            }
         }
+        (None, Some(int)) if float as i128 != int => match i64::try_from(int) {
+            Ok(int) => serializer.serialize_i64(int),
+            Err(_) => serializer.serialize_i128(int),
+        },
+        _ => serializer.serialize_f64(float),
     }
 }
 
@@ -45,81 +79,32 @@ impl<'de> Deserialize<'de> for Value {
                 Ok(Value::Bool(value))
             }
 
+            // MASON numbers are stored as f64, so integers that can not be represented
+            // exactly are rounded to the nearest f64, like when parsing MASON, unless
+            // the `arbitrary_precision` feature is enabled, which stores them exactly.
             #[inline]
-            fn visit_i64<E>(self, value: i64) -> Result<Value, E>
-            where
-                E: serde::de::Error,
-            {
-                // The largest whole number representable by a f64
-                const MAX: i64 = 2i64.pow(f64::MANTISSA_DIGITS) + 1;
-
-                if value.abs() <= MAX {
-                    Ok(Value::Number(value as f64))
-                } else {
-                    Err(serde::de::Error::invalid_value(
-                        serde::de::Unexpected::Signed(value),
-                        &self,
-                    ))
-                }
+            fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+                Ok(Value::Number(value.into()))
             }
 
             #[inline]
-            fn visit_i128<E>(self, value: i128) -> Result<Value, E>
-            where
-                E: serde::de::Error,
-            {
-                // The largest whole number representable by a f64
-                const MAX: i128 = 2i128.pow(f64::MANTISSA_DIGITS) + 1;
-
-                if value.abs() <= MAX {
-                    Ok(Value::Number(value as f64))
-                } else {
-                    Err(serde::de::Error::invalid_value(
-                        serde::de::Unexpected::Other(&format!("integer `{value}` as i128")),
-                        &self,
-                    ))
-                }
+            fn visit_i128<E>(self, value: i128) -> Result<Value, E> {
+                Ok(Value::Number(value.into()))
             }
 
             #[inline]
-            fn visit_u64<E>(self, value: u64) -> Result<Value, E>
-            where
-                E: serde::de::Error,
-            {
-                // The largest whole number representable by a f64
-                const MAX: u64 = 2u64.pow(f64::MANTISSA_DIGITS) + 1;
-
-                if value <= MAX {
-                    Ok(Value::Number(value as f64))
-                } else {
-                    Err(serde::de::Error::invalid_value(
-                        serde::de::Unexpected::Unsigned(value),
-                        &self,
-                    ))
-                }
+            fn visit_u64<E>(self, value: u64) -> Result<Value, E> {
+                Ok(Value::Number(value.into()))
             }
 
             #[inline]
-            fn visit_u128<E>(self, value: u128) -> Result<Value, E>
-            where
-                E: serde::de::Error,
-            {
-                // The largest whole number representable by a f64
-                const MAX: u128 = 2u128.pow(f64::MANTISSA_DIGITS) + 1;
-
-                if value <= MAX {
-                    Ok(Value::Number(value as f64))
-                } else {
-                    Err(serde::de::Error::invalid_value(
-                        serde::de::Unexpected::Other(&format!("integer `{value}` as u128")),
-                        &self,
-                    ))
-                }
+            fn visit_u128<E>(self, value: u128) -> Result<Value, E> {
+                Ok(Value::Number(value.into()))
             }
 
             #[inline]
             fn visit_f64<E>(self, value: f64) -> Result<Value, E> {
-                Ok(Value::Number(value))
+                Ok(Value::from(value))
             }
 
             #[inline]
@@ -132,7 +117,7 @@ impl<'de> Deserialize<'de> for Value {
 
             #[inline]
             fn visit_string<E>(self, value: String) -> Result<Value, E> {
-                Ok(Value::String(value))
+                Ok(Value::String(mason_string(value)))
             }
 
             fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
@@ -179,9 +164,9 @@ impl<'de> Deserialize<'de> for Value {
             where
                 V: MapAccess<'de>,
             {
-                let mut values = HashMap::new();
+                let mut values = Map::new();
 
-                while let Some((key, value)) = visitor.next_entry()? {
+                while let Some((key, value)) = visitor.next_entry::<MasonString, Value>()? {
                     values.insert(key, value);
                 }
 
@@ -192,3 +177,23 @@ impl<'de> Deserialize<'de> for Value {
         deserializer.deserialize_any(ValueVisitor)
     }
 }
+
+impl Serialize for Map {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_map(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Map {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        HashMap::<MasonString, Value>::deserialize(deserializer).map(Self::from)
+    }
+}
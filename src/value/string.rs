@@ -0,0 +1,218 @@
+use std::{
+    borrow::{Borrow, Cow},
+    fmt::{self, Display},
+    ops::Deref,
+};
+
+#[cfg(not(feature = "compact_str"))]
+type Repr = String;
+
+#[cfg(feature = "compact_str")]
+type Repr = compact_str::CompactString;
+
+/// The type used for [`Value::String`](super::Value::String) and the keys of a
+/// [`Map`](crate::Map).
+///
+/// A `MasonString` dereferences to [`str`], and can be created from a `&str` or a
+/// `String` with [`Into::into`]. It is a [`String`] inside, unless the `compact_str`
+/// feature is enabled, in which case it is a
+/// [`CompactString`](compact_str::CompactString), which stores strings of up to 24
+/// bytes inline, so most keys and short values do not need an allocation of their
+/// own. The type is the same either way, so the feature does not change the API.
+///
+/// ```
+/// # use mason_rs::{MasonString, Value};
+/// #
+/// let value = Value::String("port".into());
+/// if let Value::String(string) = &value {
+///     assert_eq!(string, "port");
+///     assert_eq!(string.to_uppercase(), "PORT");
+///     assert_eq!(String::from(string.clone()), "port");
+/// }
+/// assert_eq!(MasonString::from("a").as_str(), "a");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MasonString(Repr);
+
+impl MasonString {
+    /// Create an empty string.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The string as a `&str`.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Convert the string into a `String`. This is free unless the `compact_str`
+    /// feature is enabled.
+    #[allow(clippy::useless_conversion)]
+    pub fn into_string(self) -> String {
+        self.0.into()
+    }
+}
+
+impl Deref for MasonString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for MasonString {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for MasonString {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for MasonString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Display for MasonString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self.as_str(), f)
+    }
+}
+
+impl From<String> for MasonString {
+    #[allow(clippy::useless_conversion)]
+    fn from(string: String) -> Self {
+        Self(string.into())
+    }
+}
+
+impl From<&String> for MasonString {
+    fn from(string: &String) -> Self {
+        Self(string.as_str().into())
+    }
+}
+
+impl From<&str> for MasonString {
+    fn from(string: &str) -> Self {
+        Self(string.into())
+    }
+}
+
+impl From<Box<str>> for MasonString {
+    fn from(string: Box<str>) -> Self {
+        Self(string.into())
+    }
+}
+
+impl From<Cow<'_, str>> for MasonString {
+    fn from(string: Cow<'_, str>) -> Self {
+        Self(string.into())
+    }
+}
+
+impl From<MasonString> for String {
+    fn from(string: MasonString) -> Self {
+        string.into_string()
+    }
+}
+
+impl FromIterator<char> for MasonString {
+    fn from_iter<T: IntoIterator<Item = char>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl PartialEq<str> for MasonString {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for MasonString {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for MasonString {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<MasonString> for str {
+    fn eq(&self, other: &MasonString) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<MasonString> for &str {
+    fn eq(&self, other: &MasonString) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl PartialEq<MasonString> for String {
+    fn eq(&self, other: &MasonString) -> bool {
+        self == other.as_str()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for MasonString {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for MasonString {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Repr::deserialize(deserializer).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_mason_string() {
+        let string = MasonString::from("key");
+        assert_eq!(string, "key");
+        assert_eq!("key", string);
+        assert_eq!(string, String::from("key"));
+        assert_eq!(format!("{string} {string:?}"), "key \"key\"");
+
+        // strings can be looked up by `&str`, so they must hash like one
+        let map = HashMap::from([(string, 1)]);
+        assert_eq!(map.get("key"), Some(&1));
+    }
+
+    #[cfg(feature = "compact_str")]
+    #[test]
+    fn test_compact_strings() {
+        use std::str::FromStr;
+
+        use crate::Value;
+
+        let value =
+            Value::from_str("key: \"short\", long_key: \"a string that is too long\"").unwrap();
+        let object = value.as_object().unwrap();
+        assert!(object.keys().all(|key| !key.0.is_heap_allocated()));
+        let is_heap_allocated = |value: &Value| match value {
+            Value::String(string) => string.0.is_heap_allocated(),
+            _ => panic!("{value:?} is not a string"),
+        };
+        assert!(!is_heap_allocated(&object["key"]));
+        assert!(is_heap_allocated(&object["long_key"]));
+    }
+}
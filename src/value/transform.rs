@@ -0,0 +1,174 @@
+use std::mem;
+
+use crate::{MasonString, Value, utils::with_key};
+
+/// What [`Value::transform`] should do with a value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transform {
+    /// Keep the value, and transform the values inside it.
+    Keep,
+    /// Replace the value. The new value is not transformed.
+    Replace(Value),
+    /// Remove the value from the object or array it is in. Removing the top level
+    /// value replaces it with null.
+    Remove,
+    /// Move the value to another key of the object it is in, and transform the
+    /// values inside it. If the new key is already used, its value is replaced.
+    /// Array elements and the top level value have no key, so they are kept as
+    /// they are.
+    Rename(MasonString),
+}
+
+impl Value {
+    /// Transform the `Value` in a single pass, by calling `f` with the path and
+    /// value of every value in it, and doing what it returns. See [`Transform`].
+    ///
+    /// The path is a list of keys separated by `.`, where array elements are
+    /// identified by their index, like in [`Value::insert_path`]. The path of the
+    /// top level value is the empty string, and paths are always the paths the
+    /// values had before they were transformed. Values are visited before the
+    /// values inside them, and the value inside a tagged value is visited with the
+    /// same path as the tagged value.
+    ///
+    /// ```
+    /// # use mason_rs::{Transform, Value};
+    /// # use std::str::FromStr;
+    /// #
+    /// let mut value = Value::from_str(
+    ///     r#"db: {password: "hunter2", timeout_ms: 1500}, legacy: true, hosts: ["a", "b"]"#,
+    /// )
+    /// .unwrap();
+    ///
+    /// value.transform(|path, value| match path {
+    ///     "db.password" => Transform::Replace(Value::String("***".into())),
+    ///     "db.timeout_ms" => Transform::Replace(Value::from(value.as_f64().unwrap() / 1000.0)),
+    ///     "legacy" | "hosts.0" => Transform::Remove,
+    ///     "hosts" => Transform::Rename("servers".into()),
+    ///     _ => Transform::Keep,
+    /// });
+    ///
+    /// assert_eq!(
+    ///     value,
+    ///     Value::from_str(r#"db: {password: "***", timeout_ms: 1.5}, servers: ["b"]"#).unwrap(),
+    /// );
+    /// ```
+    pub fn transform<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str, &Self) -> Transform,
+    {
+        match f("", self) {
+            Transform::Keep | Transform::Rename(_) => {
+                self.transform_inside(&mut String::new(), &mut f);
+            }
+            Transform::Replace(value) => *self = value,
+            Transform::Remove => *self = Self::Null,
+        }
+    }
+
+    fn transform_inside<F>(&mut self, path: &mut String, f: &mut F)
+    where
+        F: FnMut(&str, &Self) -> Transform,
+    {
+        match self {
+            Self::Object(map) => {
+                let mut renamed = Vec::new();
+                for (key, mut value) in mem::take(map) {
+                    let transform = with_key(path, &key, |path| {
+                        let transform = f(path, &value);
+                        if matches!(transform, Transform::Keep | Transform::Rename(_)) {
+                            value.transform_inside(path, f);
+                        }
+                        transform
+                    });
+                    match transform {
+                        Transform::Keep => {
+                            map.insert(key, value);
+                        }
+                        Transform::Replace(new) => {
+                            map.insert(key, new);
+                        }
+                        Transform::Remove => {}
+                        Transform::Rename(new_key) => renamed.push((new_key, value)),
+                    }
+                }
+                // renamed values are inserted last, so they replace the values
+                // they are renamed over, no matter the order of the keys
+                map.extend(renamed);
+            }
+            Self::Array(array) => {
+                let mut i = 0;
+                array.retain_mut(|value| {
+                    let keep = with_key(path, &i.to_string(), |path| match f(path, value) {
+                        Transform::Keep | Transform::Rename(_) => {
+                            value.transform_inside(path, f);
+                            true
+                        }
+                        Transform::Replace(new) => {
+                            *value = new;
+                            true
+                        }
+                        Transform::Remove => false,
+                    });
+                    i += 1;
+                    keep
+                });
+            }
+            Self::Tagged(_, value) => match f(path, value) {
+                Transform::Keep | Transform::Rename(_) => value.transform_inside(path, f),
+                Transform::Replace(new) => **value = new,
+                Transform::Remove => **value = Self::Null,
+            },
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_transform() {
+        let secret =
+            |string: &str| Value::Tagged("secret".into(), Box::new(Value::String(string.into())));
+
+        let mut value =
+            Value::from_str("a: {b: [1, 2, 3]}, d: {e: 1}, f: 2, g: 3, h: {i: {}}").unwrap();
+        value["a"]["c"] = secret("x");
+
+        let mut paths = Vec::new();
+        value.transform(|path, value| {
+            paths.push(path.to_owned());
+            match path {
+                "a.b.1" | "h.i" => Transform::Remove,
+                "a.b.2" => Transform::Replace(Value::String("three".into())),
+                "a.c" if value.is_string() => Transform::Replace(Value::String("***".into())),
+                "d" => Transform::Rename("f".into()),
+                "d.e" | "f" => Transform::Replace(Value::Null),
+                "g" => Transform::Rename("renamed".into()),
+                _ => Transform::Keep,
+            }
+        });
+        let mut expected =
+            Value::from_str("a: {b: [1, \"three\"]}, f: {e: null}, renamed: 3, h: {}").unwrap();
+        expected["a"]["c"] = secret("***");
+        assert_eq!(value, expected);
+
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                "", "a", "a.b", "a.b.0", "a.b.1", "a.b.2", "a.c", "a.c", "d", "d.e", "f", "g", "h",
+                "h.i"
+            ]
+        );
+
+        let mut value = Value::from_str("a: 1").unwrap();
+        value.transform(|_, _| Transform::Rename("b".into()));
+        assert_eq!(value, Value::from_str("b: 1").unwrap());
+        value.transform(|_, _| Transform::Remove);
+        assert_eq!(value, Value::Null);
+    }
+}
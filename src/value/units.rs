@@ -0,0 +1,172 @@
+//! Parsing of human friendly durations and byte sizes, like `"1h30m"` and `"2GiB"`.
+
+use std::{fmt::Write, time::Duration};
+
+/// Split `string` into pairs of numbers and (possibly empty) units, like
+/// `[(1.0, "h"), (30.0, "m")]` for `"1h 30m"`.
+fn split_units(string: &str) -> Option<Vec<(f64, &str)>> {
+    let mut rest = string.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut parts = Vec::new();
+    while !rest.is_empty() {
+        let number_end = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let number = rest[..number_end].parse().ok()?;
+        rest = rest[number_end..].trim_start();
+
+        let unit_end = rest
+            .find(|c: char| !(c.is_alphabetic() || c == 'µ'))
+            .unwrap_or(rest.len());
+        parts.push((number, &rest[..unit_end]));
+        rest = rest[unit_end..].trim_start();
+    }
+    Some(parts)
+}
+
+/// Parse a duration like `"30s"`, `"1.5h"` or `"1h 30m"`. A number without a unit
+/// is interpreted as seconds.
+pub fn parse_duration(string: &str) -> Option<Duration> {
+    let parts = split_units(string)?;
+    let single_part = parts.len() == 1;
+
+    let mut nanos = 0.0;
+    for (number, unit) in parts {
+        let scale = match unit {
+            "" if single_part => 1e9,
+            "ns" => 1.0,
+            "us" | "µs" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            "m" => 60e9,
+            "h" => 3600e9,
+            "d" => 86400e9,
+            _ => return None,
+        };
+        nanos += number * scale;
+    }
+
+    let secs = (nanos / 1e9).floor();
+    if secs >= u64::MAX as f64 {
+        return None;
+    }
+    Duration::from_secs(secs as u64)
+        .checked_add(Duration::from_nanos((nanos - secs * 1e9).round() as u64))
+}
+
+/// Format a duration so that it can be parsed by [`parse_duration`], like
+/// `"1h30m"` or `"250ms"`.
+pub fn format_duration(duration: Duration) -> String {
+    if duration.is_zero() {
+        return "0s".to_owned();
+    }
+
+    let secs = duration.as_secs();
+    let nanos = duration.subsec_nanos();
+    let parts = [
+        (secs / 86400, "d"),
+        (secs / 3600 % 24, "h"),
+        (secs / 60 % 60, "m"),
+        (secs % 60, "s"),
+        (u64::from(nanos / 1_000_000), "ms"),
+        (u64::from(nanos / 1000 % 1000), "us"),
+        (u64::from(nanos % 1000), "ns"),
+    ];
+
+    parts.into_iter().filter(|(number, _)| *number != 0).fold(
+        String::new(),
+        |mut string, (number, unit)| {
+            write!(string, "{number}{unit}").expect("writing to a String does not fail");
+            string
+        },
+    )
+}
+
+/// Parse a byte size like `"512"`, `"10kB"` or `"2GiB"`. A number without a unit
+/// is interpreted as bytes. Returns `None` if the size is not a whole number of
+/// bytes.
+pub fn parse_byte_size(string: &str) -> Option<u64> {
+    let [(number, unit)] = split_units(string)?[..] else {
+        return None;
+    };
+
+    let scale: f64 = match unit {
+        "" | "B" => 1.0,
+        "kB" | "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        "PB" => 1e15,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0f64.powi(2),
+        "GiB" => 1024.0f64.powi(3),
+        "TiB" => 1024.0f64.powi(4),
+        "PiB" => 1024.0f64.powi(5),
+        _ => return None,
+    };
+    whole_bytes(number * scale)
+}
+
+/// Convert `bytes` to a u64 if it is a whole number of bytes that fits in one.
+pub fn whole_bytes(bytes: f64) -> Option<u64> {
+    if bytes >= 0.0 && bytes.fract() == 0.0 && bytes < u64::MAX as f64 {
+        Some(bytes as u64)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("30"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("5m"), Some(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1.5h"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("1h 30m"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("2d1s"), Some(Duration::from_secs(172801)));
+        assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("1ns"), Some(Duration::from_nanos(1)));
+        assert_eq!(parse_duration("3 µs"), Some(Duration::from_micros(3)));
+
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("1h 30"), None);
+        assert_eq!(parse_duration("-1s"), None);
+        assert_eq!(parse_duration("1 fortnight"), None);
+        assert_eq!(parse_duration("1e400s"), None);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        for (duration, string) in [
+            (Duration::ZERO, "0s"),
+            (Duration::from_secs(5400), "1h30m"),
+            (Duration::from_millis(250), "250ms"),
+            (Duration::new(172801, 1001), "2d1s1us1ns"),
+        ] {
+            assert_eq!(format_duration(duration), string);
+            assert_eq!(parse_duration(string), Some(duration));
+        }
+    }
+
+    #[test]
+    fn test_parse_byte_size() {
+        assert_eq!(parse_byte_size("512"), Some(512));
+        assert_eq!(parse_byte_size("512B"), Some(512));
+        assert_eq!(parse_byte_size("10kB"), Some(10_000));
+        assert_eq!(parse_byte_size("1.5 KiB"), Some(1536));
+        assert_eq!(parse_byte_size("2GiB"), Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_byte_size("3TB"), Some(3_000_000_000_000));
+
+        assert_eq!(parse_byte_size("1.1B"), None);
+        assert_eq!(parse_byte_size("1GiB 2MiB"), None);
+        assert_eq!(parse_byte_size("2Gb"), None);
+        assert_eq!(parse_byte_size("-2"), None);
+    }
+}
@@ -0,0 +1,43 @@
+//! JavaScript bindings, generated with [`wasm-bindgen`](https://docs.rs/wasm-bindgen).
+//!
+//! MASON is converted to and from plain JavaScript values: objects become plain
+//! objects, byte strings become `Uint8Array`s, and tagged values become objects
+//! with the tag, prefixed with `@`, as their only key. Non-finite numbers are
+//! written as `null`, just like when serializing a [`Value`].
+//!
+//! ```js
+//! import { parse, stringify } from "mason-rs";
+//!
+//! const config = parse("port: 8080\nhosts: [\"a\", \"b\"]");
+//! config.port += 1;
+//! console.log(stringify(config));
+//! ```
+
+use std::str::FromStr;
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::Value;
+
+/// Parse a MASON string into a JavaScript value.
+///
+/// # Errors
+///
+/// Throws if `text` is not valid MASON.
+#[wasm_bindgen]
+pub fn parse(text: &str) -> Result<JsValue, JsError> {
+    let value = Value::from_str(text)?;
+    Ok(value.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+/// Serialize a JavaScript value into a MASON string.
+///
+/// # Errors
+///
+/// Throws if `value` can not be represented in MASON, like a function or a symbol.
+#[wasm_bindgen]
+pub fn stringify(value: JsValue) -> Result<String, JsError> {
+    let value: Value = serde_wasm_bindgen::from_value(value)?;
+    Ok(value.to_string())
+}
@@ -0,0 +1,194 @@
+//! Reload a MASON file whenever it changes.
+//!
+//! The file is polled for changes, so this works on every platform without any
+//! extra dependencies. When a change is detected, the file is only reloaded once it
+//! has stopped changing for a short while, so that a file which is written in
+//! several steps is not parsed while it is incomplete.
+//!
+//! ```no_run
+//! # use mason_rs::watch::watch_file;
+//! #
+//! let watcher = watch_file("config.mason", |config| match config {
+//!     Ok(config) => println!("new config: {config}"),
+//!     Err(err) => eprintln!("invalid config: {err}"),
+//! })
+//! .unwrap();
+//!
+//! // the file is watched until `watcher` is dropped
+//! # drop(watcher);
+//! ```
+
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+use crate::Value;
+
+/// How often the file is checked for changes.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long the file must stay unchanged before it is reloaded.
+pub const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// A handle to a watched file. The file is watched until the `Watcher` is dropped.
+#[derive(Debug)]
+pub struct Watcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watcher {
+    /// Stop watching the file. This is the same as dropping the `Watcher`.
+    pub fn stop(self) {}
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            // a panic in the callback has already been reported by the thread
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Watch the MASON file at `path`, and call `callback` with the parsed [`Value`]
+/// every time the file changes. `callback` is also called once with the initial
+/// content of the file. If the file can not be read or is not valid MASON,
+/// `callback` is called with the error instead.
+///
+/// `callback` is called from a background thread, which runs until the returned
+/// [`Watcher`] is dropped.
+///
+/// # Errors
+///
+/// This function can fail if the background thread could not be created.
+pub fn watch_file<P, F>(path: P, mut callback: F) -> io::Result<Watcher>
+where
+    P: AsRef<Path>,
+    F: FnMut(crate::Result<Value>) + Send + 'static,
+{
+    spawn_watcher(path.as_ref().to_owned(), move |path| {
+        callback(
+            File::open(path)
+                .map_err(crate::Error::from)
+                .and_then(Value::from_reader),
+        );
+    })
+}
+
+/// Watch the MASON file at `path`, and call `callback` with the file deserialized
+/// as a `T` every time the file changes. See [`watch_file`] for details.
+///
+/// # Errors
+///
+/// This function can fail if the background thread could not be created.
+#[cfg(feature = "serde")]
+pub fn watch_file_as<T, P, F>(path: P, mut callback: F) -> io::Result<Watcher>
+where
+    T: serde::de::DeserializeOwned,
+    P: AsRef<Path>,
+    F: FnMut(crate::serde::error::Result<T>) + Send + 'static,
+{
+    spawn_watcher(path.as_ref().to_owned(), move |path| {
+        callback(
+            File::open(path)
+                .map_err(crate::serde::error::Error::from)
+                .and_then(crate::from_reader),
+        );
+    })
+}
+
+/// The modification time and length of the file at `path`, or `None` if the
+/// file does not exist.
+fn file_state(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn spawn_watcher<F>(path: PathBuf, mut reload: F) -> io::Result<Watcher>
+where
+    F: FnMut(&Path) + Send + 'static,
+{
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = Arc::clone(&stop);
+
+    let thread = thread::Builder::new()
+        .name("mason-watch".to_owned())
+        .spawn(move || {
+            // wait for `duration`, returning false if the watcher has been stopped
+            let wait = |duration: Duration| {
+                thread::park_timeout(duration);
+                !thread_stop.load(Ordering::Relaxed)
+            };
+
+            let mut state = file_state(&path);
+            reload(&path);
+
+            while wait(POLL_INTERVAL) {
+                let new_state = file_state(&path);
+                if new_state == state {
+                    continue;
+                }
+
+                state = new_state;
+                loop {
+                    if !wait(DEBOUNCE) {
+                        return;
+                    }
+                    let new_state = file_state(&path);
+                    if new_state == state {
+                        break;
+                    }
+                    state = new_state;
+                }
+                reload(&path);
+            }
+        })?;
+
+    Ok(Watcher {
+        stop,
+        thread: Some(thread),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::mpsc};
+
+    use super::*;
+
+    #[test]
+    fn test_watch_file() {
+        let path = std::env::temp_dir().join(format!("mason-watch-{}.mason", std::process::id()));
+        fs::write(&path, "a: 1").unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let watcher = watch_file(&path, move |value| sender.send(value).unwrap()).unwrap();
+        let timeout = Duration::from_secs(5);
+
+        let value = receiver.recv_timeout(timeout).unwrap().unwrap();
+        assert_eq!(value, Value::from_str("a: 1").unwrap());
+
+        // make sure the modification time changes on file systems with a coarse clock
+        thread::sleep(Duration::from_millis(20));
+        fs::write(&path, "a: 2, b: [true]").unwrap();
+        let value = receiver.recv_timeout(timeout).unwrap().unwrap();
+        assert_eq!(value, Value::from_str("a: 2, b: [true]").unwrap());
+
+        fs::remove_file(&path).unwrap();
+        assert!(receiver.recv_timeout(timeout).unwrap().is_err());
+
+        watcher.stop();
+        assert!(receiver.recv_timeout(POLL_INTERVAL * 2).is_err());
+    }
+}